@@ -0,0 +1,84 @@
+# English messages of AppPack.
+# To add a language, copy this file to locales/<language>/appack.ftl,
+# translate the messages and add the file to CATALOGS in src/utils/i18n.rs.
+
+## Prompts
+
+prompt-yes-no = { $question } [y/N]
+prompt-assumed-yes = { $question } [y/N] y (--yes)
+prompt-confirmation-required = Confirmation required: { $question } Run again with { $flag } to confirm
+prompt-type-word = { $question } Type { $word } to continue:
+prompt-pick = Choice [1-{ $count }]:
+prompt-choice-required = Choice required: { $question } Give { $argument } on the command line
+prompt-no-choice = Nothing chosen
+prompt-pick-app = Which app?
+prompt-install-unsigned = Install it anyway?
+prompt-continue-install = Continue the installation?
+prompt-capabilities = { $name } asks to use: { $capabilities }. Allow it?
+prompt-command-risk = { $name }: { $risk }. Allow it?
+risk-qemu-unconfined = QEMU runs without its sandbox, a guest escaping into it can do anything AppPack can
+prompt-reset = Reset { $id } { $version } to its initial state? All changes made in the app will be lost.
+prompt-uninstall-all = Uninstall all { $count } installed apps? Their data will be deleted.
+prompt-prune = Uninstall { $apps }, not listed in the manifest? Their data will be deleted.
+prompt-prune-versions = Uninstall { $versions }, older than the versions kept? Their data will be deleted.
+installation-cancelled = Installation cancelled
+reset-cancelled = Reset cancelled
+uninstall-cancelled = Uninstall cancelled
+
+## Install security warnings
+
+warning-unsigned = Warning: { $pack } is not signed, make sure you trust its source.
+error-requires-appack = This app needs AppPack { $requirement }, this is AppPack { $version }. Please update AppPack
+warning-emulated = Warning: { $reason }. It will run very slowly.
+error-emulated = { $reason }, it would run very slowly. Install it anyway with --force
+emulated-foreign-arch = { $name } is an { $arch } app and this machine is { $host }, so it can only be emulated
+warning-guest-locale = Warning: { $name } was set up for the { $locale } region, with a { $keyboard } keyboard. Its dates, numbers and keyboard may not follow the settings of your machine.
+emulated-no-kvm = KVM is not available on this machine, so { $name } can only be emulated
+security-alert-trusted = Installing it anyway (--trust-desktop-entries)
+security-alert-title = ⚠️ SECURITY ALERT: DESKTOP ENTRY REVIEW ⚠️
+security-alert-intro =
+    A desktop entry has been configured for this application. Please **CRITICALLY REVIEW** the command that will be executed upon activation against the expected safe command.
+security-alert-comparison = --- COMMAND COMPARISON ---
+security-alert-expected = 1. EXPECTED SAFE COMMAND:
+security-alert-configured = 2. CONFIGURED EXECUTION COMMAND:
+security-alert-action-title = --- IMMEDIATE ACTION REQUIRED ---
+security-alert-action =
+    If **Command 2 (Configured)** does **NOT** exactly match **Command 1 (Expected)**, this indicates a potential security risk where a malicious program may execute instead. In this case, you must **IMMEDIATELY UNINSTALL** this application upon installation completion.
+
+## Desktop notifications
+
+notification-opening-soon = "{ $name }" will open soon
+notification-be-patient = Please be patient while we're setting things up
+notification-first-launch = Launching "{ $name }" for the first time
+notification-virtualization-error = Virtualization error
+notification-virtualization-error-body = Make sure virtualization is enabled in your BIOS and that this snap has the KVM connection enabled
+notification-quota-exceeded = AppPack disk quota exceeded
+notification-update-available = An update is available for "{ $name }"
+notification-update-available-body = Version { $version } is available. Run `appack update { $id }` to install it.
+notification-slow-launch = "{ $name }" took { $seconds } seconds to start
+notification-slow-launch-body = It usually takes { $seconds } seconds. Check that virtualization works with `appack doctor`, and that the disk isn't failing.
+notification-open-url = "{ $name }" wants to open a link
+notification-open-url-action = Open in my browser
+
+## Quotas
+
+quota-storage-exceeded = AppPack uses { $usage } MB, more than the { $max } MB allowed by max_storage
+quota-image-growth-exceeded = The image of "{ $name }" grew by { $growth } MB, more than the { $max } MB allowed by max_image_growth
+
+## Error hints
+
+hint-qemu-failed = Qemu failed to start. Make sure virtualization is enabled in your BIOS and this snap has the KVM connection plugged.
+hint-qemu-not-found = { $binary } not found, make sure QEMU is installed
+hint-program-not-found = { $program } not found, make sure it is installed
+hint-freerdp-not-found = { $program } not found, install FreeRDP 3 or set rdp_client in the configuration
+hint-freerdp-alternative = { $program } not found, but { $alternative } is installed. Use it with `appack config set rdp_client { $alternative }`
+hint-freerdp-unsupported = { $program } not found. { $alternative } { $version } is installed but not supported, install FreeRDP 3 or set rdp_client in the configuration
+hint-quota-exceeded = Disk quota exceeded. Free some space with `appack reset`, `appack uninstall` or `appack cache clean`, or raise the quota
+hint-snap-plugs = These plugs of AppPack are not connected: { $plugs }. Connect them with:
+    { $commands }
+hint-check-plugs = Make sure this directory exists and that you installed AppPack using the command line from the README (that the necessary plugs are connected)
+hint-recover = Run `appack recover` to restore it from its backup or from the installed apps
+hint-trust-key = Trust the repository key with `appack repo trust <keyfile>` if you trust its author
+hint-capability = { $name } can use your { $device } if you allow it with `appack config set { $id } { $device } true`
+hint-capability-each-launch = { $name } can use your { $device } if you allow it with `appack launch { $id } --{ $device }`
+hint-open-urls = Nobody could be asked to open a link of the app, open them anyway with `appack config set { $id } open_urls always`
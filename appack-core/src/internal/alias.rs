@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::config::AppPackConfig;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+/// Id and version of an alias target, `id` or `id@version`
+fn split_target(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('@') {
+        Some((id, version)) => (id, Some(version)),
+        None => (target, None),
+    }
+}
+
+/// The app an alias stands for, with the version of the alias unless one is given.
+/// Anything else is returned as it is.
+pub fn resolve_alias(
+    settings: &AppPackLocalSettings,
+    id: String,
+    version: Option<String>,
+) -> (String, Option<String>) {
+    let Some(target) = settings.config.aliases.get(&id) else {
+        return (id, version);
+    };
+
+    let (target_id, target_version) = split_target(target);
+    tracing::debug!("{id} is an alias of {target}");
+    (
+        target_id.to_string(),
+        version.or(target_version.map(str::to_string)),
+    )
+}
+
+pub fn alias_add(settings: &AppPackLocalSettings, alias: &str, target: &str) -> Result<()> {
+    if alias.is_empty() || alias.contains(|c: char| c.is_whitespace() || c == '@') {
+        return Err(anyhow!("Invalid alias: {alias}"));
+    }
+
+    let (id, version) = split_target(target);
+    settings.get_app_installed(id, version)?;
+    if settings.get_app_installed(alias, None).is_ok() {
+        return Err(anyhow!("'{alias}' is the id of an installed app"));
+    }
+
+    // The environment overrides must not end up in the file
+    let mut config = AppPackConfig::new(&settings.config_file)?;
+    if let Some(previous) = config.aliases.insert(alias.to_string(), target.to_string()) {
+        message!("'{alias}' was an alias of {previous}");
+    }
+    config.save(&settings.config_file)?;
+
+    message!("'{alias}' is now an alias of {target}");
+
+    Ok(())
+}
+
+pub fn alias_remove(settings: &AppPackLocalSettings, alias: &str) -> Result<()> {
+    let mut config = AppPackConfig::new(&settings.config_file)?;
+    if config.aliases.remove(alias).is_none() {
+        return Err(ErrorKind::NotFound.error(format!("No alias named '{alias}'")));
+    }
+
+    config.save(&settings.config_file)
+}
+
+pub fn alias_list(settings: &AppPackLocalSettings) -> Result<()> {
+    set_json_result(&json!(settings.config.aliases))?;
+    if settings.config.aliases.is_empty() {
+        message!("No alias defined, add one with `appack alias add <alias> <id>`");
+    }
+    for (alias, target) in &settings.config.aliases {
+        message!("{alias}\t{target}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_target() {
+        assert_eq!(
+            split_target("com.adobe.photoshop"),
+            ("com.adobe.photoshop", None)
+        );
+        assert_eq!(
+            split_target("com.adobe.photoshop@2.1.0"),
+            ("com.adobe.photoshop", Some("2.1.0"))
+        );
+    }
+}
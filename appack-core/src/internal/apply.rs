@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::config::app_config_set;
+use crate::internal::install_appack::{fetch_from_repository, install_appack, install_appack_file};
+use crate::internal::repo::{find_in_repositories, find_latest_in_repositories, sync_repository};
+use crate::internal::uninstall_appack::uninstall_appack;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::types::manifest::{AppPackManifest, AppPackManifestEntry};
+use crate::utils::frontend::{message, warning};
+use crate::utils::i18n::tr;
+use crate::utils::output::set_json_result;
+use crate::utils::prompt::confirm;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::cmp::Ordering;
+use std::path::Path;
+
+/// Converges the installed apps to the ones listed in the manifest: installs the missing ones,
+/// updates the outdated ones, and with `prune` uninstalls the ones not listed.
+/// Running it again without changes to the manifest or the repositories does nothing.
+pub fn apply(settings: &AppPackLocalSettings, manifest_path: &Path, prune: bool) -> Result<()> {
+    let manifest = AppPackManifest::from_file(manifest_path)?;
+    settings.check_ok()?;
+
+    if manifest.apps.iter().any(|app| app.source.is_none()) {
+        for repository in settings.get_repositories()?.repositories.iter() {
+            if let Err(e) = sync_repository(settings, repository) {
+                // The last synced index is still usable
+                warning!("Failed to sync repository '{}': {e:?}", repository.name);
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for app in &manifest.apps {
+        match converge(settings, app) {
+            Ok((action, version)) => {
+                message!("{}: {action} ({version})", app.id);
+                results.push(json!({ "id": app.id, "action": action, "version": version }));
+            }
+            Err(e) => {
+                warning!("Failed to apply {}: {e:?}", app.id);
+                results
+                    .push(json!({ "id": app.id, "action": "failed", "error": format!("{e:#}") }));
+                failed += 1;
+            }
+        }
+    }
+
+    if prune {
+        let unlisted: Vec<_> = settings
+            .get_installed()?
+            .installed
+            .into_iter()
+            .filter(|installed| !manifest.apps.iter().any(|app| app.id == installed.id))
+            .collect();
+
+        if !unlisted.is_empty() {
+            let apps = unlisted
+                .iter()
+                .map(|app| format!("{} {}", app.id, app.version))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if !confirm(&tr!("prompt-prune", apps = apps))? {
+                return Err(ErrorKind::Cancelled.error(tr!("uninstall-cancelled")));
+            }
+
+            for app in unlisted {
+                uninstall_appack(settings, &app.id, Some(&app.version))?;
+                message!("{}: removed ({})", app.id, app.version);
+                results.push(json!({ "id": app.id, "action": "removed", "version": app.version }));
+            }
+        }
+    }
+
+    set_json_result(&results)?;
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} app(s) of the manifest could not be applied"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Brings one app to the state of the manifest, returns what was done and the version installed
+fn converge(
+    settings: &AppPackLocalSettings,
+    app: &AppPackManifestEntry,
+) -> Result<(&'static str, String)> {
+    let previous: Vec<String> = settings
+        .get_installed()?
+        .installed
+        .into_iter()
+        .filter(|installed| installed.id == app.id)
+        .map(|installed| installed.version)
+        .collect();
+
+    let version = match &app.source {
+        Some(source) => {
+            if let Some(current) = previous
+                .iter()
+                .find(|v| app.version.as_ref().is_none_or(|version| version == *v))
+            {
+                return Ok(("unchanged", current.clone()));
+            }
+
+            remove_versions(settings, &app.id, &previous)?;
+            // Apps already installed on this machine are not refused again
+            install_appack(settings, source, None, None, !previous.is_empty())?;
+            settings
+                .get_installed()?
+                .installed
+                .into_iter()
+                .find(|installed| installed.id == app.id && !previous.contains(&installed.version))
+                .map(|installed| installed.version)
+                .ok_or_else(|| anyhow!("{source} is not a pack of {}", app.id))?
+        }
+        None => {
+            if let Some(channel) = &app.channel
+                && settings.get_app_config(&app.id)?.channel() != channel
+            {
+                app_config_set(settings, &app.id, "channel", channel)?;
+            }
+
+            let (repository, entry) = match &app.version {
+                Some(version) => {
+                    find_in_repositories(settings, &app.id, Some(version), app.repo.as_deref())?
+                }
+                None => {
+                    let channel = settings.get_app_config(&app.id)?.channel().to_string();
+                    find_latest_in_repositories(settings, &app.id, &channel)?.ok_or_else(|| {
+                        ErrorKind::NotFound.error(format!(
+                            "{} is not available in any repository on the {channel} channel",
+                            app.id
+                        ))
+                    })?
+                }
+            };
+
+            // Following a channel, a newer version installed by other means is kept
+            let up_to_date = |v: &String| match app.version {
+                Some(_) => *v == entry.version,
+                None => AppBuildConfig::compare_versions(v, &entry.version) != Ordering::Less,
+            };
+            if let Some(current) = previous.iter().find(|v| up_to_date(v)) {
+                return Ok(("unchanged", current.clone()));
+            }
+
+            let pack_path = fetch_from_repository(settings, &repository, &entry)?;
+            remove_versions(settings, &app.id, &previous)?;
+            install_appack_file(&pack_path, settings, Some(&entry), !previous.is_empty())?;
+            entry.version
+        }
+    };
+
+    let action = if previous.is_empty() {
+        "installed"
+    } else {
+        "updated"
+    };
+    Ok((action, version))
+}
+
+/// Only one version of an app can be installed, the previous ones make room for the new one
+fn remove_versions(settings: &AppPackLocalSettings, id: &str, versions: &[String]) -> Result<()> {
+    for version in versions {
+        uninstall_appack(settings, id, Some(version))?;
+    }
+
+    Ok(())
+}
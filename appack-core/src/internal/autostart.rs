@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::atomic_write::write_atomic;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::sandbox::appack_command;
+use anyhow::{Context, Result};
+use serde_json::json;
+
+/// How an app launched at login shows up
+#[derive(Debug, Default)]
+pub struct AutostartOptions {
+    pub fullscreen: bool,
+    /// Only serve remote clients on this address, without opening the app on this machine
+    pub listen: Option<String>,
+}
+
+/// Starts an app when the user logs in, through an XDG autostart entry
+pub fn autostart_enable(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    options: &AutostartOptions,
+) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, None)
+        .context("Failed to get installed AppPack")?;
+
+    // No version, so the entry keeps working after `appack update`
+    let mut exec = format!("{} launch {id}", appack_command());
+    if options.fullscreen {
+        exec.push_str(" --fullscreen");
+    }
+    if let Some(address) = &options.listen {
+        exec.push_str(&format!(" --listen {address} --headless"));
+    }
+
+    let entry_path = settings.get_autostart_entry_path(id);
+    std::fs::create_dir_all(&settings.autostart_dir).context(format!(
+        "Failed to create {}",
+        settings.autostart_dir.display()
+    ))?;
+    let contents = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={}\n\
+         Exec={exec}\n\
+         X-GNOME-Autostart-enabled=true\n\
+         NoDisplay=true\n",
+        app.name
+    );
+    write_atomic(&entry_path, contents)
+        .context(format!("Failed to write {}", entry_path.display()))?;
+
+    set_json_result(&json!({ "path": entry_path, "exec": exec }))?;
+    message!("{} will start at login", app.name);
+    tracing::info!(id, exec, "Autostart enabled");
+
+    Ok(())
+}
+
+pub fn autostart_disable(settings: &AppPackLocalSettings, id: &str) -> Result<()> {
+    let entry_path = settings.get_autostart_entry_path(id);
+    if !entry_path.exists() {
+        return Err(ErrorKind::NotFound.error(format!("{id} does not start at login")));
+    }
+
+    std::fs::remove_file(&entry_path)
+        .context(format!("Failed to remove {}", entry_path.display()))?;
+    message!("{id} will no longer start at login");
+    tracing::info!(id, "Autostart disabled");
+
+    Ok(())
+}
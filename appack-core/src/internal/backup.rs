@@ -0,0 +1,360 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::desktop::regenerate_desktop_entries;
+use crate::internal::install_appack::{consent_capabilities, install_appack, review_commands};
+use crate::internal::launch::running_rdp_port;
+use crate::types::app_installed::{InstalledAppPackEntry, InstalledAppPacks};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::{message, warning};
+use crate::utils::output::set_json_result;
+use crate::utils::prompt::confirm;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, Builder, EntryType};
+
+const MANIFEST: &str = "backup.yaml";
+const INSTALLED: &str = "installed.yaml";
+const SETTINGS_DIR: &str = "settings";
+const APPS_DIR: &str = "apps";
+
+/// First file of a backup, describing it
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupManifest {
+    appack_version: String,
+    /// Whether the app directories, with their disk images, are in the backup
+    images: bool,
+}
+
+/// Settings files of the user and their name in a backup. Folders are merged at restore.
+fn settings_files(settings: &AppPackLocalSettings) -> [(&'static str, &Path); 5] {
+    [
+        ("config.yaml", &settings.config_file),
+        ("app_config.yaml", &settings.app_config_file),
+        ("repositories.yaml", &settings.repositories_file),
+        ("repositories", &settings.repositories_dir),
+        ("trusted_keys", &settings.trusted_keys_dir),
+    ]
+}
+
+/// Settings deciding which packs are installed without warning, only restored if the user
+/// trusts them
+const TRUST_SETTINGS: [&str; 3] = ["repositories.yaml", "repositories", "trusted_keys"];
+
+/// Adds the files of a folder, sockets and other special files are left out
+fn append_dir(builder: &mut Builder<File>, dir: &Path, name: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = name.join(entry.file_name());
+        if file_type.is_dir() {
+            append_dir(builder, &entry.path(), &name)?;
+        } else if file_type.is_file() {
+            builder
+                .append_path_with_name(entry.path(), &name)
+                .context(format!("Failed to add {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn append_yaml<T: Serialize>(builder: &mut Builder<File>, name: &str, value: &T) -> Result<()> {
+    let content = serde_yaml::to_string(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, content.as_bytes())?;
+
+    Ok(())
+}
+
+/// Saves the installed apps, their data and the settings of the user to a tar file, to restore
+/// them on another machine with `appack restore`
+pub fn backup(settings: &AppPackLocalSettings, output: &Path, images: bool) -> Result<()> {
+    settings.check_ok()?;
+    let installed = settings.get_installed()?;
+    for app in &installed.installed {
+        if images && running_rdp_port(settings, app).is_some() {
+            return Err(ErrorKind::VmRunning.error(format!("{} is running", app.id)))
+                .context("Close the app before backing it up");
+        }
+    }
+
+    let file = File::create(output).context(format!("Failed to create {}", output.display()))?;
+    let mut builder = Builder::new(file);
+    let manifest = BackupManifest {
+        appack_version: env!("CARGO_PKG_VERSION").to_string(),
+        images,
+    };
+    append_yaml(&mut builder, MANIFEST, &manifest)?;
+    append_yaml(&mut builder, INSTALLED, &installed)?;
+
+    for (name, path) in settings_files(settings) {
+        let name = Path::new(SETTINGS_DIR).join(name);
+        if path.is_dir() {
+            append_dir(&mut builder, path, &name)?;
+        } else if path.is_file() {
+            builder.append_path_with_name(path, &name)?;
+        }
+    }
+
+    if images {
+        for app in &installed.installed {
+            message!(
+                "Saving {} {}.. This can take a few minutes.",
+                app.id, app.version
+            );
+            let name = Path::new(APPS_DIR).join(&app.id).join(&app.version);
+            // Not uninstalled or started while its files are read
+            let _lock = settings.lock_app(&app.id)?;
+            if running_rdp_port(settings, app).is_some() {
+                return Err(ErrorKind::VmRunning.error(format!("{} is running", app.id)))
+                    .context("Close the app before backing it up");
+            }
+            append_dir(&mut builder, &settings.get_app_home_dir(app), &name)?;
+        }
+    }
+    builder.into_inner()?;
+
+    let apps: Vec<_> = installed
+        .installed
+        .iter()
+        .map(|app| json!({ "id": app.id, "version": app.version }))
+        .collect();
+    set_json_result(&json!({ "file": output, "apps": apps, "images": images }))?;
+    message!(
+        "Saved {} apps to {}",
+        installed.installed.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Path of a file of a backup, refusing the ones escaping the folder they are restored to
+fn relative_path(path: &Path) -> Result<PathBuf> {
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(ErrorKind::ArchiveInvalid.error(format!("Invalid path in backup: {path:?}")));
+    }
+
+    Ok(path.to_path_buf())
+}
+
+fn read_yaml<T: for<'de> Deserialize<'de>>(entry: &mut impl Read, name: &str) -> Result<T> {
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    serde_yaml::from_str(&content).context(format!("Invalid {name} in backup"))
+}
+
+/// Reinstates the apps and settings of a backup made by `appack backup`. Installed apps and
+/// existing settings are kept, unless the user agrees to replace the settings.
+pub fn restore(settings: &AppPackLocalSettings, file: &Path) -> Result<()> {
+    settings.check_ok()?;
+    let backup = File::open(file).context(format!("Failed to open {}", file.display()))?;
+    let mut archive = Archive::new(backup);
+    let mut entries = archive.entries()?;
+
+    let mut next_yaml = |name: &str| -> Result<_> {
+        let mut entry = entries
+            .next()
+            .ok_or_else(|| ErrorKind::ArchiveInvalid.error("Not an AppPack backup"))??;
+        if entry.path()? != Path::new(name) {
+            return Err(ErrorKind::ArchiveInvalid.error("Not an AppPack backup"));
+        }
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        Ok(content)
+    };
+    let manifest: BackupManifest = read_yaml(&mut next_yaml(MANIFEST)?.as_bytes(), MANIFEST)?;
+    let backup_installed: InstalledAppPacks =
+        InstalledAppPacks::from_yaml(&next_yaml(INSTALLED)?, &settings.home_dir)?.0;
+    tracing::info!(version = manifest.appack_version, "Restoring backup");
+
+    // Held until the apps are in the list, so they are not installed meanwhile
+    let mut _app_locks = Vec::new();
+    for app in &backup_installed.installed {
+        _app_locks.push(settings.lock_app(&app.id)?);
+    }
+    let installed = settings.get_installed()?;
+    let is_installed = |app: &InstalledAppPackEntry, installed: &InstalledAppPacks| {
+        installed
+            .installed
+            .iter()
+            .any(|i| i.id == app.id && i.version == app.version)
+    };
+    let mut restored: Vec<InstalledAppPackEntry> = Vec::new();
+    let mut skipped = Vec::new();
+    for app in backup_installed.installed {
+        if app.system || is_installed(&app, &installed) {
+            continue;
+        }
+        // Apps installed again from the repositories are reviewed by the installation
+        if manifest.images {
+            let reviewed = review_commands(&app, &installed)
+                .and_then(|_| consent_capabilities(&app, &installed));
+            if let Err(e) = reviewed {
+                if ErrorKind::of(&e) != ErrorKind::Cancelled {
+                    return Err(e);
+                }
+                message!("Skipping {} {}", app.id, app.version);
+                skipped.push(app.id.clone());
+                continue;
+            }
+        }
+        restored.push(InstalledAppPackEntry {
+            home_dir: None,
+            ..app
+        });
+    }
+
+    let settings_targets = settings_files(settings);
+    let mut replace_settings = None;
+    let mut trust_settings = None;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = relative_path(&entry.path()?)?;
+        // Links could point the next entries outside of the folder they are restored to
+        let entry_type = entry.header().entry_type();
+        if !matches!(entry_type, EntryType::Regular | EntryType::Directory) {
+            return Err(ErrorKind::ArchiveInvalid.error(format!(
+                "Unsupported file type in backup: {path:?} ({entry_type:?})"
+            )));
+        }
+        let mut components = path.iter();
+
+        let target = match components.next().and_then(|c| c.to_str()) {
+            Some(SETTINGS_DIR) => {
+                let name = components
+                    .next()
+                    .and_then(|c| c.to_str())
+                    .unwrap_or_default();
+                let Some((_, target)) = settings_targets.iter().find(|(n, _)| *n == name) else {
+                    continue;
+                };
+                let target = target.join(components.as_path());
+                if TRUST_SETTINGS.contains(&name) {
+                    if trust_settings.is_none() {
+                        trust_settings = Some(confirm(
+                            "Trust the signing keys and repositories of the backup? \
+                            Packs they sign or list can then be installed without warning.",
+                        )?);
+                    }
+                    if trust_settings == Some(false) {
+                        continue;
+                    }
+                }
+                if target.exists() {
+                    if replace_settings.is_none() {
+                        replace_settings = Some(confirm(
+                            "Replace your AppPack settings with the ones of the backup?",
+                        )?);
+                    }
+                    if replace_settings == Some(false) {
+                        continue;
+                    }
+                }
+                target
+            }
+            Some(APPS_DIR) => {
+                let (Some(id), Some(version)) = (components.next(), components.next()) else {
+                    continue;
+                };
+                let Some(app) = restored
+                    .iter()
+                    .find(|app| app.id.as_str() == id && app.version.as_str() == version)
+                else {
+                    continue;
+                };
+                settings
+                    .default_app_home_dir(app)
+                    .join(components.as_path())
+            }
+            _ => continue,
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry
+            .unpack(&target)
+            .context(format!("Failed to restore {}", target.display()))?;
+    }
+
+    let mut failed = Vec::new();
+    if manifest.images {
+        for app in &restored {
+            if !settings
+                .default_app_home_dir(app)
+                .join(&app.image)
+                .is_file()
+            {
+                return Err(anyhow!("The backup misses the image of {}", app.id));
+            }
+        }
+        let _lock = settings.lock_installed()?;
+        let mut installed = settings.get_installed()?;
+        installed.installed.extend(restored.iter().cloned());
+        settings.save_installed(installed)?;
+        drop(_lock);
+        drop(_app_locks);
+        if !restored.is_empty() {
+            regenerate_desktop_entries(settings, None)?;
+        }
+    } else {
+        // The installation takes the app locks again
+        drop(_app_locks);
+        // Only the list of apps was saved, they are installed again from the repositories
+        for app in &restored {
+            if let Err(e) = install_appack(settings, &app.id, Some(&app.version), None, false) {
+                warning!("Failed to install {} {}: {e:?}", app.id, app.version);
+                failed.push(app.id.clone());
+            }
+        }
+    }
+
+    let apps: Vec<_> = restored
+        .iter()
+        .map(|app| json!({ "id": app.id, "version": app.version }))
+        .collect();
+    set_json_result(&json!({ "apps": apps, "failed": failed, "skipped": skipped }))?;
+    if !failed.is_empty() {
+        return Err(anyhow!("Failed to restore {}", failed.join(", ")));
+    }
+    message!("Restored {} apps", restored.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert!(relative_path(Path::new("apps/demo/1.0.0/image.qcow2")).is_ok());
+        assert!(relative_path(Path::new("apps/../../.bashrc")).is_err());
+        assert!(relative_path(Path::new("/etc/passwd")).is_err());
+    }
+}
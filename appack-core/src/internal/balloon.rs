@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use qapi::{Qmp, qmp};
+use serde_json::json;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+const MB: i64 = 1024 * 1024;
+
+fn set_balloon(qmp_socket: &Path, bytes: i64) -> Result<()> {
+    let qmp_stream = UnixStream::connect(qmp_socket).context("Failed to connect to QMP socket")?;
+    let mut qmp = Qmp::from_stream(&qmp_stream);
+    qmp.handshake().context("Failed to connect to QMP socket")?;
+    qmp.execute(&qmp::balloon { value: bytes })
+        .context("The VM has no balloon device")?;
+
+    Ok(())
+}
+
+/// Asks the guest of a running app to give its memory back to the host, down to `memory_mb`.
+/// The guest frees it gradually, `appack top` shows how much it kept.
+pub fn balloon(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    memory_mb: u64,
+) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    running_rdp_port(settings, &app)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("{id} is not running")))?;
+
+    let qmp_socket = settings.get_app_home_dir(&app).join("qmp-appack.sock");
+    // Memory above the size of the VM is ignored by QEMU
+    set_balloon(&qmp_socket, memory_mb as i64 * MB)?;
+
+    set_json_result(&json!({ "memory_mb": memory_mb }))?;
+    message!("Asked {} to use {memory_mb} MB", app.name);
+
+    Ok(())
+}
+
+/// Shrinks the guest while no client shows the app, and gives its memory back when one does
+pub(crate) struct IdleBalloon {
+    qmp_socket: PathBuf,
+    idle_mb: u64,
+    parked: bool,
+    /// Memory of the guest before it was shrunk
+    restore: Option<i64>,
+}
+
+impl IdleBalloon {
+    pub fn new(qmp_socket: PathBuf, idle_mb: u64) -> Self {
+        Self {
+            qmp_socket,
+            idle_mb,
+            parked: false,
+            restore: None,
+        }
+    }
+
+    /// Called repeatedly while no client is connected, only does something the first time
+    pub fn park(&mut self) {
+        if self.parked {
+            return;
+        }
+        self.parked = true;
+
+        let actual = UnixStream::connect(&self.qmp_socket)
+            .ok()
+            .and_then(|stream| {
+                let mut qmp = Qmp::from_stream(&stream);
+                qmp.handshake().ok()?;
+                qmp.execute(&qmp::query_balloon {}).ok()
+            })
+            .map(|balloon| balloon.actual);
+        let Some(actual) = actual else {
+            tracing::debug!("The VM has no balloon device, keeping its memory");
+            return;
+        };
+        if actual <= self.idle_mb as i64 * MB {
+            return;
+        }
+
+        match set_balloon(&self.qmp_socket, self.idle_mb as i64 * MB) {
+            Ok(()) => {
+                tracing::info!(
+                    idle_mb = self.idle_mb,
+                    "No client left, shrinking the guest"
+                );
+                self.restore = Some(actual);
+            }
+            Err(e) => tracing::warn!("Failed to shrink the guest: {e:?}"),
+        }
+    }
+
+    pub fn unpark(&mut self) {
+        self.parked = false;
+        let Some(actual) = self.restore.take() else {
+            return;
+        };
+
+        tracing::info!("A client connected, giving the guest its memory back");
+        if let Err(e) = set_balloon(&self.qmp_socket, actual) {
+            tracing::warn!("Failed to give the guest its memory back: {e:?}");
+        }
+    }
+}
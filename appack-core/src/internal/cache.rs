@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::install_appack::extract_config;
+use crate::internal::repo::signature_path;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Packs are cached by checksum, so the same pack is shared whatever repository it comes from
+pub fn cached_pack_path(settings: &AppPackLocalSettings, sha256: &str) -> PathBuf {
+    settings
+        .cache_dir
+        .join(format!("{}.zip", sha256.to_ascii_lowercase()))
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    // The cache directory can be on another filesystem
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to).context(format!(
+            "Failed to copy {} to {}",
+            from.display(),
+            to.display()
+        ))?;
+        std::fs::remove_file(from)?;
+    }
+
+    Ok(())
+}
+
+/// Moves a verified pack and its signature to the cache
+pub fn store_in_cache(
+    settings: &AppPackLocalSettings,
+    pack_path: &Path,
+    sha256: &str,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(&settings.cache_dir).context(format!(
+        "Failed to create cache directory {}",
+        settings.cache_dir.display()
+    ))?;
+
+    let cached_path = cached_pack_path(settings, sha256);
+    move_file(pack_path, &cached_path)?;
+    move_file(&signature_path(pack_path), &signature_path(&cached_path))?;
+
+    Ok(cached_path)
+}
+
+pub fn remove_from_cache(cached_path: &Path) {
+    let _ = std::fs::remove_file(cached_path); // Ignore error
+    let _ = std::fs::remove_file(signature_path(cached_path)); // Ignore error
+}
+
+fn cached_packs(settings: &AppPackLocalSettings) -> Result<Vec<PathBuf>> {
+    if !settings.cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut packs = Vec::new();
+    for entry in std::fs::read_dir(&settings.cache_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "zip") {
+            packs.push(path);
+        }
+    }
+    packs.sort();
+
+    Ok(packs)
+}
+
+/// Finds the newest cached pack of an app, or the pack of a given version
+pub fn find_cached_pack(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+) -> Option<PathBuf> {
+    cached_packs(settings)
+        .ok()?
+        .into_iter()
+        .filter(|path| {
+            File::open(path)
+                .ok()
+                .and_then(|file| ZipArchive::new(file).ok())
+                .and_then(|mut archive| extract_config(&mut archive).ok())
+                .is_some_and(|config| {
+                    config.id == id && version.is_none_or(|v| v == config.version)
+                })
+        })
+        .max_by_key(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+pub fn cache_list(settings: &AppPackLocalSettings) -> Result<()> {
+    let packs = cached_packs(settings)?;
+    if packs.is_empty() {
+        message!("Cache is empty");
+        return set_json_result(&Vec::<serde_json::Value>::new());
+    }
+
+    let mut total_size = 0;
+    let mut results = Vec::new();
+    for path in packs {
+        let size = std::fs::metadata(&path)?.len();
+        total_size += size;
+
+        let config = File::open(&path)
+            .ok()
+            .and_then(|file| ZipArchive::new(file).ok())
+            .and_then(|mut archive| extract_config(&mut archive).ok());
+        let description = config
+            .as_ref()
+            .map(|config| format!("{} {}", config.id, config.version))
+            .unwrap_or("invalid pack".to_string());
+        let sha256 = path.file_stem().unwrap_or_default().to_string_lossy();
+        results.push(serde_json::json!({
+            "sha256": sha256,
+            "id": config.as_ref().map(|c| &c.id),
+            "version": config.as_ref().map(|c| &c.version),
+            "size": size,
+        }));
+
+        message!("{sha256}\t{description}\t{} MB", size / 1024 / 1024);
+    }
+    message!("Total: {} MB", total_size / 1024 / 1024);
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+pub fn cache_clean(settings: &AppPackLocalSettings) -> Result<()> {
+    let packs = cached_packs(settings)?;
+    for path in packs.iter() {
+        remove_from_cache(path);
+    }
+    message!("Removed {} packs from the cache", packs.len());
+
+    Ok(())
+}
+
+pub fn cache_path(settings: &AppPackLocalSettings) -> Result<()> {
+    message!("{}", settings.cache_dir.display());
+
+    Ok(())
+}
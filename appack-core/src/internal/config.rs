@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::AppCapability;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_config::{
+    AppPackAppConfig, CpuPinning, CpuTopology, MonitorSelection, NetworkPolicy, PerformanceProfile,
+    RtcBase, SharedFolder, UrlPolicy, UsbDevice,
+};
+use crate::types::config::{AppPackConfig, CONFIG_KEYS, check_config_key};
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::rate_limit::parse_size;
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// A setting of `appack app-config`, `set` stores a value once it is checked, and `get` gives the
+/// value in use
+struct AppConfigKey {
+    name: &'static str,
+    set: fn(&mut AppPackAppConfig, &str) -> Result<()>,
+    unset: fn(&mut AppPackAppConfig),
+    get: fn(&AppPackAppConfig, &AppPackLocalSettings) -> Option<String>,
+}
+
+/// Builds `APP_CONFIG_KEYS` from the field of each key, the function parsing its values, and the
+/// function reading it
+macro_rules! app_config_keys {
+    ($($key:ident: $parse:expr, $get:expr;)*) => {
+        const APP_CONFIG_KEYS: &[AppConfigKey] = &[$(AppConfigKey {
+            name: stringify!($key),
+            set: |config, value| {
+                config.$key = Some(($parse)(value)?);
+                Ok(())
+            },
+            unset: |config| config.$key = None,
+            get: $get,
+        }),*];
+    };
+}
+
+app_config_keys! {
+    channel: parse_channel, |config, _| Some(config.channel().to_string());
+    update_notifications: parse_bool, |config, settings| {
+        Some(config.update_notifications(&settings.config).to_string())
+    };
+    max_image_growth: |value| checked(value, parse_size), |config, _| {
+        config.max_image_growth.clone()
+    };
+    monitors: |value| checked(value, MonitorSelection::parse), |config, _| {
+        config.monitors.clone()
+    };
+    audio: parse_bool, |config, _| Some(config.audio().to_string());
+    microphone: parse_bool, |config, _| {
+        Some(config.allows(AppCapability::Microphone).to_string())
+    };
+    printer: parse_bool, |config, _| Some(config.allows(AppCapability::Printer).to_string());
+    smartcard: parse_bool, |config, _| {
+        Some(config.allows(AppCapability::Smartcard).to_string())
+    };
+    usb_devices: |value| checked(value, UsbDevice::parse_list), |config, _| {
+        config.usb_devices.clone()
+    };
+    shares: |value| checked(value, SharedFolder::parse_list), |config, _| config.shares.clone();
+    performance: PerformanceProfile::parse, |config, _| {
+        Some(config.performance().as_str().to_string())
+    };
+    open_urls: UrlPolicy::parse, |config, _| Some(config.open_urls().as_str().to_string());
+    rtc: RtcBase::parse, |config, _| config.rtc.map(|rtc| rtc.as_str().to_string());
+    time_sync: parse_bool, |config, _| Some(config.time_sync().to_string());
+    host_locale: parse_bool, |config, _| Some(config.host_locale().to_string());
+    host_resolution: parse_bool, |config, _| Some(config.host_resolution().to_string());
+    cpu_pinning: |value| checked(value, CpuPinning::parse), |config, _| {
+        config.cpu_pinning.clone()
+    };
+    cpu_topology: |value| checked(value, CpuTopology::parse), |config, _| {
+        config.cpu_topology.clone()
+    };
+    hugepages: parse_bool, |config, _| Some(config.hugepages().to_string());
+    network_allow: |value| checked(value, NetworkPolicy::parse), |config, _| {
+        config.network_allow.clone()
+    };
+    log_retention_days: parse_days, |config, _| {
+        config.log_retention_days.map(|days| days.to_string())
+    };
+    keep_snapshots: parse_count, |config, _| config.keep_snapshots.map(|count| count.to_string());
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Invalid value '{value}', expected true or false"))
+}
+
+fn parse_channel(value: &str) -> Result<String> {
+    if value.is_empty() || !AppBuildConfig::is_valid_version(value) {
+        return Err(anyhow!("Invalid channel name '{value}'"));
+    }
+
+    Ok(value.to_string())
+}
+
+fn parse_days(value: &str) -> Result<u32> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Invalid value '{value}', expected a number of days"))
+}
+
+fn parse_count(value: &str) -> Result<u32> {
+    value
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| anyhow!("Invalid value '{value}', expected 1 or more"))
+}
+
+/// Keeps a value as it was written, once `parse` accepts it
+fn checked<T>(value: &str, parse: fn(&str) -> Result<T>) -> Result<String> {
+    parse(value)?;
+    Ok(value.to_string())
+}
+
+fn app_config_key(key: &str) -> Result<&'static AppConfigKey> {
+    APP_CONFIG_KEYS
+        .iter()
+        .find(|config_key| config_key.name == key)
+        .ok_or_else(|| {
+            let keys: Vec<&str> = APP_CONFIG_KEYS.iter().map(|key| key.name).collect();
+            anyhow!(
+                "Unknown setting '{key}', expected one of: {}",
+                keys.join(", ")
+            )
+        })
+}
+
+pub fn app_config_set(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    key: &str,
+    value: &str,
+) -> Result<()> {
+    let config_key = app_config_key(key)?;
+
+    let mut configs = settings.get_app_configs()?;
+    let config = configs.apps.entry(id.to_string()).or_default();
+    (config_key.set)(config, value)?;
+    settings.save_app_configs(configs)?;
+
+    message!("{id}: {key} set to {value}");
+
+    Ok(())
+}
+
+pub fn app_config_unset(settings: &AppPackLocalSettings, id: &str, key: &str) -> Result<()> {
+    let config_key = app_config_key(key)?;
+
+    let mut configs = settings.get_app_configs()?;
+    if let Some(config) = configs.apps.get_mut(id) {
+        (config_key.unset)(config);
+    }
+    settings.save_app_configs(configs)?;
+
+    Ok(())
+}
+
+/// Prints one setting of an app, or all of them
+pub fn app_config_get(settings: &AppPackLocalSettings, id: &str, key: Option<&str>) -> Result<()> {
+    if let Some(key) = key {
+        app_config_key(key)?;
+    }
+
+    let config = settings.get_app_config(id)?;
+    let mut values = BTreeMap::new();
+    for config_key in APP_CONFIG_KEYS {
+        if key.is_some_and(|k| k != config_key.name) {
+            continue;
+        }
+
+        let value = (config_key.get)(&config, settings);
+        message!(
+            "{}: {}",
+            config_key.name,
+            value.as_deref().unwrap_or("(not set)")
+        );
+        values.insert(config_key.name, value);
+    }
+
+    set_json_result(&values)
+}
+
+fn env_override(key: &str) -> Option<String> {
+    let variable = format!("APPACK_{}", key.to_uppercase());
+    std::env::var(&variable).ok().map(|_| variable)
+}
+
+/// Prints one global setting, or all of them
+pub fn config_get(settings: &AppPackLocalSettings, key: Option<&str>) -> Result<()> {
+    if let Some(key) = key {
+        check_config_key(key)?;
+    }
+
+    let mut values = BTreeMap::new();
+    for config_key in CONFIG_KEYS {
+        if key.is_some_and(|k| k != config_key) {
+            continue;
+        }
+
+        let value = settings.config.get(config_key)?;
+        let shown = value.as_deref().unwrap_or("(not set)");
+        match env_override(config_key) {
+            Some(variable) => message!("{config_key}: {shown} (from {variable})"),
+            None => message!("{config_key}: {shown}"),
+        }
+        values.insert(config_key, value);
+    }
+
+    set_json_result(&values)
+}
+
+pub fn config_set(settings: &AppPackLocalSettings, key: &str, value: &str) -> Result<()> {
+    // The environment overrides must not end up in the file
+    let mut config = AppPackConfig::new(&settings.config_file)?;
+    config.set(key, value)?;
+    config.save(&settings.config_file)?;
+
+    message!("{key} set to {value}");
+    if let Some(variable) = env_override(key) {
+        message!("Warning: {variable} is set and takes precedence over the configuration file");
+    }
+
+    Ok(())
+}
+
+pub fn config_unset(settings: &AppPackLocalSettings, key: &str) -> Result<()> {
+    let mut config = AppPackConfig::new(&settings.config_file)?;
+    config.unset(key)?;
+    config.save(&settings.config_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_config_keys() {
+        let mut names: Vec<&str> = APP_CONFIG_KEYS.iter().map(|key| key.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), APP_CONFIG_KEYS.len());
+
+        let mut config = AppPackAppConfig::default();
+        let audio = app_config_key("audio").unwrap();
+        assert!((audio.set)(&mut config, "yes").is_err());
+        (audio.set)(&mut config, "false").unwrap();
+        assert_eq!(config.audio, Some(false));
+        (audio.unset)(&mut config);
+        assert_eq!(config.audio, None);
+
+        let keep_snapshots = app_config_key("keep_snapshots").unwrap();
+        assert!((keep_snapshots.set)(&mut config, "0").is_err());
+        (keep_snapshots.set)(&mut config, "3").unwrap();
+        assert_eq!(config.keep_snapshots, Some(3));
+        assert!(app_config_key("nope").is_err());
+    }
+}
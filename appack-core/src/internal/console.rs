@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem::ManuallyDrop;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::net::UnixStream;
+
+/// Socket of the `org.appack.console` virtio console, in the app home
+pub(crate) const CONSOLE_SOCKET: &str = "console.sock";
+
+/// Ctrl+], detaches from the console like telnet and virsh
+const DETACH_KEY: u8 = 0x1d;
+
+/// Terminal in raw mode, so keys go to the guest as they are typed. Restored when dropped.
+struct RawTerminal {
+    original: Option<libc::termios>,
+}
+
+impl RawTerminal {
+    fn enable() -> Self {
+        // SAFETY: tcgetattr and tcsetattr only read and write the given struct
+        let original = unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                // Not a terminal, e.g. piped input
+                return Self { original: None };
+            }
+            let original = termios;
+            libc::cfmakeraw(&mut termios);
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            original
+        };
+
+        Self {
+            original: Some(original),
+        }
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if let Some(original) = &self.original {
+            // SAFETY: restores the settings read by tcgetattr
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+            }
+        }
+    }
+}
+
+/// Attaches the terminal to the serial console of a running app, until Ctrl+] is pressed
+/// or the app stops. Reaches the guest when RDP doesn't.
+pub fn console(settings: &AppPackLocalSettings, id: &str, version: Option<&str>) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    running_rdp_port(settings, &app)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("{id} is not running")))?;
+
+    let mut stream = UnixStream::connect(settings.get_app_home_dir(&app).join(CONSOLE_SOCKET))
+        .map_err(|_| {
+            ErrorKind::NotFound.error(format!(
+                "{id} has no serial console, its pack was built without the org.appack.console port"
+            ))
+        })?;
+    println!(
+        "Connected to the console of {}, press Ctrl+] to detach",
+        app.name
+    );
+
+    let raw_terminal = RawTerminal::enable();
+    // Unbuffered, data left in the buffer of `Stdin` would be missed by poll
+    // SAFETY: stdin stays open, ManuallyDrop keeps it from being closed
+    let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(libc::STDIN_FILENO) });
+    let mut stdout = std::io::stdout().lock();
+    let mut fds = [
+        libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: stream.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+    let mut buffer = [0u8; 4096];
+    loop {
+        // SAFETY: the fds outlive the call
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(anyhow!(error).context("Failed to wait for the console"));
+        }
+
+        if fds[1].revents != 0 {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            stdout.write_all(&buffer[..read])?;
+            stdout.flush()?;
+        }
+        if fds[0].revents != 0 {
+            let read = stdin.read(&mut buffer)?;
+            let input = &buffer[..read];
+            let detach = input.iter().position(|byte| *byte == DETACH_KEY);
+            stream.write_all(&input[..detach.unwrap_or(read)])?;
+            if read == 0 || detach.is_some() {
+                break;
+            }
+        }
+    }
+
+    drop(raw_terminal);
+    println!("\nDetached from the console of {}", app.name);
+
+    Ok(())
+}
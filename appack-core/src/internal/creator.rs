@@ -0,0 +1,690 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::helpers::get_os_assigned_port;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::{
+    AppDesktopEntry, AppImageOptions, AppSnapshotTriggerMode, CreatorTemplate, GuestLocale,
+};
+use crate::utils::frontend::{frontend, message};
+use crate::utils::qmp::{
+    JobProgress, delete_snapshot_blocking, has_snapshot_qmp, take_snapshot_with_progress,
+};
+use crate::utils::checksum::sha256_file;
+use crate::utils::download::download_file_resumable;
+use crate::utils::host_locale::{host_locale, host_timezone, windows_timezone};
+use crate::utils::keyboard;
+use crate::utils::sandbox;
+use crate::utils::zip_dir::zip_dir;
+use anyhow::{Context, Result, anyhow};
+use qapi::{Qmp, qmp};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::utils::xdg_session_type_detector::get_freerdp_executable;
+
+/// Cloud-init files served to the VM at its first boot, see `serve_cloud_init`
+const CLOUD_INIT_FILES: [&str; 4] = ["meta-data", "user-data", "vendor-data", "network-config"];
+
+fn qemu_img(args: &[&OsStr]) -> Result<()> {
+    let status = sandbox::command("qemu-img")
+        .args(args)
+        .status()
+        .context("Failed to run qemu-img")?;
+    if !status.success() {
+        return Err(anyhow!("qemu-img failed to create the disk image"));
+    }
+
+    Ok(())
+}
+
+/// Creates an empty disk image, or one starting as the `base` image, downloaded if it is a URL
+fn create_image(path: &Path, options: &AppImageOptions) -> Result<()> {
+    sandbox::require("qemu-img")?;
+    let qcow2_options = options.qemu_img_options();
+    let mut args: Vec<&OsStr> = Vec::new();
+    let Some(base) = &options.base else {
+        args.extend(["create", "-f", "qcow2"].map(OsStr::new));
+        if let Some(qcow2_options) = &qcow2_options {
+            args.extend([OsStr::new("-o"), OsStr::new(qcow2_options)]);
+        }
+        args.extend([path.as_os_str(), OsStr::new(&options.size)]);
+        return qemu_img(&args);
+    };
+
+    let download = path.with_extension("base");
+    let base_path = if base.contains("://") {
+        message!("Downloading {base}..");
+        download_file_resumable(base, &download, None, None)?;
+        download.as_path()
+    } else {
+        Path::new(base)
+    };
+    args.extend(["convert", "-O", "qcow2"].map(OsStr::new));
+    if let Some(qcow2_options) = &qcow2_options {
+        args.extend([OsStr::new("-o"), OsStr::new(qcow2_options)]);
+    }
+    args.extend([base_path.as_os_str(), path.as_os_str()]);
+    qemu_img(&args)?;
+    let _ = std::fs::remove_file(&download);
+
+    qemu_img(&[
+        OsStr::new("resize"),
+        path.as_os_str(),
+        OsStr::new(&options.size),
+    ])
+}
+
+/// Answers the HTTP requests of cloud-init for the files of a folder, from the VM started by
+/// `creator boot-install`. Returns the port to give to the VM.
+fn serve_cloud_init(dir: PathBuf) -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("Failed to serve cloud-init")?;
+    let port = listener.local_addr()?.port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = answer_cloud_init(&dir, stream) {
+                tracing::warn!("Failed to answer cloud-init: {e:?}");
+            }
+        }
+    });
+
+    Ok(port)
+}
+
+fn answer_cloud_init(dir: &Path, stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers are not needed
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let name = request
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .trim_start_matches('/');
+    let content = CLOUD_INIT_FILES
+        .contains(&name)
+        .then(|| std::fs::read(dir.join(name)).ok())
+        .flatten();
+    tracing::info!(file = name, found = content.is_some(), "cloud-init request");
+
+    let mut stream = &stream;
+    match content {
+        Some(content) => {
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n",
+                content.len()
+            )?;
+            stream.write_all(&content)?;
+        }
+        None => write!(stream, "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n")?,
+    }
+
+    Ok(())
+}
+
+// TODO: rewrite the logic, we shouldn't ever run that, we're in a snap though
+fn get_xfreerdp3_pids() -> Result<String> {
+    let freerdp_exec = get_freerdp_executable();
+    let shell_cmd = format!(
+        "ps aux | grep {} | grep -v grep | awk '{{print $2}}'",
+        freerdp_exec
+    );
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(shell_cmd)
+        .output()
+        .context("Failed to get FreeRDP pids")?;
+
+    if !output.status.success() {
+        return Err(anyhow!("PID finding command failed"));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn terminate_xfreerdp3() -> Result<()> {
+    // Return the PIDs as a single string (e.g., "1234 5678 9012")
+    let pids_string = get_xfreerdp3_pids()?;
+
+    if pids_string.is_empty() {
+        return Ok(());
+    }
+
+    let pids: Vec<&str> = pids_string.split_whitespace().collect();
+
+    let mut command = Command::new("kill");
+    command.arg("-TERM");
+    command.args(pids);
+    tracing::debug!("Executing: kill -TERM {pids_string}");
+
+    match command.status() {
+        Ok(status) => {
+            if !status.success() {
+                tracing::error!(%status, "'kill' command failed");
+            }
+        }
+        Err(e) => return Err(anyhow!("Failed to execute 'kill' command: {}", e)),
+    }
+
+    while let Ok(pids) = get_xfreerdp3_pids() {
+        if pids.is_empty() {
+            break;
+        }
+
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    Ok(())
+}
+
+fn zip_appack(config: &AppBuildConfig) -> Result<()> {
+    let zip_name = format!("{}_{}.zip", config.id, config.version);
+    let zip_file = std::fs::File::create(zip_name).context("Failed to create zip file")?;
+    let mut zip = ZipWriter::new(zip_file);
+
+    let zip_options = SimpleFileOptions::default()
+        .large_file(true)
+        .compression_method(CompressionMethod::Zstd)
+        // .compression_level(Some(9))
+        .unix_permissions(0o755);
+
+    // Add readme folder
+    for (language, locale) in &config.locales {
+        if let Some(readme) = &locale.readme
+            && !Path::new(&config.readme.folder).join(readme).is_file()
+        {
+            return Err(anyhow!(
+                "Readme '{readme}' of language '{language}' not found in {}",
+                config.readme.folder
+            ));
+        }
+    }
+    zip_dir(&mut zip, &zip_options, Path::new(&config.readme.folder))?;
+
+    // Does not copy the desktop entries
+    let mut installed_appack_entry = InstalledAppPackEntry::from(config.clone());
+
+    // Add desktop entries
+    if let Some(entries) = &config.desktop_entries {
+        installed_appack_entry.desktop_entries = Some(Vec::new());
+
+        for entry in entries {
+            let entry_path = Path::new(&entry.entry);
+            let entry_file_name = entry_path.file_name().ok_or_else(|| {
+                anyhow!("Could not get file name of desktop entry {entry_path:?}")
+            })?;
+
+            let entry_icon_path = Path::new(&entry.icon);
+            let entry_icon_name = entry_icon_path.file_name().ok_or_else(|| {
+                anyhow!("Could not get icon name of desktop entry {entry_icon_path:?}")
+            })?;
+
+            let mut f1 = std::fs::File::open(&entry.entry)
+                .context(format!("Failed to open entry {entry_path:?}"))?;
+            let file_in_zip = format!("desktop/{}", entry_file_name.display());
+            zip.start_file(&file_in_zip, zip_options)
+                .context(format!("Failed to start zip entry {file_in_zip}"))?;
+            std::io::copy(&mut f1, &mut zip)
+                .context(format!("Failed to copy to archive {file_in_zip}"))?;
+
+            let mut f1 = std::fs::File::open(&entry.icon)
+                .context(format!("Failed to open icon {entry_icon_path:?}"))?;
+            let file_in_zip = format!("desktop/{}", entry_icon_name.display());
+
+            // Same icon may be reused for multiple entries
+            match zip.start_file(&file_in_zip, zip_options) {
+                Ok(_) => {
+                    std::io::copy(&mut f1, &mut zip)
+                        .context(format!("Failed to copy to archive {file_in_zip}"))?;
+                }
+                Err(e) => {
+                    message!("Failed to start icon zip entry {file_in_zip}: {}", e);
+                    message!("This can be intentional, skipping.")
+                }
+            };
+
+            let installed_desktop_entry = AppDesktopEntry {
+                entry: entry_file_name.to_string_lossy().to_string(),
+                icon: entry_icon_name.to_string_lossy().to_string(),
+                rdp_args: entry.rdp_args.clone(),
+                remote_app: entry.remote_app.clone(),
+                user: entry.user.clone(),
+            };
+
+            installed_appack_entry
+                .desktop_entries
+                .as_mut()
+                .unwrap()
+                .push(installed_desktop_entry);
+            message!("Added {entry_file_name:?} to package");
+        }
+    }
+
+    // Lets installs of the same image share it
+    message!("Computing the checksum of the image..");
+    installed_appack_entry.image_sha256 = Some(sha256_file(Path::new(&config.image))?);
+
+    let installed_entry_str = serde_yaml::to_string(&installed_appack_entry)?;
+    zip.start_file("AppPack.yaml", zip_options)
+        .context("Failed to start file AppPack")?;
+    zip.write_all(installed_entry_str.as_bytes())
+        .context("Failed to write AppPack.yaml to zip")?;
+
+    // Add image
+    message!("Adding image file to package. This will take a while.");
+    zip.start_file("image.qcow2", zip_options)
+        .context("Failed to start image.qcow2".to_string())?;
+    let mut f1 = std::fs::File::open(&config.image)
+        .context(format!("Failed to open image file {}", config.image))?;
+    std::io::copy(&mut f1, &mut zip)
+        .context(format!("Failed to copy to archive file {}", config.image))?;
+    message!("Added \"image.qcow2\" to package");
+
+    zip.finish().context("Failed to finish zip")?;
+
+    Ok(())
+}
+
+/// Regional settings of the Windows of a new pack, the ones of this machine when not given
+#[derive(Debug, Default)]
+pub struct UnattendOptions {
+    pub locale: Option<String>,
+    pub keyboard: Option<String>,
+    pub timezone: Option<String>,
+}
+
+impl UnattendOptions {
+    fn is_empty(&self) -> bool {
+        self.locale.is_none() && self.keyboard.is_none() && self.timezone.is_none()
+    }
+
+    fn guest_locale(self) -> Result<GuestLocale> {
+        let locale = self
+            .locale
+            .or_else(host_locale)
+            .unwrap_or_else(|| "en-US".to_string());
+        let valid = locale.split_once('-').is_some_and(|(language, region)| {
+            [language, region]
+                .iter()
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()))
+        });
+        if !valid {
+            return Err(anyhow!(
+                "Invalid locale '{locale}', expected a language tag like fr-FR"
+            ));
+        }
+
+        // Settings of this machine Windows doesn't know are left out. Only the first of
+        // several layouts is used.
+        let keyboard = self
+            .keyboard
+            .or_else(|| keyboard::host_layout().filter(|l| keyboard::layout_id(l).is_ok()))
+            .map(|layout| layout.split(',').next().unwrap_or_default().trim().to_string())
+            .unwrap_or_else(|| "us".to_string());
+        keyboard::layout_id(&keyboard)?;
+
+        let timezone = self
+            .timezone
+            .or_else(|| host_timezone().filter(|tz| windows_timezone(tz).is_some()))
+            .unwrap_or_else(|| "UTC".to_string());
+        unattend_timezone(&timezone)?;
+
+        Ok(GuestLocale {
+            locale,
+            keyboard,
+            timezone,
+        })
+    }
+}
+
+/// Windows name of a timezone given by its IANA name, or already by its Windows name
+fn unattend_timezone(timezone: &str) -> Result<&str> {
+    if let Some(windows) = windows_timezone(timezone) {
+        return Ok(windows);
+    }
+    if timezone.ends_with("Standard Time") {
+        return Ok(timezone);
+    }
+
+    Err(anyhow!(
+        "Unknown timezone '{timezone}', use its Windows name instead, \
+         like \"Romance Standard Time\""
+    ))
+}
+
+/// `autounattend.xml` answering the regional questions of Windows Setup
+fn autounattend(template: &str, guest: &GuestLocale) -> Result<String> {
+    let layout = keyboard::layout_id(&guest.keyboard)?;
+    // Language of the layout, then the layout, e.g. 040c:0000040c
+    let input_locale = format!("{:04x}:{layout:08x}", layout & 0xFFFF);
+
+    Ok(template
+        .replace("$INPUT_LOCALE", &input_locale)
+        .replace("$LOCALE", &guest.locale)
+        .replace("$TIMEZONE", unattend_timezone(&guest.timezone)?))
+}
+
+fn scaffold_windows(assets_path: &Path, guest: &GuestLocale) -> Result<()> {
+    std::fs::create_dir("AppPack/guest").context("Failed to create guest directory")?;
+    std::fs::create_dir("AppPack/unattend").context("Failed to create unattend directory")?;
+
+    std::fs::copy(
+        assets_path.join("creator").join("README.md"),
+        "AppPack/readme/README.md",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("AppPackBuildConfig.yaml"),
+        "AppPack/AppPackBuildConfig.yaml",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("ms-cmd.desktop"),
+        "AppPack/desktop/ms-cmd.desktop",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("plain-rdp.desktop"),
+        "AppPack/desktop/plain-rdp.desktop",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("ms-cmd.svg"),
+        "AppPack/desktop/ms-cmd.svg",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("appack-open-url.ps1"),
+        "AppPack/guest/appack-open-url.ps1",
+    )?;
+    std::fs::copy(
+        assets_path.join("creator").join("appack-locale.ps1"),
+        "AppPack/guest/appack-locale.ps1",
+    )?;
+
+    let template = std::fs::read_to_string(assets_path.join("creator").join("autounattend.xml"))
+        .context("Failed to read autounattend.xml")?;
+    std::fs::write("AppPack/unattend/autounattend.xml", autounattend(&template, guest)?)
+        .context("Failed to write autounattend.xml")?;
+
+    // Installers of other regions are warned
+    let mut config = OpenOptions::new()
+        .append(true)
+        .open("AppPack/AppPackBuildConfig.yaml")?;
+    writeln!(config, "# Regional settings of unattend/autounattend.xml")?;
+    let guest_locale = serde_yaml::to_string(&BTreeMap::from([("guest_locale", guest)]))?;
+    config.write_all(guest_locale.as_bytes())?;
+
+    Ok(())
+}
+
+fn scaffold_linux_xrdp(assets_path: &Path) -> Result<()> {
+    let template = assets_path.join("creator").join("linux-xrdp");
+    std::fs::create_dir("AppPack/cloud-init").context("Failed to create cloud-init directory")?;
+    for (file, destination) in [
+        ("README.md", "readme/README.md"),
+        ("AppPackBuildConfig.yaml", "AppPackBuildConfig.yaml"),
+        ("linux-mousepad.desktop", "desktop/linux-mousepad.desktop"),
+        ("linux-mousepad.svg", "desktop/linux-mousepad.svg"),
+        ("user-data", "cloud-init/user-data"),
+        ("meta-data", "cloud-init/meta-data"),
+    ] {
+        std::fs::copy(template.join(file), Path::new("AppPack").join(destination))
+            .context(format!("Failed to copy {file}"))?;
+    }
+
+    Ok(())
+}
+
+pub fn creator_new(template: CreatorTemplate, unattend: UnattendOptions) -> Result<()> {
+    let guest_locale = match template {
+        CreatorTemplate::Windows => Some(unattend.guest_locale()?),
+        _ if !unattend.is_empty() => {
+            return Err(anyhow!(
+                "The locale, keyboard and timezone are only set by the windows template"
+            ));
+        }
+        _ => None,
+    };
+
+    let assets_path = sandbox::assets_dir()?;
+    std::fs::create_dir("AppPack").context("Failed to create AppPack directory")?;
+    std::fs::create_dir("AppPack/readme").context("Failed to create readme directory")?;
+    std::fs::create_dir("AppPack/desktop").context("Failed to create desktop directory")?;
+    match guest_locale {
+        Some(guest_locale) => scaffold_windows(&assets_path, &guest_locale)?,
+        None => scaffold_linux_xrdp(&assets_path)?,
+    }
+
+    let config = AppBuildConfig::new(Path::new("AppPack/AppPackBuildConfig.yaml"))?;
+    create_image(Path::new("AppPack/image.qcow2"), &config.image_options)?;
+
+    Ok(())
+}
+
+/// Creates the disk image of the pack with the `image_options` of its manifest, e.g. after
+/// changing them and removing the image made by `creator new`
+pub fn creator_image() -> Result<()> {
+    let config = AppBuildConfig::new(Path::new("AppPackBuildConfig.yaml"))?;
+    let image = Path::new(&config.image);
+    if image.exists() {
+        return Err(anyhow!(
+            "{} already exists, remove it first to create it again",
+            image.display()
+        ));
+    }
+
+    create_image(image, &config.image_options)?;
+    message!("Created {}", image.display());
+
+    Ok(())
+}
+
+pub fn creator_boot_install() -> Result<()> {
+    let config = AppBuildConfig::new(Path::new("AppPackBuildConfig.yaml"))?;
+
+    // The first boot of cloud images sets them up with the files of the cloud-init folder
+    let cloud_init_port = if config.install_append.contains("$CLOUD_INIT_URL") {
+        Some(serve_cloud_init(PathBuf::from("cloud-init"))?)
+    } else {
+        None
+    };
+    let mut command = config.get_boot_install_command(cloud_init_port)?;
+
+    command.status()?;
+
+    Ok(())
+}
+
+pub fn creator_boot() -> Result<()> {
+    let config = AppBuildConfig::new(Path::new("AppPackBuildConfig.yaml"))?;
+    let free_port = get_os_assigned_port()?;
+
+    let mut qemu_command = config.get_boot_configure_command(free_port)?;
+    let mut qemu_child = qemu_command.spawn()?;
+
+    // Wait for qmp socket to be available
+    let qmp_socket_path = Path::new("qmp-appack.sock");
+    loop {
+        match qemu_child.try_wait() {
+            // 1. Ok(None): Child is STILL RUNNING
+            Ok(None) => {
+                match UnixStream::connect(qmp_socket_path) {
+                    Ok(_) => {
+                        break;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Waiting for QMP socket connection: {e}");
+                        thread::sleep(Duration::from_millis(200));
+                    }
+                };
+            }
+
+            // 2. Ok(Some(status)): Child has EXITED
+            Ok(Some(status)) => {
+                tracing::error!(%status, "QEMU process unexpectedly exited");
+                return Err(anyhow!("QEMU process died before QMP socket was ready.")
+                    .context("Qemu failed to start. Make sure you installed AppPack with the command on the Readme (with the appropriate connections)."));
+            }
+
+            // 3. Err(e): An error occurred while trying to check the status
+            Err(e) => {
+                return Err(anyhow!(e).context("Error while checking QEMU status"));
+            }
+        }
+    }
+
+    tracing::debug!("QMP socket is ready");
+
+    let mut rdp_command = config.get_rdp_configure_command(free_port);
+
+    match rdp_command.status() {
+        Ok(status) => {
+            if status.success() {
+                message!("RDP was successful");
+            } else {
+                return Err(anyhow!("RDP failed with status: {status:?}"));
+            }
+        }
+        Err(e) => {
+            return Err(anyhow!("RDP process failed with error: {e:?}"));
+        }
+    }
+
+    qemu_child.wait()?;
+    message!("Qemu exited");
+
+    Ok(())
+}
+
+/// Prints the progress of a snapshot on a single line
+fn print_snapshot_progress(progress: JobProgress) {
+    let percent = progress.current * 100 / progress.total.max(1);
+    frontend().progress(&format!("Saving the snapshot.. {percent}%"));
+}
+
+// For now we will take a snapshot of the disk and memory and this is what will be shipped.
+// It is probably possible to optimize this further.
+pub fn creator_snapshot() -> Result<()> {
+    // We read the config first to validate its contents before proceeding with the snapshot
+    let config = AppBuildConfig::new(Path::new("AppPackBuildConfig.yaml"))?;
+    let socket_addr = "./qmp-appack.sock";
+    let stream = UnixStream::connect(socket_addr).context("Failed to connect to QMP socket")?;
+    let mut qmp = Qmp::from_stream(&stream);
+
+    qmp.handshake().context("Failed to handshake with QMP")?;
+
+    match has_snapshot_qmp(&mut qmp, "appack-init") {
+        Ok(true) => {
+            return Err(anyhow!(
+                "Snapshot 'appack-init' already exists. Please delete it first."
+            ));
+        }
+        Err(e) => return Err(e),
+        _ => {}
+    }
+
+    match has_snapshot_qmp(&mut qmp, "appack-onclose") {
+        Ok(true) => {
+            return Err(anyhow!(
+                "Snapshot 'appack-onclose' already exists. Please delete it first."
+            ));
+        }
+        Err(e) => return Err(e),
+        _ => {}
+    }
+
+    // 1. Close RDP connections (ctrl+c on xfreerdp?)
+    terminate_xfreerdp3()?;
+
+    // 2. Pause VM
+    qmp.execute(&qmp::stop {}).context("Failed to stop VM")?;
+
+    // 3. Take a snapshot (internal)
+    match config.snapshot {
+        AppSnapshotTriggerMode::OnClose | AppSnapshotTriggerMode::Never => {
+            take_snapshot_with_progress(&mut qmp, "appack-init", &mut print_snapshot_progress)?;
+        }
+        AppSnapshotTriggerMode::NeverLoad => {}
+    }
+
+    // 4. Destroy the VM. Why do this gracefully?
+    qmp.execute(&qmp::quit {}).context("Failed to quit QMP")?;
+
+    // 5. Zip files
+    match zip_appack(&config) {
+        Ok(_) => message!("AppPack created successfully"),
+        Err(e) => {
+            delete_snapshot_blocking(&mut qmp, "appack-init")?;
+            message!("Snapshot deleted. You can safely retry.");
+
+            let zip_name = format!("{}_{}.zip", config.id, config.version);
+            let _ = std::fs::remove_file(zip_name); // Ignore error
+
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn creator_pack() -> Result<()> {
+    let config = AppBuildConfig::new(Path::new("AppPackBuildConfig.yaml"))?;
+    match zip_appack(&config) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let zip_name = format!("{}_{}.zip", config.id, config.version);
+            let _ = std::fs::remove_file(zip_name); // Ignore error
+
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autounattend() {
+        let guest = GuestLocale {
+            locale: "fr-FR".to_string(),
+            keyboard: "fr".to_string(),
+            timezone: "Europe/Paris".to_string(),
+        };
+        assert_eq!(
+            autounattend("$INPUT_LOCALE $LOCALE $TIMEZONE", &guest).unwrap(),
+            "040c:0000040c fr-FR Romance Standard Time"
+        );
+
+        assert_eq!(unattend_timezone("UTC").unwrap(), "UTC");
+        assert_eq!(
+            unattend_timezone("Tokyo Standard Time").unwrap(),
+            "Tokyo Standard Time"
+        );
+        assert!(unattend_timezone("Mars/Olympus_Mons").is_err());
+    }
+}
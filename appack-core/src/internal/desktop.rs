@@ -0,0 +1,343 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::cache::find_cached_pack;
+use crate::internal::install_appack::{
+    entry_launch_command, install_themed_icon, process_desktop_entry, set_trust_desktop_entries,
+    themed_icon_name, themed_icon_path,
+};
+use crate::types::AppDesktopEntry;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::{AppPackLocalSettings, SYSTEM_FILE_PREFIX};
+use crate::utils::atomic_write::write_atomic;
+use crate::utils::frontend::{message, warning};
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use zip::ZipArchive;
+
+/// Finds the desktop entry of a pack as it was before installation: the copy kept in the app
+/// directory, the cached pack, or the installed entry with its generated lines reset
+fn read_template(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    desktop_entry: &AppDesktopEntry,
+) -> Result<String> {
+    let kept = settings
+        .get_app_files_dir(app)
+        .join("desktop")
+        .join(&desktop_entry.entry);
+    if let Ok(template) = std::fs::read_to_string(&kept) {
+        return Ok(template);
+    }
+
+    if let Some(pack) = find_cached_pack(settings, &app.id, Some(&app.version)) {
+        let mut archive = ZipArchive::new(File::open(&pack)?)?;
+        let mut template = String::new();
+        archive
+            .by_name(&format!("desktop/{}", desktop_entry.entry))?
+            .read_to_string(&mut template)?;
+        return Ok(template);
+    }
+
+    let installed = std::fs::read_to_string(settings.get_desktop_entry_path(app, desktop_entry))
+        .map_err(|_| anyhow!("No copy of the desktop entry found, install the app again"))?;
+    let template: Vec<&str> = installed
+        .lines()
+        .filter(|line| !line.starts_with("StartupWMClass"))
+        .map(|line| {
+            if line.starts_with("Exec=") {
+                "Exec=$APPACK_LAUNCH_CMD"
+            } else if line.starts_with("Icon=") {
+                "Icon=$ICON_FULL_PATH"
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    Ok(template.join("\n") + "\n")
+}
+
+fn regenerate_entry(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    desktop_entry: &AppDesktopEntry,
+) -> Result<()> {
+    let template = read_template(settings, app, desktop_entry)?;
+
+    let icon = std::fs::read(
+        settings
+            .get_app_files_dir(app)
+            .join("desktop")
+            .join(&desktop_entry.icon),
+    )
+    .unwrap_or_default();
+    let themed_icon = themed_icon_path(settings, app, desktop_entry, &icon);
+
+    let contents = process_desktop_entry(
+        &template,
+        desktop_entry,
+        app,
+        settings,
+        themed_icon.as_deref().and_then(themed_icon_name),
+    )?;
+    if let Some(path) = &themed_icon {
+        install_themed_icon(path, &icon)?;
+    }
+
+    let entry_path = settings.get_desktop_entry_path(app, desktop_entry);
+    write_atomic(&entry_path, contents).context(format!("Failed to write {}", entry_path.display()))
+}
+
+/// Value of the `Name` key of a desktop entry
+fn entry_name(contents: &str) -> Option<&str> {
+    contents.lines().find_map(|line| line.strip_prefix("Name="))
+}
+
+/// Whether a user refers to a desktop entry by its file name, with or without `.desktop`, or
+/// by the name shown in the menu
+fn is_entry_named(desktop_entry: &AppDesktopEntry, name: Option<&str>, query: &str) -> bool {
+    let file_name = desktop_entry.entry.as_str();
+    file_name == query
+        || file_name.strip_suffix(".desktop") == Some(query)
+        || name.is_some_and(|name| name.eq_ignore_ascii_case(query))
+}
+
+/// FreeRDP arguments the menu item of a desktop entry launches the app with
+pub fn entry_rdp_args(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    query: &str,
+) -> Result<String> {
+    let mut names = Vec::new();
+    for desktop_entry in app.desktop_entries.iter().flatten() {
+        let template = read_template(settings, app, desktop_entry).unwrap_or_default();
+        let name = entry_name(&template);
+        if is_entry_named(desktop_entry, name, query) {
+            return Ok(app.declared_freerdp_args(&desktop_entry.freerdp_args()));
+        }
+        names.push(name.unwrap_or(&desktop_entry.entry).to_string());
+    }
+
+    Err(ErrorKind::NotFound.error(if names.is_empty() {
+        format!("{} has no desktop entries", app.id)
+    } else {
+        format!(
+            "{} has no desktop entry named '{query}', its entries are: {}",
+            app.id,
+            names.join(", ")
+        )
+    }))
+}
+
+/// Lists the desktop entries of an installed app with the command each one runs, and flags the
+/// ones whose `Exec` line on disk isn't the one AppPack generated
+pub fn list_entries(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+) -> Result<()> {
+    let app = settings.get_app_installed(id, version)?;
+    let desktop_entries = app.desktop_entries.clone().unwrap_or_default();
+    if desktop_entries.is_empty() {
+        message!("{} has no desktop entries", app.name);
+    }
+
+    let mut results = Vec::new();
+    let mut drifted = 0;
+    for desktop_entry in &desktop_entries {
+        let path = settings.get_desktop_entry_path(&app, desktop_entry);
+        let installed = std::fs::read_to_string(&path).ok();
+        let contents = match &installed {
+            Some(contents) => contents.clone(),
+            None => read_template(settings, &app, desktop_entry).unwrap_or_default(),
+        };
+        let rdp_args = app.declared_freerdp_args(&desktop_entry.freerdp_args());
+        let expected = entry_launch_command(&contents, &app, &rdp_args);
+        let exec = installed
+            .as_deref()
+            .and_then(|contents| contents.lines().find_map(|line| line.strip_prefix("Exec=")));
+        let status = match exec {
+            None => "missing",
+            Some(exec) if exec == expected => "ok",
+            Some(_) => "drift",
+        };
+
+        let name = entry_name(&contents).unwrap_or(&desktop_entry.entry);
+        message!("{name} ({})", desktop_entry.entry);
+        message!("  Icon:     {}", desktop_entry.icon);
+        message!("  RDP args: {rdp_args}");
+        match exec {
+            None => message!("  Exec:     not installed, expected {expected}"),
+            Some(exec) => message!("  Exec:     {exec}"),
+        }
+        if status == "drift" {
+            drifted += 1;
+            message!("  Expected: {expected}");
+            message!("  Warning: this Exec line was changed after installation");
+        }
+
+        results.push(json!({
+            "entry": desktop_entry.entry,
+            "name": name,
+            "icon": desktop_entry.icon,
+            "rdp_args": rdp_args,
+            "path": path,
+            "exec": exec,
+            "expected_exec": expected,
+            "status": status,
+        }));
+    }
+    if drifted > 0 {
+        message!(
+            "{drifted} of the entries run another command than expected, \
+             `appack desktop regenerate {}` writes them again",
+            app.id
+        );
+    }
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+/// Writes the desktop entries of an installed app, or of all installed apps, again
+pub fn regenerate_desktop_entries(settings: &AppPackLocalSettings, id: Option<&str>) -> Result<()> {
+    settings.check_ok()?;
+    let installed = settings.get_installed()?;
+    let apps: Vec<&InstalledAppPackEntry> = installed
+        .installed
+        .iter()
+        .filter(|app| id.is_none_or(|id| app.id == id))
+        .collect();
+    if let Some(id) = id
+        && apps.is_empty()
+    {
+        return Err(ErrorKind::NotInstalled.error(format!("AppPack not installed: {id}")));
+    }
+
+    let mut regenerated = Vec::new();
+    for app in apps {
+        for desktop_entry in app.desktop_entries.iter().flatten() {
+            regenerate_entry(settings, app, desktop_entry).context(format!(
+                "Failed to regenerate desktop entry {} of {}",
+                desktop_entry.entry, app.id
+            ))?;
+            regenerated.push(settings.get_desktop_entry_path(app, desktop_entry));
+        }
+    }
+
+    set_json_result(&json!({ "regenerated": regenerated }))?;
+    message!("{} desktop entries regenerated", regenerated.len());
+    tracing::info!(count = regenerated.len(), "Desktop entries regenerated");
+
+    Ok(())
+}
+
+/// Writes the desktop entries of the apps installed system-wide in the menu of the user, and
+/// removes the ones of the apps no longer installed. Run at the login of every user.
+pub fn sync_system_entries(settings: &AppPackLocalSettings) -> Result<()> {
+    if settings.is_system() {
+        return Err(ErrorKind::General.error(
+            "Each user syncs the desktop entries of system-wide apps, run it without --system",
+        ));
+    }
+    settings.check_ok()?;
+    // The administrator reviewed them when installing the apps, and nobody answers at login
+    set_trust_desktop_entries();
+
+    let mut entries = HashSet::new();
+    let mut icons = HashSet::new();
+    let mut written = Vec::new();
+    for app in settings.get_system_installed()?.installed.iter() {
+        for desktop_entry in app.desktop_entries.iter().flatten() {
+            let path = settings.get_desktop_entry_path(app, desktop_entry);
+            entries.insert(path.clone());
+            icons.insert(settings.get_icon_name(app, desktop_entry));
+            match regenerate_entry(settings, app, desktop_entry) {
+                Ok(()) => written.push(path),
+                Err(e) => warning!(
+                    "Failed to write desktop entry {} of {}: {e:?}",
+                    desktop_entry.entry,
+                    app.id
+                ),
+            }
+        }
+    }
+
+    // Entries and icons of the apps no longer installed
+    let mut removed = Vec::new();
+    let icon_prefix = format!("appack_{SYSTEM_FILE_PREFIX}");
+    let icon_dirs = std::fs::read_dir(&settings.icon_theme_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|size_dir| size_dir.path().join("apps"));
+    for dir in [settings.desktop_entries_dir.clone()].into_iter().chain(icon_dirs) {
+        for path in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = path.path();
+            let stale = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if name.starts_with(SYSTEM_FILE_PREFIX) => !entries.contains(&path),
+                Some(name) if name.starts_with(&icon_prefix) => path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| !icons.contains(stem)),
+                _ => false,
+            };
+            if stale {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => removed.push(path),
+                    Err(e) => warning!("Failed to remove {}: {e}", path.display()),
+                }
+            }
+        }
+    }
+
+    set_json_result(&json!({ "written": written, "removed": removed }))?;
+    message!(
+        "{} desktop entries of system-wide apps written, {} files removed",
+        written.len(),
+        removed.len()
+    );
+    tracing::info!(
+        written = written.len(),
+        removed = removed.len(),
+        "Desktop entries of system-wide apps synced"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_entry_named() {
+        let desktop_entry: AppDesktopEntry =
+            serde_yaml::from_str("{entry: word.desktop, icon: word.png}").unwrap();
+        let name = entry_name("[Desktop Entry]\nName=Microsoft Word\nExec=x\n");
+        assert_eq!(name, Some("Microsoft Word"));
+
+        assert!(is_entry_named(&desktop_entry, name, "word.desktop"));
+        assert!(is_entry_named(&desktop_entry, name, "word"));
+        assert!(is_entry_named(&desktop_entry, name, "microsoft word"));
+        assert!(!is_entry_named(&desktop_entry, name, "Excel"));
+    }
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::sandbox;
+use crate::utils::snap;
+use crate::utils::xdg_session_type_detector::get_freerdp_executable;
+use anyhow::Result;
+use serde_json::json;
+use std::path::Path;
+
+/// Names of the checks of `appack doctor`, with how to fix the failed ones
+pub(crate) fn doctor_checks(settings: &AppPackLocalSettings) -> Vec<(String, Result<(), String>)> {
+    let mut checks: Vec<(String, Result<(), String>)> = Vec::new();
+
+    for plug in snap::missing_plugs() {
+        checks.push((
+            format!("Plug {plug}"),
+            Err(format!(
+                "not connected, run `{}`",
+                snap::connect_command(plug)
+            )),
+        ));
+    }
+    checks.push((
+        "KVM".to_string(),
+        if Path::new("/dev/kvm").exists() {
+            Ok(())
+        } else {
+            Err("/dev/kvm not found, enable virtualization in your BIOS".to_string())
+        },
+    ));
+
+    let freerdp = settings
+        .config
+        .rdp_client
+        .as_deref()
+        .unwrap_or(get_freerdp_executable());
+    let qemu = format!("qemu-system-{}", std::env::consts::ARCH);
+    for program in [qemu.as_str(), "qemu-img", freerdp] {
+        let found = sandbox::require(program).map_err(|e| e.to_string());
+        checks.push((program.to_string(), found));
+    }
+
+    checks
+}
+
+/// Checks AppPack can run apps on this machine, and tells how to fix what is missing
+pub fn doctor(settings: &AppPackLocalSettings) -> Result<()> {
+    let checks = doctor_checks(settings);
+    let failed = checks.iter().filter(|(_, check)| check.is_err()).count();
+    set_json_result(&json!({
+        "checks": checks
+            .iter()
+            .map(|(name, check)| json!({ "name": name, "error": check.as_ref().err() }))
+            .collect::<Vec<_>>(),
+    }))?;
+    for (name, check) in &checks {
+        match check {
+            Ok(()) => message!("✓ {name}"),
+            Err(e) => message!("✗ {name}: {e}"),
+        }
+    }
+
+    if failed > 0 {
+        return Err(ErrorKind::EnvironmentMissing.error(format!("{failed} check(s) failed")));
+    }
+
+    Ok(())
+}
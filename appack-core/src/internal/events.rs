@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::{EVENTS_SOCKET, running_rdp_port};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::{emit_event, json_enabled};
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// How often the progress of running jobs, like snapshots, is shown
+const JOB_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `HH:MM:SS` in local time
+fn format_time(seconds: i64) -> String {
+    // SAFETY: localtime_r only writes to the given struct
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&seconds, &mut tm);
+        tm
+    };
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+fn print_event(event: &Value) {
+    let name = event["event"].as_str().unwrap_or_default();
+    let data = event.get("data").cloned().unwrap_or(Value::Null);
+    let seconds = event["timestamp"]["seconds"].as_i64().unwrap_or_default();
+
+    if json_enabled() {
+        emit_event(
+            "qmp_event",
+            json!({ "name": name, "data": data, "timestamp": event["timestamp"] }),
+        );
+    } else if data.is_null() {
+        message!("{} {name}", format_time(seconds));
+    } else {
+        message!("{} {name} {data}", format_time(seconds));
+    }
+}
+
+fn print_job_progress(jobs: &Value, running: &HashSet<String>) {
+    for job in jobs.as_array().into_iter().flatten() {
+        let id = job["id"].as_str().unwrap_or_default();
+        if !running.contains(id) {
+            continue;
+        }
+
+        let current = job["current-progress"].as_u64().unwrap_or_default();
+        let total = job["total-progress"].as_u64().unwrap_or_default().max(1);
+        if json_enabled() {
+            emit_event(
+                "job_progress",
+                json!({ "id": id, "current": current, "total": total }),
+            );
+        } else {
+            message!("Job {id}: {}%", current * 100 / total);
+        }
+    }
+}
+
+/// Prints the QMP events of a running app until it stops, like `SHUTDOWN`, `RESET` or
+/// `BLOCK_IO_ERROR`, with the progress of its jobs
+pub fn events(settings: &AppPackLocalSettings, id: &str, version: Option<&str>) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    running_rdp_port(settings, &app)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("{id} is not running")))?;
+
+    let socket = settings.get_app_home_dir(&app).join(EVENTS_SOCKET);
+    let mut stream = UnixStream::connect(&socket).map_err(|_| {
+        anyhow!(
+            "{id} was launched by an older AppPack version, launch it again to follow its events"
+        )
+    })?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut greeting = String::new();
+    reader.read_line(&mut greeting)?;
+    writeln!(stream, r#"{{"execute": "qmp_capabilities"}}"#)?;
+    stream.set_read_timeout(Some(JOB_PROGRESS_INTERVAL))?;
+    if !json_enabled() {
+        message!("Following the events of {}, press Ctrl+C to stop", app.name);
+    }
+
+    let mut running_jobs = HashSet::new();
+    let mut line = String::new();
+    loop {
+        // A timed out read keeps what it read so far in `line`
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                if !running_jobs.is_empty() {
+                    writeln!(stream, r#"{{"execute": "query-jobs"}}"#)?;
+                }
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to read the QMP events"),
+        }
+
+        let message: Value = serde_json::from_str(&line).unwrap_or_default();
+        line.clear();
+        if message.get("event").is_some() {
+            if message["event"] == "JOB_STATUS_CHANGE" {
+                let job = message["data"]["id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                match message["data"]["status"].as_str() {
+                    Some("running") => running_jobs.insert(job),
+                    _ => running_jobs.remove(&job),
+                };
+            }
+            print_event(&message);
+        } else if let Some(jobs) = message.get("return") {
+            print_job_progress(jobs, &running_jobs);
+        }
+    }
+
+    if !json_enabled() {
+        message!("{} stopped", app.name);
+    }
+
+    Ok(())
+}
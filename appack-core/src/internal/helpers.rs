@@ -13,10 +13,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::utils::sandbox;
 use anyhow::{Context, Result, anyhow};
 use std::net::{Ipv4Addr, TcpListener};
 use std::path::Path;
-use std::process::Command;
 
 pub fn get_os_assigned_port() -> Result<u16> {
     let listener = TcpListener::bind(format!("{}:0", Ipv4Addr::LOCALHOST))?;
@@ -25,7 +25,8 @@ pub fn get_os_assigned_port() -> Result<u16> {
 }
 
 pub fn has_snapshot(snapshot_name: &str, image_name: &Path) -> Result<bool> {
-    let output = Command::new("qemu-img")
+    sandbox::require("qemu-img")?;
+    let output = sandbox::command("qemu-img")
         .arg("snapshot")
         .arg("-lU")
         .arg(image_name)
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_installed::{InstalledAppPackEntry, check_appack_version};
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+pub fn print_info(file: &Path) -> anyhow::Result<()> {
+    const TARGET_FILE: &str = "AppPack.yaml";
+
+    let zip_file = File::open(file)?;
+    let mut archive = ZipArchive::new(zip_file)?;
+
+    // 2. Find and open the file named "AppPack.yaml" inside the archive
+    let mut packed_file = archive.by_name(TARGET_FILE).map_err(|_| {
+        anyhow::anyhow!(
+            "File '{}' not found in zip archive: {}",
+            TARGET_FILE,
+            file.display()
+        )
+    })?;
+
+    // 3. Read the content of the file into a String
+    let mut contents = String::new();
+    packed_file.read_to_string(&mut contents)?;
+    drop(packed_file);
+
+    // 4. Unserialize the YAML content with serde_yaml, packs for newer versions of AppPack
+    // may not deserialize
+    let info: InstalledAppPackEntry = match serde_yaml::from_str(&contents) {
+        Ok(info) => info,
+        Err(e) => {
+            let value: serde_yaml::Value = serde_yaml::from_str(&contents).unwrap_or_default();
+            if let Some(requirement) = value.get("requires_appack").and_then(|r| r.as_str()) {
+                check_appack_version(requirement)?;
+            }
+            return Err(anyhow::anyhow!("Failed to deserialize '{}': {}", TARGET_FILE, e));
+        }
+    };
+    let supported = info
+        .requires_appack
+        .as_deref()
+        .map_or(Ok(()), check_appack_version);
+
+    set_json_result(&info)?;
+
+    // 5. Print the deserialized information
+    message!(
+        "Successfully read info from '{}' in {}:",
+        TARGET_FILE,
+        file.display()
+    );
+    message!("{:#?}", info);
+
+    // 6. Print the description and the readme in the language of the user
+    if let Some(description) = info.localized_description() {
+        message!("\n{description}");
+    }
+    if let (Some(readme), Some(index)) = (&info.readme, info.localized_readme()) {
+        let mut text = String::new();
+        match archive.by_name(&format!("{}/{index}", readme.folder)) {
+            Ok(mut readme_file) => {
+                readme_file.read_to_string(&mut text)?;
+                message!("\n{text}");
+            }
+            Err(_) => message!("Warning: readme '{index}' not found in the pack"),
+        }
+    }
+    if let Err(e) = supported {
+        message!("Warning: {e}");
+    }
+
+    Ok(())
+}
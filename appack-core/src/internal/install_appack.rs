@@ -0,0 +1,1137 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::cache::{
+    cached_pack_path, find_cached_pack, remove_from_cache, store_in_cache,
+};
+use crate::internal::launch::wm_class;
+use crate::internal::pool;
+use crate::internal::repo::{find_in_repositories, signature_path, verify_pack_signature};
+use crate::internal::system::{download_unprivileged, enable_login_sync};
+use crate::types::{AppDesktopEntry, AppLocale, AppReadme};
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_installed::{InstalledAppPackEntry, InstalledAppPacks};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::{AppPackLocalSettings, RESERVED_HOME_DIR_NAMES};
+use crate::types::repository::{AppPackRepository, ChunkIndex, RepositoryIndexEntry};
+use crate::utils::checksum::sha256_file;
+use crate::utils::chunks::download_chunked;
+use crate::utils::command_review;
+use crate::utils::frontend::{message, warning};
+use crate::utils::host_locale::host_locale;
+use crate::utils::download::{download_file, download_file_resumable, fetch_bytes};
+use crate::utils::extract::advise;
+use crate::utils::i18n::tr;
+use crate::utils::icon;
+use crate::utils::output::set_json_result;
+use crate::utils::prompt::{confirm, confirm_typed};
+use crate::utils::sandbox::{self, appack_command};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use zip::ZipArchive;
+
+/// Copy of the installed entry kept in the directory of each app
+pub const INSTALLED_ENTRY_FILE_NAME: &str = "AppPack.yaml";
+
+static TRUST_DESKTOP_ENTRIES: AtomicBool = AtomicBool::new(false);
+
+/// Installs desktop entries running unexpected commands without asking, set by
+/// `--trust-desktop-entries`
+pub fn set_trust_desktop_entries() {
+    TRUST_DESKTOP_ENTRIES.store(true, Ordering::Relaxed);
+}
+
+/// Whether a desktop entry declares the file types it opens
+fn opens_files(file_entry_contents: &str) -> bool {
+    file_entry_contents
+        .lines()
+        .any(|line| line.starts_with("MimeType="))
+}
+
+/// Command the `Exec` line of an installed desktop entry must run, from the FreeRDP arguments
+/// of the entry the pack is allowed to use
+pub(crate) fn entry_launch_command(
+    file_entry_contents: &str,
+    app: &InstalledAppPackEntry,
+    rdp_args: &str,
+) -> String {
+    let mut appack_launch_cmd = if rdp_args.is_empty() {
+        format!(
+            "{} launch {} --version={}",
+            appack_command(),
+            app.id,
+            app.version
+        )
+    } else {
+        let escaped_rdp_args = rdp_args
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'");
+
+        format!(
+            "{} launch {} '{}' --version={}",
+            appack_command(),
+            app.id,
+            escaped_rdp_args,
+            app.version
+        )
+    };
+
+    // Entries declaring the file types they open get the file the user opens
+    if opens_files(file_entry_contents) {
+        appack_launch_cmd.push_str(" --open %f");
+    }
+
+    appack_launch_cmd
+}
+
+/// Weirdly enough this doesn't need escaping. To confirm, I escape anyway.
+/// https://specifications.freedesktop.org/desktop-entry-spec/1.1/value-types.html
+pub(crate) fn process_desktop_entry(
+    file_entry_contents: &str,
+    desktop_entry: &AppDesktopEntry,
+    app: &InstalledAppPackEntry,
+    settings: &AppPackLocalSettings,
+    themed_icon: Option<&str>,
+) -> Result<String> {
+    let icon_dir = settings.get_app_files_dir(app).join("desktop");
+    let icon_full_path = icon_dir.join(&desktop_entry.icon);
+    // Icons installed in the theme are referenced by name, so they follow the scale of the desktop
+    let icon = themed_icon.unwrap_or(icon_full_path.to_str().unwrap());
+
+    let rdp_args = app.declared_freerdp_args(&desktop_entry.freerdp_args());
+    let appack_launch_cmd = entry_launch_command(file_entry_contents, app, &rdp_args);
+    if opens_files(file_entry_contents) && desktop_entry.remote_app.is_none() {
+        tracing::warn!(
+            entry = desktop_entry.entry,
+            "Desktop entries can only open files with a remote_app"
+        );
+    }
+
+    let final_contents = file_entry_contents
+        .replace("$APPACK_LAUNCH_CMD", &appack_launch_cmd)
+        .replace(&format!("$ICON_DIR/{}", desktop_entry.icon), icon)
+        .replace("$ICON_DIR", icon_dir.to_str().unwrap())
+        .replace("$ICON_FULL_PATH", icon);
+
+    tracing::debug!(exec = %appack_launch_cmd, "Installed desktop entry");
+
+    let mut final_contents =
+        with_startup_wm_class(&final_contents, &entry_wm_class(app, &rdp_args));
+    for (key, values) in [("Categories", &app.categories), ("Keywords", &app.keywords)] {
+        final_contents = with_default_list(&final_contents, key, values);
+    }
+    let final_contents = with_locales(&final_contents, app);
+
+    review_entry(&final_contents, &appack_launch_cmd)?;
+    Ok(final_contents)
+}
+
+/// Checks the finished desktop entry only has its own group and runs `appack_launch_cmd`, or
+/// that the user accepts the command it runs instead
+fn review_entry(final_contents: &str, appack_launch_cmd: &str) -> Result<()> {
+    let groups: Vec<_> = final_contents
+        .lines()
+        .filter(|line| line.trim_start().starts_with('['))
+        .collect();
+    if groups != ["[Desktop Entry]"] {
+        return Err(anyhow!(
+            "Desktop entries must only have a [Desktop Entry] group"
+        ));
+    }
+
+    let final_exec_lines: Vec<_> = final_contents
+        .lines()
+        .filter(|line| line.starts_with("Exec"))
+        .collect();
+
+    if final_exec_lines.len() != 1 {
+        return Err(anyhow!("Incorrect amount of Exec entries"));
+    }
+
+    let final_exec_lines = final_exec_lines[0];
+    let mut final_exec_line_split = final_exec_lines.splitn(2, "=");
+    if final_exec_lines.len() == 1 {
+        return Err(anyhow!("Malformed exec entry: {}", final_exec_lines));
+    }
+
+    let exec_line = final_exec_line_split
+        .nth(1)
+        .context("Sanitization error, this should never happen")?
+        .to_string();
+
+    if appack_launch_cmd != exec_line {
+        message!("=============================================");
+        message!("  {}  ", tr!("security-alert-title"));
+        message!("=============================================");
+
+        message!("{}", tr!("security-alert-intro"));
+        message!();
+
+        message!("{}", tr!("security-alert-comparison"));
+        message!("  {}", tr!("security-alert-expected"));
+        message!("     > {appack_launch_cmd}");
+        message!();
+
+        message!("  {}", tr!("security-alert-configured"));
+        message!("     > {exec_line}");
+        message!();
+
+        message!("{}", tr!("security-alert-action-title"));
+        message!("{}", tr!("security-alert-action"));
+        message!("=============================================");
+
+        if TRUST_DESKTOP_ENTRIES.load(Ordering::Relaxed) {
+            message!("{}", tr!("security-alert-trusted"));
+        } else if !confirm_typed(
+            &tr!("prompt-continue-install"),
+            "INSTALL",
+            "--trust-desktop-entries",
+        )? {
+            return Err(ErrorKind::Cancelled.error(tr!("installation-cancelled")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Class of the windows of a desktop entry. The entry arguments come after the ones of the
+/// pack on the FreeRDP command line, and FreeRDP keeps the last `/wm-class`.
+fn entry_wm_class(app: &InstalledAppPackEntry, rdp_args: &str) -> String {
+    app.freerdp_command
+        .split_whitespace()
+        .chain(rdp_args.split_whitespace())
+        .rev()
+        .find_map(|arg| arg.strip_prefix("/wm-class:"))
+        .map(|class| class.replace("$WHITESPACE", " "))
+        .unwrap_or_else(|| wm_class(&app.id))
+}
+
+/// Sets `StartupWMClass` to the class of the FreeRDP window, so docks group it with the entry
+fn with_startup_wm_class(contents: &str, class: &str) -> String {
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter(|line| !line.starts_with("StartupWMClass"))
+        .map(str::to_string)
+        .collect();
+    let header = lines
+        .iter()
+        .position(|line| line.trim() == "[Desktop Entry]")
+        .map_or(0, |index| index + 1);
+    lines.insert(header, format!("StartupWMClass={class}"));
+
+    lines.join("\n") + "\n"
+}
+
+/// Adds a list key, e.g. `Categories=Office;`, to a desktop entry not setting it itself
+fn with_default_list(contents: &str, key: &str, values: &[String]) -> String {
+    let prefix = format!("{key}=");
+    if values.is_empty() || contents.lines().any(|line| line.starts_with(&prefix)) {
+        return contents.to_string();
+    }
+
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let header = lines
+        .iter()
+        .position(|line| line.trim() == "[Desktop Entry]")
+        .map_or(0, |index| index + 1);
+    let line = format!("{prefix}{};", values.join(";"));
+    lines.insert(header, &line);
+
+    lines.join("\n") + "\n"
+}
+
+/// Adds the translations of the pack to the `Name=` and `Comment=` of a desktop entry, when
+/// they are the name and the description of the pack
+fn with_locales(contents: &str, app: &InstalledAppPackEntry) -> String {
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        lines.push(line.to_string());
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let translation: fn(&AppLocale) -> Option<&String> = match key {
+            "Name" if value == app.name => |locale| locale.name.as_ref(),
+            "Comment" if app.description.as_deref() == Some(value) => {
+                |locale| locale.description.as_ref()
+            }
+            _ => continue,
+        };
+
+        for (language, locale) in &app.locales {
+            let localized_key = format!("{key}[{}]=", language.replace('-', "_"));
+            if let Some(translation) = translation(locale)
+                && !contents.lines().any(|line| line.starts_with(&localized_key))
+            {
+                lines.push(format!("{localized_key}{translation}"));
+            }
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+pub fn extract_config(archive: &mut ZipArchive<File>) -> Result<InstalledAppPackEntry> {
+    let mut file = archive
+        .by_name("AppPack.yaml")
+        .context("File 'AppPack.yaml' not found in archive")?;
+
+    let mut buffer = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut buffer)
+        .context("Unable to read config file")?;
+    InstalledAppPackEntry::from_pack_yaml(&buffer)
+}
+
+// Needs improvement:
+// If the installation only partially finishes and is interrupted, clean the previous installation
+// and try again if the user tries to install the same app again.
+// We cannot use a temp dir because Snap hits us with cross-device errors when trying to copy over
+// and desktop entries must be copied in a different directory
+fn extract_files(
+    archive: &mut ZipArchive<File>,
+    new_app_entry: &InstalledAppPackEntry,
+    local_settings: &AppPackLocalSettings,
+) -> Result<String> {
+    let image_filename = new_app_entry.image.as_str();
+    let new_app_version = new_app_entry.version.as_str();
+    let new_app_base_dir = local_settings.get_app_home_dir(new_app_entry);
+    let desktop_entries = new_app_entry.desktop_entries.clone().unwrap_or_default();
+
+    if new_app_base_dir.exists() {
+        return Err(anyhow!(
+            "App directory already exists: {}",
+            new_app_base_dir.display()
+        ));
+    }
+
+    // Desktop entries are reviewed before extracting anything, so a refused install leaves no files
+    let mut reviewed_entries = Vec::with_capacity(desktop_entries.len());
+    let mut templates = Vec::with_capacity(desktop_entries.len());
+    let mut themed_icons = Vec::with_capacity(desktop_entries.len());
+    for entry in desktop_entries.iter() {
+        let mut icon = Vec::new();
+        archive
+            .by_name(&format!("desktop/{}", entry.icon))
+            .context(format!(
+                "Desktop entry icon '{}' not found in archive",
+                entry.icon
+            ))?
+            .read_to_end(&mut icon)
+            .context("Unable to read desktop entry icon")?;
+        let themed_icon = themed_icon_path(local_settings, new_app_entry, entry, &icon);
+
+        let mut entry_file = archive
+            .by_name(&format!("desktop/{}", entry.entry))
+            .context(format!(
+                "Desktop entry '{}' not found in archive",
+                entry.entry
+            ))?;
+
+        let mut file_content = String::new();
+        entry_file
+            .read_to_string(&mut file_content)
+            .context("Unable to read entry file")?;
+        reviewed_entries.push(
+            process_desktop_entry(
+                &file_content,
+                entry,
+                new_app_entry,
+                local_settings,
+                themed_icon.as_deref().and_then(themed_icon_name),
+            )
+            .context("Unable to parse desktop entry")?,
+        );
+        templates.push(file_content);
+        themed_icons.push(themed_icon.map(|path| (path, icon)));
+
+        let entry_file_fullpath = local_settings
+            .desktop_entries_dir
+            .join(format!("{new_app_version}_{}", entry.entry));
+        if !local_settings.is_system() && entry_file_fullpath.exists() {
+            return Err(anyhow!("Desktop entry already exists: {entry_file_fullpath:?}").context("That app is seems to have been incorrectly uninstalled previously. Please delete the files from the previous installation before proceeding."));
+        }
+    }
+
+    std::fs::create_dir_all(new_app_base_dir.join("desktop"))?;
+
+    message!("Extracting app data.. This can take a few minutes.");
+
+    let image_sha256 = {
+        let mut image_file = archive
+            .by_name(image_filename)
+            .context(format!("Image '{}' not found in archive", image_filename))?;
+        let image_fullpath = new_app_base_dir.join(image_filename);
+        let size = image_file.size();
+
+        pool::install_image(
+            local_settings,
+            &mut image_file,
+            size,
+            new_app_entry.image_sha256.as_deref(),
+            &image_fullpath,
+        )?
+    };
+
+    message!("Extracting desktop entries..");
+
+    // Apps installed system-wide have their entries written in the menu of each user, by
+    // `appack desktop sync`
+    let menu = !local_settings.is_system();
+    for ((entry, file_content), template) in
+        desktop_entries.iter().zip(reviewed_entries).zip(templates)
+    {
+        if menu {
+            let entry_fullpath = local_settings.get_desktop_entry_path(new_app_entry, entry);
+
+            let mut outfile =
+                File::create(&entry_fullpath).context("Unable to create desktop entry file")?;
+
+            outfile.write_all(file_content.as_bytes())?;
+        }
+
+        // Kept for `appack desktop regenerate`
+        std::fs::write(
+            new_app_base_dir.join("desktop").join(&entry.entry),
+            template,
+        )
+        .context("Unable to save desktop entry template")?;
+
+        {
+            let mut entry_file = archive
+                .by_name(&format!("desktop/{}", entry.icon))
+                .context(format!(
+                    "Desktop entry '{}' not found in archive",
+                    entry.icon
+                ))?;
+            let entry_fullpath = new_app_base_dir.join("desktop").join(&entry.icon);
+            let mut outfile =
+                File::create(&entry_fullpath).context("Unable to create desktop entry icon")?;
+
+            io::copy(&mut entry_file, &mut outfile)?;
+        }
+    }
+
+    for (path, icon) in themed_icons.into_iter().flatten().filter(|_| menu) {
+        install_themed_icon(&path, &icon)?;
+    }
+
+    if let Some(readme) = &new_app_entry.readme {
+        extract_readme(archive, readme, &local_settings.get_app_readme_dir(new_app_entry))?;
+    }
+
+    Ok(image_sha256)
+}
+
+/// Extracts the readme folder of a pack, for `appack readme`
+fn extract_readme(archive: &mut ZipArchive<File>, readme: &AppReadme, dir: &Path) -> Result<()> {
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(path) = file
+            .enclosed_name()
+            .and_then(|path| path.strip_prefix(&readme.folder).ok().map(Path::to_path_buf))
+        else {
+            continue;
+        };
+
+        let destination = dir.join(path);
+        if file.is_dir() {
+            std::fs::create_dir_all(&destination)?;
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut outfile = File::create(&destination).context(format!(
+            "Unable to create file {}",
+            destination.display()
+        ))?;
+        io::copy(&mut file, &mut outfile)?;
+    }
+
+    Ok(())
+}
+
+/// Where the icon of a desktop entry goes in the icon theme, `None` if the theme can't hold it
+pub(crate) fn themed_icon_path(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    desktop_entry: &AppDesktopEntry,
+    icon: &[u8],
+) -> Option<PathBuf> {
+    let subdir = icon::theme_subdir(&desktop_entry.icon, icon)?;
+    let extension = icon::extension(&desktop_entry.icon)?;
+
+    Some(settings.icon_theme_dir.join(subdir).join(format!(
+        "{}.{extension}",
+        settings.get_icon_name(app, desktop_entry)
+    )))
+}
+
+/// Name desktop entries reference an icon of the theme by
+pub(crate) fn themed_icon_name(path: &Path) -> Option<&str> {
+    path.file_stem()?.to_str()
+}
+
+pub(crate) fn install_themed_icon(path: &Path, icon: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .context(format!("Unable to create icon directory {}", dir.display()))?;
+    }
+    std::fs::write(path, icon).context(format!("Unable to install icon {}", path.display()))
+}
+
+/// Rejects a truncated or corrupted pack before anything is extracted. Checks that the data of
+/// every member is within the archive, and the CRC of every member but the image, which is
+/// checked while it is extracted.
+pub fn check_archive_integrity(archive: &mut ZipArchive<File>, image: &str) -> Result<()> {
+    let central_directory_start = archive.central_directory_start();
+    for i in 0..archive.len() {
+        let (name, end) = {
+            let file = archive.by_index_raw(i).map_err(|e| {
+                ErrorKind::ArchiveInvalid.error(format!(
+                    "The pack is corrupted, member {i} is unreadable: {e}"
+                ))
+            })?;
+            (file.name().to_string(), file.data_start() + file.compressed_size())
+        };
+        if end > central_directory_start {
+            return Err(ErrorKind::ArchiveInvalid.error(format!(
+                "The pack is truncated, {name} ends past the end of its data"
+            )));
+        }
+        if name == image {
+            continue;
+        }
+
+        let checked = archive
+            .by_index(i)
+            .and_then(|mut file| Ok(io::copy(&mut file, &mut io::sink())?));
+        if let Err(e) = checked {
+            return Err(ErrorKind::ArchiveInvalid.error(format!(
+                "The pack is corrupted, {name} is unreadable: {e}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the following files are present:
+/// * image file
+/// * desktop entries
+pub fn check_pack_contents(
+    archive: &mut ZipArchive<File>,
+    new_app_entry: &InstalledAppPackEntry,
+) -> Result<()> {
+    if !AppBuildConfig::is_valid_version(&new_app_entry.version) {
+        return Err(ErrorKind::ArchiveInvalid.error(format!(
+            "Invalid character in version: {}",
+            new_app_entry.version
+        )));
+    }
+
+    if RESERVED_HOME_DIR_NAMES.contains(&new_app_entry.id.as_str()) {
+        return Err(ErrorKind::ArchiveInvalid.error(format!("Invalid AppPack id: {}", new_app_entry.id)));
+    }
+
+    let mut required_files = [new_app_entry.image.clone()].to_vec();
+    if let Some(entries) = new_app_entry.desktop_entries.clone() {
+        for entry in entries {
+            required_files.push(format!("desktop/{}", entry.entry));
+            required_files.push(format!("desktop/{}", entry.icon));
+        }
+    }
+
+    // Collect all file names present in the archive into a HashSet
+    let mut present_files = HashSet::new();
+    for i in 0..archive.len() {
+        if let Ok(file) = archive.by_index(i) {
+            present_files.insert(file.name().to_string());
+        }
+    }
+
+    // Check which required files are missing
+    let missing_files: Vec<_> = required_files
+        .iter()
+        .filter(|&f| !present_files.contains(f))
+        .collect();
+
+    if !missing_files.is_empty() {
+        return Err(ErrorKind::ArchiveInvalid.error(format!("Missing files: {:?}", missing_files)));
+    }
+
+    Ok(())
+}
+
+fn check_valid_app_pack(
+    archive: &mut ZipArchive<File>,
+    new_app_entry: &InstalledAppPackEntry,
+    installed: &InstalledAppPacks,
+) -> Result<()> {
+    for entry in installed.installed.iter() {
+        if entry.id == new_app_entry.id {
+            message!("AppPack already installed: {}", entry.id);
+            message!("Installed version: {}", entry.version);
+            message!("File version: {}", new_app_entry.version);
+            return Err(ErrorKind::AlreadyInstalled.error("AppPack already installed"));
+        }
+    }
+
+    check_pack_contents(archive, new_app_entry)
+}
+
+/// Why this machine could only run the app under emulation, if so
+fn emulation_reason(
+    app: &InstalledAppPackEntry,
+    host_arch: &str,
+    has_kvm: bool,
+) -> Option<String> {
+    if app.arch.as_str() != host_arch {
+        return Some(tr!(
+            "emulated-foreign-arch",
+            name = app.name.as_str(),
+            arch = app.arch.as_str(),
+            host = host_arch
+        ));
+    }
+
+    let needs_kvm = app.requires_nested_virt
+        || app
+            .qemu_command
+            .split_whitespace()
+            .any(|arg| arg == "-enable-kvm" || arg.contains("accel=kvm"));
+    if needs_kvm && !has_kvm {
+        return Some(tr!("emulated-no-kvm", name = app.name.as_str()));
+    }
+
+    None
+}
+
+/// Refuses apps this machine can only emulate, which are too slow to use, unless `force` is set
+fn check_host_support(app: &InstalledAppPackEntry, force: bool) -> Result<()> {
+    // Simulated VMs run anywhere
+    if sandbox::simulating() {
+        return Ok(());
+    }
+    let has_kvm = Path::new("/dev/kvm").exists();
+    let Some(reason) = emulation_reason(app, std::env::consts::ARCH, has_kvm) else {
+        return Ok(());
+    };
+
+    if !force {
+        return Err(ErrorKind::EnvironmentMissing.error(tr!("error-emulated", reason = reason)));
+    }
+    message!("{}", tr!("warning-emulated", reason = reason));
+
+    Ok(())
+}
+
+/// Warns when the guest was installed for another region than the one of this machine
+fn check_guest_locale(app: &InstalledAppPackEntry) {
+    let (Some(guest), Some(host)) = (&app.guest_locale, host_locale()) else {
+        return;
+    };
+    let language = |tag: &str| tag.split('-').next().unwrap_or_default().to_lowercase();
+    if language(&guest.locale) == language(&host) {
+        return;
+    }
+
+    message!(
+        "{}",
+        tr!(
+            "warning-guest-locale",
+            name = app.name.as_str(),
+            locale = guest.locale.as_str(),
+            keyboard = guest.keyboard.as_str()
+        )
+    );
+}
+
+/// Options of the commands of a pack reaching the host, by `command_review`
+fn command_risks(app: &InstalledAppPackEntry) -> Vec<String> {
+    let mut risks = command_review::qemu_risks(&app.qemu_command);
+    if app.qemu_unconfined {
+        risks.push(tr!("risk-qemu-unconfined"));
+    }
+    risks.extend(command_review::freerdp_risks(&app.freerdp_command));
+    for entry in app.desktop_entries.iter().flatten() {
+        risks.extend(command_review::freerdp_risks(&entry.rdp_args));
+    }
+
+    risks
+}
+
+/// Asks the user to approve each option of the pack commands reaching the host, except the
+/// ones an installed version of the app already has
+pub(crate) fn review_commands(
+    app: &InstalledAppPackEntry,
+    installed: &InstalledAppPacks,
+) -> Result<()> {
+    let approved: HashSet<String> = installed
+        .installed
+        .iter()
+        .filter(|installed| installed.id == app.id)
+        .flat_map(command_risks)
+        .collect();
+
+    for risk in command_risks(app) {
+        if approved.contains(&risk) {
+            continue;
+        }
+        if !confirm(&tr!("prompt-command-risk", name = app.name.as_str(), risk = risk))? {
+            return Err(ErrorKind::Cancelled.error(tr!("installation-cancelled")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks the user to grant the capabilities the pack declares, except the ones an installed
+/// version of the app already has
+pub(crate) fn consent_capabilities(
+    app: &InstalledAppPackEntry,
+    installed: &InstalledAppPacks,
+) -> Result<()> {
+    let granted: HashSet<&str> = installed
+        .installed
+        .iter()
+        .filter(|installed| installed.id == app.id)
+        .flat_map(|installed| installed.capabilities.iter().map(|c| c.config_key()))
+        .collect();
+    let asked: Vec<&str> = app
+        .capabilities
+        .iter()
+        .map(|capability| capability.config_key())
+        .filter(|capability| !granted.contains(capability))
+        .collect();
+    if asked.is_empty() {
+        return Ok(());
+    }
+
+    let question = tr!(
+        "prompt-capabilities",
+        name = app.name.as_str(),
+        capabilities = asked.join(", ")
+    );
+    if !confirm(&question)? {
+        return Err(ErrorKind::Cancelled.error(tr!("installation-cancelled")));
+    }
+
+    Ok(())
+}
+
+pub fn install_appack_file(
+    file_path: &Path,
+    settings: &AppPackLocalSettings,
+    expected: Option<&RepositoryIndexEntry>,
+    force: bool,
+) -> Result<()> {
+    let file = File::open(file_path).context(format!("Unable to open file {file_path:?}"))?;
+    // Larger readahead for the image, read from start to end
+    advise(&file, libc::POSIX_FADV_SEQUENTIAL);
+    let mut archive = ZipArchive::new(file).context("Unable to open file as zip archive")?;
+
+    settings.check_ok()?;
+    let mut new_app_entry = extract_config(&mut archive)?;
+    check_archive_integrity(&mut archive, &new_app_entry.image)?;
+    // Other apps can be launched or installed meanwhile, installed.yaml is only locked to save it
+    let _app_lock = settings.lock_app(&new_app_entry.id)?;
+
+    if let Some(expected) = expected
+        && (expected.id != new_app_entry.id || expected.version != new_app_entry.version)
+    {
+        return Err(ErrorKind::ArchiveInvalid.error(format!(
+            "Pack contents ({} {}) do not match the repository index ({} {})",
+            new_app_entry.id,
+            new_app_entry.version,
+            expected.id,
+            expected.version
+        )));
+    }
+
+    check_host_support(&new_app_entry, force)?;
+    check_guest_locale(&new_app_entry);
+    let installed_apps = settings.get_installed()?;
+    review_commands(&new_app_entry, &installed_apps)?;
+    consent_capabilities(&new_app_entry, &installed_apps)?;
+    check_valid_app_pack(&mut archive, &new_app_entry, &installed_apps)?;
+    new_app_entry.image_sha256 = Some(extract_files(&mut archive, &new_app_entry, settings)?);
+    new_app_entry.image_size = std::fs::metadata(
+        settings
+            .get_app_home_dir(&new_app_entry)
+            .join(&new_app_entry.image),
+    )
+    .ok()
+    .map(|m| m.len());
+
+    // Allows `appack recover` to rebuild installed.yaml from the app directories
+    std::fs::write(
+        settings
+            .get_app_home_dir(&new_app_entry)
+            .join(INSTALLED_ENTRY_FILE_NAME),
+        serde_yaml::to_string(&new_app_entry)?,
+    )
+    .context("Failed to save the app configuration")?;
+
+    // 2. Add to installed list
+    let _lock = settings.lock_installed()?;
+    let mut installed_apps = settings.get_installed()?;
+    installed_apps.installed.push(new_app_entry.clone());
+    settings.save_installed(installed_apps)?;
+    tracing::info!(id = new_app_entry.id, version = new_app_entry.version, "AppPack installed");
+    set_json_result(&new_app_entry)?;
+
+    if settings.is_system() {
+        enable_login_sync(settings)?;
+        message!("Installation complete. Users get its desktop entries at their next login.");
+        return Ok(());
+    }
+
+    message!("Installation complete. You might need to log off and in again for the desktop integration to show.");
+
+    Ok(())
+}
+
+fn check_downloaded_pack(
+    settings: &AppPackLocalSettings,
+    pack_path: &Path,
+    entry: &RepositoryIndexEntry,
+) -> Result<()> {
+    let checksum = sha256_file(pack_path)?;
+    if !checksum.eq_ignore_ascii_case(&entry.sha256) {
+        return Err(ErrorKind::DownloadFailed.error(format!(
+            "Checksum mismatch: expected {}, got {checksum}",
+            entry.sha256
+        )));
+    }
+
+    verify_pack_signature(settings, pack_path)
+}
+
+/// Rebuilds the pack from a cached previous version, downloading only the chunks that changed
+fn download_pack_chunks(
+    settings: &AppPackLocalSettings,
+    repository: &AppPackRepository,
+    entry: &RepositoryIndexEntry,
+    chunks_file: &str,
+    pack_path: &Path,
+) -> Result<bool> {
+    let Some(base) = find_cached_pack(settings, &entry.id, None) else {
+        return Ok(false);
+    };
+
+    let url = repository.file_url(&entry.file);
+    message!("Downloading changes from {url}..");
+    let index: ChunkIndex = serde_yaml::from_slice(&fetch_bytes(&repository.file_url(chunks_file))?)
+        .context("Invalid chunk index")?;
+    download_chunked(&url, &index, &base, pack_path, settings.limit_rate)?;
+    download_file(&format!("{url}.sig"), &signature_path(pack_path)).context("Pack is not signed")?;
+    check_downloaded_pack(settings, pack_path, entry)?;
+
+    Ok(true)
+}
+
+/// Pairs of pack and signature URLs of a pack: the peer-to-peer sources first, then the
+/// repository URL and each mirror in order
+fn pack_sources(
+    repository: &AppPackRepository,
+    entry: &RepositoryIndexEntry,
+) -> Vec<(String, String)> {
+    let repository_url = repository.file_url(&entry.file);
+    let repository_signature_url = format!("{repository_url}.sig");
+
+    // (pack URL, signature URL)
+    let mut sources = Vec::new();
+    if let Some(magnet) = &entry.magnet {
+        // BitTorrent is optional, the other sources are tried without aria2c
+        match sandbox::require("aria2c") {
+            Ok(()) => sources.push((magnet.clone(), repository_signature_url.clone())),
+            Err(_) => tracing::info!("aria2c is not installed, skipping the magnet link"),
+        }
+    }
+    if let Some(cid) = &entry.ipfs {
+        sources.push((format!("ipfs://{cid}"), repository_signature_url.clone()));
+    }
+    sources.push((repository_url, repository_signature_url));
+    for mirror in entry.mirrors.iter() {
+        let url = repository.file_url(mirror);
+        sources.push((url.clone(), format!("{url}.sig")));
+    }
+
+    sources
+}
+
+/// Downloads a pack from the first of `sources` that works and passes `check`. The signature
+/// is optional unless `signed`.
+pub fn download_from_sources(
+    sources: &[(String, String)],
+    sha256: Option<&str>,
+    signed: bool,
+    pack_path: &Path,
+    limit_rate: Option<u64>,
+    check: impl Fn(&Path) -> Result<()>,
+) -> Result<()> {
+    for (url, signature_url) in sources.iter() {
+        message!("Downloading {url}..");
+        let result = download_file_resumable(url, pack_path, sha256, limit_rate)
+            .and_then(|_| match download_file(signature_url, &signature_path(pack_path)) {
+                Err(e) if signed => Err(e.context("Pack is not signed")),
+                _ => Ok(()),
+            })
+            .and_then(|_| check(pack_path));
+
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) => warning!("Download from {url} failed: {e:?}"),
+        }
+    }
+
+    Err(ErrorKind::DownloadFailed.error(format!(
+        "Failed to download the pack from any of its {} sources",
+        sources.len()
+    )))
+}
+
+/// Tries the sources of `pack_sources` in order, after the differential download if possible.
+/// Whatever the source, the pack must match the checksum and signature of the index.
+fn download_pack(
+    settings: &AppPackLocalSettings,
+    repository: &AppPackRepository,
+    entry: &RepositoryIndexEntry,
+    pack_path: &Path,
+) -> Result<()> {
+    if let Some(chunks_file) = &entry.chunks {
+        match download_pack_chunks(settings, repository, entry, chunks_file, pack_path) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => warning!("Differential download failed, downloading the whole pack: {e:?}"),
+        }
+    }
+
+    download_from_sources(
+        &pack_sources(repository, entry),
+        Some(&entry.sha256),
+        true,
+        pack_path,
+        settings.limit_rate,
+        |pack_path| check_downloaded_pack(settings, pack_path, entry),
+    )
+    .context(format!("Failed to download {} {}", entry.id, entry.version))
+}
+
+fn install_from_repository(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    repository: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    let (repository, entry) = find_in_repositories(settings, id, version, repository)?;
+    let pack_path = fetch_from_repository(settings, &repository, &entry)?;
+    install_appack_file(&pack_path, settings, Some(&entry), force)
+}
+
+/// Returns the path of the verified pack in the cache, downloading it if needed
+pub fn fetch_from_repository(
+    settings: &AppPackLocalSettings,
+    repository: &AppPackRepository,
+    entry: &RepositoryIndexEntry,
+) -> Result<PathBuf> {
+    let cached_path = cached_pack_path(settings, &entry.sha256);
+    if cached_path.exists() {
+        message!("Checking cached {} {}..", entry.id, entry.version);
+        if check_downloaded_pack(settings, &cached_path, entry).is_ok() {
+            return Ok(cached_path);
+        }
+        message!("Cached pack is invalid, downloading it again");
+        remove_from_cache(&cached_path);
+    }
+
+    std::fs::create_dir_all(&settings.downloads_dir)
+        .context("Failed to create downloads directory")?;
+    let pack_path = settings
+        .downloads_dir
+        .join(format!("{}_{}.zip", entry.id, entry.version));
+
+    message!("Downloading {} {} from '{}'..", entry.id, entry.version, repository.name);
+    let result = if settings.is_system() {
+        let sources = pack_sources(repository, entry);
+        download_unprivileged(settings, &sources, Some(&entry.sha256), true, &pack_path)
+            .and_then(|_| check_downloaded_pack(settings, &pack_path, entry))
+    } else {
+        download_pack(settings, repository, entry, &pack_path)
+    };
+    if result.is_err() {
+        let _ = std::fs::remove_file(&pack_path); // Ignore error
+        let _ = std::fs::remove_file(signature_path(&pack_path)); // Ignore error
+    }
+    result?;
+
+    store_in_cache(settings, &pack_path, &entry.sha256)
+}
+
+fn install_from_url(settings: &AppPackLocalSettings, url: &str, force: bool) -> Result<()> {
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download.zip");
+
+    std::fs::create_dir_all(&settings.downloads_dir)
+        .context("Failed to create downloads directory")?;
+    let pack_path = settings.downloads_dir.join(file_name);
+
+    let _ = std::fs::remove_file(signature_path(&pack_path)); // Ignore error
+    let sources = [(url.to_string(), format!("{url}.sig"))];
+    if settings.is_system() {
+        download_unprivileged(settings, &sources, None, false, &pack_path)?;
+    } else {
+        download_from_sources(&sources, None, false, &pack_path, settings.limit_rate, |_| Ok(()))?;
+    }
+
+    let result = if signature_path(&pack_path).exists() {
+        verify_pack_signature(settings, &pack_path)
+    } else {
+        confirm_unsigned(url)
+    }
+    .and_then(|_| install_appack_file(&pack_path, settings, None, force));
+
+    let _ = std::fs::remove_file(&pack_path); // Ignore error
+    let _ = std::fs::remove_file(signature_path(&pack_path)); // Ignore error
+
+    result
+}
+
+fn confirm_unsigned(pack: &str) -> Result<()> {
+    message!("{}", tr!("warning-unsigned", pack = pack));
+    if !confirm(&tr!("prompt-install-unsigned"))? {
+        return Err(ErrorKind::Cancelled.error(tr!("installation-cancelled")));
+    }
+
+    Ok(())
+}
+
+/// Installs an AppPack from a local file, a URL, or from the synced repositories if `pack` is an id,
+/// `force` also installs apps this machine can only emulate
+pub fn install_appack(
+    settings: &AppPackLocalSettings,
+    pack: &str,
+    version: Option<&str>,
+    repository: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    if pack.starts_with("https://") || pack.starts_with("http://") {
+        return install_from_url(settings, pack, force);
+    }
+
+    let file_path = Path::new(pack);
+    if repository.is_some() || !file_path.exists() {
+        return install_from_repository(settings, pack, version, repository, force);
+    }
+
+    if signature_path(file_path).exists() {
+        verify_pack_signature(settings, file_path)?;
+    } else {
+        confirm_unsigned(&file_path.display().to_string())?;
+    }
+
+    install_appack_file(file_path, settings, None, force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_wm_class() {
+        let mut app: InstalledAppPackEntry = serde_yaml::from_str(
+            "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: ''}",
+        )
+        .unwrap();
+        assert_eq!(entry_wm_class(&app, "/app:program:cmd.exe"), "appack-demo");
+
+        app.freerdp_command = "/u:x /wm-class:Pack".to_string();
+        assert_eq!(entry_wm_class(&app, ""), "Pack");
+        assert_eq!(entry_wm_class(&app, "/wm-class:Entry"), "Entry");
+
+        let contents = with_startup_wm_class("[Desktop Entry]\nStartupWMClass=old\nName=a", "Pack");
+        assert_eq!(contents, "[Desktop Entry]\nStartupWMClass=Pack\nName=a\n");
+        let categories = ["Office".to_string(), "WordProcessor".to_string()];
+        let contents = with_default_list(&contents, "Categories", &categories);
+        assert_eq!(
+            contents,
+            "[Desktop Entry]\nCategories=Office;WordProcessor;\nStartupWMClass=Pack\nName=a\n"
+        );
+        assert_eq!(with_default_list(&contents, "Categories", &["Game".into()]), contents);
+    }
+
+    #[test]
+    fn test_review_entry() {
+        let entry = "[Desktop Entry]\nName=Demo\nExec=appack launch demo\n";
+        assert!(review_entry(entry, "appack launch demo").is_ok());
+
+        // Installed entries are not checked again, e.g. by `desktop regenerate`
+        let keywords = ["x\nActions=a;\n[Desktop Action a]\nExec=sh -c id".to_string()];
+        let injected = with_default_list(entry, "Keywords", &keywords);
+        assert!(review_entry(&injected, "appack launch demo").is_err());
+        let injected = format!("{entry}Keywords=x;\nExec=sh -c id\n");
+        assert!(review_entry(&injected, "appack launch demo").is_err());
+
+        let app: InstalledAppPackEntry = serde_yaml::from_str(
+            "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: '', \
+             locales: {fr: {name: \"Démo\\n[Desktop Action a]\\nExec=sh -c id\"}}}",
+        )
+        .unwrap();
+        let injected = with_locales(entry, &app);
+        assert!(review_entry(&injected, "appack launch demo").is_err());
+    }
+
+    #[test]
+    fn test_with_locales() {
+        let app: InstalledAppPackEntry = serde_yaml::from_str(
+            "{id: demo, version: 1.0.0, name: Demo, description: A demo, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: '', \
+             locales: {fr: {name: Démo, description: Une démo}, pt-BR: {name: Demonstração}}}",
+        )
+        .unwrap();
+        let contents = with_locales("Name=Demo\nName[pt_BR]=Demo\nComment=A demo", &app);
+        assert_eq!(
+            contents,
+            "Name=Demo\nName[fr]=Démo\nName[pt_BR]=Demo\nComment=A demo\nComment[fr]=Une démo\n"
+        );
+        assert_eq!(with_locales("Name=Other", &app), "Name=Other\n");
+    }
+    #[test]
+    fn test_emulation_reason() {
+        let mut app: InstalledAppPackEntry = serde_yaml::from_str(
+            "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '-m 4G', freerdp_command: ''}",
+        )
+        .unwrap();
+        assert_eq!(emulation_reason(&app, "x86_64", false), None);
+        assert!(emulation_reason(&app, "aarch64", true).is_some());
+
+        app.qemu_command = "-machine q35,accel=kvm -m 4G".to_string();
+        assert_eq!(emulation_reason(&app, "x86_64", true), None);
+        assert!(emulation_reason(&app, "x86_64", false).is_some());
+    }
+}
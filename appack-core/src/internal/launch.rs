@@ -0,0 +1,2142 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::balloon::IdleBalloon;
+use crate::internal::helpers::{get_os_assigned_port, has_snapshot};
+use crate::internal::system::ensure_user_overlay;
+use crate::internal::logs::open_app_log;
+use crate::internal::pool;
+use crate::internal::retention::{self, CloseSnapshot, close_snapshots};
+use crate::internal::orphan::{LaunchState, PID_FILE, qemu_pid, remove_stale_files};
+use crate::internal::quota::check_quotas;
+use crate::internal::remote::{RemoteSession, serve_remote};
+use crate::internal::stats::LaunchTimer;
+use crate::internal::stop::stop;
+use crate::internal::update::notify_if_update_available;
+use crate::internal::url_bridge::{URL_SOCKET, serve_urls};
+use crate::types::{AppCapability, AppSnapshotTriggerMode, SharedDirsMode};
+use crate::types::app_config::{
+    AppPackAppConfig, CpuTopology, MonitorSelection, NetworkPolicy, RtcBase, SharedFolder,
+};
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::config::NotificationLevel;
+use crate::types::error;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::notify::notify;
+use crate::utils::output::{emit_event, set_json_result};
+use crate::utils::qmp::{
+    attach_usb_devices, delete_snapshot_blocking, detach_usb_devices, pin_vcpus,
+    take_snapshot_with_progress,
+};
+use crate::utils::display;
+use crate::utils::guest_agent;
+use crate::utils::host_locale;
+use crate::utils::keyboard;
+use crate::utils::keyring;
+use crate::utils::nested_virt;
+use crate::utils::qemu_sandbox;
+use crate::utils::sandbox;
+use crate::utils::virtiofs;
+use crate::utils::scale;
+use crate::utils::snap;
+use anyhow::{Context, Result, anyhow};
+use qapi::{Qmp, qmp};
+use serde_json::json;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::utils::xdg_session_type_detector::get_freerdp_executable;
+
+/// QMP socket `appack events` listens to, in the app home
+pub(crate) const EVENTS_SOCKET: &str = "qmp-events.sock";
+
+/// RDP port of the running VM, in the app home, for `appack rdp-file`
+pub(crate) const RDP_PORT_FILE: &str = "rdp-port";
+
+/// RDP port of an app while it runs, `None` when it is not running
+pub fn running_rdp_port(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Option<u16> {
+    let home = settings.get_app_home_dir(app);
+    // The port file is left behind if QEMU crashed
+    UnixStream::connect(home.join("qmp-appack.sock")).ok()?;
+    std::fs::read_to_string(home.join(RDP_PORT_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Window class of the sessions of an app, unless its FreeRDP arguments set another one
+pub(crate) fn wm_class(app_id: &str) -> String {
+    format!("appack-{app_id}")
+}
+
+fn to_win_escaped_path(path: &str) -> String {
+    const PREFIX: &str = "\\\\tsclient\\home\\";
+
+    if path.is_empty() {
+        return "".to_string();
+    }
+
+    let mut stripped_path = path;
+
+    if path.starts_with("'") && path.ends_with("'") && path.len() >= 2 {
+        stripped_path = &path[1..path.len() - 1];
+    }
+
+    if stripped_path.starts_with("/home/") {
+        // Find the slash that comes after "/home"
+        if let Some(first_slash_after_home) = stripped_path[6..].find('/') {
+            let start_index = 6 + first_slash_after_home + 1;
+            stripped_path = &stripped_path[start_index..];
+        }
+    }
+
+    let clean_path = stripped_path.trim_start_matches('/');
+    let windows_style_path = clean_path.replace('/', "\\");
+    format!("{}{}", PREFIX, windows_style_path)
+}
+
+/// Folders shared with `/drive:<name>,<path>` options, as `(name, path)`
+fn shared_drives(argstr: &str) -> Vec<(String, PathBuf)> {
+    argstr
+        .split_whitespace()
+        .filter_map(|arg| arg.strip_prefix("/drive:")?.split_once(','))
+        .map(|(name, path)| {
+            (
+                name.to_string(),
+                PathBuf::from(path.replace("$WHITESPACE", " ")),
+            )
+        })
+        .collect()
+}
+
+/// Converts a Unix path to its path in the guest, through the most specific shared folder.
+/// Paths outside of the shared folders are assumed to be in the home shared as `home`.
+fn to_win_shared_path(path: &str, drives: &[(String, PathBuf)]) -> String {
+    let unquoted = path.trim_matches('\'').replace("$WHITESPACE", " ");
+    let drive = drives
+        .iter()
+        .filter_map(|(name, dir)| Some((name, Path::new(&unquoted).strip_prefix(dir).ok()?)))
+        .min_by_key(|(_, relative)| relative.components().count());
+
+    match drive {
+        Some((name, relative)) => {
+            let relative = relative.to_string_lossy().replace('/', "\\");
+            format!("\\\\tsclient\\{name}\\{relative}")
+        }
+        None => to_win_escaped_path(path),
+    }
+}
+
+fn detect_and_replace_win_escape(argstr: &str) -> String {
+    const FUNC_START: &str = "$TO_WIN_ESCAPED_PATH**";
+    const FUNC_END: &str = "**";
+
+    let drives = shared_drives(argstr);
+
+    let mut result = String::new();
+    let mut current_pos = 0;
+
+    while let Some(start_relative_index) = argstr[current_pos..].find(FUNC_START) {
+        let absolute_start = current_pos + start_relative_index;
+        let arg_start = absolute_start + FUNC_START.len();
+        result.push_str(&argstr[current_pos..absolute_start]);
+
+        if let Some(end_relative_index) = argstr[arg_start..].find(FUNC_END) {
+            let absolute_end = arg_start + end_relative_index;
+            let unix_path_arg = &argstr[arg_start..absolute_end];
+            let windows_path =
+                to_win_shared_path(unix_path_arg, &drives).replace(" ", "$WHITESPACE");
+            result.push_str(&windows_path);
+            current_pos = absolute_end + FUNC_END.len();
+        } else {
+            // Error handling: FUNC_START found but no matching FUNC_END
+            result.push_str(&argstr[absolute_start..]);
+            current_pos = argstr.len();
+            break;
+        }
+    }
+
+    result.push_str(&argstr[current_pos..]);
+
+    result
+}
+
+/// Parses a Qemu memory size (`2048`, `512M`, `4G`) in megabytes
+fn parse_memory_mb(size: &str) -> Option<u64> {
+    let size = size.trim_start_matches("size=");
+    match size.chars().last()?.to_ascii_uppercase() {
+        'M' => size[..size.len() - 1].parse().ok(),
+        'G' => size[..size.len() - 1].parse::<u64>().ok().map(|s| s * 1024),
+        'T' => size[..size.len() - 1].parse::<u64>().ok().map(|s| s * 1024 * 1024),
+        _ => size.parse().ok(),
+    }
+}
+
+/// Memory of the VM in megabytes, from the `-m` option of the Qemu command
+pub(crate) fn qemu_memory_mb(qemu_command: &str) -> Option<u64> {
+    qemu_command
+        .split_whitespace()
+        .skip_while(|arg| *arg != "-m")
+        .nth(1)
+        .and_then(parse_memory_mb)
+}
+
+/// Lowers the memory (`-m`) and CPU count (`-smp`) of the Qemu command to the configured limits
+fn apply_resource_limits(
+    qemu_command: &str,
+    max_memory: Option<&str>,
+    max_cpus: Option<u32>,
+) -> Result<String> {
+    let max_memory_mb = max_memory
+        .map(|m| parse_memory_mb(m).ok_or_else(|| anyhow!("Invalid max_memory setting: {m}")))
+        .transpose()?;
+
+    let mut args: Vec<String> = qemu_command.split_whitespace().map(str::to_string).collect();
+    for i in 1..args.len() {
+        match args[i - 1].as_str() {
+            "-m" => {
+                if let (Some(max), Some(memory)) = (max_memory_mb, parse_memory_mb(&args[i]))
+                    && memory > max
+                {
+                    tracing::info!("Limiting memory from {memory}M to {max}M");
+                    args[i] = format!("{max}M");
+                }
+            }
+            "-smp" => {
+                let cpus = args[i]
+                    .split(',')
+                    .find_map(|part| part.trim_start_matches("cpus=").parse::<u32>().ok());
+                if let (Some(max), Some(cpus)) = (max_cpus, cpus)
+                    && cpus > max
+                {
+                    tracing::info!("Limiting CPUs from {cpus} to {max}");
+                    args[i] = max.to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(args.join(" "))
+}
+
+/// Replaces the `-smp` option of the Qemu command with the given topology
+fn apply_cpu_topology(qemu_command: &str, topology: CpuTopology) -> String {
+    let mut args: Vec<String> = qemu_command.split_whitespace().map(str::to_string).collect();
+    match args.iter().position(|arg| arg == "-smp") {
+        Some(i) if i + 1 < args.len() => args[i + 1] = topology.qemu_smp(),
+        _ => args.extend(["-smp".to_string(), topology.qemu_smp()]),
+    }
+
+    args.join(" ")
+}
+
+/// Backs the memory of the VM with huge pages. They are allocated when QEMU starts, so that
+/// it fails right away rather than crashing when the host runs out of them.
+fn apply_hugepages(qemu_command: &str) -> String {
+    // virtiofs already gives the VM a memory backend
+    if qemu_command.contains("memory-backend-memfd,") {
+        return qemu_command.replacen(
+            "memory-backend-memfd,",
+            "memory-backend-memfd,hugetlb=on,prealloc=on,",
+            1,
+        );
+    }
+
+    format!("{qemu_command} -mem-path /dev/hugepages -mem-prealloc")
+}
+
+/// Checks that enough huge pages are reserved on the host for the memory of the VM
+fn check_hugepages(app_id: &str, memory_mb: u64) -> Result<()> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let value = |key: &str| {
+        meminfo.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.trim_start_matches(':').trim();
+            value.trim_end_matches("kB").trim().parse::<u64>().ok()
+        })
+    };
+    let page_kb = value("Hugepagesize").unwrap_or(2048);
+    let free_pages = value("HugePages_Free").unwrap_or(0);
+    let needed_pages = (memory_mb * 1024).div_ceil(page_kb);
+    if free_pages < needed_pages {
+        return Err(error::ErrorKind::EnvironmentMissing.error(format!(
+            "The app needs {needed_pages} free huge pages, {free_pages} are available. \
+             Reserve them with `sudo sysctl vm.nr_hugepages=<count>`, or run \
+             `appack config set {app_id} hugepages false`"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Sets the time the clock of the VM keeps, keeping the other `-rtc` options of the pack
+fn apply_rtc_base(qemu_command: &str, base: RtcBase) -> String {
+    let mut args: Vec<String> = qemu_command.split_whitespace().map(str::to_string).collect();
+    let base = format!("base={}", base.as_str());
+    match args.iter().position(|arg| arg == "-rtc") {
+        Some(i) if i + 1 < args.len() => {
+            let options = args[i + 1]
+                .split(',')
+                .filter(|option| !option.starts_with("base="))
+                .map(str::to_string);
+            args[i + 1] = std::iter::once(base).chain(options).collect::<Vec<_>>().join(",");
+        }
+        _ => args.extend(["-rtc".to_string(), base]),
+    }
+
+    args.join(" ")
+}
+
+/// Sets the clock of the guest to the time of this machine. A guest resumed from an old
+/// snapshot is days behind otherwise, which breaks TLS and license checks.
+fn sync_guest_clock(app_home: &Path) {
+    let app_home = app_home.to_path_buf();
+    thread::spawn(move || {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let arguments = json!({ "time": now.as_nanos() as u64 });
+        match guest_agent::execute(&app_home, "guest-set-time", arguments) {
+            Ok(_) => tracing::debug!("Guest clock set"),
+            Err(e) => tracing::warn!("Failed to set the clock of the guest: {e:?}"),
+        }
+    });
+}
+
+/// Gives the guest the timezone and the regional format of this machine. The guest agent
+/// only answers once Windows booted, so this retries for a while.
+fn apply_host_locale(app_home: &Path) {
+    let mut script = vec![
+        "$dir = 'C:\\ProgramData\\AppPack'".to_string(),
+        "New-Item -Force -ItemType Directory $dir | Out-Null".to_string(),
+    ];
+    // Regional formats are per user, the appack-locale.ps1 task of the pack applies them
+    if let Some(locale) = host_locale::host_locale() {
+        script.push(format!("Set-Content \"$dir\\host-locale.txt\" '{locale}'"));
+        script.push("schtasks /run /tn 'AppPack locale' 2>$null | Out-Null".to_string());
+    }
+    match host_locale::host_timezone() {
+        Some(timezone) => match host_locale::windows_timezone(&timezone) {
+            Some(windows_timezone) => script.push(format!("tzutil /s '{windows_timezone}'")),
+            None => tracing::info!(timezone, "No Windows timezone is known for this timezone"),
+        },
+        None => tracing::debug!("The timezone of this machine is unknown"),
+    }
+
+    let arguments = json!({
+        "path": "powershell.exe",
+        "arg": ["-NoProfile", "-NonInteractive", "-Command", script.join("; ")],
+    });
+    let app_home = app_home.to_path_buf();
+    thread::spawn(move || {
+        let deadline = Instant::now() + Duration::from_secs(120);
+        loop {
+            match guest_agent::execute(&app_home, "guest-exec", arguments.clone()) {
+                Ok(_) => {
+                    tracing::debug!("Host locale applied to the guest");
+                    return;
+                }
+                Err(e) if Instant::now() >= deadline => {
+                    tracing::warn!("Failed to apply the host locale to the guest: {e:?}");
+                    return;
+                }
+                Err(_) => thread::sleep(Duration::from_secs(5)),
+            }
+        }
+    });
+}
+
+// This is repetitive and ugly. To refactor.
+/// Packs can't redirect host devices, only the user can allow them
+fn strip_device_redirections(freerdp_command: &str) -> String {
+    freerdp_command
+        .split_whitespace()
+        .filter(|arg| {
+            let redirects = AppCapability::DEVICES.iter().any(|c| c.is_redirected_by(arg));
+            if redirects {
+                tracing::warn!(arg, "Ignoring device redirection of the pack");
+            }
+            !redirects
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// FreeRDP executable and arguments opening a window of the app, and the passwords to write to
+/// its stdin
+fn freerdp_command_line(
+    settings: &AppPackLocalSettings,
+    host: &str,
+    rdp_port: &str,
+    app_id: &str,
+    app_name: &str,
+    freerdp_command: &str,
+    rdp_args: Option<&str>,
+) -> Result<(String, Vec<String>, Vec<String>)> {
+    let base = strip_device_redirections(freerdp_command);
+    let real_home = sandbox::real_home()?;
+
+    let mut full_cmd = match rdp_args {
+        Some(args) => format!("{} {} /v:{host}:$RDP_PORT", base, args),
+        None => format!("{} /v:{host}:$RDP_PORT", base),
+    };
+
+    full_cmd = full_cmd
+        .replace("$RDP_PORT", rdp_port)
+        .replace("$HOME", &real_home.to_string_lossy());
+
+    full_cmd = detect_and_replace_win_escape(&full_cmd);
+
+    let mut args: Vec<String> = full_cmd
+        .split_whitespace()
+        .map(|s| s.replace("$WHITESPACE", " "))
+        .collect();
+    let is_remote_app = args.iter().any(|arg| arg.starts_with("/app:"));
+    let sets_resizing = args
+        .iter()
+        .any(|arg| arg.ends_with("dynamic-resolution") || arg.ends_with("smart-sizing"));
+    if args.iter().any(|arg| arg == "/audio-mode:2") {
+        // Muted, even if the pack plays sound
+        args.retain(|arg| !arg.starts_with("/sound"));
+    } else if !args
+        .iter()
+        .any(|arg| arg.starts_with("/sound") || arg.starts_with("/audio-mode:"))
+    {
+        // Play the sound of the app on this machine
+        args.push("/sound".to_string());
+    }
+    if !is_remote_app && !sets_resizing {
+        // The guest follows the window size, `-dynamic-resolution` keeps a fixed resolution
+        args.push("/dynamic-resolution".to_string());
+    }
+
+    if !args.iter().any(|arg| arg.starts_with("/network:")) {
+        // Picks the codecs for the network, e.g. when the app is displayed on another machine
+        args.push("/network:auto".to_string());
+    }
+    if !args.iter().any(|arg| arg.starts_with("/kbd:")) {
+        // Type with the layout of this machine, Windows defaults to US
+        match keyboard::host_layout().map(|layout| keyboard::freerdp_arg(&layout)) {
+            Some(Ok(arg)) => args.push(arg),
+            Some(Err(e)) => tracing::debug!("Keeping the keyboard layout of the guest: {e}"),
+            None => {}
+        }
+    }
+    let sets_size = args.iter().any(|arg| {
+        matches!(arg.as_str(), "/f" | "/span" | "/workarea")
+            || ["/size:", "/multimon", "/monitors:"]
+                .iter()
+                .any(|prefix| arg.starts_with(prefix))
+    });
+    if !sets_size && settings.get_app_config(app_id)?.host_resolution() {
+        // Resumed sessions would keep the resolution of the screen of the pack creator
+        if let Some((width, height)) = display::primary_display_size() {
+            args.push(format!("/size:{width}x{height}"));
+        }
+    }
+    if !args.iter().any(|arg| arg.starts_with("/scale")) {
+        // Apps would be tiny on HiDPI screens
+        if let Some(percent) = scale::host_scale().filter(|percent| *percent > 100) {
+            args.extend(scale::freerdp_args(percent));
+        }
+    }
+
+    if is_remote_app {
+        // RemoteApp windows are managed by the host, a fullscreen desktop would hide them
+        args.retain(|arg| arg != "/f");
+    }
+    // Docks group the windows under the desktop entries of the app, and show its name
+    if !args.iter().any(|arg| arg.starts_with("/wm-class:")) {
+        args.push(format!("/wm-class:{}", wm_class(app_id)));
+    }
+    if !is_remote_app && !args.iter().any(|arg| arg.starts_with("/t:")) {
+        args.push(format!("/t:{app_name}"));
+    }
+
+    // The password of the keyring replaces the one of the pack, without being an argument
+    let keyring_password = match rdp_args.and_then(guest_user) {
+        Some(user) => Some(guest_password(app_id, &user)?),
+        None => None,
+    };
+    let passwords = take_passwords(&mut args, keyring_password);
+
+    let freerdp_exec = settings
+        .config
+        .rdp_client
+        .as_deref()
+        .unwrap_or(get_freerdp_executable());
+
+    Ok((freerdp_exec.to_string(), args, passwords))
+}
+
+/// Moves the passwords out of the FreeRDP arguments, which every user of the machine can read.
+/// FreeRDP reads them from stdin with `/from-stdin:force`, the password of the server first,
+/// then the one of the gateway. `password` replaces the `/p:` of the arguments.
+fn take_passwords(args: &mut Vec<String>, password: Option<String>) -> Vec<String> {
+    let mut arg_password = None;
+    args.retain(|arg| match arg.strip_prefix("/p:") {
+        Some(value) => {
+            // FreeRDP keeps the last one
+            arg_password = Some(value.to_string());
+            false
+        }
+        None => true,
+    });
+    let password = password.or(arg_password);
+
+    let mut gateway_password = None;
+    for arg in args.iter_mut() {
+        let Some(options) = arg.strip_prefix("/gateway:") else {
+            continue;
+        };
+        let mut kept = Vec::new();
+        for option in options.split(',') {
+            match option.strip_prefix("p:") {
+                Some(value) => gateway_password = Some(value.to_string()),
+                None => kept.push(option),
+            }
+        }
+        *arg = format!("/gateway:{}", kept.join(","));
+    }
+
+    if password.is_none() && gateway_password.is_none() {
+        return Vec::new();
+    }
+    args.push("/from-stdin:force".to_string());
+    // FreeRDP asks for the password of the server anyway, an empty line answers it
+    let mut passwords = vec![password.unwrap_or_default()];
+    passwords.extend(gateway_password);
+    passwords
+}
+
+/// Guest user of a desktop entry or of the launch arguments, logging in with the password of
+/// the keyring rather than the one of the pack
+fn guest_user(rdp_args: &str) -> Option<String> {
+    let args: Vec<&str> = rdp_args.split_whitespace().collect();
+    if args.iter().any(|arg| arg.starts_with("/p:")) {
+        return None;
+    }
+
+    args.iter()
+        .rev()
+        .find_map(|arg| arg.strip_prefix("/u:"))
+        .map(|user| user.replace("$WHITESPACE", " "))
+}
+
+fn guest_password(app_id: &str, user: &str) -> Result<String> {
+    let attributes = [("app", app_id), ("user", user)];
+    keyring::lookup(&attributes)?.ok_or_else(|| {
+        error::ErrorKind::EnvironmentMissing.error(format!(
+            "No password for the guest user {user} of {app_id}, store it with `{}`",
+            keyring::store_command("AppPack guest user", &attributes)
+        ))
+    })
+}
+
+/// Set for FreeRDP to the port it connects to, `appack stop` finds its windows with it
+pub(crate) const RDP_PORT_VARIABLE: &str = "APPACK_RDP_PORT";
+
+pub(crate) fn spawn_freerdp(
+    settings: &AppPackLocalSettings,
+    host: &str,
+    rdp_port: &str,
+    app_id: &str,
+    app_name: &str,
+    freerdp_command: &str,
+    rdp_args: Option<&str>,
+) -> Result<Child> {
+    let (freerdp_exec, args, passwords) = freerdp_command_line(
+        settings,
+        host,
+        rdp_port,
+        app_id,
+        app_name,
+        freerdp_command,
+        rdp_args,
+    )?;
+    tracing::info!(?args, "Launching {freerdp_exec}");
+
+    sandbox::require(&freerdp_exec)?;
+    let log = open_app_log(settings, app_id, "freerdp")?;
+    let mut command = sandbox::command_with_env(&freerdp_exec, &[(RDP_PORT_VARIABLE, rdp_port)]);
+    command.args(&args);
+    if !passwords.is_empty() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .spawn()
+        .context(format!("Failed to launch {freerdp_exec}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // One password per line, in the order FreeRDP asks for them
+        for password in &passwords {
+            writeln!(stdin, "{password}")
+                .context(format!("Failed to pass the passwords to {freerdp_exec}"))?;
+        }
+    }
+
+    Ok(child)
+}
+
+/// Hides the passwords, which may come from the keyring, from the logs
+pub(crate) fn redact_password(arg: &str) -> String {
+    if arg.starts_with("/p:") {
+        return "/p:***".to_string();
+    }
+    match arg.strip_prefix("/gateway:") {
+        Some(options) => {
+            let options: Vec<&str> = options
+                .split(',')
+                .map(|option| if option.starts_with("p:") { "p:***" } else { option })
+                .collect();
+            format!("/gateway:{}", options.join(","))
+        }
+        None => arg.to_string(),
+    }
+}
+
+fn connect_to_appack_socket_and_launch_rdp(
+    settings: &AppPackLocalSettings,
+    appack_socket_path: &Path,
+    app_installed: &InstalledAppPackEntry,
+    rdp_args: Option<&str>,
+) -> Result<()> {
+    tracing::debug!(socket = ?appack_socket_path, "Client: Connecting to AppPack socket");
+
+    let mut stream =
+        UnixStream::connect(appack_socket_path).context("Failed to connect to AppPack socket")?;
+
+    tracing::debug!("Client: Connected");
+    emit_event("connected", json!({ "socket": appack_socket_path }));
+
+    // Read server startup message (2 bytes = u16)
+    let mut rdp_port = [0u8; 2];
+    stream.read_exact(&mut rdp_port)?;
+    let rdp_port = u16::from_le_bytes(rdp_port);
+
+    tracing::debug!(rdp_port, "Client: Received RDP port");
+
+    let mut freerdp = spawn_freerdp(
+        settings,
+        "localhost",
+        &rdp_port.to_string(),
+        &app_installed.id,
+        &app_installed.name,
+        &app_installed.granted_freerdp_command(),
+        rdp_args,
+    )?;
+    emit_event("rdp_started", json!({ "rdp_port": rdp_port }));
+    let status = freerdp.wait()?;
+    emit_event("rdp_closed", json!({ "exit_code": status.code() }));
+
+    tracing::debug!("Client: Done. Disconnecting...");
+
+    // Drop the socket to disconnect
+    drop(stream);
+
+    tracing::debug!("Client: Disconnected");
+    Ok(())
+}
+
+/// Time without any client before the guest is shrunk, so that a window reopening right away
+/// doesn't wait for its memory
+const IDLE_BALLOON_DELAY: Duration = Duration::from_secs(10);
+
+/// Sent by a client of the AppPack socket to stop the VM, e.g. by `appack stop` when no
+/// window shows the app
+pub(crate) const STOP_REQUEST: u8 = b'q';
+
+/// Tells the AppPack server the VM may stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerSignal {
+    /// The last client disconnected, the VM stops once the linger period is over
+    LastClientLeft,
+    /// A client asked to stop the VM right away
+    Stop,
+}
+
+/// Blocks until a connected client disconnects, and requests the shutdown of the VM
+/// if it was the last one
+pub(crate) fn wait_for_client_disconnect(
+    stream: &mut impl Read,
+    client_count: &AtomicUsize,
+    shutdown_tx: &Sender<ServerSignal>,
+) {
+    let mut buf = [0u8; 1];
+    match stream.read_exact(&mut buf) {
+        Ok(_) if buf[0] == STOP_REQUEST => {
+            tracing::info!("Server: A client asked to stop the VM");
+            let _ = shutdown_tx.send(ServerSignal::Stop);
+        }
+        Ok(_) => {
+            tracing::warn!("Server: Received unexpected value from client: {}", buf[0]);
+        }
+        Err(ref e)
+            if e.kind() == ErrorKind::UnexpectedEof || e.kind() == ErrorKind::ConnectionReset =>
+        {
+            tracing::debug!("Server: Client disconnected gracefully");
+        }
+        Err(e) => {
+            tracing::error!("Server Handler: Error reading from socket: {e}");
+        }
+    }
+
+    client_count.fetch_sub(1, Ordering::SeqCst);
+    let c = client_count.load(Ordering::SeqCst);
+    tracing::info!(count = c, "Server Handler: Client disconnected");
+
+    // if no clients remain, notify the listener thread
+    if c == 0 {
+        // ignore send error (receiver might have been dropped)
+        let _ = shutdown_tx.send(ServerSignal::LastClientLeft);
+    }
+}
+
+/// What the AppPack server does while no client shows the app
+pub(crate) struct IdlePolicy {
+    /// Time the VM keeps running after the last client disconnected
+    pub linger: Duration,
+    pub balloon: Option<IdleBalloon>,
+}
+
+fn appack_server_logic(
+    socket_path: &Path,
+    rdp_port: u16,
+    mut idle: IdlePolicy,
+) -> std::io::Result<(Arc<AtomicUsize>, Sender<ServerSignal>, JoinHandle<()>)> {
+    let client_count = Arc::new(AtomicUsize::new(0));
+
+    // create channel in outer scope
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<ServerSignal>();
+
+    // clone a sender to move into the listener thread, keep the original to return
+    let tx_for_thread = shutdown_tx.clone();
+    let socket_path = socket_path.to_path_buf();
+    let client_count_for_thread = client_count.clone();
+
+    tracing::debug!("Launching AppPack server thread");
+    let handle = thread::spawn(move || {
+        tracing::debug!(socket = ?socket_path, "AppPack server thread spawned, binding socket");
+        let listener = match UnixListener::bind(&socket_path) {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!("Server: Error binding socket: {e}");
+                return;
+            }
+        };
+
+        // Make accept non-blocking so we can poll for a shutdown signal
+        listener
+            .set_nonblocking(true)
+            .expect("set_nonblocking failed");
+        tracing::debug!("Server: Listening for external RDP clients...");
+
+        let mut linger_until: Option<Instant> = None;
+        let mut idle_since: Option<Instant> = None;
+        loop {
+            match listener.accept() {
+                Ok((mut stream, _addr)) => {
+                    // Increment count immediately
+                    client_count_for_thread.fetch_add(1, Ordering::SeqCst);
+                    tracing::info!(
+                        count = client_count_for_thread.load(Ordering::SeqCst),
+                        "Server: New client connected"
+                    );
+
+                    // Clone handles for the handler thread.
+                    let client_count_handler = client_count_for_thread.clone();
+                    let handler_tx = tx_for_thread.clone();
+
+                    // spawn handler thread
+                    thread::spawn(move || {
+                        // Send RDP port on client connection
+                        match stream.write_all(rdp_port.to_le_bytes().as_slice()) {
+                            Ok(_) => (),
+                            Err(e) => {
+                                tracing::error!("Server: Error writing RDP port to client: {e}");
+                                return;
+                            }
+                        }
+
+                        wait_for_client_disconnect(
+                            &mut stream,
+                            &client_count_handler,
+                            &handler_tx,
+                        );
+                    });
+                }
+
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    // No connection right now: check for shutdown signal
+                    match shutdown_rx.try_recv() {
+                        Ok(ServerSignal::LastClientLeft) if !idle.linger.is_zero() => {
+                            tracing::info!(
+                                seconds = idle.linger.as_secs(),
+                                "Server: No client left, keeping the VM running"
+                            );
+                            linger_until = Some(Instant::now() + idle.linger);
+                        }
+                        Ok(_) => {
+                            tracing::debug!("Server: Shutdown requested and received. Exiting listener loop.");
+                            break;
+                        }
+                        Err(_) => {}
+                    }
+
+                    // Remote clients are counted without going through this listener
+                    if client_count_for_thread.load(Ordering::SeqCst) > 0 {
+                        linger_until = None;
+                        idle_since = None;
+                        if let Some(balloon) = &mut idle.balloon {
+                            balloon.unpark();
+                        }
+                    } else {
+                        let since = *idle_since.get_or_insert_with(Instant::now);
+                        if let Some(balloon) = &mut idle.balloon
+                            && since.elapsed() >= IDLE_BALLOON_DELAY
+                        {
+                            balloon.park();
+                        }
+                        if linger_until.is_some_and(|until| Instant::now() >= until) {
+                            tracing::info!("Server: Linger period over, stopping the VM");
+                            break;
+                        }
+                    }
+                    // small sleep to avoid busy loop
+                    thread::sleep(Duration::from_millis(500));
+                }
+
+                Err(e) => {
+                    tracing::error!("Server: Error accepting connection: {e}");
+                    break;
+                }
+            }
+        }
+
+        // Clean up socket file (ignore errors)
+        let _ = std::fs::remove_file(&socket_path);
+        tracing::debug!("Server thread exiting");
+    });
+
+    // return the original sender so caller can optionally request shutdown
+    Ok((client_count, shutdown_tx, handle))
+}
+
+/// How the FreeRDP client presents the app, on top of its arguments
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Monitors to use, the ones saved for the app when not set
+    pub monitors: Option<MonitorSelection>,
+    pub fullscreen: bool,
+    /// Initial window size, `WxH` or a percentage of the screen like `80%`
+    pub size: Option<String>,
+    /// Don't play the sound of the app, whatever its settings
+    pub mute: bool,
+    /// Lets the app record from the microphone this time
+    pub microphone: bool,
+    /// Lets the app print on the printers of this machine this time
+    pub printer: bool,
+    /// Lets the app use the smartcards and security keys of this machine this time
+    pub smartcard: bool,
+    /// Lets the app use the webcams of this machine this time
+    pub camera: bool,
+    /// Folders shared on top of the ones saved for the app
+    pub shares: Vec<SharedFolder>,
+    /// How shared folders reach the VM, the choice of the pack when not set
+    pub shared_dirs: Option<SharedDirsMode>,
+    /// Document the app opens, through its RemoteApp
+    pub open: Option<PathBuf>,
+    /// Keyboard layout of the session, the one of this machine when not set
+    pub keyboard: Option<String>,
+    /// Scale factor of the session in percent, the one of this machine when not set
+    pub scale: Option<u32>,
+}
+
+impl ClientOptions {
+    /// Whether the user allowed a device for this launch
+    fn grants(&self, capability: AppCapability) -> bool {
+        match capability {
+            AppCapability::Microphone => self.microphone,
+            AppCapability::Printer => self.printer,
+            AppCapability::Smartcard => self.smartcard,
+            AppCapability::Camera => self.camera,
+            _ => false,
+        }
+    }
+
+    fn freerdp_args(&self, settings: &AppPackLocalSettings, app_id: &str) -> Result<Vec<String>> {
+        let app_config = settings.get_app_config(app_id)?;
+        let monitors = match &self.monitors {
+            Some(monitors) => Some(monitors.clone()),
+            None => app_config.monitors()?,
+        };
+
+        let mut args: Vec<String> = monitors
+            .and_then(|m| m.freerdp_args())
+            .into_iter()
+            .collect();
+        if self.fullscreen {
+            args.push("/f".to_string());
+        }
+        if let Some(size) = &self.size {
+            args.push(format!("/size:{}", parse_window_size(size)?));
+        }
+        args.extend(
+            app_config
+                .performance()
+                .freerdp_args()
+                .iter()
+                .map(ToString::to_string),
+        );
+        if self.mute || !app_config.audio() {
+            args.push("/audio-mode:2".to_string());
+        }
+        for capability in AppCapability::DEVICES {
+            if (self.grants(capability) || app_config.allows(capability))
+                && let Some(arg) = capability.freerdp_arg()
+            {
+                args.push(arg.to_string());
+            }
+        }
+        if self.shared_dirs != Some(SharedDirsMode::Virtiofs) {
+            for share in app_config.shares()?.iter().chain(&self.shares) {
+                args.push(share.freerdp_arg()?);
+            }
+        }
+        if let Some(layout) = &self.keyboard {
+            args.push(keyboard::freerdp_arg(layout)?);
+        }
+        if let Some(percent) = self.scale {
+            args.extend(scale::freerdp_args(percent));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Checks a window size is `WxH` or a percentage like `80%`
+pub fn parse_window_size(size: &str) -> Result<String> {
+    let valid = match size.split_once('x') {
+        Some((width, height)) => width.parse::<u32>().is_ok() && height.parse::<u32>().is_ok(),
+        None => size
+            .strip_suffix('%')
+            .and_then(|percent| percent.parse::<u8>().ok())
+            .is_some_and(|percent| (1..=100).contains(&percent)),
+    };
+    if !valid {
+        return Err(anyhow!(
+            "Invalid window size '{size}', expected WxH like 1920x1080 or a percentage like 80%"
+        ));
+    }
+
+    Ok(size.to_string())
+}
+
+/// How `launch` shares the app with other machines
+#[derive(Debug, Clone, Default)]
+pub struct RemoteOptions {
+    /// Address remote clients connect to, e.g. `0.0.0.0:7000`
+    pub listen: Option<String>,
+    /// Only serve remote clients, without opening the app on this machine
+    pub headless: bool,
+}
+
+/// An app launched by `spawn_launch`
+pub struct LaunchHandle {
+    settings: AppPackLocalSettings,
+    id: String,
+    version: Option<String>,
+    thread: JoinHandle<Result<()>>,
+}
+
+impl LaunchHandle {
+    /// Whether the launch is over, because the app was closed or failed to start
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the app to be closed, and returns how the launch ended
+    pub fn wait(self) -> Result<()> {
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("The launch of {} panicked", self.id))?
+    }
+
+    /// Closes the app like `appack stop`, saving its state as its snapshot mode says, and waits
+    /// for the launch to end
+    pub fn stop(self) -> Result<()> {
+        if !self.is_finished() {
+            stop(&self.settings, &self.id, self.version.as_deref())?;
+        }
+        self.wait()
+    }
+}
+
+/// Launches an app on its own thread, the handle finishes when the app is closed.
+/// Progress is reported to the `Frontend` set with `set_frontend`.
+pub fn spawn_launch(
+    settings: AppPackLocalSettings,
+    id: String,
+    version: Option<String>,
+    rdp_args: Option<String>,
+    client: ClientOptions,
+    remote: RemoteOptions,
+) -> LaunchHandle {
+    let thread = {
+        let settings = settings.clone();
+        let id = id.clone();
+        let version = version.clone();
+        thread::spawn(move || {
+            launch(
+                &settings,
+                id,
+                version.as_deref(),
+                rdp_args.as_deref(),
+                &client,
+                &remote,
+            )
+        })
+    };
+
+    LaunchHandle {
+        settings,
+        id,
+        version,
+        thread,
+    }
+}
+
+/// Arguments from the configuration come first so they can be overridden,
+/// then the client options, then the given arguments
+pub(crate) fn with_default_rdp_args(
+    settings: &AppPackLocalSettings,
+    app_id: &str,
+    rdp_args: Option<&str>,
+    client: &ClientOptions,
+) -> Result<Option<String>> {
+    let client_args = client.freerdp_args(settings, app_id)?;
+    let args = settings
+        .config
+        .rdp_args
+        .iter()
+        .map(String::as_str)
+        .chain(client_args.iter().map(String::as_str))
+        .chain(rdp_args)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok((!args.is_empty()).then_some(args))
+}
+
+/// Passes a document to the RemoteApp of the arguments, as `$FILE` or at the end of its
+/// command line. The folder of the document is shared if it isn't already.
+pub(crate) fn with_open_file(
+    rdp_args: Option<&str>,
+    freerdp_command: &str,
+    file: &Path,
+) -> Result<String> {
+    let path = std::fs::canonicalize(file).context(format!("{} not found", file.display()))?;
+    let mut args = rdp_args.unwrap_or_default().to_string();
+    if !args.split_whitespace().any(|arg| arg.starts_with("/app:")) {
+        return Err(anyhow!(
+            "This app can't open files, only desktop entries with a remote_app can"
+        ));
+    }
+
+    let real_home = sandbox::real_home()?;
+    let drives = shared_drives(
+        &format!("{freerdp_command} {args}").replace("$HOME", &real_home.to_string_lossy()),
+    );
+    if !drives.iter().any(|(_, dir)| path.starts_with(dir)) {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        let drive = format!("/drive:appack-open,{}", parent.display());
+        args = format!("{args} {}", drive.replace(' ', "$WHITESPACE"));
+    }
+
+    let document = format!(
+        "\"$TO_WIN_ESCAPED_PATH**{}**\"",
+        path.to_string_lossy().replace(' ', "$WHITESPACE")
+    );
+    if args.contains("$FILE") {
+        return Ok(args.replace("$FILE", &document));
+    }
+
+    let args: Vec<String> = args
+        .split_whitespace()
+        .map(|arg| {
+            if !arg.starts_with("/app:") {
+                arg.to_string()
+            } else if arg.contains(",cmd:") {
+                format!("{arg}$WHITESPACE{document}")
+            } else {
+                format!("{arg},cmd:{document}")
+            }
+        })
+        .collect();
+
+    Ok(args.join(" "))
+}
+
+/// Runs a step of the VM shutdown, `None` if it takes longer than `timeout`. The QMP
+/// connection of the step is then closed, so that it returns.
+fn with_timeout<T: Send>(
+    qmp_stream: &UnixStream,
+    timeout: Duration,
+    step: impl FnOnce() -> Result<T> + Send,
+) -> Option<Result<T>> {
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        scope.spawn(move || tx.send(step()));
+
+        let result = rx.recv_timeout(timeout).ok();
+        if result.is_none() {
+            let _ = qmp_stream.shutdown(std::net::Shutdown::Both);
+        }
+        result
+    })
+}
+
+/// QEMU process of a VM, started by this process or taken over from one that crashed
+pub(crate) enum QemuProcess {
+    Child(Child),
+    /// With its QMP socket, and its pid when it is visible from here
+    Adopted(PathBuf, Option<u32>),
+}
+
+impl QemuProcess {
+    fn has_exited(&mut self) -> Result<bool> {
+        match self {
+            QemuProcess::Child(child) => Ok(child
+                .try_wait()
+                .context("Failed to wait for qemu process to exit")?
+                .is_some()),
+            QemuProcess::Adopted(qmp_socket_path, _) => {
+                Ok(UnixStream::connect(qmp_socket_path).is_err())
+            }
+        }
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        match self {
+            QemuProcess::Child(child) => {
+                child.kill().context("Failed to kill Qemu process")?;
+                child
+                    .wait()
+                    .context("Failed to wait for qemu process to exit")?;
+            }
+            QemuProcess::Adopted(_, Some(pid)) => {
+                // SAFETY: kill only sends a signal
+                if unsafe { libc::kill(*pid as i32, libc::SIGKILL) } != 0 {
+                    return Err(anyhow!(std::io::Error::last_os_error())
+                        .context("Failed to kill Qemu process"));
+                }
+            }
+            QemuProcess::Adopted(_, None) => {
+                return Err(anyhow!("Qemu runs out of reach, it can't be killed"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether QEMU exited within `timeout`
+fn wait_for_exit(qemu: &mut QemuProcess, timeout: Duration) -> Result<bool> {
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        if qemu.has_exited()? {
+            return Ok(true);
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    Ok(false)
+}
+
+/// Stops a VM hung while shutting down, e.g. with its guest stuck in logoff: asks the guest
+/// to power off, then kills QEMU. The unsaved state of the app is lost.
+fn emergency_stop(qmp_socket_path: &Path, qemu: &mut QemuProcess, timeout: Duration) -> Result<()> {
+    tracing::error!("Qemu doesn't respond while stopping, asking the guest to power off");
+    emit_event("vm_hung", json!({}));
+
+    let powerdown = UnixStream::connect(qmp_socket_path).ok().and_then(|qmp_stream| {
+        qmp_stream.set_read_timeout(Some(timeout)).ok()?;
+        let mut qmp = Qmp::from_stream(&qmp_stream);
+        qmp.handshake().ok()?;
+        qmp.execute(&qmp::system_powerdown {}).ok()
+    });
+    if powerdown.is_some() && wait_for_exit(qemu, timeout)? {
+        tracing::warn!("Guest powered off");
+        return Ok(());
+    }
+
+    tracing::error!("The guest didn't power off, killing Qemu");
+    qemu.kill()
+}
+
+/// Saves the state of the VM if its app wants it, and stops it. Returns how long saving the
+/// state took.
+pub(crate) fn shut_down_vm(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    qmp_socket_path: &Path,
+    state: &LaunchState,
+    qemu: &mut QemuProcess,
+) -> Result<Option<Duration>> {
+    // Send a QMP message to destroy VM
+    let qmp_stream = UnixStream::connect(qmp_socket_path).context(format!(
+        "Failed to connect to QMP socket ({qmp_socket_path:?})"
+    ))?;
+    let mut qmp = Qmp::from_stream(&qmp_stream);
+
+    qmp.handshake().context("Failed to connect to QMP socket")?;
+
+    detach_usb_devices(&mut qmp, state.usb_devices);
+
+    let quit_timeout = settings.config.quit_timeout();
+    let mut hung = false;
+    let mut snapshot_save = None;
+    if let AppSnapshotTriggerMode::OnClose = state.snapshot_mode {
+        let close_snapshot = CloseSnapshot::plan(settings, app)?;
+        let tag = close_snapshot.tag.as_str();
+        tracing::info!("App has snapshot mode OnClose, taking '{tag}' snapshot before quitting");
+        emit_event("snapshot_started", json!({ "snapshot": tag }));
+
+        // Wait a little bit before taking the snapshot, so the OS has time to finish the logoff
+        thread::sleep(Duration::from_millis(500));
+
+        let save_started = Instant::now();
+        let snapshot = with_timeout(&qmp_stream, settings.config.snapshot_timeout(), || {
+            // This can fail silently if the snapshot doesn't exist for example
+            for replaced in &close_snapshot.replaces {
+                let _ = delete_snapshot_blocking(&mut qmp, replaced);
+            }
+            take_snapshot_with_progress(&mut qmp, tag, &mut |progress| {
+                emit_event(
+                    "snapshot_progress",
+                    json!({ "current": progress.current, "total": progress.total }),
+                );
+            })
+        });
+        match snapshot {
+            Some(result) => {
+                result?;
+                snapshot_save = Some(save_started.elapsed());
+            }
+            None => {
+                tracing::error!("The '{tag}' snapshot timed out");
+                hung = true;
+            }
+        }
+    }
+
+    let quit = if hung {
+        None
+    } else {
+        with_timeout(&qmp_stream, quit_timeout, || {
+            qmp.execute(&qmp::quit {}).map_err(anyhow::Error::from)
+        })
+    };
+    match quit {
+        Some(Ok(_)) => {
+            if !wait_for_exit(qemu, quit_timeout)? {
+                tracing::error!("Qemu didn't exit after quit");
+                emergency_stop(qmp_socket_path, qemu, quit_timeout)?;
+            }
+        }
+        Some(Err(e)) => {
+            tracing::warn!("Failed to execute quit QMP: {e}");
+            qemu.kill()?;
+        }
+        None => emergency_stop(qmp_socket_path, qemu, quit_timeout)?,
+    };
+
+    Ok(snapshot_save)
+}
+
+/// Serves the clients of a running VM, and stops it once the last one disconnects. Returns how
+/// long saving its state took.
+fn serve_clients(
+    settings: &AppPackLocalSettings,
+    app_installed: &InstalledAppPackEntry,
+    state: &LaunchState,
+    mut qemu: QemuProcess,
+    rdp_args: Option<&str>,
+    remote: &RemoteOptions,
+    control_token: Option<String>,
+) -> Result<Option<Duration>> {
+    let app_installed_home = settings.get_app_home_dir(app_installed);
+    let qmp_socket_path = app_installed_home.join("qmp-appack.sock");
+    let appack_socket_path = app_installed_home.join("appack.sock");
+
+    // Left behind by the crashed launcher of an adopted VM
+    let _ = std::fs::remove_file(&appack_socket_path);
+    let idle_memory = settings
+        .config
+        .idle_memory
+        .as_deref()
+        .map(|m| parse_memory_mb(m).ok_or_else(|| anyhow!("Invalid idle_memory setting: {m}")))
+        .transpose()?;
+    let idle = IdlePolicy {
+        linger: settings.config.linger(),
+        balloon: idle_memory.map(|mb| IdleBalloon::new(qmp_socket_path.clone(), mb)),
+    };
+    let (client_count, shutdown_tx, handle) =
+        appack_server_logic(&appack_socket_path, state.rdp_port, idle)?;
+
+    let remote_server = match (&remote.listen, control_token) {
+        (Some(address), Some(token)) => {
+            let session = RemoteSession {
+                app_id: app_installed.id.clone(),
+                app_name: app_installed.name.clone(),
+                rdp_port: state.rdp_port,
+                freerdp_command: app_installed.granted_freerdp_command(),
+            };
+            Some(serve_remote(address, token, session, client_count, shutdown_tx)?)
+        }
+        _ => None,
+    };
+
+    // Just wait a little bit to make sure the server thread started
+    thread::sleep(Duration::from_millis(50));
+
+    if !remote.headless {
+        match connect_to_appack_socket_and_launch_rdp(
+            settings,
+            &appack_socket_path,
+            app_installed,
+            rdp_args,
+        ) {
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Failed to connect to appack socket as same process: {e}");
+            }
+        }
+    }
+
+    handle
+        .join()
+        .map_err(|e| anyhow!("Could not join handle: {e:?}"))?;
+    if let Some(remote_server) = remote_server {
+        remote_server.stop()?;
+    }
+
+    tracing::info!("All RDP sessions finished. Killing QEMU.");
+
+    // Until the state is saved and the retention policy applied
+    let _stopping = settings.lock_app(&app_installed.id)?;
+    let snapshot_save = shut_down_vm(settings, app_installed, &qmp_socket_path, state, &mut qemu)?;
+
+    tracing::info!("Qemu exited");
+    let _ = std::fs::remove_file(app_installed_home.join(RDP_PORT_FILE));
+    LaunchState::remove(&app_installed_home);
+    emit_event("vm_stopped", json!({}));
+    if let Err(e) = retention::enforce(settings, app_installed) {
+        tracing::warn!("Failed to apply the retention policy: {e:?}");
+    }
+
+    Ok(snapshot_save)
+}
+
+/// FreeRDP arguments of a launch, and the folders shared with virtiofs
+fn client_launch_options(
+    settings: &AppPackLocalSettings,
+    app_installed: &InstalledAppPackEntry,
+    rdp_args: Option<&str>,
+    client: &ClientOptions,
+) -> Result<(Option<String>, Vec<SharedFolder>)> {
+    let shared_dirs = client.shared_dirs.unwrap_or(app_installed.shared_dirs);
+    let client = &ClientOptions {
+        shared_dirs: Some(shared_dirs),
+        ..client.clone()
+    };
+    let mut rdp_args = with_default_rdp_args(settings, &app_installed.id, rdp_args, client)?;
+    if let Some(file) = &client.open {
+        let args = with_open_file(rdp_args.as_deref(), &app_installed.freerdp_command, file)?;
+        rdp_args = Some(args);
+    }
+    let virtiofs_shares = match shared_dirs {
+        SharedDirsMode::Virtiofs => settings
+            .get_app_config(&app_installed.id)?
+            .shares()?
+            .into_iter()
+            .chain(client.shares.iter().cloned())
+            .collect(),
+        SharedDirsMode::Rdp => Vec::new(),
+    };
+
+    Ok((rdp_args, virtiofs_shares))
+}
+
+/// QEMU command line of a launch, and the snapshot it starts from
+struct QemuLaunch {
+    command: String,
+    snapshot_mode: AppSnapshotTriggerMode,
+    /// `None` for a cold boot
+    snapshot: Option<String>,
+}
+
+/// Builds the QEMU command line of an app from its pack and its settings, without starting
+/// anything
+fn qemu_launch(
+    settings: &AppPackLocalSettings,
+    app_installed: &InstalledAppPackEntry,
+    app_config: &AppPackAppConfig,
+    rdp_port: u16,
+    virtiofs_shares: &[SharedFolder],
+) -> Result<QemuLaunch> {
+    let app_installed_home = settings.get_app_home_dir(app_installed);
+    let absolute_image_file_path = app_installed_home.join(&app_installed.image);
+
+    let mut qemu_command_str = app_installed.qemu_command.clone();
+    qemu_command_str = qemu_command_str.replace("$RDP_PORT", &rdp_port.to_string());
+    qemu_command_str = qemu_command_str.replace(
+        "$IMAGE_FILE_PATH",
+        absolute_image_file_path.to_str().unwrap(),
+    );
+
+    let network = app_installed.declares(AppCapability::Network);
+    let pack_command = NetworkPolicy::of_pack(app_installed.network_allow.as_deref(), network)?
+        .apply(&qemu_command_str)?;
+    qemu_command_str = app_config
+        .network_policy(app_installed.network_allow.as_deref(), network)?
+        .apply(&qemu_command_str)?;
+    if let Some(topology) = app_config.cpu_topology()? {
+        qemu_command_str = apply_cpu_topology(&qemu_command_str, topology);
+    }
+
+    // virtiofs devices can't be saved in snapshots
+    let snapshot_mode = if !virtiofs_shares.is_empty() {
+        tracing::info!("Folders are shared with virtiofs, doing cold boot without snapshots");
+        AppSnapshotTriggerMode::NeverLoad
+    } else if qemu_command_str != pack_command {
+        // Snapshots only load on the CPUs and the network they were taken with
+        tracing::info!(
+            "The CPU topology or the network policy is changed, doing cold boot without snapshots"
+        );
+        AppSnapshotTriggerMode::NeverLoad
+    } else {
+        app_installed.snapshot_mode.clone()
+    };
+    let snapshot = match snapshot_mode {
+        // Never load any state, cold boot
+        AppSnapshotTriggerMode::NeverLoad => None,
+
+        // Always load the same startup state
+        AppSnapshotTriggerMode::Never => {
+            if has_snapshot("appack-init", &absolute_image_file_path)? {
+                Some("appack-init".to_string())
+            } else {
+                return Err(anyhow!("Missing snapshot 'appack-init' from image")
+                    .context("The AppPack hasn't been packaged properly"));
+            }
+        }
+
+        // Load the most significant or none at all
+        AppSnapshotTriggerMode::OnClose => {
+            if let Some(newest) = close_snapshots(&absolute_image_file_path)?.pop() {
+                Some(newest)
+            } else if has_snapshot("appack-init", &absolute_image_file_path)? {
+                tracing::info!(
+                    "AppPack doesn't have a running state, using 'appack-init' snapshot as backup"
+                );
+                Some("appack-init".to_string())
+            } else {
+                tracing::info!("AppPack doesn't have any live state, doing cold boot as backup");
+                None
+            }
+        }
+    };
+    if let Some(snapshot) = &snapshot {
+        qemu_command_str = format!("{qemu_command_str} -loadvm {snapshot}");
+    }
+
+    qemu_command_str = apply_resource_limits(
+        &qemu_command_str,
+        settings.config.max_memory.as_deref(),
+        settings.config.max_cpus,
+    )?;
+    if let Some(rtc) = app_config.rtc {
+        qemu_command_str = apply_rtc_base(&qemu_command_str, rtc);
+    }
+    qemu_command_str = app_installed.arch.with_defaults(&qemu_command_str);
+    if app_installed.requires_nested_virt {
+        qemu_command_str = nested_virt::with_nested_virt(&qemu_command_str)?;
+    }
+    if !app_installed.qemu_unconfined {
+        qemu_command_str =
+            qemu_sandbox::with_confinement(&qemu_command_str, &app_installed.arch.qemu_binary());
+    }
+
+    if !virtiofs_shares.is_empty() {
+        let memory_mb = qemu_memory_mb(&qemu_command_str)
+            .ok_or_else(|| anyhow!("virtiofs needs the memory of the VM to be set with -m"))?;
+        let exports = virtiofs::qemu_args(virtiofs_shares, memory_mb);
+        qemu_command_str = format!("{qemu_command_str} {exports}");
+    }
+
+    if app_config.hugepages() {
+        let memory_mb = qemu_memory_mb(&qemu_command_str)
+            .ok_or_else(|| anyhow!("Huge pages need the memory of the VM to be set with -m"))?;
+        check_hugepages(&app_installed.id, memory_mb)?;
+        qemu_command_str = apply_hugepages(&qemu_command_str);
+    }
+
+    // Devices are part of the snapshots, only VMs booting from scratch can get one more
+    if matches!(snapshot_mode, AppSnapshotTriggerMode::NeverLoad)
+        && !qemu_command_str.contains("virtio-balloon")
+    {
+        qemu_command_str = format!("{qemu_command_str} -device virtio-balloon-pci,id=appack-balloon");
+    }
+
+    // A monitor of its own for `appack events`, QEMU serves one client per monitor. Monitors
+    // are not part of the snapshots.
+    qemu_command_str = format!("{qemu_command_str} -qmp unix:{EVENTS_SOCKET},server=on,wait=off");
+    qemu_command_str = format!("{qemu_command_str} -pidfile {PID_FILE}");
+
+    Ok(QemuLaunch {
+        command: qemu_command_str,
+        snapshot_mode,
+        snapshot,
+    })
+}
+
+/// Command line as typed in a shell
+fn shell_command(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_=,.:/+@%".contains(c));
+            if plain {
+                arg.to_string()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prints the QEMU and FreeRDP command lines `launch` would run, and the snapshot it would
+/// load, without starting anything
+pub fn print_launch_command(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    rdp_args: Option<&str>,
+    client: &ClientOptions,
+) -> Result<()> {
+    let app_installed = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    let (rdp_args, virtiofs_shares) =
+        client_launch_options(settings, &app_installed, rdp_args, client)?;
+
+    // A running app only gets one more window
+    let running_port = running_rdp_port(settings, &app_installed);
+    let rdp_port = match running_port {
+        Some(port) => port,
+        None => get_os_assigned_port()?,
+    };
+    let qemu = match running_port {
+        Some(_) => None,
+        None => {
+            let app_config = settings.get_app_config(id)?;
+            Some(qemu_launch(settings, &app_installed, &app_config, rdp_port, &virtiofs_shares)?)
+        }
+    };
+    let (freerdp_exec, freerdp_args, _) = freerdp_command_line(
+        settings,
+        "localhost",
+        &rdp_port.to_string(),
+        &app_installed.id,
+        &app_installed.name,
+        &app_installed.granted_freerdp_command(),
+        rdp_args.as_deref(),
+    )?;
+    let freerdp = shell_command(&freerdp_exec, &freerdp_args);
+
+    let qemu = qemu.map(|qemu| {
+        let args: Vec<String> = qemu.command.split_whitespace().map(str::to_string).collect();
+        (qemu, shell_command(&app_installed.arch.qemu_binary(), &args))
+    });
+    match &qemu {
+        Some((launch, command)) => {
+            message!("Snapshot mode: {:?}", launch.snapshot_mode);
+            message!("Snapshot: {}", launch.snapshot.as_deref().unwrap_or("none, cold boot"));
+            message!("RDP port: {rdp_port}");
+            let home = settings.get_app_home_dir(&app_installed);
+            message!("QEMU, run from {}:\n{command}", home.display());
+        }
+        None => {
+            message!("{id} is running on RDP port {rdp_port}, launching it only opens a window")
+        }
+    }
+    message!("FreeRDP:\n{freerdp}");
+
+    set_json_result(&json!({
+        "running": running_port.is_some(),
+        "rdp_port": rdp_port,
+        "snapshot_mode": qemu.as_ref().map(|(launch, _)| &launch.snapshot_mode),
+        "snapshot": qemu.as_ref().and_then(|(launch, _)| launch.snapshot.as_deref()),
+        "qemu": qemu.as_ref().map(|(_, command)| command),
+        "freerdp": freerdp,
+    }))
+}
+
+pub fn launch(
+    settings: &AppPackLocalSettings,
+    id: String,
+    version: Option<&str>,
+    rdp_args: Option<&str>,
+    client: &ClientOptions,
+    remote: &RemoteOptions,
+) -> Result<()> {
+    let app_installed = settings
+        .get_app_installed(&id, version)
+        .context("Failed to get installed AppPack")?;
+    let app_installed_home = settings.get_app_home_dir(&app_installed);
+    let qmp_socket_path = app_installed_home.join("qmp-appack.sock");
+    snap::require_plugs()?;
+    let appack_socket_path = app_installed_home.join("appack.sock");
+
+    let (rdp_args, virtiofs_shares) =
+        client_launch_options(settings, &app_installed, rdp_args, client)?;
+    let app_config = settings.get_app_config(&id)?;
+    for capability in &app_installed.capabilities {
+        if !capability.is_device() || client.grants(*capability) || app_config.allows(*capability) {
+            continue;
+        }
+
+        let device = capability.config_key();
+        let hint = if capability.can_be_saved() {
+            tr!("hint-capability", name = app_installed.name, id = id, device = device)
+        } else {
+            tr!("hint-capability-each-launch", name = app_installed.name, id = id, device = device)
+        };
+        tracing::info!("{hint}");
+    }
+
+    let control_token = match &remote.listen {
+        Some(_) => Some(settings.config.control_token.clone().ok_or_else(|| {
+            anyhow!("Set control_token with `appack config set control_token <token>` to listen for remote clients")
+        })?),
+        None => None,
+    };
+
+    tracing::info!(id, ?version, ?rdp_args, "Launching AppPack");
+    remove_stale_files(&app_installed_home);
+
+    if remote.headless && UnixStream::connect(&appack_socket_path).is_ok() {
+        return Err(error::ErrorKind::VmRunning.error(format!("{id} is already running")));
+    }
+
+    if !remote.headless {
+        match connect_to_appack_socket_and_launch_rdp(
+            settings,
+            &appack_socket_path,
+            &app_installed,
+            rdp_args.as_deref(),
+        ) {
+            Ok(_) => {
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::info!("Failed to connect to appack socket, starting server: {e}");
+            }
+        }
+    }
+
+    // The VM outlived the process that launched it, e.g. after a crash
+    if let Some(launch_state) = LaunchState::orphaned(&app_installed_home)
+        && let Ok(_supervisor) = launch_state.supervise(&app_installed_home)
+    {
+        tracing::warn!("{id} was still running without AppPack, taking it over");
+        serve_urls(settings, &app_installed, &app_installed_home.join(URL_SOCKET));
+        emit_event("vm_adopted", json!({ "rdp_port": launch_state.rdp_port }));
+        let qemu = QemuProcess::Adopted(qmp_socket_path, qemu_pid(&app_installed_home));
+        serve_clients(
+            settings,
+            &app_installed,
+            &launch_state,
+            qemu,
+            rdp_args.as_deref(),
+            remote,
+            control_token,
+        )?;
+        return Ok(());
+    }
+
+    // Wait util it's not possible to connect to the QMP socket
+    // This is to handle the case when a user is trying to relaunch an appack when it's doing an OnClose snapshot
+    // or shutting down
+    {
+        let mut notif_shown = false;
+        while UnixStream::connect(&qmp_socket_path).is_ok() {
+            if !notif_shown {
+                notify(
+                    &settings.config,
+                    NotificationLevel::Info,
+                    &tr!("notification-opening-soon", name = app_installed.name),
+                    &tr!("notification-be-patient"),
+                );
+                notif_shown = true;
+            }
+
+            tracing::info!(
+                "It looks like a VM is still running for this AppPack.. Waiting for it to close"
+            );
+            thread::sleep(Duration::from_millis(300));
+        }
+    }
+
+    check_quotas(settings, &app_installed)?;
+    // Until the VM runs, e.g. `appack reset` would delete the snapshot it starts from
+    let starting = settings.lock_app(&id)?;
+
+    {
+        let settings = settings.clone();
+        let app_installed = app_installed.clone();
+        thread::spawn(move || {
+            if let Err(e) = notify_if_update_available(&settings, &app_installed) {
+                tracing::warn!("Failed to check for updates: {e:?}");
+            }
+        });
+    }
+
+    if app_installed.system {
+        ensure_user_overlay(settings, &app_installed)?;
+    }
+    // Installs of the same image share it until one of them runs
+    pool::unshare(settings, &app_installed_home.join(&app_installed.image))?;
+
+    let free_port = get_os_assigned_port()?;
+    let QemuLaunch {
+        command: qemu_command_str,
+        snapshot_mode,
+        snapshot,
+    } = qemu_launch(settings, &app_installed, &app_config, free_port, &virtiofs_shares)?;
+    if snapshot.is_none() && matches!(snapshot_mode, AppSnapshotTriggerMode::OnClose) {
+        notify(
+            &settings.config,
+            NotificationLevel::Info,
+            &tr!("notification-first-launch", name = app_installed.name),
+            &tr!("notification-be-patient"),
+        );
+    }
+
+    let _virtiofs_exports = if virtiofs_shares.is_empty() {
+        None
+    } else {
+        let log = open_app_log(settings, &app_installed.id, "virtiofsd")?;
+        Some(virtiofs::start_exports(&app_installed_home, &virtiofs_shares, &log)?)
+    };
+    // Left by the previous launch, QEMU creates it again
+    let _ = std::fs::remove_file(app_installed_home.join(EVENTS_SOCKET));
+
+    let mut usb_devices = app_config.usb_devices()?;
+    if !usb_devices.is_empty() && !app_installed.declares(AppCapability::Usb) {
+        tracing::warn!("{} didn't declare the usb capability, ignoring usb_devices", id);
+        usb_devices.clear();
+    }
+    let cpu_pinning = app_config.cpu_pinning()?;
+    let launch_state = LaunchState {
+        rdp_port: free_port,
+        snapshot_mode,
+        usb_devices: usb_devices.len(),
+    };
+    sandbox::require(&app_installed.arch.qemu_binary())?;
+    // Held before anything is written, the stale files of a crashed launch are told apart
+    // with it
+    let _supervisor = launch_state.supervise(&app_installed_home)?;
+    std::fs::write(app_installed_home.join(RDP_PORT_FILE), free_port.to_string())
+        .context("Failed to write the RDP port file")?;
+
+    tracing::info!(params = %qemu_command_str, "Starting Qemu");
+    let qemu_command_args = qemu_command_str.split_whitespace().collect::<Vec<&str>>();
+
+    let mut qemu_command = sandbox::command(&app_installed.arch.qemu_binary());
+    qemu_command
+        .current_dir(&app_installed_home) // Necessary to make the qmp socket in the dir, although we could find and replace it like other vars it
+        .args(qemu_command_args);
+    let qemu_log = open_app_log(settings, &app_installed.id, "qemu")?;
+    qemu_command.stdout(qemu_log.try_clone()?).stderr(qemu_log);
+    let mut timer = LaunchTimer::start(qemu_command_str.contains("-loadvm"));
+    let mut qemu_child = qemu_command.spawn().context("Failed to start Qemu")?;
+    emit_event("vm_starting", json!({ "pid": qemu_child.id() }));
+
+    // Wait for qmp socket to be available
+    loop {
+        match qemu_child.try_wait() {
+            // 1. Ok(None): Child is STILL RUNNING
+            Ok(None) => {
+                match UnixStream::connect(&qmp_socket_path) {
+                    Ok(_) => {
+                        break;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Waiting for QMP socket connection: {e}");
+                        thread::sleep(Duration::from_millis(200));
+                    }
+                };
+            }
+
+            // 2. Ok(Some(status)): Child has EXITED
+            Ok(Some(status)) => {
+                tracing::error!(%status, "QEMU process unexpectedly exited");
+
+                notify(
+                    &settings.config,
+                    NotificationLevel::Error,
+                    &tr!("notification-virtualization-error"),
+                    &tr!("notification-virtualization-error-body"),
+                );
+
+                return Err(anyhow!(
+                    "QEMU process died before QMP socket was ready. Its output is in {}",
+                    settings.logs_dir.join(&app_installed.id).join("qemu.log").display()
+                )
+                .context(tr!("hint-qemu-failed")));
+            }
+
+            // 3. Err(e): An error occurred while trying to check the status
+            Err(e) => {
+                return Err(anyhow!(e).context("Error while checking QEMU status"));
+            }
+        }
+    }
+
+    tracing::debug!("QMP socket is ready");
+    drop(starting);
+    timer.qmp_ready(settings, &app_installed, free_port);
+    serve_urls(settings, &app_installed, &app_installed_home.join(URL_SOCKET));
+    if qemu_command_str.contains("-loadvm") && app_config.time_sync() {
+        sync_guest_clock(&app_installed_home);
+    }
+    if app_config.host_locale() {
+        apply_host_locale(&app_installed_home);
+    }
+
+    if !usb_devices.is_empty() || cpu_pinning.is_some() {
+        let qmp_stream = UnixStream::connect(&qmp_socket_path).context(format!(
+            "Failed to connect to QMP socket ({qmp_socket_path:?})"
+        ))?;
+        let mut qmp = Qmp::from_stream(&qmp_stream);
+        qmp.handshake().context("Failed to connect to QMP socket")?;
+
+        // The app can still start without them, e.g. if a device is unplugged
+        if let Err(e) = attach_usb_devices(&mut qmp, &usb_devices) {
+            tracing::warn!("{e:?}");
+        }
+        if let Some(pinning) = &cpu_pinning
+            && let Err(e) = pin_vcpus(&mut qmp, pinning)
+        {
+            tracing::warn!("{e:?}");
+        }
+    }
+    emit_event("vm_started", json!({ "rdp_port": free_port }));
+
+    let snapshot_save = serve_clients(
+        settings,
+        &app_installed,
+        &launch_state,
+        QemuProcess::Child(qemu_child),
+        rdp_args.as_deref(),
+        remote,
+        control_token,
+    )?;
+    timer.finish(settings, &app_installed, snapshot_save);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_limits() {
+        let command = "-enable-kvm -m 8G -smp 8 -drive file=x";
+        assert_eq!(
+            apply_resource_limits(command, Some("4G"), Some(2)).unwrap(),
+            "-enable-kvm -m 4096M -smp 2 -drive file=x"
+        );
+        assert_eq!(
+            apply_resource_limits(command, Some("16G"), None).unwrap(),
+            command
+        );
+        assert!(apply_resource_limits(command, Some("lots"), None).is_err());
+    }
+
+    #[test]
+    fn test_stop_request() {
+        let client_count = AtomicUsize::new(2);
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        wait_for_client_disconnect(&mut &[STOP_REQUEST][..], &client_count, &shutdown_tx);
+        assert_eq!(shutdown_rx.try_recv(), Ok(ServerSignal::Stop));
+        assert_eq!(client_count.load(Ordering::SeqCst), 1);
+
+        wait_for_client_disconnect(&mut &[][..], &client_count, &shutdown_tx);
+        assert_eq!(shutdown_rx.try_recv(), Ok(ServerSignal::LastClientLeft));
+    }
+
+    #[test]
+    fn test_cpu_and_memory_tuning() {
+        let topology = CpuTopology {
+            cores: 4,
+            threads: 2,
+        };
+        assert_eq!(
+            apply_cpu_topology("-m 4G -smp 4 -drive file=x", topology),
+            "-m 4G -smp 8,sockets=1,cores=4,threads=2 -drive file=x"
+        );
+        assert_eq!(
+            apply_hugepages("-m 4G"),
+            "-m 4G -mem-path /dev/hugepages -mem-prealloc"
+        );
+        assert_eq!(
+            apply_hugepages("-object memory-backend-memfd,id=appack-mem,size=4096M,share=on"),
+            "-object memory-backend-memfd,hugetlb=on,prealloc=on,id=appack-mem,size=4096M,share=on"
+        );
+    }
+
+    #[test]
+    fn test_rtc_base() {
+        assert_eq!(
+            apply_rtc_base("-m 4G -rtc base=localtime,driftfix=slew", RtcBase::Utc),
+            "-m 4G -rtc base=utc,driftfix=slew"
+        );
+        assert_eq!(
+            apply_rtc_base("-m 4G", RtcBase::Localtime),
+            "-m 4G -rtc base=localtime"
+        );
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let (stream, _qemu) = UnixStream::pair().unwrap();
+        let hung = with_timeout(&stream, Duration::from_millis(100), || {
+            let mut reply = [0u8; 1];
+            Ok((&stream).read(&mut reply)?)
+        });
+        assert!(hung.is_none());
+
+        let done = with_timeout(&stream, Duration::from_secs(5), || Ok(1));
+        assert_eq!(done.unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_open_file() {
+        let dir = std::env::temp_dir().join("appack open test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("report.docx");
+        std::fs::write(&file, "").unwrap();
+        let drive = format!("/drive:docs,{}", dir.display()).replace(' ', "$WHITESPACE");
+
+        let args = with_open_file(Some("/app:program:word.exe"), &drive, &file).unwrap();
+        assert_eq!(
+            detect_and_replace_win_escape(&format!("{drive} {args}")),
+            format!("{drive} /app:program:word.exe,cmd:\"\\\\tsclient\\docs\\report.docx\"")
+        );
+
+        let args = with_open_file(Some("/app:program:word.exe,cmd:/q$WHITESPACE$FILE"), "", &file)
+            .unwrap();
+        assert!(args.contains("/drive:appack-open,"));
+        let args = detect_and_replace_win_escape(&args);
+        assert!(args.starts_with(
+            "/app:program:word.exe,cmd:/q$WHITESPACE\"\\\\tsclient\\appack-open\\report.docx\""
+        ));
+
+        assert!(with_open_file(Some("/f"), "", &file).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let args = ["/v:localhost:3389".to_string(), "/t:My App".to_string(), "it's".to_string()];
+        assert_eq!(
+            shell_command("xfreerdp3", &args),
+            r"xfreerdp3 /v:localhost:3389 '/t:My App' 'it'\''s'"
+        );
+    }
+
+    #[test]
+    fn test_redact_password() {
+        assert_eq!(
+            redact_password("/gateway:g:gw.corp:443,u:alice,p:hunter2,d:CORP"),
+            "/gateway:g:gw.corp:443,u:alice,p:***,d:CORP"
+        );
+        assert_eq!(redact_password("/sound"), "/sound");
+        assert_eq!(redact_password("/p:hunter2"), "/p:***");
+
+        let gateway = "/gateway:g:gw,u:bob,p:pw";
+        let mut args = ["/u:alice", "/p:old", "/p:hunter2", gateway]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(take_passwords(&mut args.clone(), None), ["hunter2", "pw"]);
+        assert_eq!(take_passwords(&mut args, Some("keyring".into())), ["keyring", "pw"]);
+        let expected = ["/u:alice", "/gateway:g:gw,u:bob", "/from-stdin:force"];
+        assert_eq!(args, expected);
+        let mut args = vec![gateway.to_string()];
+        assert_eq!(take_passwords(&mut args, None), ["", "pw"]);
+        let mut args = vec!["/u:alice".to_string()];
+        assert!(take_passwords(&mut args, None).is_empty());
+        assert_eq!(args, ["/u:alice"]);
+        assert_eq!(take_passwords(&mut args, Some("keyring".into())), ["keyring"]);
+        assert_eq!(args, ["/u:alice", "/from-stdin:force"]);
+        assert_eq!(guest_user("/u:Admin /app:program:mmc.exe").as_deref(), Some("Admin"));
+        assert_eq!(guest_user("/u:Admin /p:secret"), None);
+    }
+
+    #[test]
+    fn test_win_shared_path() {
+        let drives = shared_drives("/u:User /drive:home,/home/alice /drive:scans,/mnt/My$WHITESPACEScans");
+        assert_eq!(
+            to_win_shared_path("/mnt/My Scans/doc.pdf", &drives),
+            "\\\\tsclient\\scans\\doc.pdf"
+        );
+        assert_eq!(
+            to_win_shared_path("/home/alice/notes.txt", &drives),
+            "\\\\tsclient\\home\\notes.txt"
+        );
+        assert_eq!(
+            to_win_shared_path("/srv/file", &drives),
+            "\\\\tsclient\\home\\srv\\file"
+        );
+    }
+
+    #[test]
+    fn test_strip_device_redirections() {
+        assert_eq!(
+            strip_device_redirections("/u:User /microphone:sys:pulse /mic +clipboard"),
+            "/u:User +clipboard"
+        );
+    }
+
+    #[test]
+    fn test_window_size() {
+        assert!(parse_window_size("1920x1080").is_ok());
+        assert!(parse_window_size("80%").is_ok());
+        assert!(parse_window_size("150%").is_err());
+        assert!(parse_window_size("big").is_err());
+    }
+
+    #[test]
+    fn test_path_with_username_and_file() {
+        // Test case: standard path with username and file
+        let path = "/home/john_doe/documents/report.pdf";
+        let expected = "\\\\tsclient\\home\\documents\\report.pdf";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_with_different_username() {
+        // Test case: different username
+        let path = "/home/dev-user/code/main.rs";
+        let expected = "\\\\tsclient\\home\\code\\main.rs";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_with_no_trailing_file() {
+        // Test case: path is just a directory after the username
+        let path = "/home/alice/Projects/";
+        let expected = "\\\\tsclient\\home\\Projects\\";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_is_only_root_home() {
+        // Test case: path is exactly /home/{username} (edge case, result is the root share path)
+        let path = "/home/bob";
+        let expected = "\\\\tsclient\\home\\home\\bob";
+        // NOTE: The current simple implementation relies on finding the *next* slash.
+        // If the input path is exactly `/home/username`, the implementation assumes it's
+        // not a valid path and doesn't strip it, leaving it as a relative path.
+        // If the desired output for `/home/bob` is `\\\\tsclient\\home\\`, then the
+        // function's logic needs more complexity. Sticking to the primary request:
+        // /home/anyusername/ is the pattern to remove. Since there's no trailing '/',
+        // the path is NOT stripped.
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_is_only_root_home_with_slash() {
+        // Test case: path is exactly /home/{username}/ (should be stripped to empty)
+        let path = "/home/bob/";
+        let expected = "\\\\tsclient\\home\\";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_already_stripped() {
+        // Test case: path does not start with /home/
+        let path = "/tmp/data/log.txt";
+        let expected = "\\\\tsclient\\home\\tmp\\data\\log.txt";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_relative_path() {
+        // Test case: relative path
+        let path = "data/input.csv";
+        let expected = "\\\\tsclient\\home\\data\\input.csv";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_empty_path() {
+        // Test case: empty path
+        let path = "";
+        let expected = "";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_path_with_leading_slash_only() {
+        // Test case: just a leading slash (should result in the base path)
+        let path = "/";
+        let expected = "\\\\tsclient\\home\\";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_with_space() {
+        let path = "/home/dude/i have space/file.txt";
+        let expected = "\\\\tsclient\\home\\i have space\\file.txt";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+
+    #[test]
+    fn test_with_space_and_single_quotes() {
+        let path = "'/home/dude/i have space/file.txt'";
+        let expected = "\\\\tsclient\\home\\i have space\\file.txt";
+        assert_eq!(to_win_escaped_path(path), expected);
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::output::set_json_result;
+use crate::utils::prompt::pick;
+use anyhow::Result;
+
+pub fn list_installed(settings: AppPackLocalSettings) -> Result<()> {
+    let mut installed_apps = settings.get_installed()?;
+    installed_apps
+        .installed
+        .extend(settings.get_system_installed()?.installed);
+    set_json_result(&installed_apps.installed)?;
+    message!("Installed app packs:");
+    message!("{:#?}", installed_apps); // Todo impl display or something
+    if !settings.config.aliases.is_empty() {
+        message!("Aliases:");
+        for (alias, target) in &settings.config.aliases {
+            message!("  {alias} -> {target}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Lets the user pick one of the installed apps and versions, for commands run without an id
+pub fn pick_installed_app(settings: &AppPackLocalSettings) -> Result<InstalledAppPackEntry> {
+    let mut installed = settings.get_installed()?.installed;
+    installed.extend(settings.get_system_installed()?.installed);
+    if installed.is_empty() {
+        return Err(ErrorKind::NotInstalled.error("No app is installed"));
+    }
+
+    let options: Vec<String> = installed
+        .iter()
+        .map(|app| {
+            let system = if app.system { " (system)" } else { "" };
+            format!("{} {}\t{}{system}", app.name, app.version, app.id)
+        })
+        .collect();
+    let choice = pick(&tr!("prompt-pick-app"), &options, "the id of the app")?;
+
+    Ok(installed.swap_remove(choice))
+}
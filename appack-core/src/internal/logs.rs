@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::retention::RetentionPolicy;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::rate_limit::parse_size;
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Rotated copies kept of each app log (`qemu.log.1` to `qemu.log.5`)
+const ROTATED_LOG_FILES: u32 = 5;
+const DEFAULT_MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_LOG_RETENTION_DAYS: u32 = 7;
+
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
+
+fn max_log_size(settings: &AppPackLocalSettings) -> Result<u64> {
+    settings
+        .config
+        .max_log_size
+        .as_deref()
+        .map(parse_size)
+        .transpose()
+        .context("Invalid max_log_size setting")
+        .map(|size| size.unwrap_or(DEFAULT_MAX_LOG_SIZE))
+}
+
+/// Renames `name.log` to `name.log.1`, `name.log.1` to `name.log.2` and so on
+fn rotate(path: &Path) -> Result<()> {
+    for index in (1..ROTATED_LOG_FILES).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))?;
+
+    Ok(())
+}
+
+/// Removes the log files of a directory that were not written to during the retention period,
+/// returns how many
+pub(crate) fn remove_expired(dir: &Path, retention: Duration) -> Result<usize> {
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let expired = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > retention);
+
+        if expired && path.is_file() && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Opens the log file of an app for appending, e.g. `logs/<id>/qemu.log`,
+/// rotating it first if it is too big
+pub fn open_app_log(settings: &AppPackLocalSettings, id: &str, name: &str) -> Result<File> {
+    let dir = settings.logs_dir.join(id);
+    std::fs::create_dir_all(&dir)
+        .context(format!("Failed to create log directory {}", dir.display()))?;
+    remove_expired(&dir, RetentionPolicy::of(settings, id)?.log_retention)?;
+
+    let path = dir.join(format!("{name}.log"));
+    let max_size = max_log_size(settings)?;
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() > max_size) {
+        rotate(&path).context(format!("Failed to rotate {}", path.display()))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context(format!("Failed to open log file {}", path.display()))
+}
+
+pub(crate) fn log_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            log_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(())
+}
+
+/// Log file of an app written last, `None` if it has no logs
+pub fn latest_app_log(settings: &AppPackLocalSettings, id: &str) -> Result<Option<PathBuf>> {
+    let mut files = Vec::new();
+    log_files(&settings.logs_dir.join(id), &mut files)?;
+
+    Ok(files
+        .into_iter()
+        .max_by_key(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok()))
+}
+
+/// Lists the log files of an app, or all of them, or removes them with `clean`
+pub fn logs(settings: &AppPackLocalSettings, id: Option<&str>, clean: bool) -> Result<()> {
+    let dir = match id {
+        Some(id) => settings.logs_dir.join(id),
+        None => settings.logs_dir.clone(),
+    };
+    let mut files = Vec::new();
+    log_files(&dir, &mut files)?;
+
+    if clean {
+        for file in files.iter() {
+            std::fs::remove_file(file).context(format!("Failed to remove {}", file.display()))?;
+        }
+        message!("Removed {} log files", files.len());
+        return Ok(());
+    }
+
+    if files.is_empty() {
+        message!("No logs");
+    }
+
+    let mut results = Vec::new();
+    for file in files {
+        let size = std::fs::metadata(&file)?.len();
+        message!("{}\t{} KB", file.display(), size / 1024);
+        results.push(serde_json::json!({ "path": file, "size": size }));
+    }
+
+    set_json_result(&results)
+}
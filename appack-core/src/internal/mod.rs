@@ -13,12 +13,48 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod alias;
+pub mod apply;
+pub mod autostart;
+pub mod backup;
+pub mod balloon;
+pub mod cache;
+pub mod config;
+pub mod console;
 pub mod creator;
+pub mod desktop;
+pub mod doctor;
+pub mod events;
 mod helpers;
 pub mod info;
 pub mod install_appack;
 pub mod launch;
 pub mod list_installed;
+pub mod logs;
+mod orphan;
+pub mod pool;
+pub mod prune;
+pub mod publish;
+pub mod quota;
+pub mod rdp_file;
+pub mod readme;
+pub mod recover;
+pub mod registry;
+pub mod relocate;
+pub mod report;
+pub mod remote;
+pub mod repo;
 pub mod reset;
+pub mod retention;
+pub mod screenshot;
+pub mod search;
+pub mod self_update;
+pub mod simulate;
+pub mod stats;
+pub mod stop;
+pub mod system;
+pub mod top;
 pub mod uninstall_appack;
+pub mod update;
+mod url_bridge;
 pub mod version;
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::console::CONSOLE_SOCKET;
+use crate::internal::launch::{EVENTS_SOCKET, RDP_PORT_FILE};
+use crate::internal::url_bridge::URL_SOCKET;
+use crate::types::AppSnapshotTriggerMode;
+use crate::utils::guest_agent::GUEST_AGENT_SOCKET;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+/// Launch of the running VM, in the app home. Locked by the `appack` process supervising the VM.
+pub(crate) const LAUNCH_STATE_FILE: &str = "launch.yaml";
+
+/// Written by QEMU with `-pidfile`, in the app home
+pub(crate) const PID_FILE: &str = "qemu.pid";
+
+/// What another `appack` process needs to take over a running VM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct LaunchState {
+    pub rdp_port: u16,
+    pub snapshot_mode: AppSnapshotTriggerMode,
+    /// USB devices passed through, removed before the VM stops
+    pub usb_devices: usize,
+}
+
+impl LaunchState {
+    /// Records the launch, this process supervises the VM until the returned file is closed.
+    /// Fails if another process supervises it.
+    pub fn supervise(&self, app_home: &Path) -> Result<File> {
+        let path = app_home.join(LAUNCH_STATE_FILE);
+        // Written in place, a new file wouldn't be locked
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .context(format!("Failed to open {}", path.display()))?;
+        file.try_lock()
+            .map_err(|_| anyhow!("The VM is supervised by another AppPack process"))?;
+
+        file.set_len(0)?;
+        file.write_all(serde_yaml::to_string(self)?.as_bytes())
+            .context(format!("Failed to write {}", path.display()))?;
+
+        Ok(file)
+    }
+
+    /// Launch of a VM still running while the `appack` process supervising it is gone,
+    /// e.g. after a crash
+    pub fn orphaned(app_home: &Path) -> Option<Self> {
+        let file = File::open(app_home.join(LAUNCH_STATE_FILE)).ok()?;
+        // The lock is released when the supervisor exits, whatever the reason
+        file.try_lock_shared().ok()?;
+        UnixStream::connect(app_home.join("qmp-appack.sock")).ok()?;
+
+        serde_yaml::from_reader(&file).ok()
+    }
+
+    pub fn remove(app_home: &Path) {
+        let _ = std::fs::remove_file(app_home.join(LAUNCH_STATE_FILE));
+        let _ = std::fs::remove_file(app_home.join(PID_FILE));
+    }
+}
+
+/// Pid of the QEMU process of the VM, if it runs in the same pid namespace
+pub(crate) fn qemu_pid(app_home: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(app_home.join(PID_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    // In Flatpak QEMU runs on the host, its pid means nothing in the sandbox
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+
+    comm.starts_with("qemu").then_some(pid)
+}
+
+/// Removes the sockets and files a crashed launch leaves in the app home, so that the next
+/// one starts cleanly. Nothing is removed while a VM runs or is being started.
+pub(crate) fn remove_stale_files(app_home: &Path) {
+    if let Ok(file) = File::open(app_home.join(LAUNCH_STATE_FILE))
+        && file.try_lock_shared().is_err()
+    {
+        return;
+    }
+    if UnixStream::connect(app_home.join("qmp-appack.sock")).is_ok() {
+        return;
+    }
+
+    let virtiofs_sockets = std::fs::read_dir(app_home)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("virtiofs-") && name.ends_with(".sock"));
+    let stale_files = [
+        "appack.sock",
+        "qmp-appack.sock",
+        EVENTS_SOCKET,
+        CONSOLE_SOCKET,
+        URL_SOCKET,
+        GUEST_AGENT_SOCKET,
+        RDP_PORT_FILE,
+        LAUNCH_STATE_FILE,
+        PID_FILE,
+    ]
+    .map(str::to_string)
+    .into_iter()
+    .chain(virtiofs_sockets);
+    for name in stale_files {
+        if std::fs::remove_file(app_home.join(&name)).is_ok() {
+            tracing::info!(file = name, "Removed a file left by a previous launch");
+        }
+    }
+}
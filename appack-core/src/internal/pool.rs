@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Extracted images, named after their SHA-256 and hard linked into the homes of the apps using
+//! them. Installs of the same image share it until one of them modifies it.
+
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::checksum::sha256_file;
+use crate::utils::extract::copy_to_file;
+use crate::utils::frontend::{message, warning};
+use crate::utils::lock::FileLock;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Images being extracted, suffixed with the id of the extracting process
+const PARTIAL_PREFIX: &str = "partial-";
+
+fn pool_dir(settings: &AppPackLocalSettings) -> PathBuf {
+    settings.home_dir.join("pool")
+}
+
+/// Locks the pool against other AppPack processes until the returned guard is dropped.
+/// Taken last, after the locks of apps and of the installed list.
+fn lock(settings: &AppPackLocalSettings) -> Result<FileLock> {
+    FileLock::acquire(&settings.home_dir.join("pool.lock"))
+}
+
+fn is_sha256(name: &str) -> bool {
+    name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether an image at `destination` can be hard linked to the pool
+fn same_filesystem(dir: &Path, destination: &Path) -> bool {
+    let parent = destination.parent().unwrap_or(destination);
+    match (fs::metadata(dir), fs::metadata(parent)) {
+        (Ok(dir), Ok(parent)) => dir.dev() == parent.dev(),
+        _ => false,
+    }
+}
+
+/// Puts the image of a pack at `destination`, linked to the identical image of another install
+/// if there is one. Returns the SHA-256 of the image.
+pub(crate) fn install_image(
+    settings: &AppPackLocalSettings,
+    image: &mut impl Read,
+    size: u64,
+    sha256: Option<&str>,
+    destination: &Path,
+) -> Result<String> {
+    let dir = pool_dir(settings);
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let shared = same_filesystem(&dir, destination);
+
+    if shared && let Some(sha256) = sha256.filter(|sha256| is_sha256(sha256)) {
+        let _lock = lock(settings)?;
+        let pooled = dir.join(sha256);
+        if fs::metadata(&pooled).is_ok_and(|m| m.len() == size)
+            && fs::hard_link(&pooled, destination).is_ok()
+        {
+            message!("The image is shared with another install, nothing to extract.");
+            return Ok(sha256.to_string());
+        }
+    }
+
+    // The pool could not share an image on another filesystem, it is extracted in place
+    let partial = if shared {
+        dir.join(format!("{PARTIAL_PREFIX}{}", std::process::id()))
+    } else {
+        let mut partial = destination.as_os_str().to_owned();
+        partial.push(".partial");
+        PathBuf::from(partial)
+    };
+    let file =
+        File::create(&partial).context(format!("Unable to create file {}", partial.display()))?;
+    let mut hasher = Sha256::new();
+    let copied = copy_to_file(image, &file, size, |chunk| hasher.update(chunk));
+    drop(file);
+    let computed = hex::encode(hasher.finalize());
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&partial);
+        return Err(e).context("Failed to extract the image");
+    }
+    if let Some(expected) = sha256
+        && !expected.eq_ignore_ascii_case(&computed)
+    {
+        let _ = fs::remove_file(&partial);
+        return Err(ErrorKind::ArchiveInvalid.error(format!(
+            "The image of the pack is corrupted, its SHA-256 is {computed} instead of {expected}"
+        )));
+    }
+
+    let _lock = lock(settings)?;
+    // Linked before it is named, the pool never holds an unused image another process removes
+    if shared && fs::hard_link(&partial, destination).is_ok() {
+        fs::rename(&partial, dir.join(&computed))
+            .context("Failed to add the image to the pool")?;
+    } else {
+        // The filesystem has no hard links
+        fs::rename(&partial, destination)
+            .context(format!("Unable to create file {}", destination.display()))?;
+    }
+
+    Ok(computed)
+}
+
+/// Entry of the pool an image is linked to
+fn pool_entry(settings: &AppPackLocalSettings, image: &Metadata) -> Option<PathBuf> {
+    fs::read_dir(pool_dir(settings))
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|m| m.dev() == image.dev() && m.ino() == image.ino())
+        })
+        .map(|entry| entry.path())
+}
+
+/// Gives an app its own image before it is modified, as other installs may share it
+pub(crate) fn unshare(settings: &AppPackLocalSettings, image: &Path) -> Result<()> {
+    let metadata = {
+        let _lock = lock(settings)?;
+        let metadata = fs::metadata(image).context(format!("{} not found", image.display()))?;
+        if metadata.nlink() <= 1 {
+            return Ok(());
+        }
+
+        // Only the pool shares it, the app takes it over
+        if metadata.nlink() == 2
+            && let Some(pooled) = pool_entry(settings, &metadata)
+        {
+            return fs::remove_file(&pooled)
+                .context(format!("Failed to remove {}", pooled.display()));
+        }
+        metadata
+    };
+
+    message!("Copying the image shared with another install..");
+    let mut copy = image.as_os_str().to_owned();
+    copy.push(".unshared");
+    // Copies are reflinks on filesystems supporting them, e.g. Btrfs and XFS
+    fs::copy(image, &copy).context(format!("Failed to copy {}", image.display()))?;
+    fs::rename(&copy, image).context(format!("Failed to replace {}", image.display()))?;
+
+    // The other installs may have taken their own copy meanwhile
+    let _lock = lock(settings)?;
+    if let Some(pooled) = pool_entry(settings, &metadata)
+        && fs::metadata(&pooled).is_ok_and(|m| m.nlink() == 1)
+    {
+        fs::remove_file(&pooled).context(format!("Failed to remove {}", pooled.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Removes the images no install uses anymore, and the ones of interrupted installs. Returns
+/// the space freed.
+pub(crate) fn prune(settings: &AppPackLocalSettings) -> Result<u64> {
+    let Ok(entries) = fs::read_dir(pool_dir(settings)) else {
+        return Ok(0);
+    };
+    let _lock = lock(settings)?;
+
+    let mut freed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let unused = match name.strip_prefix(PARTIAL_PREFIX) {
+            // The process extracting it is gone
+            Some(pid) => !Path::new("/proc").join(pid).exists(),
+            None => entry.metadata().is_ok_and(|m| m.nlink() == 1),
+        };
+        if !unused {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(entry.path())
+            .context(format!("Failed to remove {}", entry.path().display()))?;
+        tracing::info!(image = name, "Removed an image no install uses");
+        freed += size;
+    }
+
+    Ok(freed)
+}
+
+/// Checks that the images shared between installs were not modified, by hashing them again.
+/// The image of an app stops being shared the first time the app runs.
+pub fn verify(settings: &AppPackLocalSettings) -> Result<()> {
+    let installed = settings.get_installed()?.installed;
+    let Ok(entries) = fs::read_dir(pool_dir(settings)) else {
+        message!("No image is shared between installs");
+        return set_json_result(&Vec::<serde_json::Value>::new());
+    };
+
+    let mut results = Vec::new();
+    let mut corrupted = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !is_sha256(&name) {
+            continue;
+        }
+
+        let apps: Vec<String> = installed
+            .iter()
+            .filter(|app| {
+                fs::metadata(settings.get_app_home_dir(app).join(&app.image))
+                    .is_ok_and(|m| m.dev() == metadata.dev() && m.ino() == metadata.ino())
+            })
+            .map(|app| format!("{} {}", app.id, app.version))
+            .collect();
+        message!("Checking {}.. ({})", &name[..12], apps.join(", "));
+        // Removed meanwhile by another AppPack process
+        let Ok(computed) = sha256_file(&entry.path()) else {
+            continue;
+        };
+        let ok = computed.eq_ignore_ascii_case(&name);
+        if !ok {
+            corrupted += 1;
+            warning!(
+                "The image {} is corrupted, reinstall {}",
+                &name[..12],
+                apps.join(", ")
+            );
+        }
+        results.push(json!({ "sha256": name, "apps": apps, "ok": ok }));
+    }
+    set_json_result(&results)?;
+
+    if corrupted > 0 {
+        return Err(ErrorKind::ArchiveInvalid.error(format!("{corrupted} image(s) are corrupted")));
+    }
+    message!("{} shared image(s) checked, no corruption found", results.len());
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::internal::retention::close_snapshots;
+use crate::internal::uninstall_appack::uninstall_appack;
+use crate::types::AppSnapshotTriggerMode;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::output::set_json_result;
+use crate::utils::prompt::confirm;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fs;
+
+/// Versions of each app beyond the newest `keep` ones, oldest first
+fn superseded(installed: &[InstalledAppPackEntry], keep: usize) -> Vec<&InstalledAppPackEntry> {
+    let mut ids: Vec<&str> = installed.iter().map(|app| app.id.as_str()).collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut superseded = Vec::new();
+    for id in ids {
+        let mut versions: Vec<_> = installed.iter().filter(|app| app.id == id).collect();
+        versions.sort_by(|a, b| AppBuildConfig::compare_versions(&a.version, &b.version));
+        let count = versions.len().saturating_sub(keep);
+        superseded.extend(versions.into_iter().take(count));
+    }
+
+    superseded
+}
+
+/// Moves the saved states of a version of an app to its newest version, which has none yet.
+/// A state holds the disk of the VM, so it is only moved between versions of the same image.
+/// Returns whether it was moved.
+fn migrate_state(
+    settings: &AppPackLocalSettings,
+    from: &InstalledAppPackEntry,
+    to: &InstalledAppPackEntry,
+) -> Result<bool> {
+    if !matches!(to.snapshot_mode, AppSnapshotTriggerMode::OnClose) {
+        return Ok(false);
+    }
+    let _lock = settings.lock_app(&to.id)?;
+    if running_rdp_port(settings, from).is_some() || running_rdp_port(settings, to).is_some() {
+        return Err(ErrorKind::VmRunning.error(format!("{} is running", to.id)))
+            .context("Close the app before moving its state");
+    }
+
+    let from_image = settings.get_app_home_dir(from).join(&from.image);
+    let to_image = settings.get_app_home_dir(to).join(&to.image);
+    if close_snapshots(&from_image)?.is_empty() || !close_snapshots(&to_image)?.is_empty() {
+        return Ok(false);
+    }
+    if from.image_sha256.is_none() || from.image_sha256 != to.image_sha256 {
+        message!(
+            "{}: the state of {} is not moved to {}, their images differ",
+            to.id, from.version, to.version
+        );
+        return Ok(false);
+    }
+
+    // The image of the newest version may be shared with other installs, it is replaced and
+    // not written to
+    let mut copy = to_image.as_os_str().to_owned();
+    copy.push(".migrating");
+    if fs::rename(&from_image, &to_image).is_err() {
+        // The versions are in folders on different filesystems
+        fs::copy(&from_image, &copy).context(format!("Failed to copy {}", from_image.display()))?;
+        fs::rename(&copy, &to_image)
+            .context(format!("Failed to replace {}", to_image.display()))?;
+    }
+
+    Ok(true)
+}
+
+/// Uninstalls the versions of the given app, or all apps, older than their newest `keep` ones,
+/// the global `keep_versions` when not given. The newest version, the one started by
+/// `appack launch`, is always kept. With `migrate`, the newest version takes over the saved
+/// state of the last version removed, see `migrate_state`.
+pub fn prune(
+    settings: &AppPackLocalSettings,
+    id: Option<&str>,
+    keep: Option<u32>,
+    migrate: bool,
+) -> Result<()> {
+    let keep = keep.or(settings.config.keep_versions).unwrap_or(1).max(1) as usize;
+    let installed = settings.get_installed()?.installed;
+    if let Some(id) = id
+        && !installed.iter().any(|app| app.id == id)
+    {
+        return Err(ErrorKind::NotInstalled.error(format!("{id} is not installed")));
+    }
+
+    let installed: Vec<_> = installed
+        .into_iter()
+        .filter(|app| id.is_none_or(|id| app.id == id))
+        .collect();
+    let superseded = superseded(&installed, keep);
+    if superseded.is_empty() {
+        message!("No version to remove");
+        return set_json_result(&Vec::<serde_json::Value>::new());
+    }
+
+    let versions = superseded
+        .iter()
+        .map(|app| format!("{} {}", app.id, app.version))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !confirm(&tr!("prompt-prune-versions", versions = versions))? {
+        return Err(ErrorKind::Cancelled.error(tr!("uninstall-cancelled")));
+    }
+
+    let mut migrated = Vec::new();
+    if migrate {
+        // Versions of an app are listed together, oldest first
+        for (i, from) in superseded.iter().enumerate() {
+            if superseded.get(i + 1).is_some_and(|next| next.id == from.id) {
+                continue;
+            }
+            let newest = installed
+                .iter()
+                .filter(|app| app.id == from.id)
+                .max_by(|a, b| AppBuildConfig::compare_versions(&a.version, &b.version))
+                .expect("superseded versions have a newer one");
+            if migrate_state(settings, from, newest)? {
+                message!(
+                    "{}: moved the state of {} to {}",
+                    from.id, from.version, newest.version
+                );
+                migrated.push((from.id.as_str(), from.version.as_str()));
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for app in superseded {
+        uninstall_appack(settings, &app.id, Some(&app.version))?;
+        message!("{}: removed {}", app.id, app.version);
+        let state_migrated = migrated.contains(&(app.id.as_str(), app.version.as_str()));
+        results.push(json!({
+            "id": app.id,
+            "version": app.version,
+            "state_migrated": state_migrated,
+        }));
+    }
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superseded() {
+        let app = |id: &str, version: &str| -> InstalledAppPackEntry {
+            serde_yaml::from_str(&format!(
+                "{{id: {id}, version: {version}, name: Demo, image: image.qcow2, \
+                 snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: ''}}"
+            ))
+            .unwrap()
+        };
+        let installed = vec![
+            app("ms-cmd", "1.10.0"),
+            app("ms-cmd", "1.2.0"),
+            app("ms-cmd", "1.9.1"),
+            app("notepad", "2.0.0"),
+        ];
+
+        let removed: Vec<_> = superseded(&installed, 1)
+            .iter()
+            .map(|app| app.version.as_str())
+            .collect();
+        assert_eq!(removed, ["1.2.0", "1.9.1"]);
+        assert_eq!(superseded(&installed, 2).len(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::install_appack::{
+    check_archive_integrity, check_pack_contents, extract_config,
+};
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::types::repository::{RepositoryIndex, RepositoryIndexEntry};
+use crate::types::signing::{AppPackSecretKey, AppPackSignature};
+use crate::utils::checksum::{sha256_bytes, sha256_file};
+use crate::utils::chunks::chunk_file;
+use crate::utils::frontend::message;
+use crate::utils::upload::{UploadBackend, get_upload_backend};
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Fetches the current index of the repository, making sure nobody tampered with it
+/// before we sign it again.
+fn fetch_current_index(
+    settings: &AppPackLocalSettings,
+    backend: &dyn UploadBackend,
+    secret: &AppPackSecretKey,
+) -> Result<RepositoryIndex> {
+    let Some(index) = backend.fetch("index.yaml")? else {
+        message!("Repository has no index yet, creating one");
+        return Ok(RepositoryIndex::default());
+    };
+
+    let signature = backend
+        .fetch("index.yaml.sig")?
+        .ok_or_else(|| anyhow!("Repository index is not signed"))?;
+    let signature: AppPackSignature =
+        serde_yaml::from_slice(&signature).context("Invalid index signature file")?;
+
+    let mut trusted_keys = settings.get_trusted_keys()?;
+    trusted_keys.push(secret.public_key(None)?);
+    signature
+        .verify(&sha256_bytes(&index), &trusted_keys)
+        .context("Refusing to update the repository index")?;
+
+    serde_yaml::from_slice(&index).context("Invalid repository index")
+}
+
+/// Optional fields of the index entry created by `appack publish`
+pub struct PublishOptions {
+    pub mirrors: Vec<String>,
+    pub ipfs: Option<String>,
+    pub magnet: Option<String>,
+    pub channel: Option<String>,
+}
+
+pub fn publish(
+    settings: &AppPackLocalSettings,
+    file: &Path,
+    repository_name: &str,
+    keyfile: &Path,
+    options: PublishOptions,
+) -> Result<()> {
+    let repository = settings
+        .get_repositories()?
+        .repositories
+        .into_iter()
+        .find(|r| r.name == repository_name)
+        .ok_or_else(|| anyhow!("Repository '{repository_name}' does not exist"))?;
+    let secret = AppPackSecretKey::new(keyfile)?;
+    let backend = get_upload_backend(&repository, settings.limit_rate)?;
+
+    // 1. Validate the pack
+    let zip_file = File::open(file).context(format!("Unable to open file {file:?}"))?;
+    let mut archive = ZipArchive::new(zip_file).context("Unable to open file as zip archive")?;
+    let pack = extract_config(&mut archive)?;
+    check_pack_contents(&mut archive, &pack).context("Invalid AppPack")?;
+    check_archive_integrity(&mut archive, &pack.image).context("Invalid AppPack")?;
+
+    // 2. Checksum and sign
+    message!("Computing checksum of {}..", file.display());
+    let sha256 = sha256_file(file)?;
+    let size = std::fs::metadata(file)?.len();
+    let signature = secret.sign_digest(&sha256)?;
+
+    // 3. Add to the index
+    let mut index = fetch_current_index(settings, backend.as_ref(), &secret)?;
+    if index
+        .packs
+        .iter()
+        .any(|p| p.id == pack.id && p.version == pack.version)
+    {
+        return Err(anyhow!(
+            "{} {} is already published in '{repository_name}'",
+            pack.id,
+            pack.version
+        ));
+    }
+
+    let file_name = format!("{}_{}.zip", pack.id, pack.version);
+    let chunks_file_name = format!("{file_name}.chunks");
+    message!("Computing chunks of {}..", file.display());
+    let chunks = chunk_file(file)?;
+    index.packs.push(RepositoryIndexEntry {
+        id: pack.id.clone(),
+        name: pack.name.clone(),
+        version: pack.version.clone(),
+        description: pack.description.clone(),
+        categories: pack.categories.clone(),
+        keywords: pack.keywords.clone(),
+        file: file_name.clone(),
+        sha256,
+        size: Some(size),
+        mirrors: options.mirrors,
+        ipfs: options.ipfs,
+        magnet: options.magnet,
+        chunks: Some(chunks_file_name.clone()),
+        channel: options.channel,
+    });
+
+    let index_content = serde_yaml::to_string(&index)?;
+    let index_signature = secret.sign_digest(&sha256_bytes(index_content.as_bytes()))?;
+
+    // 4. Upload, index last so it never references a missing pack
+    message!("Uploading {file_name}. This will take a while.");
+    backend.upload_file(&file_name, file)?;
+    backend.upload_bytes(
+        &format!("{file_name}.sig"),
+        serde_yaml::to_string(&signature)?.as_bytes(),
+    )?;
+    backend.upload_bytes(&chunks_file_name, serde_yaml::to_string(&chunks)?.as_bytes())?;
+    backend.upload_bytes("index.yaml", index_content.as_bytes())?;
+    backend.upload_bytes(
+        "index.yaml.sig",
+        serde_yaml::to_string(&index_signature)?.as_bytes(),
+    )?;
+
+    message!(
+        "Published {} {} to '{repository_name}'",
+        pack.id, pack.version
+    );
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::config::NotificationLevel;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::notify::notify;
+use crate::utils::output::set_json_result;
+use crate::utils::rate_limit::parse_size;
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::path::Path;
+
+const MB: u64 = 1024 * 1024;
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Disk space used by an installed app
+pub fn app_disk_usage(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> u64 {
+    dir_size(&settings.get_app_home_dir(app))
+}
+
+/// How much the image of an app grew since it was installed, unknown for apps installed
+/// before AppPack recorded the image size
+fn image_growth(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Option<u64> {
+    let installed_size = app.image_size?;
+    let image_path = settings.get_app_home_dir(app).join(&app.image);
+    let size = std::fs::metadata(image_path).ok()?.len();
+    Some(size.saturating_sub(installed_size))
+}
+
+fn max_storage(settings: &AppPackLocalSettings) -> Result<Option<u64>> {
+    settings
+        .config
+        .max_storage
+        .as_deref()
+        .map(parse_size)
+        .transpose()
+        .context("Invalid max_storage setting")
+}
+
+fn max_image_growth(settings: &AppPackLocalSettings, id: &str) -> Result<Option<u64>> {
+    settings
+        .get_app_config(id)?
+        .max_image_growth
+        .as_deref()
+        .map(parse_size)
+        .transpose()
+        .context(format!("Invalid max_image_growth setting of {id}"))
+}
+
+/// Disk space used by all installed apps, wherever they are, and the cache
+fn storage_usage(settings: &AppPackLocalSettings) -> Result<u64> {
+    let apps_size: u64 = settings
+        .get_installed()?
+        .installed
+        .iter()
+        .map(|app| dir_size(&settings.get_app_home_dir(app)))
+        .sum();
+
+    Ok(apps_size + dir_size(&settings.cache_dir))
+}
+
+/// Describes the quotas exceeded globally or by the given app
+fn exceeded_quotas(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+) -> Result<Vec<String>> {
+    let mut exceeded = Vec::new();
+
+    if let Some(max_storage) = max_storage(settings)? {
+        let usage = storage_usage(settings)?;
+        if usage > max_storage {
+            exceeded.push(tr!(
+                "quota-storage-exceeded",
+                usage = usage / MB,
+                max = max_storage / MB
+            ));
+        }
+    }
+
+    if let Some(max_growth) = max_image_growth(settings, &app.id)?
+        && let Some(growth) = image_growth(settings, app)
+        && growth > max_growth
+    {
+        exceeded.push(tr!(
+            "quota-image-growth-exceeded",
+            name = app.name,
+            growth = growth / MB,
+            max = max_growth / MB
+        ));
+    }
+
+    Ok(exceeded)
+}
+
+/// Warns about exceeded quotas before launching an app, or refuses to launch it
+/// when `enforce_quotas` is set
+pub fn check_quotas(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Result<()> {
+    let exceeded = exceeded_quotas(settings, app)?;
+    if exceeded.is_empty() {
+        return Ok(());
+    }
+
+    if settings.config.enforce_quotas.unwrap_or(false) {
+        return Err(anyhow!(exceeded.join("\n"))).context(tr!("hint-quota-exceeded"));
+    }
+
+    for message in exceeded.iter() {
+        message!("Warning: {message}");
+    }
+
+    notify(
+        &settings.config,
+        NotificationLevel::Warning,
+        &tr!("notification-quota-exceeded"),
+        &exceeded.join("\n"),
+    );
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct AppDiskUsage {
+    id: String,
+    version: String,
+    size: u64,
+    image_growth: Option<u64>,
+    max_image_growth: Option<u64>,
+}
+
+/// Prints the disk space used by each app and the cache, against the quotas
+pub fn du(settings: &AppPackLocalSettings) -> Result<()> {
+    let mut apps = Vec::new();
+    for app in settings.get_installed()?.installed.iter() {
+        let usage = AppDiskUsage {
+            id: app.id.clone(),
+            version: app.version.clone(),
+            size: app_disk_usage(settings, app),
+            image_growth: image_growth(settings, app),
+            max_image_growth: max_image_growth(settings, &app.id)?,
+        };
+
+        let growth = match (usage.image_growth, usage.max_image_growth) {
+            (Some(growth), Some(max)) => format!("image +{} MB / {} MB", growth / MB, max / MB),
+            (Some(growth), None) => format!("image +{} MB", growth / MB),
+            (None, _) => "image growth unknown".to_string(),
+        };
+        message!(
+            "{}\t{}\t{} MB\t({growth})",
+            usage.id,
+            usage.version,
+            usage.size / MB
+        );
+        apps.push(usage);
+    }
+
+    let cache = dir_size(&settings.cache_dir);
+    message!("Cache\t{} MB", cache / MB);
+
+    let total = storage_usage(settings)?;
+    let max_storage = max_storage(settings)?;
+    match max_storage {
+        Some(max) => message!("Total: {} MB / {} MB", total / MB, max / MB),
+        None => message!("Total: {} MB", total / MB),
+    }
+
+    set_json_result(&serde_json::json!({
+        "apps": apps,
+        "cache": cache,
+        "total": total,
+        "max_storage": max_storage,
+    }))
+}
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::{ClientOptions, running_rdp_port, with_default_rdp_args};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::atomic_write::write_atomic;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Writes a `.rdp` file to connect to a running app with any RDP client
+pub fn export_rdp_file(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    host: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    let port = running_rdp_port(settings, &app).ok_or_else(|| {
+        ErrorKind::NotFound.error(format!("{id} is not running, launch it first"))
+    })?;
+
+    let host = match host {
+        Some(host) => host.to_string(),
+        None => std::fs::read_to_string("/proc/sys/kernel/hostname")
+            .map(|hostname| hostname.trim().to_string())
+            .unwrap_or_else(|_| "localhost".to_string()),
+    };
+    let config_args = with_default_rdp_args(settings, id, None, &ClientOptions::default())?;
+    let freerdp_command = app.granted_freerdp_command();
+    let args: Vec<&str> = freerdp_command
+        .split_whitespace()
+        .chain(
+            config_args
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace(),
+        )
+        .collect();
+    let content = rdp_file_content(&format!("{host}:{port}"), &args);
+
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(format!("{id}.rdp")));
+    write_atomic(&output, content).context(format!("Failed to write {}", output.display()))?;
+
+    set_json_result(&json!({ "path": output }))?;
+    message!("Wrote {}", output.display());
+    if let Some(password) = args.iter().find_map(|arg| arg.strip_prefix("/p:")) {
+        message!("The client asks for the password of the app: {password}");
+    }
+
+    Ok(())
+}
+
+/// Translates FreeRDP options to the settings of a `.rdp` file. Passwords can't be stored
+/// in clear, the client asks for it.
+fn rdp_file_content(address: &str, args: &[&str]) -> String {
+    let mut lines = vec![
+        format!("full address:s:{address}"),
+        "prompt for credentials:i:1".to_string(),
+    ];
+    let mut fullscreen = false;
+    for arg in args {
+        let arg = arg.replace("$WHITESPACE", " ");
+        let (option, value) = arg.split_once(':').unwrap_or((&arg, ""));
+        match option {
+            "/u" => lines.push(format!("username:s:{value}")),
+            "/d" => lines.push(format!("domain:s:{value}")),
+            "/f" => fullscreen = true,
+            "/multimon" => lines.push("use multimon:i:1".to_string()),
+            "/size" => {
+                if let Some((width, height)) = value.split_once('x') {
+                    lines.push(format!("desktopwidth:i:{width}"));
+                    lines.push(format!("desktopheight:i:{height}"));
+                }
+            }
+            "/audio-mode" => lines.push(format!("audiomode:i:{value}")),
+            "/microphone" => lines.push("audiocapturemode:i:1".to_string()),
+            "/printer" => lines.push("redirectprinters:i:1".to_string()),
+            "/smartcard" => lines.push("redirectsmartcards:i:1".to_string()),
+            "/drive" => lines.push("drivestoredirect:s:*".to_string()),
+            "-clipboard" => lines.push("redirectclipboard:i:0".to_string()),
+            "/cert" if value == "ignore" => lines.push("authentication level:i:0".to_string()),
+            "/app" => {
+                lines.push("remoteapplicationmode:i:1".to_string());
+                for (key, value) in value.split(',').filter_map(|o| o.split_once(':')) {
+                    match key {
+                        "program" => lines.push(format!("remoteapplicationprogram:s:{value}")),
+                        "name" => lines.push(format!("remoteapplicationname:s:{value}")),
+                        "cmd" => lines.push(format!("remoteapplicationcmdline:s:{value}")),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    lines.push(format!(
+        "screen mode id:i:{}",
+        if fullscreen { 2 } else { 1 }
+    ));
+    lines.dedup();
+
+    // Windows clients expect CRLF line endings
+    lines.iter().map(|line| format!("{line}\r\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rdp_file_content() {
+        let content = rdp_file_content(
+            "desk:3390",
+            &[
+                "/u:User",
+                "/p:secret",
+                "/cert:ignore",
+                "/drive:home,/home/a",
+                "/drive:x,/x",
+            ],
+        );
+        assert_eq!(
+            content,
+            "full address:s:desk:3390\r\nprompt for credentials:i:1\r\nusername:s:User\r\n\
+             authentication level:i:0\r\ndrivestoredirect:s:*\r\nscreen mode id:i:1\r\n"
+        );
+    }
+}
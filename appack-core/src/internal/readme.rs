@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use serde_json::json;
+
+/// Prints the readme of an installed app, in the language of the user when the pack has it
+pub fn readme(settings: &AppPackLocalSettings, id: &str, version: Option<&str>) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    let path = app
+        .localized_readme()
+        .map(|index| settings.get_app_readme_dir(&app).join(index))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| {
+            ErrorKind::NotFound.error(format!(
+                "{} has no readme, apps installed by older versions of AppPack get it once \
+                 reinstalled",
+                app.name
+            ))
+        })?;
+
+    let text = std::fs::read_to_string(&path).context(format!("Failed to read {path:?}"))?;
+    set_json_result(&json!({ "path": path, "readme": text }))?;
+    message!("{text}");
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::cache::find_cached_pack;
+use crate::internal::install_appack::{INSTALLED_ENTRY_FILE_NAME, extract_config};
+use crate::types::app_installed::{InstalledAppPackEntry, InstalledAppPacks};
+use crate::types::local_settings::{AppPackLocalSettings, RESERVED_HOME_DIR_NAMES};
+use crate::utils::frontend::message;
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Finds the configuration of an app from its directory, or from its pack if it is still cached
+fn recover_entry(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: &str,
+    app_dir: &Path,
+) -> Result<InstalledAppPackEntry> {
+    let entry_file = app_dir.join(INSTALLED_ENTRY_FILE_NAME);
+    if entry_file.exists() {
+        let content = std::fs::read_to_string(&entry_file)?;
+        return serde_yaml::from_str(&content)
+            .context(format!("Failed to parse {}", entry_file.display()));
+    }
+
+    let pack = find_cached_pack(settings, id, Some(version)).ok_or(anyhow!(
+        "No configuration found in the app directory or the cache"
+    ))?;
+    let mut archive = ZipArchive::new(File::open(&pack)?)?;
+    extract_config(&mut archive)
+}
+
+/// Rebuilds the list of installed apps from the app directories of the AppPack home
+fn rebuild_installed(settings: &AppPackLocalSettings) -> Result<InstalledAppPacks> {
+    let mut installed = InstalledAppPacks::default();
+
+    for id_entry in std::fs::read_dir(&settings.home_dir)? {
+        let id_entry = id_entry?;
+        let id = id_entry.file_name().to_string_lossy().to_string();
+        if !id_entry.file_type()?.is_dir() || RESERVED_HOME_DIR_NAMES.contains(&id.as_str()) {
+            continue;
+        }
+
+        for version_entry in std::fs::read_dir(id_entry.path())? {
+            let version_entry = version_entry?;
+            if !version_entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let version = version_entry.file_name().to_string_lossy().to_string();
+            match recover_entry(settings, &id, &version, &version_entry.path()) {
+                Ok(entry) if entry.id == id && entry.version == version => {
+                    message!("Recovered {id} {version}");
+                    installed.installed.push(entry);
+                }
+                Ok(_) => message!("Could not recover {id} {version}: configuration mismatch"),
+                Err(e) => message!("Could not recover {id} {version}: {e}"),
+            }
+        }
+    }
+
+    Ok(installed)
+}
+
+/// Restores a corrupted `installed.yaml` from its backup, or rebuilds it from the app directories
+pub fn recover(settings: &AppPackLocalSettings, rebuild: bool) -> Result<()> {
+    settings.check_ok()?;
+    let _lock = settings.lock_installed()?;
+
+    if !rebuild {
+        if !settings.installed_file.exists()
+            || settings
+                .read_installed_file(&settings.installed_file)
+                .is_ok()
+        {
+            message!("The list of installed apps is not corrupted, nothing to recover");
+            return Ok(());
+        }
+
+        let backup_file = settings.installed_backup_file();
+        match settings.read_installed_file(&backup_file) {
+            Ok((installed, _)) => {
+                message!("Restoring the list of installed apps from its backup");
+                settings.save_installed(installed)?;
+                return Ok(());
+            }
+            Err(e) => {
+                message!("The backup cannot be used ({e}), rebuilding from the installed apps")
+            }
+        }
+    }
+
+    let installed = rebuild_installed(settings)?;
+    message!(
+        "{} app(s) recovered. Apps moved with `appack relocate` must be installed again.",
+        installed.installed.len()
+    );
+    settings.save_installed(installed)?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::install_appack::install_appack;
+use crate::internal::launch::{
+    ClientOptions, LaunchHandle, RemoteOptions, running_rdp_port, spawn_launch,
+};
+use crate::internal::uninstall_appack::uninstall_appack;
+use crate::internal::update::update;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::local_settings::AppPackLocalSettings;
+use anyhow::Result;
+
+/// The installed apps of a user, and of the machine, for frontends. The list is read again on
+/// each call, so changes made by other AppPack processes are seen.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    settings: AppPackLocalSettings,
+}
+
+impl Registry {
+    pub fn new(settings: AppPackLocalSettings) -> Self {
+        Self { settings }
+    }
+
+    pub fn settings(&self) -> &AppPackLocalSettings {
+        &self.settings
+    }
+
+    /// Every installed version of every app, the system-wide ones last
+    pub fn apps(&self) -> Result<Vec<InstalledAppPackEntry>> {
+        let mut apps = self.settings.get_installed()?.installed;
+        apps.extend(self.settings.get_system_installed()?.installed);
+
+        Ok(apps)
+    }
+
+    /// An installed app, in its newest version when none is given
+    pub fn app(&self, id: &str, version: Option<&str>) -> Result<InstalledAppPackEntry> {
+        self.settings.get_app_installed(id, version)
+    }
+
+    pub fn is_running(&self, app: &InstalledAppPackEntry) -> bool {
+        running_rdp_port(&self.settings, app).is_some()
+    }
+
+    /// Installs a pack from a file, a URL or the repositories, like `appack install`
+    pub fn install(&self, pack: &str, version: Option<&str>) -> Result<()> {
+        install_appack(&self.settings, pack, version, None, false)
+    }
+
+    pub fn uninstall(&self, id: &str, version: Option<&str>) -> Result<()> {
+        uninstall_appack(&self.settings, id, version)
+    }
+
+    /// Updates an app, or all apps, from the repositories
+    pub fn update(&self, id: Option<&str>) -> Result<()> {
+        update(&self.settings, id)
+    }
+
+    /// Opens an app on this machine, on its own thread
+    pub fn launch(&self, id: &str, version: Option<&str>, client: ClientOptions) -> LaunchHandle {
+        spawn_launch(
+            self.settings.clone(),
+            id.to_string(),
+            version.map(str::to_string),
+            None,
+            client,
+            RemoteOptions::default(),
+        )
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::pool;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target).context(format!(
+                "Failed to copy {} to {}",
+                entry.path().display(),
+                target.display()
+            ))?;
+        }
+        // Sockets of a stopped VM are left behind, they are recreated on launch
+    }
+
+    Ok(())
+}
+
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to.parent().unwrap())?;
+
+    // The destination is usually on another filesystem, in which case renaming fails
+    if fs::rename(from, to).is_err() {
+        if let Err(e) = copy_dir(from, to) {
+            let _ = fs::remove_dir_all(to);
+            return Err(e);
+        }
+        fs::remove_dir_all(from)?;
+    }
+
+    Ok(())
+}
+
+/// Points the icons of the desktop entries of an app to its new directory
+fn update_desktop_entries(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    old_dir: &Path,
+    new_dir: &Path,
+) -> Result<()> {
+    let old_icon_dir = old_dir.join("desktop");
+    let new_icon_dir = new_dir.join("desktop");
+
+    for desktop_entry in app.desktop_entries.iter().flatten() {
+        let entry_path = settings.get_desktop_entry_path(app, desktop_entry);
+        if !entry_path.exists() {
+            message!("Desktop entry not found: {}", entry_path.display());
+            continue;
+        }
+
+        let contents = fs::read_to_string(&entry_path)?.replace(
+            old_icon_dir.to_str().unwrap(),
+            new_icon_dir.to_str().unwrap(),
+        );
+        fs::write(&entry_path, contents).context(format!(
+            "Failed to update desktop entry {}",
+            entry_path.display()
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Moves the directory of installed apps under `destination`.
+/// Relocating to the AppPack home moves apps back to their default location.
+pub fn relocate(
+    settings: &AppPackLocalSettings,
+    destination: &Path,
+    id: Option<&str>,
+    version: Option<&str>,
+) -> Result<()> {
+    settings.check_ok()?;
+
+    fs::create_dir_all(destination).context(format!(
+        "Failed to create directory {}",
+        destination.display()
+    ))?;
+    let destination = destination.canonicalize()?;
+    let back_home = destination == settings.home_dir.canonicalize()?;
+
+    let selected: Vec<InstalledAppPackEntry> = settings
+        .get_installed()?
+        .installed
+        .into_iter()
+        .filter(|e| id.is_none_or(|id| e.id == id))
+        .filter(|e| version.is_none_or(|v| e.version == v))
+        .collect();
+
+    if selected.is_empty() {
+        return Err(ErrorKind::NotInstalled.error("AppPack (or version) is not installed"));
+    }
+
+    for app in selected {
+        // Moving takes a while, only this app is locked meanwhile
+        let _app_lock = settings.lock_app(&app.id)?;
+        let Some(app) = settings
+            .get_installed()?
+            .installed
+            .into_iter()
+            .find(|e| e.id == app.id && e.version == app.version)
+        else {
+            continue;
+        };
+        let old_dir = settings.get_app_home_dir(&app);
+        let new_dir = if back_home {
+            settings.default_app_home_dir(&app)
+        } else {
+            destination.join(&app.id).join(&app.version)
+        };
+
+        if old_dir == new_dir {
+            message!(
+                "{} {} is already in {}",
+                app.id,
+                app.version,
+                destination.display()
+            );
+            continue;
+        }
+
+        if UnixStream::connect(old_dir.join("qmp-appack.sock")).is_ok() {
+            return Err(ErrorKind::VmRunning.error(format!("{} {} is running", app.id, app.version)))
+                .context("Close the app before relocating it");
+        }
+
+        if new_dir.exists() {
+            return Err(anyhow!("Directory already exists: {}", new_dir.display()));
+        }
+
+        message!(
+            "Moving {} {} to {}.. This can take a few minutes.",
+            app.id,
+            app.version,
+            new_dir.display()
+        );
+        move_dir(&old_dir, &new_dir)?;
+        update_desktop_entries(settings, &app, &old_dir, &new_dir)?;
+
+        // Saved after each app, so a failure leaves the apps already moved usable
+        {
+            let _lock = settings.lock_installed()?;
+            let mut installed = settings.get_installed()?;
+            if let Some(entry) = installed
+                .installed
+                .iter_mut()
+                .find(|e| e.id == app.id && e.version == app.version)
+            {
+                entry.home_dir = if back_home { None } else { Some(new_dir) };
+            }
+            settings.save_installed(installed)?;
+        }
+
+        // Remove the directory of the app id if no other version is left in it
+        if let Some(parent) = old_dir.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+
+    // Apps moved to another filesystem have their own copy of the image now
+    pool::prune(settings)?;
+
+    Ok(())
+}
@@ -0,0 +1,487 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::{
+    ClientOptions, ServerSignal, spawn_freerdp, wait_for_client_disconnect,
+    with_default_rdp_args, with_open_file,
+};
+use crate::internal::helpers::get_os_assigned_port;
+use crate::types::app_installed::granted_freerdp_command;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::checksum::sha256_bytes;
+use crate::utils::command_review::freerdp_risks;
+use crate::utils::frontend::{message, warning};
+use crate::utils::keyring;
+use crate::utils::output::emit_event;
+use crate::utils::sandbox;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Time a remote client has to send its token
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prefix of the remote addresses reached through SSH, `ssh://[user@]host[:port]/<listen port>`
+const SSH_SCHEME: &str = "ssh://";
+
+/// Time the user has to log in to the remote machine
+const SSH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What a remote client needs to open an app running on another machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteSession {
+    pub app_id: String,
+    /// Title of the window, older servers don't send it
+    #[serde(default)]
+    pub app_name: String,
+    pub rdp_port: u16,
+    pub freerdp_command: String,
+}
+
+/// First line sent to remote clients, as JSON, after they sent the control token
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RemoteResponse {
+    Session(RemoteSession),
+    Error { error: String },
+}
+
+pub struct RemoteServer {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RemoteServer {
+    pub fn stop(self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle
+            .join()
+            .map_err(|e| anyhow!("Could not join handle: {e:?}"))
+    }
+}
+
+/// Serves an app to remote clients over TCP. Clients count like local ones:
+/// the VM is stopped once the last one disconnects.
+pub fn serve_remote(
+    address: &str,
+    token: String,
+    session: RemoteSession,
+    client_count: Arc<AtomicUsize>,
+    shutdown_tx: Sender<ServerSignal>,
+) -> Result<RemoteServer> {
+    let listener = TcpListener::bind(address).context(format!("Failed to listen on {address}"))?;
+    listener.set_nonblocking(true)?;
+    message!("Listening for remote clients on {address}");
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_for_thread = stop.clone();
+    let handle = thread::spawn(move || {
+        while !stop_for_thread.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    let token = token.clone();
+                    let session = session.clone();
+                    let client_count = client_count.clone();
+                    let shutdown_tx = shutdown_tx.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = handle_remote_client(
+                            stream,
+                            &token,
+                            &session,
+                            &client_count,
+                            &shutdown_tx,
+                        ) {
+                            tracing::warn!(%peer, "Remote client rejected: {e:?}");
+                        }
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(200));
+                }
+                Err(e) => {
+                    tracing::error!("Remote server: Error accepting connection: {e}");
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(RemoteServer { stop, handle })
+}
+
+fn handle_remote_client(
+    mut stream: TcpStream,
+    token: &str,
+    session: &RemoteSession,
+    client_count: &AtomicUsize,
+    shutdown_tx: &Sender<ServerSignal>,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut received = String::new();
+    reader.read_line(&mut received)?;
+
+    // Hashes are compared so the comparison time doesn't depend on the token
+    if sha256_bytes(received.trim_end().as_bytes()) != sha256_bytes(token.as_bytes()) {
+        let response = RemoteResponse::Error {
+            error: "Invalid control token".to_string(),
+        };
+        writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+        return Err(anyhow!("Invalid control token"));
+    }
+
+    client_count.fetch_add(1, Ordering::SeqCst);
+    tracing::info!(
+        count = client_count.load(Ordering::SeqCst),
+        "Remote server: New client connected"
+    );
+
+    let response = RemoteResponse::Session(session.clone());
+    writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+    stream.set_read_timeout(None)?;
+
+    wait_for_client_disconnect(&mut reader, client_count, shutdown_tx);
+
+    Ok(())
+}
+
+/// Ports of a remote machine forwarded to this one through a single SSH connection, closed
+/// when dropped
+struct SshTunnel {
+    /// `[user@]host`
+    destination: String,
+    port: Option<String>,
+    control_socket: PathBuf,
+    connection: Child,
+}
+
+impl SshTunnel {
+    /// Connects to `[user@]host[:port]`, SSH asks for a password in the terminal if needed
+    fn open(target: &str) -> Result<Self> {
+        sandbox::require("ssh")?;
+        let (destination, port) = match target.rsplit_once(':') {
+            Some((destination, port)) => (destination.to_string(), Some(port.to_string())),
+            None => (target.to_string(), None),
+        };
+        let control_socket =
+            std::env::temp_dir().join(format!("appack-ssh-{}.sock", std::process::id()));
+
+        let mut tunnel = Self {
+            connection: sandbox::command("ssh")
+                .args(["-N", "-M", "-S"])
+                .arg(&control_socket)
+                .args(["-o", "ExitOnForwardFailure=yes"])
+                .args(port.iter().flat_map(|port| ["-p", port.as_str()]))
+                .arg(&destination)
+                .spawn()
+                .context("Failed to run ssh")?,
+            destination,
+            port,
+            control_socket,
+        };
+
+        let started = Instant::now();
+        while !tunnel.control_socket.exists() {
+            if let Some(status) = tunnel.connection.try_wait()? {
+                return Err(anyhow!("ssh exited with {status}"))
+                    .context(format!("Failed to connect to {target}"));
+            }
+            if started.elapsed() > SSH_TIMEOUT {
+                return Err(anyhow!("Timed out connecting to {target}"));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(tunnel)
+    }
+
+    fn control(&self, operation: &str) -> std::process::Command {
+        let mut command = sandbox::command("ssh");
+        command
+            .arg("-S")
+            .arg(&self.control_socket)
+            .args(["-O", operation])
+            .args(self.port.iter().flat_map(|port| ["-p", port.as_str()]))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+
+    /// Forwards a port of the loopback interface of the remote machine, returns the local one
+    fn forward(&self, remote_port: u16) -> Result<u16> {
+        let local_port = get_os_assigned_port()?;
+        let status = self
+            .control("forward")
+            .arg("-L")
+            .arg(format!("127.0.0.1:{local_port}:127.0.0.1:{remote_port}"))
+            .arg(&self.destination)
+            .status()
+            .context("Failed to run ssh")?;
+        if !status.success() {
+            return Err(anyhow!(
+                "Failed to forward the port {remote_port} of {}",
+                self.destination
+            ));
+        }
+
+        Ok(local_port)
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        let _ = self.control("exit").arg(&self.destination).status();
+        let _ = self.connection.kill();
+        let _ = self.connection.wait();
+    }
+}
+
+/// Whether the control token sent to this address stays on this machine
+fn is_loopback(address: &str) -> bool {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host == "localhost" || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// FreeRDP command of a remote app: the one of the app installed on this machine, reviewed when
+/// it was installed, or the one sent by the remote machine if it only shows the app. Apps not
+/// installed here get no capability, e.g. no clipboard and no sound.
+fn remote_freerdp_command(
+    settings: &AppPackLocalSettings,
+    address: &str,
+    session: &RemoteSession,
+) -> Result<String> {
+    if let Ok(app) = settings.get_app_installed(&session.app_id, None) {
+        return Ok(app.granted_freerdp_command());
+    }
+
+    let risks = freerdp_risks(&session.freerdp_command);
+    if !risks.is_empty() {
+        return Err(anyhow!(
+            "{address} sent FreeRDP options giving the app access to this machine: {}",
+            risks.join(", ")
+        ))
+        .context(format!(
+            "Install {} on this machine to open it remotely",
+            session.app_id
+        ));
+    }
+
+    Ok(granted_freerdp_command(&[], &session.freerdp_command))
+}
+
+/// Opens an app served by `appack launch --listen` on another machine, directly or through SSH
+/// for `ssh://` addresses
+pub fn launch_remote(
+    settings: &AppPackLocalSettings,
+    address: &str,
+    id: &str,
+    rdp_args: Option<&str>,
+    client: &ClientOptions,
+) -> Result<()> {
+    let token = settings.config.control_token.clone().ok_or_else(|| {
+        anyhow!("Set control_token with `appack config set control_token <token>` to the token of the remote machine")
+    })?;
+
+    let (tunnel, control_address) = match address.strip_prefix(SSH_SCHEME) {
+        Some(target) => {
+            let (target, port) = target
+                .rsplit_once('/')
+                .and_then(|(target, port)| Some((target, port.parse::<u16>().ok()?)))
+                .ok_or_else(|| anyhow!("Expected ssh://[user@]host/<port>, got {address}"))?;
+            if settings.config.gateway.is_some() {
+                return Err(anyhow!("Apps opened through SSH can't use the RD Gateway"));
+            }
+            let tunnel = SshTunnel::open(target)?;
+            let local_port = tunnel.forward(port)?;
+            (Some(tunnel), format!("127.0.0.1:{local_port}"))
+        }
+        None => {
+            if !is_loopback(address) {
+                warning!(
+                    "The control token is sent unencrypted to {address}, use ssh://<host>/<port> \
+                     on untrusted networks"
+                );
+            }
+            (None, address.to_string())
+        }
+    };
+
+    let mut stream = TcpStream::connect(&control_address)
+        .context(format!("Failed to connect to {address}"))?;
+    writeln!(stream, "{token}")?;
+
+    let mut line = String::new();
+    BufReader::new(stream.try_clone()?).read_line(&mut line)?;
+    let session =
+        match serde_json::from_str(&line).context(format!("Invalid answer from {address}"))? {
+            RemoteResponse::Session(session) => session,
+            RemoteResponse::Error { error } => {
+                return Err(anyhow!(error).context(format!("{address} refused the connection")));
+            }
+        };
+
+    if session.app_id != id {
+        return Err(anyhow!("{address} serves '{}', not '{id}'", session.app_id));
+    }
+    emit_event("connected", json!({ "remote": address }));
+
+    let freerdp_command = remote_freerdp_command(settings, address, &session)?;
+    let (host, rdp_port) = match &tunnel {
+        Some(tunnel) => ("127.0.0.1", tunnel.forward(session.rdp_port)?),
+        None => (
+            address.rsplit_once(':').map_or(address, |(host, _)| host),
+            session.rdp_port,
+        ),
+    };
+    let rdp_args = with_default_rdp_args(settings, id, rdp_args, client)?;
+    let rdp_args = match &client.open {
+        Some(file) => Some(with_open_file(rdp_args.as_deref(), &freerdp_command, file)?),
+        None => rdp_args,
+    };
+    let rdp_args = match gateway_arg(settings)? {
+        Some(gateway) => Some(format!("{} {gateway}", rdp_args.unwrap_or_default())),
+        None => rdp_args,
+    };
+    let mut freerdp = spawn_freerdp(
+        settings,
+        host,
+        &rdp_port.to_string(),
+        &session.app_id,
+        match session.app_name.as_str() {
+            "" => &session.app_id,
+            name => name,
+        },
+        &freerdp_command,
+        rdp_args.as_deref(),
+    )?;
+    emit_event("rdp_started", json!({ "rdp_port": session.rdp_port }));
+    let status = freerdp.wait()?;
+    emit_event("rdp_closed", json!({ "exit_code": status.code() }));
+
+    // Disconnecting lets the remote machine stop the VM when no client is left
+    drop(stream);
+    drop(tunnel);
+
+    Ok(())
+}
+
+/// FreeRDP option routing the session through the configured RD Gateway. FreeRDP gets its
+/// password from stdin, see `spawn_freerdp`.
+fn gateway_arg(settings: &AppPackLocalSettings) -> Result<Option<String>> {
+    let config = &settings.config;
+    let Some(gateway) = &config.gateway else {
+        return Ok(None);
+    };
+
+    let mut options = vec![format!("g:{gateway}")];
+    if let Some(user) = &config.gateway_user {
+        let attributes = [("gateway", gateway.as_str()), ("user", user.as_str())];
+        let password = keyring::lookup(&attributes)?.ok_or_else(|| {
+            anyhow!(
+                "No password for {user} on the gateway {gateway}, store it with `{}`",
+                keyring::store_command("AppPack gateway", &attributes)
+            )
+        })?;
+        options.push(format!("u:{user}"));
+        options.push(format!("p:{password}"));
+    }
+    if let Some(domain) = &config.gateway_domain {
+        options.push(format!("d:{domain}"));
+    }
+
+    Ok(Some(
+        format!("/gateway:{}", options.join(",")).replace(' ', "$WHITESPACE"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn connect(
+        token: &str,
+    ) -> (
+        TcpStream,
+        String,
+        JoinHandle<Result<()>>,
+        Arc<AtomicUsize>,
+        mpsc::Receiver<ServerSignal>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let client_count = Arc::new(AtomicUsize::new(0));
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let session = RemoteSession {
+            app_id: "demo".to_string(),
+            app_name: "Demo".to_string(),
+            rdp_port: 3390,
+            freerdp_command: "/u:user".to_string(),
+        };
+        let count = client_count.clone();
+        let handler = thread::spawn(move || {
+            handle_remote_client(stream, "secret", &session, &count, &shutdown_tx)
+        });
+
+        writeln!(client, "{token}").unwrap();
+        let mut line = String::new();
+        BufReader::new(client.try_clone().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+
+        (client, line, handler, client_count, shutdown_rx)
+    }
+
+    #[test]
+    fn remote_clients_need_the_token() {
+        let (_, line, handler, client_count, _) = connect("wrong");
+        assert!(line.contains("Invalid control token"));
+        assert!(handler.join().unwrap().is_err());
+        assert_eq!(client_count.load(Ordering::SeqCst), 0);
+
+        let (client, line, handler, client_count, shutdown_rx) = connect("secret");
+        let response: RemoteResponse = serde_json::from_str(&line).unwrap();
+        assert!(matches!(response, RemoteResponse::Session(s) if s.rdp_port == 3390));
+        assert_eq!(client_count.load(Ordering::SeqCst), 1);
+
+        drop(client);
+        handler.join().unwrap().unwrap();
+        assert_eq!(client_count.load(Ordering::SeqCst), 0);
+        assert_eq!(shutdown_rx.try_recv(), Ok(ServerSignal::LastClientLeft));
+    }
+
+    #[test]
+    fn test_is_loopback() {
+        assert!(is_loopback("127.0.0.1:7000"));
+        assert!(is_loopback("localhost:7000"));
+        assert!(is_loopback("[::1]:7000"));
+        assert!(!is_loopback("vm-host:7000"));
+        assert!(!is_loopback("192.168.1.2:7000"));
+    }
+}
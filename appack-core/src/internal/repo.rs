@@ -0,0 +1,500 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_config::DEFAULT_CHANNEL;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::types::repository::{
+    AppPackRepository, RepositoryIndex, RepositoryIndexEntry, RepositoryPublishConfig,
+};
+use crate::types::signing::{AppPackPublicKey, AppPackSecretKey, AppPackSignature};
+use crate::utils::checksum::{sha256_bytes, sha256_file};
+use crate::utils::download::fetch_bytes;
+use crate::utils::frontend::{message, warning};
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result, anyhow};
+use std::cmp::Ordering;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+pub fn signature_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+pub fn repo_add(
+    settings: &AppPackLocalSettings,
+    name: &str,
+    url: &str,
+    publish: Option<RepositoryPublishConfig>,
+) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return Err(anyhow!("Invalid repository name: {name}"));
+    }
+
+    if !url.starts_with("https://") && !url.starts_with("http://") && !url.starts_with("file://")
+    {
+        return Err(anyhow!(
+            "Unsupported repository URL: {url} (expected http(s):// or file://)"
+        ));
+    }
+
+    if settings.get_repositories()?.repositories.iter().any(|r| r.name == name) {
+        return Err(anyhow!("Repository '{name}' already exists"));
+    }
+
+    let mut repositories = settings.get_user_repositories()?;
+    repositories.repositories.push(AppPackRepository {
+        name: name.to_string(),
+        url: url.to_string(),
+        publish,
+    });
+    settings.save_repositories(repositories)?;
+
+    message!("Repository '{name}' added. Run `appack repo sync` to fetch its index.");
+
+    Ok(())
+}
+
+pub fn repo_remove(settings: &AppPackLocalSettings, name: &str) -> Result<()> {
+    let mut repositories = settings.get_user_repositories()?;
+    let count = repositories.repositories.len();
+    repositories.repositories.retain(|r| r.name != name);
+
+    if repositories.repositories.len() == count {
+        if settings.config.repositories.iter().any(|r| r.name == name) {
+            return Err(anyhow!(
+                "Repository '{name}' is defined in {}, remove it from there",
+                settings.config_file.display()
+            ));
+        }
+        return Err(anyhow!("Repository '{name}' does not exist"));
+    }
+
+    settings.save_repositories(repositories)?;
+
+    let index_dir = settings.repositories_dir.join(name);
+    if index_dir.exists() {
+        std::fs::remove_dir_all(&index_dir).context("Failed to remove repository index")?;
+    }
+
+    Ok(())
+}
+
+pub fn repo_list(settings: &AppPackLocalSettings) -> Result<()> {
+    let repositories = settings.get_repositories()?;
+
+    if repositories.repositories.is_empty() {
+        message!("No repository configured");
+    }
+
+    let mut results = Vec::new();
+    for repository in repositories.repositories {
+        let index = settings.get_repository_index(&repository);
+        let status = match &index {
+            Ok(index) => format!("{} packs", index.packs.len()),
+            Err(e) => format!("unavailable: {e}"),
+        };
+        message!("{}\t{}\t({status})", repository.name, repository.url);
+        results.push(serde_json::json!({
+            "name": repository.name,
+            "url": repository.url,
+            "packs": index.as_ref().ok().map(|index| index.packs.len()),
+            "error": index.as_ref().err().map(|e| e.to_string()),
+        }));
+    }
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+pub fn sync_repository(
+    settings: &AppPackLocalSettings,
+    repository: &AppPackRepository,
+) -> Result<()> {
+    let index_url = repository.index_url();
+    message!("Fetching {index_url}");
+
+    let index = fetch_bytes(&index_url)?;
+    let signature = fetch_bytes(&format!("{index_url}.sig"))
+        .context("Repository index is not signed")?;
+    let signature: AppPackSignature =
+        serde_yaml::from_slice(&signature).context("Invalid index signature file")?;
+
+    signature
+        .verify(&sha256_bytes(&index), &settings.get_trusted_keys()?)
+        .context(format!(
+            "Refusing to use the index of repository '{}'",
+            repository.name
+        ))?;
+
+    let parsed: RepositoryIndex =
+        serde_yaml::from_slice(&index).context("Invalid repository index")?;
+
+    let index_path = settings.get_repository_index_path(repository);
+    std::fs::create_dir_all(index_path.parent().unwrap())?;
+    std::fs::write(&index_path, &index).context("Failed to save repository index")?;
+    std::fs::write(
+        signature_path(&index_path),
+        serde_yaml::to_string(&signature)?,
+    )
+    .context("Failed to save repository index signature")?;
+
+    message!(
+        "Repository '{}' synced: {} packs available",
+        repository.name,
+        parsed.packs.len()
+    );
+
+    Ok(())
+}
+
+pub fn repo_sync(settings: &AppPackLocalSettings, name: Option<&str>) -> Result<()> {
+    settings.check_ok()?;
+    let repositories = settings.get_repositories()?;
+    let mut failed = false;
+
+    for repository in repositories.repositories.iter() {
+        if name.is_some_and(|n| n != repository.name) {
+            continue;
+        }
+
+        if let Err(e) = sync_repository(settings, repository) {
+            warning!("Failed to sync repository '{}': {e:?}", repository.name);
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(anyhow!("Some repositories could not be synced"));
+    }
+
+    Ok(())
+}
+
+/// Finds a pack in the synced repository indexes
+pub fn find_in_repositories(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    repository_name: Option<&str>,
+) -> Result<(AppPackRepository, RepositoryIndexEntry)> {
+    let repositories = settings.get_repositories()?;
+    let mut indexes = Vec::new();
+
+    for repository in repositories.repositories {
+        if repository_name.is_some_and(|n| n != repository.name) {
+            continue;
+        }
+
+        let index = match settings.get_repository_index(&repository) {
+            Ok(index) => index,
+            Err(e) if repository_name.is_none() => {
+                warning!("Skipping repository '{}': {e:?}", repository.name);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        indexes.push((repository, index));
+    }
+
+    select_pack(indexes, id, version)
+}
+
+/// Picks a pack among the indexes of repositories: the given version, or the newest one with
+/// the stable ones first. The pack must be in a single repository.
+fn select_pack(
+    indexes: Vec<(AppPackRepository, RepositoryIndex)>,
+    id: &str,
+    version: Option<&str>,
+) -> Result<(AppPackRepository, RepositoryIndexEntry)> {
+    let mut matches = Vec::new();
+    for (repository, index) in indexes {
+        for entry in index.packs {
+            if entry.id == id && version.is_none_or(|v| v == entry.version) {
+                matches.push((repository.clone(), entry));
+            }
+        }
+    }
+
+    match matches.len() {
+        0 => Err(ErrorKind::NotFound.error(format!(
+            "AppPack (or version) '{id}' not found in any repository"
+        ))),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            if matches.iter().any(|(r, _)| r.name != matches[0].0.name) {
+                Err(anyhow!(
+                    "AppPack '{id}' is available in multiple repositories — please specify a repository"
+                ))
+            } else {
+                // The newest version, stable ones first
+                let newest = matches
+                    .into_iter()
+                    .max_by(|(_, a), (_, b)| {
+                        (a.channel() == DEFAULT_CHANNEL)
+                            .cmp(&(b.channel() == DEFAULT_CHANNEL))
+                            .then_with(|| AppBuildConfig::compare_versions(&a.version, &b.version))
+                    })
+                    .unwrap();
+                Ok(newest)
+            }
+        }
+    }
+}
+
+/// Finds the newest version of a pack across all synced repositories.
+/// Channels other than stable also receive stable releases.
+pub fn find_latest_in_repositories(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    channel: &str,
+) -> Result<Option<(AppPackRepository, RepositoryIndexEntry)>> {
+    let mut indexes = Vec::new();
+    for repository in settings.get_repositories()?.repositories {
+        match settings.get_repository_index(&repository) {
+            Ok(index) => indexes.push((repository, index)),
+            Err(e) => warning!("Skipping repository '{}': {e:?}", repository.name),
+        }
+    }
+
+    Ok(latest_pack(indexes, id, channel))
+}
+
+/// Newest version of a pack in `channel` or in the stable channel, across the indexes
+fn latest_pack(
+    indexes: Vec<(AppPackRepository, RepositoryIndex)>,
+    id: &str,
+    channel: &str,
+) -> Option<(AppPackRepository, RepositoryIndexEntry)> {
+    let mut latest: Option<(AppPackRepository, RepositoryIndexEntry)> = None;
+    for (repository, index) in indexes {
+        for entry in index.packs {
+            let is_newer = latest.as_ref().is_none_or(|(_, l)| {
+                AppBuildConfig::compare_versions(&entry.version, &l.version) == Ordering::Greater
+            });
+            let in_channel = entry.channel() == channel || entry.channel() == DEFAULT_CHANNEL;
+            if entry.id == id && in_channel && is_newer {
+                latest = Some((repository.clone(), entry));
+            }
+        }
+    }
+
+    latest
+}
+
+/// Verifies the detached signature of a pack file against the trusted keys
+pub fn verify_pack_signature(settings: &AppPackLocalSettings, file: &Path) -> Result<()> {
+    let signature = AppPackSignature::new(&signature_path(file))?;
+    message!("Verifying signature of {}..", file.display());
+    signature.verify(&sha256_file(file)?, &settings.get_trusted_keys()?)
+}
+
+pub fn repo_trust(settings: &AppPackLocalSettings, keyfile: &Path) -> Result<()> {
+    let key = AppPackPublicKey::new(keyfile)?;
+    let key_id = key.key_id()?;
+
+    std::fs::create_dir_all(&settings.trusted_keys_dir)
+        .context("Failed to create trusted keys directory")?;
+    std::fs::write(
+        settings.trusted_keys_dir.join(format!("{key_id}.pub")),
+        serde_yaml::to_string(&key)?,
+    )
+    .context("Failed to save trusted key")?;
+
+    message!("Trusted key {key_id} ({})", key.comment.unwrap_or_default());
+
+    Ok(())
+}
+
+pub fn repo_untrust(settings: &AppPackLocalSettings, key_id: &str) -> Result<()> {
+    let key_path = settings.trusted_keys_dir.join(format!("{key_id}.pub"));
+    if !key_path.exists() {
+        return Err(anyhow!("Key '{key_id}' is not trusted"));
+    }
+
+    std::fs::remove_file(&key_path).context("Failed to remove trusted key")?;
+
+    Ok(())
+}
+
+pub fn repo_keys(settings: &AppPackLocalSettings) -> Result<()> {
+    let keys = settings.get_trusted_keys()?;
+
+    if keys.is_empty() {
+        message!("No trusted key");
+    }
+
+    for key in keys {
+        message!("{}\t{}", key.key_id()?, key.comment.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+pub fn repo_keygen(output: &Path, comment: Option<String>) -> Result<()> {
+    let secret_path = output.with_extension("key");
+    let public_path = output.with_extension("pub");
+
+    if secret_path.exists() || public_path.exists() {
+        return Err(anyhow!("Key files already exist: {}", output.display()));
+    }
+
+    let secret = AppPackSecretKey::generate()?;
+    let public = secret.public_key(comment)?;
+
+    let mut secret_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&secret_path)
+        .context(format!("Unable to create {}", secret_path.display()))?;
+    secret_file.write_all(serde_yaml::to_string(&secret)?.as_bytes())?;
+    std::fs::write(&public_path, serde_yaml::to_string(&public)?)
+        .context(format!("Unable to create {}", public_path.display()))?;
+
+    message!("Key {} generated", public.key_id()?);
+    message!("Secret key: {} (keep it private)", secret_path.display());
+    message!("Public key: {} (share it with your users)", public_path.display());
+
+    Ok(())
+}
+
+pub fn repo_sign(file: &Path, keyfile: &Path) -> Result<()> {
+    let secret = AppPackSecretKey::new(keyfile)?;
+    let signature = secret.sign_digest(&sha256_file(file)?)?;
+    let output = signature_path(file);
+
+    std::fs::write(&output, serde_yaml::to_string(&signature)?)
+        .context(format!("Unable to write {}", output.display()))?;
+
+    message!("Signature written to {}", output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = "
+packs:
+  - {id: demo, name: Demo, version: 1.9.0, file: demo_1.9.0.zip, sha256: a}
+  - {id: demo, name: Demo, version: 1.10.0, file: demo_1.10.0.zip, sha256: b}
+  - {id: demo, name: Demo, version: 2.0.0, file: demo_2.0.0.zip, sha256: c, channel: beta}
+  - id: other
+    name: Other
+    version: 1.0.0
+    description: Another app
+    file: https://mirror.example.com/other.zip
+    sha256: d
+    size: 42
+    mirrors: [mirror/other.zip]
+    magnet: magnet:?xt=urn:btih:0
+";
+
+    fn synced(name: &str, yaml: &str) -> (AppPackRepository, RepositoryIndex) {
+        let repository = AppPackRepository {
+            name: name.to_string(),
+            url: format!("https://{name}.example.com/packs/"),
+            publish: None,
+        };
+        (repository, serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn test_index_parsing() {
+        let (repository, index) = synced("main", INDEX);
+        assert_eq!(index.packs.len(), 4);
+
+        let demo = &index.packs[0];
+        assert_eq!(demo.channel(), DEFAULT_CHANNEL);
+        assert!(demo.mirrors.is_empty() && demo.magnet.is_none() && demo.size.is_none());
+        assert_eq!(
+            repository.file_url(&demo.file),
+            "https://main.example.com/packs/demo_1.9.0.zip"
+        );
+        assert_eq!(index.packs[2].channel(), "beta");
+
+        let other = &index.packs[3];
+        assert_eq!(other.size, Some(42));
+        assert_eq!(other.mirrors, ["mirror/other.zip"]);
+        assert_eq!(other.magnet.as_deref(), Some("magnet:?xt=urn:btih:0"));
+        // Absolute URLs are kept as they are
+        assert_eq!(
+            repository.file_url(&other.file),
+            "https://mirror.example.com/other.zip"
+        );
+
+        let (_, empty) = synced("empty", "{}");
+        assert!(empty.packs.is_empty());
+        assert!(serde_yaml::from_str::<RepositoryIndex>("packs: [{id: demo}]").is_err());
+    }
+
+    #[test]
+    fn test_select_pack() {
+        // The newest stable version, compared as versions and not as text
+        let (_, entry) = select_pack(vec![synced("main", INDEX)], "demo", None).unwrap();
+        assert_eq!(entry.version, "1.10.0");
+
+        let (_, entry) = select_pack(vec![synced("main", INDEX)], "demo", Some("2.0.0")).unwrap();
+        assert_eq!(entry.sha256, "c");
+
+        let error = select_pack(vec![synced("main", INDEX)], "demo", Some("3.0.0")).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+        let error = select_pack(vec![synced("main", INDEX)], "missing", None).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+
+        // Repositories without the pack don't count
+        let indexes = vec![synced("main", INDEX), synced("empty", "packs: []")];
+        let (repository, _) = select_pack(indexes, "other", None).unwrap();
+        assert_eq!(repository.name, "main");
+
+        let indexes = vec![synced("main", INDEX), synced("fork", INDEX)];
+        assert!(select_pack(indexes, "demo", None).is_err());
+    }
+
+    #[test]
+    fn test_latest_pack() {
+        let latest = |channel| {
+            latest_pack(
+                vec![synced("main", INDEX), synced("old", "packs: []")],
+                "demo",
+                channel,
+            )
+            .map(|(_, entry)| entry.version)
+        };
+        assert_eq!(latest(DEFAULT_CHANNEL).as_deref(), Some("1.10.0"));
+        // Other channels also get the stable releases
+        assert_eq!(latest("beta").as_deref(), Some("2.0.0"));
+        assert_eq!(latest("nightly").as_deref(), Some("1.10.0"));
+
+        let newer = "packs: [{id: demo, name: Demo, version: 1.11.0, file: d.zip, sha256: e}]";
+        let (repository, entry) = latest_pack(
+            vec![synced("main", INDEX), synced("new", newer)],
+            "demo",
+            "stable",
+        )
+        .unwrap();
+        assert_eq!(
+            (repository.name.as_str(), entry.version.as_str()),
+            ("new", "1.11.0")
+        );
+        assert!(latest_pack(vec![synced("main", INDEX)], "missing", "stable").is_none());
+    }
+}
@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::doctor::doctor_checks;
+use crate::internal::launch::redact_password;
+use crate::internal::logs::log_files;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::sandbox;
+use crate::utils::xdg_session_type_detector::get_freerdp_executable;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tar::Builder;
+
+/// Logs written before this are left out of reports
+const LOG_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Only the end of longer logs is kept
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+fn append_text(builder: &mut Builder<File>, name: &str, content: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    header.set_mtime(now.as_secs());
+    header.set_cksum();
+    builder.append_data(&mut header, name, content)?;
+
+    Ok(())
+}
+
+/// The last `MAX_LOG_SIZE` bytes of a file
+fn tail(path: &Path) -> Result<Vec<u8>> {
+    let mut file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(size.saturating_sub(MAX_LOG_SIZE)))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+
+    Ok(content)
+}
+
+/// First line printed by a program asked for its version
+fn program_version(program: &str, arg: &str) -> String {
+    sandbox::command(program)
+        .arg(arg)
+        .output()
+        .ok()
+        .and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            stdout.lines().next().map(str::to_string)
+        })
+        .unwrap_or_else(|| "not found".to_string())
+}
+
+fn system_info(settings: &AppPackLocalSettings) -> String {
+    let os = std::fs::read_to_string("/etc/os-release")
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim_matches('"').to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let kernel = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+    let freerdp = settings
+        .config
+        .rdp_client
+        .as_deref()
+        .unwrap_or(get_freerdp_executable());
+    let qemu = format!("qemu-system-{}", std::env::consts::ARCH);
+
+    format!(
+        "AppPack: {}\nSandbox: {:?}\nOS: {os}\nKernel: {}\nArch: {}\nSession: {}\n\
+         {qemu}: {}\nqemu-img: {}\n{freerdp}: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        sandbox::get_sandbox(),
+        kernel.trim(),
+        std::env::consts::ARCH,
+        std::env::var("XDG_SESSION_TYPE").unwrap_or_else(|_| "unknown".to_string()),
+        program_version(&qemu, "--version"),
+        program_version("qemu-img", "--version"),
+        program_version(freerdp, "/version"),
+    )
+}
+
+fn doctor_report(settings: &AppPackLocalSettings) -> String {
+    doctor_checks(settings)
+        .into_iter()
+        .map(|(name, check)| match check {
+            Ok(()) => format!("✓ {name}\n"),
+            Err(e) => format!("✗ {name}: {e}\n"),
+        })
+        .collect()
+}
+
+fn redact_args(args: &str) -> String {
+    args.split_whitespace()
+        .map(redact_password)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The installed entry of an app, without the passwords given to FreeRDP
+fn redacted_entry(app: &InstalledAppPackEntry) -> InstalledAppPackEntry {
+    let mut app = app.clone();
+    app.freerdp_command = redact_args(&app.freerdp_command);
+    for entry in app.desktop_entries.iter_mut().flatten() {
+        entry.rdp_args = redact_args(&entry.rdp_args);
+    }
+
+    app
+}
+
+/// Logs of an app written lately, and its last QEMU log however old it is
+fn recent_logs(settings: &AppPackLocalSettings, id: &str) -> Result<Vec<PathBuf>> {
+    let dir = settings.logs_dir.join(id);
+    let mut logs = Vec::new();
+    log_files(&dir, &mut logs)?;
+
+    let mut files = Vec::new();
+    for entry in logs {
+        let recent = std::fs::metadata(&entry)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .is_ok_and(|age| age < LOG_AGE)
+            });
+        if recent || entry == dir.join("qemu.log") {
+            files.push(entry);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Gathers what is needed to diagnose a problem, with an app or AppPack itself, into a tar
+/// file to attach to a bug report
+pub fn report(settings: &AppPackLocalSettings, id: Option<&str>, output: &Path) -> Result<()> {
+    let app = id
+        .map(|id| settings.get_app_installed(id, None))
+        .transpose()
+        .context("Failed to get installed AppPack")?;
+
+    let file = File::create(output).context(format!("Failed to create {}", output.display()))?;
+    let mut builder = Builder::new(file);
+    append_text(&mut builder, "system.txt", system_info(settings).as_bytes())?;
+    append_text(
+        &mut builder,
+        "doctor.txt",
+        doctor_report(settings).as_bytes(),
+    )?;
+
+    let mut logs = Vec::new();
+    if let Some(app) = &app {
+        let entry = serde_yaml::to_string(&redacted_entry(app))?;
+        append_text(&mut builder, "installed.yaml", entry.as_bytes())?;
+
+        let dir = settings.logs_dir.join(&app.id);
+        for log in recent_logs(settings, &app.id)? {
+            let name = Path::new("logs").join(log.strip_prefix(&dir)?);
+            append_text(&mut builder, &name.to_string_lossy(), &tail(&log)?)?;
+            logs.push(name);
+        }
+    }
+    builder.into_inner()?;
+
+    set_json_result(&json!({ "file": output, "logs": logs }))?;
+    message!(
+        "Saved the report to {}, attach it to your bug report",
+        output.display()
+    );
+    if !logs.is_empty() {
+        message!(
+            "The logs of the app may show the names of the files you opened, check them first"
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_args() {
+        assert_eq!(
+            redact_args("/u:user /p:secret /gateway:g:host,u:me,p:secret /cert:ignore"),
+            "/u:user /p:*** /gateway:g:host,u:me,p:*** /cert:ignore"
+        );
+    }
+}
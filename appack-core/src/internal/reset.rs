@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::internal::pool;
+use crate::internal::retention::{close_snapshots, delete_snapshot};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::i18n::tr;
+use crate::utils::prompt::confirm;
+use anyhow::Result;
+use anyhow::Context;
+
+pub fn reset(settings: &AppPackLocalSettings, id: String, version: Option<&str>) -> Result<()> {
+    let app_installed = settings
+        .get_app_installed(&id, version)
+        .context("Failed to get installed AppPack")?;
+    let app_installed_home = settings.get_app_home_dir(&app_installed);
+    let image_name = app_installed.image.clone();
+    let image_path = app_installed_home.join(image_name);
+
+    let question = tr!(
+        "prompt-reset",
+        id = app_installed.id,
+        version = app_installed.version
+    );
+    if !confirm(&question)? {
+        return Err(ErrorKind::Cancelled.error(tr!("reset-cancelled")));
+    }
+
+    let _lock = settings.lock_app(&app_installed.id)?;
+    if running_rdp_port(settings, &app_installed).is_some() {
+        return Err(ErrorKind::VmRunning.error(format!(
+            "{id} is running, stop it with `appack stop {id}` before resetting it"
+        )));
+    }
+
+    // Every state kept by the retention policy
+    let snapshots = close_snapshots(&image_path)?;
+    if !snapshots.is_empty() {
+        pool::unshare(settings, &image_path)?;
+    }
+    for tag in snapshots {
+        if delete_snapshot(&image_path, &tag).is_err() {
+            return Err(ErrorKind::VmRunning.error(
+                "Failed to reset the AppPack. Make sure the AppPack is NOT running.",
+            ))
+            .context(format!("Failed to delete snapshot '{tag}'"));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::internal::pool;
+use crate::internal::logs::{DEFAULT_LOG_RETENTION_DAYS, remove_expired};
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::sandbox;
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Snapshot taken when an app closes. Apps keeping several of them suffix the newer ones with
+/// the time they were taken, e.g. `appack-onclose-1760000000`.
+pub(crate) const CLOSE_SNAPSHOT: &str = "appack-onclose";
+
+/// How long the logs and how many states of an app are kept, from the settings of the app or
+/// the global ones
+pub(crate) struct RetentionPolicy {
+    pub keep_snapshots: u32,
+    pub log_retention: Duration,
+}
+
+impl RetentionPolicy {
+    pub fn of(settings: &AppPackLocalSettings, id: &str) -> Result<Self> {
+        let app_config = settings.get_app_config(id)?;
+        let keep_snapshots = app_config
+            .keep_snapshots
+            .or(settings.config.keep_snapshots)
+            .unwrap_or(1);
+        let days = app_config
+            .log_retention_days
+            .or(settings.config.log_retention_days)
+            .unwrap_or(DEFAULT_LOG_RETENTION_DAYS);
+
+        Ok(Self {
+            keep_snapshots: keep_snapshots.max(1),
+            log_retention: Duration::from_secs(days as u64 * 24 * 60 * 60),
+        })
+    }
+
+    /// Tag of the snapshot taken when the app closes
+    pub fn close_snapshot_tag(&self) -> String {
+        if self.keep_snapshots == 1 {
+            return CLOSE_SNAPSHOT.to_string();
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        format!("{CLOSE_SNAPSHOT}-{now}")
+    }
+}
+
+/// Snapshot an app takes when it closes
+pub(crate) struct CloseSnapshot {
+    pub tag: String,
+    /// Close snapshots deleted first, the older ones beyond the policy of the app
+    pub replaces: Vec<String>,
+}
+
+impl CloseSnapshot {
+    pub fn plan(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Result<Self> {
+        let policy = RetentionPolicy::of(settings, &app.id)?;
+        let image = settings.get_app_home_dir(app).join(&app.image);
+        let mut replaces = close_snapshots(&image).unwrap_or_else(|e| {
+            tracing::warn!("Failed to list the snapshots, replacing the default one: {e:?}");
+            vec![CLOSE_SNAPSHOT.to_string()]
+        });
+        // The new snapshot is one of the ones kept
+        let kept = policy.keep_snapshots as usize - 1;
+        replaces.truncate(replaces.len().saturating_sub(kept));
+
+        Ok(Self {
+            tag: policy.close_snapshot_tag(),
+            replaces,
+        })
+    }
+}
+
+/// Time a close snapshot was taken, 0 for the one of apps keeping a single state
+fn close_snapshot_time(tag: &str) -> Option<u64> {
+    match tag.strip_prefix(CLOSE_SNAPSHOT)? {
+        "" => Some(0),
+        suffix => suffix.strip_prefix('-')?.parse().ok(),
+    }
+}
+
+/// Close snapshots in the output of `qemu-img snapshot -l`, the oldest first
+fn parse_close_snapshots(list: &str) -> Vec<String> {
+    let mut snapshots: Vec<(u64, String)> = list
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|tag| Some((close_snapshot_time(tag)?, tag.to_string())))
+        .collect();
+    snapshots.sort();
+
+    snapshots.into_iter().map(|(_, tag)| tag).collect()
+}
+
+/// Close snapshots of an image, the oldest first
+pub(crate) fn close_snapshots(image: &Path) -> Result<Vec<String>> {
+    sandbox::require("qemu-img")?;
+    let output = sandbox::command("qemu-img")
+        .arg("snapshot")
+        .arg("-lU")
+        .arg(image)
+        .output()
+        .context("Failed to get image snapshots")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to get image snapshots (output failed: {output:?})"
+        ));
+    }
+
+    Ok(parse_close_snapshots(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Deletes a snapshot of the image of an app that is not running
+pub(crate) fn delete_snapshot(image: &Path, tag: &str) -> Result<()> {
+    let status = sandbox::command("qemu-img")
+        .arg("snapshot")
+        .arg("-d")
+        .arg(tag)
+        .arg(image)
+        .status()
+        .context(format!("Failed to delete snapshot '{tag}'"))?;
+    if !status.success() {
+        return Err(anyhow!("Failed to delete snapshot '{tag}'"));
+    }
+
+    Ok(())
+}
+
+/// Deletes the close snapshots and the logs of an app beyond its policy, once it stopped.
+/// Returns the number of snapshots and log files removed.
+pub(crate) fn enforce(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+) -> Result<(usize, usize)> {
+    let policy = RetentionPolicy::of(settings, &app.id)?;
+
+    let log_dir = settings.logs_dir.join(&app.id);
+    let logs = if log_dir.is_dir() {
+        remove_expired(&log_dir, policy.log_retention)?
+    } else {
+        0
+    };
+
+    if running_rdp_port(settings, app).is_some() {
+        return Ok((0, logs));
+    }
+    let image = settings.get_app_home_dir(app).join(&app.image);
+    let snapshots = close_snapshots(&image)?;
+    let expired = snapshots
+        .len()
+        .saturating_sub(policy.keep_snapshots as usize);
+    if expired > 0 {
+        pool::unshare(settings, &image)?;
+    }
+    for tag in &snapshots[..expired] {
+        tracing::info!(
+            id = app.id,
+            tag,
+            "Deleting a snapshot beyond the retention policy"
+        );
+        delete_snapshot(&image, tag)?;
+    }
+
+    Ok((expired, logs))
+}
+
+/// Deletes the close snapshots and the logs of the installed apps beyond their policies
+pub fn gc(settings: &AppPackLocalSettings, id: Option<&str>) -> Result<()> {
+    let installed = settings.get_installed()?.installed;
+    if let Some(id) = id
+        && !installed.iter().any(|app| app.id == id)
+    {
+        return Err(ErrorKind::NotInstalled.error(format!("{id} is not installed")));
+    }
+
+    let mut results = Vec::new();
+    for app in installed
+        .iter()
+        .filter(|app| id.is_none_or(|id| app.id == id))
+    {
+        let _lock = settings.lock_app(&app.id)?;
+        let (snapshots, logs) = enforce(settings, app)
+            .context(format!("Failed to clean up {} {}", app.id, app.version))?;
+        message!(
+            "{} {}: {snapshots} snapshots and {logs} log files removed",
+            app.id, app.version
+        );
+        results.push(json!({
+            "id": app.id,
+            "version": app.version,
+            "snapshots_removed": snapshots,
+            "logs_removed": logs,
+        }));
+    }
+
+    let freed = {
+        let _lock = settings.lock_installed()?;
+        pool::prune(settings)?
+    };
+    if freed > 0 {
+        message!("{} MB freed from images no install uses", freed / 1024 / 1024);
+    }
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_close_snapshots() {
+        let list = "Snapshot list:\n\
+            ID        TAG               VM SIZE                DATE     VM CLOCK     ICOUNT\n\
+            1         appack-init 0 B 1970-01-01 00:00:00 00:00:00.000 0\n\
+            2         appack-onclose-1760000100 0 B 1970-01-01 00:00:00 00:00:00.000 0\n\
+            3         appack-onclose 0 B 1970-01-01 00:00:00 00:00:00.000 0\n\
+            4         appack-onclose-1760000000 0 B 1970-01-01 00:00:00 00:00:00.000 0\n\
+            5         appack-onclose-old 0 B 1970-01-01 00:00:00 00:00:00.000 0\n";
+        assert_eq!(
+            parse_close_snapshots(list),
+            [
+                "appack-onclose",
+                "appack-onclose-1760000000",
+                "appack-onclose-1760000100"
+            ]
+        );
+    }
+}
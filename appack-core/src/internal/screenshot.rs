@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use qapi::{Qmp, qmp};
+use serde_json::json;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// Saves the screen of the VM of a running app as a PNG, like it is before the RDP client
+/// draws it
+pub fn screenshot(
+    settings: &AppPackLocalSettings,
+    id: &str,
+    version: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    running_rdp_port(settings, &app)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("{id} is not running")))?;
+
+    let home = settings.get_app_home_dir(&app);
+    let qmp_stream = UnixStream::connect(home.join("qmp-appack.sock"))
+        .context("Failed to connect to QMP socket")?;
+    let mut qmp = Qmp::from_stream(&qmp_stream);
+    qmp.handshake().context("Failed to connect to QMP socket")?;
+
+    // QEMU writes the file itself, in the app home it can always write to
+    let dump = home.join("screenshot.png");
+    qmp.execute(&qmp::screendump {
+        filename: dump.to_string_lossy().to_string(),
+        format: Some(qmp::ImageFormat::png),
+        device: None,
+        head: None,
+    })
+    .context("Failed to capture the screen, PNG screenshots need QEMU 7.1 or later")?;
+
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(format!("{id}.png")));
+    let copied = std::fs::copy(&dump, &output);
+    let _ = std::fs::remove_file(&dump);
+    copied.context(format!("Failed to write {}", output.display()))?;
+
+    set_json_result(&json!({ "path": output }))?;
+    message!("Wrote {}", output.display());
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::{message, warning};
+use crate::utils::output::set_json_result;
+use anyhow::Result;
+use serde_json::json;
+
+/// Lists the packs of the synced repositories matching a query, and a category if given
+pub fn search(settings: &AppPackLocalSettings, query: &str, category: Option<&str>) -> Result<()> {
+    let mut results = Vec::new();
+
+    for repository in settings.get_repositories()?.repositories {
+        let index = match settings.get_repository_index(&repository) {
+            Ok(index) => index,
+            Err(e) => {
+                warning!("Skipping repository '{}': {e:?}", repository.name);
+                continue;
+            }
+        };
+
+        for entry in index.packs {
+            let in_category = category.is_none_or(|category| {
+                entry
+                    .categories
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(category))
+            });
+            if !in_category || !entry.matches(query) {
+                continue;
+            }
+
+            message!(
+                "{}\t{}\t{}\t{}",
+                entry.id,
+                entry.version,
+                repository.name,
+                entry.description.as_deref().unwrap_or(&entry.name)
+            );
+            results.push(json!({
+                "repository": repository.name,
+                "id": entry.id,
+                "name": entry.name,
+                "version": entry.version,
+                "description": entry.description,
+                "categories": entry.categories,
+                "keywords": entry.keywords,
+            }));
+        }
+    }
+    set_json_result(&results)?;
+
+    if results.is_empty() {
+        return Err(ErrorKind::NotFound.error(format!(
+            "No AppPack matching '{query}', run `appack repo sync` to refresh the repositories"
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::types::signing::{AppPackPublicKey, AppPackSignature};
+use crate::utils::checksum::{sha256_bytes, sha256_file};
+use crate::utils::download::{download_file, fetch_bytes};
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
+use crate::utils::sandbox::{Sandbox, get_sandbox};
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use serde_json::json;
+use std::os::unix::fs::PermissionsExt;
+
+/// Latest release of AppPack, `APPACK_RELEASES_URL` replaces it
+const RELEASES_URL: &str = "https://api.github.com/repos/PaulCombal/appack-cli/releases/latest";
+
+/// Key signing the released binaries, hex encoded. Set when building releases, builds without
+/// it can only check for updates.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("APPACK_RELEASE_PUBLIC_KEY");
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn version(&self) -> Result<Version> {
+        Version::parse(self.tag_name.trim_start_matches('v'))
+            .context(format!("Invalid release version: {}", self.tag_name))
+    }
+
+    fn asset_url(&self, name: &str) -> Result<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+            .ok_or_else(|| {
+                ErrorKind::NotFound.error(format!("Release {} has no {name}", self.tag_name))
+            })
+    }
+}
+
+/// What the release key signs: a binary tied to its version and machine, so that an older
+/// signed binary can't be served as a newer release
+#[derive(Debug, Deserialize)]
+struct ReleaseManifest {
+    version: String,
+    arch: String,
+    sha256: String,
+}
+
+impl ReleaseManifest {
+    /// Checks that the manifest describes a newer release for this machine, `latest` being the
+    /// unsigned version announced by the release
+    fn check(&self, latest: &Version, current: &Version) -> Result<()> {
+        let invalid = |msg: String| Err(ErrorKind::SignatureInvalid.error(msg));
+        let version = Version::parse(self.version.trim_start_matches('v')).context(format!(
+            "Invalid version in the release manifest: {}",
+            self.version
+        ))?;
+        if self.arch != std::env::consts::ARCH {
+            return invalid(format!(
+                "The release manifest is for {}, not {}",
+                self.arch,
+                std::env::consts::ARCH
+            ));
+        }
+        if &version != latest {
+            return invalid(format!(
+                "The release manifest is for version {version}, not {latest}"
+            ));
+        }
+        if &version <= current {
+            return invalid(format!(
+                "Release {version} is not newer than this AppPack ({current})"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Name of the released binary for this machine. Its manifest has the `.yaml` extension, and the
+/// signature of the manifest `.yaml.sig`.
+fn binary_name() -> String {
+    format!("appack-{}", std::env::consts::ARCH)
+}
+
+/// Downloads the manifest of a release and checks it is signed by `key`
+fn fetch_manifest(release: &Release, name: &str, key: AppPackPublicKey) -> Result<ReleaseManifest> {
+    let manifest = fetch_bytes(release.asset_url(&format!("{name}.yaml"))?)?;
+    let signature = fetch_bytes(release.asset_url(&format!("{name}.yaml.sig"))?)?;
+    let signature: AppPackSignature =
+        serde_yaml::from_slice(&signature).context("Invalid release signature file")?;
+    signature.verify(&sha256_bytes(&manifest), &[key])?;
+
+    serde_yaml::from_slice(&manifest).context("Invalid release manifest")
+}
+
+fn latest_release() -> Result<Release> {
+    let url = std::env::var("APPACK_RELEASES_URL").unwrap_or_else(|_| RELEASES_URL.to_string());
+    let release = fetch_bytes(&url).map_err(|e| {
+        ErrorKind::DownloadFailed
+            .error(format!("{e:#}"))
+            .context("Failed to check for a new AppPack release")
+    })?;
+
+    serde_json::from_slice(&release).context("Invalid release description")
+}
+
+/// Replaces the running binary by the one of a release, once its signed manifest is checked
+fn apply(release: &Release, current_version: &Version) -> Result<()> {
+    let key = RELEASE_PUBLIC_KEY.ok_or_else(|| {
+        ErrorKind::EnvironmentMissing.error(
+            "This build of AppPack can't verify releases, update it the way you installed it",
+        )
+    })?;
+    let key = AppPackPublicKey {
+        public_key: key.to_string(),
+        comment: None,
+    };
+
+    let name = binary_name();
+    let manifest = fetch_manifest(release, &name, key)
+        .and_then(|manifest| {
+            manifest.check(&release.version()?, current_version)?;
+            Ok(manifest)
+        })
+        .context("Refusing to install the new release")?;
+
+    let current = std::env::current_exe().context("Failed to find the AppPack binary")?;
+    let dir = current
+        .parent()
+        .context("Failed to find the AppPack binary")?;
+    // Next to the binary, so that it replaces it in one rename
+    let download = dir.join(".appack-update");
+    message!("Downloading AppPack {}..", release.tag_name);
+    download_file(release.asset_url(&name)?, &download).context(format!(
+        "Failed to write to {}, update AppPack the way you installed it",
+        dir.display()
+    ))?;
+
+    let checksum = sha256_file(&download).and_then(|sha256| {
+        if sha256.eq_ignore_ascii_case(&manifest.sha256) {
+            Ok(())
+        } else {
+            Err(ErrorKind::SignatureInvalid.error(format!(
+                "The checksum of {name} doesn't match its release manifest"
+            )))
+        }
+    });
+    if let Err(e) = checksum {
+        let _ = std::fs::remove_file(&download);
+        return Err(e.context("Refusing to install the new release"));
+    }
+
+    std::fs::set_permissions(&download, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&download, &current)
+        .context(format!("Failed to replace {}", current.display()))?;
+
+    Ok(())
+}
+
+/// Tells whether a newer AppPack was released, and installs it with `apply`. Snap and Flatpak
+/// installs are updated by their store.
+pub fn self_update(apply_update: bool) -> Result<()> {
+    match get_sandbox() {
+        Sandbox::Snap => {
+            return Err(ErrorKind::EnvironmentMissing
+                .error("AppPack is updated by snap, run `sudo snap refresh appack`"));
+        }
+        Sandbox::Flatpak => {
+            return Err(ErrorKind::EnvironmentMissing
+                .error("AppPack is updated by Flatpak, run `flatpak update`"));
+        }
+        Sandbox::None => {}
+    }
+
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    let release = latest_release()?;
+    let latest = release.version()?;
+    let available = latest > current;
+    let applied = available && apply_update;
+    if applied {
+        apply(&release, &current)?;
+    }
+    set_json_result(&json!({
+        "current": current.to_string(),
+        "latest": latest.to_string(),
+        "update_available": available,
+        "applied": applied,
+    }))?;
+
+    if applied {
+        message!("Updated AppPack from {current} to {latest}");
+    } else if available {
+        message!("AppPack {latest} is available (this is {current})");
+        message!("Install it with `appack self-update --apply`");
+    } else {
+        message!("AppPack {current} is up to date");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release() {
+        let release: Release = serde_json::from_str(
+            r#"{"tag_name": "v0.2.0", "draft": false, "assets": [
+                {"name": "appack-x86_64", "browser_download_url": "https://example.com/a"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(release.version().unwrap(), Version::new(0, 2, 0));
+        assert_eq!(
+            release.asset_url("appack-x86_64").unwrap(),
+            "https://example.com/a"
+        );
+        assert!(release.asset_url("appack-aarch64").is_err());
+    }
+
+    #[test]
+    fn test_release_manifest() {
+        let manifest = |version: &str, arch: &str| ReleaseManifest {
+            version: version.to_string(),
+            arch: arch.to_string(),
+            sha256: "00".to_string(),
+        };
+        let arch = std::env::consts::ARCH;
+        let latest = Version::new(0, 2, 0);
+        let current = Version::new(0, 1, 0);
+        let kind = |result: Result<()>| ErrorKind::of(&result.unwrap_err());
+
+        assert!(manifest("0.2.0", arch).check(&latest, &current).is_ok());
+        assert!(manifest("v0.2.0", arch).check(&latest, &current).is_ok());
+        // Another machine, another version than announced, or an older binary
+        let other = if arch == "aarch64" {
+            "x86_64"
+        } else {
+            "aarch64"
+        };
+        assert_eq!(
+            kind(manifest("0.2.0", other).check(&latest, &current)),
+            ErrorKind::SignatureInvalid
+        );
+        assert_eq!(
+            kind(manifest("0.1.5", arch).check(&latest, &current)),
+            ErrorKind::SignatureInvalid
+        );
+        assert_eq!(
+            kind(manifest("0.1.0", arch).check(&current, &current)),
+            ErrorKind::SignatureInvalid
+        );
+        assert_eq!(
+            kind(manifest("0.0.9", arch).check(&Version::new(0, 0, 9), &current)),
+            ErrorKind::SignatureInvalid
+        );
+        assert!(manifest("latest", arch).check(&latest, &current).is_err());
+    }
+}
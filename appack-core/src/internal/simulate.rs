@@ -0,0 +1,409 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::qemu_memory_mb;
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// First line of the disk images of simulated VMs, followed by the names of their snapshots
+const IMAGE_MAGIC: &str = "APPACK-SIMULATED-IMAGE";
+
+/// Real images are never read, they are much larger than simulated ones
+const MAX_IMAGE_SIZE: u64 = 64 * 1024;
+
+/// Memory of VMs without `-m`, like QEMU
+const DEFAULT_MEMORY_MB: u64 = 128;
+
+/// Snapshots of a simulated image, an empty file has none
+fn image_snapshots(image: &Path) -> Result<Vec<String>> {
+    let size = std::fs::metadata(image)
+        .context(format!("{} not found", image.display()))?
+        .len();
+    let contents = match size {
+        0 => format!("{IMAGE_MAGIC}\n"),
+        1..=MAX_IMAGE_SIZE => std::fs::read_to_string(image).unwrap_or_default(),
+        _ => String::new(),
+    };
+    let mut lines = contents.lines();
+    if lines.next() != Some(IMAGE_MAGIC) {
+        return Err(anyhow!(
+            "{} is not the image of a simulated VM",
+            image.display()
+        ));
+    }
+
+    Ok(lines.map(str::to_string).collect())
+}
+
+fn save_image(image: &Path, snapshots: &[String]) -> Result<()> {
+    let contents: String = std::iter::once(IMAGE_MAGIC)
+        .chain(snapshots.iter().map(String::as_str))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    std::fs::write(image, contents).context(format!("Failed to write {}", image.display()))
+}
+
+/// Arguments of a `qemu-img` command that are not options or their values
+fn positional(args: &[String]) -> Vec<&str> {
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-f" | "-F" | "-b" | "-o" | "-a" | "-c" | "-d") {
+            args.next();
+        } else if !arg.starts_with('-') {
+            positional.push(arg.as_str());
+        }
+    }
+
+    positional
+}
+
+fn qemu_img(args: &[String]) -> Result<()> {
+    let (subcommand, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("Missing qemu-img command"))?;
+    let positional = positional(args);
+    match subcommand.as_str() {
+        "create" => {
+            let image = positional.first().ok_or_else(|| anyhow!("Missing image"))?;
+            save_image(Path::new(image), &[])
+        }
+        "convert" => {
+            let image = positional.last().ok_or_else(|| anyhow!("Missing image"))?;
+            save_image(Path::new(image), &[])
+        }
+        "snapshot" => {
+            let image = Path::new(positional.last().ok_or_else(|| anyhow!("Missing image"))?);
+            let mut snapshots = image_snapshots(image)?;
+            let value_of = |option| {
+                args.iter()
+                    .position(|arg| arg == option)
+                    .and_then(|i| args.get(i + 1))
+            };
+            if let Some(tag) = value_of("-c") {
+                snapshots.retain(|snapshot| snapshot != tag);
+                snapshots.push(tag.clone());
+            } else if let Some(tag) = value_of("-d") {
+                let count = snapshots.len();
+                snapshots.retain(|snapshot| snapshot != tag);
+                if snapshots.len() == count {
+                    return Err(anyhow!("Can't find the snapshot {tag}"));
+                }
+            } else if let Some(tag) = value_of("-a") {
+                if !snapshots.contains(tag) {
+                    return Err(anyhow!("Can't find the snapshot {tag}"));
+                }
+                return Ok(());
+            } else {
+                // The layout of `qemu-img snapshot -l`
+                println!("Snapshot list:");
+                println!(
+                    "ID        TAG               VM SIZE                DATE     VM CLOCK     ICOUNT"
+                );
+                for (i, snapshot) in snapshots.iter().enumerate() {
+                    println!(
+                        "{:<10}{snapshot} 0 B 1970-01-01 00:00:00 00:00:00.000 0",
+                        i + 1
+                    );
+                }
+                return Ok(());
+            }
+            save_image(image, &snapshots)
+        }
+        // Nothing to check or convert in a simulated image
+        _ => Ok(()),
+    }
+}
+
+/// State of a simulated VM, shared by its monitors
+struct SimulatedVm {
+    image: Option<PathBuf>,
+    memory: i64,
+    balloon: i64,
+    jobs: Vec<Value>,
+    /// Sent to the monitor client after the reply to its command
+    events: Vec<Value>,
+    /// Removed when the VM stops, like QEMU does
+    files: Vec<PathBuf>,
+}
+
+impl SimulatedVm {
+    fn image(&self) -> Result<&Path> {
+        self.image
+            .as_deref()
+            .ok_or_else(|| anyhow!("The VM has no disk image"))
+    }
+
+    fn query_block(&self) -> Result<Value> {
+        let Some(image) = &self.image else {
+            return Ok(json!([]));
+        };
+        let snapshots: Vec<Value> = image_snapshots(image)?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                json!({
+                    "id": (i + 1).to_string(), "name": name, "vm-state-size": 0,
+                    "date-sec": 0, "date-nsec": 0, "vm-clock-sec": 0, "vm-clock-nsec": 0,
+                })
+            })
+            .collect();
+
+        Ok(json!([{
+            "device": "appack-disk", "type": "unknown", "removable": false, "locked": false,
+            "inserted": {
+                "file": image, "node-name": "appack-disk", "ro": false, "drv": "qcow2",
+                "backing_file_depth": 0, "encrypted": false, "detect_zeroes": "off",
+                "bps": 0, "bps_rd": 0, "bps_wr": 0, "iops": 0, "iops_rd": 0, "iops_wr": 0,
+                "write_threshold": 0,
+                "cache": { "writeback": true, "direct": false, "no-flush": false },
+                "image": {
+                    "filename": image, "format": "qcow2", "virtual-size": 0,
+                    "snapshots": snapshots,
+                },
+            },
+        }]))
+    }
+
+    fn execute(&mut self, command: &str, arguments: &Value) -> Result<Value> {
+        let result = match command {
+            "query-block" => self.query_block()?,
+            // Snapshot jobs conclude right away
+            "snapshot-save" | "snapshot-delete" => {
+                let tag = arguments["tag"].as_str().unwrap_or_default();
+                let image = self.image()?;
+                let mut snapshots = image_snapshots(image)?;
+                snapshots.retain(|snapshot| snapshot != tag);
+                if command == "snapshot-save" {
+                    snapshots.push(tag.to_string());
+                }
+                save_image(image, &snapshots)?;
+                self.jobs.push(json!({
+                    "id": arguments["job-id"], "type": command, "status": "concluded",
+                    "current-progress": 1, "total-progress": 1,
+                }));
+                for status in ["created", "running", "pending", "concluded"] {
+                    self.events.push(json!({
+                        "event": "JOB_STATUS_CHANGE",
+                        "data": { "id": arguments["job-id"], "status": status },
+                        "timestamp": { "seconds": 0, "microseconds": 0 },
+                    }));
+                }
+                json!({})
+            }
+            "query-jobs" => json!(self.jobs),
+            "job-dismiss" => {
+                self.jobs.retain(|job| job["id"] != arguments["id"]);
+                json!({})
+            }
+            "query-version" => json!({
+                "qemu": { "major": 10, "minor": 0, "micro": 0 }, "package": "appack-simulate",
+            }),
+            "query-balloon" => json!({ "actual": self.balloon }),
+            "balloon" => {
+                let value = arguments["value"].as_i64().unwrap_or(self.memory);
+                self.balloon = value.min(self.memory);
+                json!({})
+            }
+            "query-cpus-fast" | "query-blockstats" => json!([]),
+            "screendump" => {
+                let filename = arguments["filename"].as_str().unwrap_or_default();
+                // A black pixel
+                std::fs::write(filename, b"P6\n1 1\n255\n\0\0\0")?;
+                json!({})
+            }
+            _ => json!({}),
+        };
+
+        Ok(result)
+    }
+
+    fn power_off(&self) -> ! {
+        for file in &self.files {
+            let _ = std::fs::remove_file(file);
+        }
+        std::process::exit(0)
+    }
+}
+
+fn serve_monitor(stream: UnixStream, vm: &Mutex<SimulatedVm>) -> Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    let greeting = json!({ "QMP": {
+        "version": { "qemu": { "major": 10, "minor": 0, "micro": 0 }, "package": "appack-simulate" },
+        "capabilities": [],
+    }});
+    write!(stream, "{greeting}\r\n")?;
+
+    for line in reader.lines() {
+        let request: Value = serde_json::from_str(&line?)?;
+        let command = request["execute"].as_str().unwrap_or_default();
+        let mut vm = vm.lock().map_err(|_| anyhow!("Simulated VM poisoned"))?;
+        let mut reply = match vm.execute(command, &request["arguments"]) {
+            Ok(result) => json!({ "return": result }),
+            Err(e) => json!({ "error": { "class": "GenericError", "desc": e.to_string() } }),
+        };
+        if let Some(id) = request.get("id") {
+            reply["id"] = id.clone();
+        }
+        write!(stream, "{reply}\r\n")?;
+        for event in vm.events.drain(..) {
+            write!(stream, "{event}\r\n")?;
+        }
+
+        if matches!(command, "quit" | "system_powerdown") {
+            vm.power_off();
+        }
+    }
+
+    Ok(())
+}
+
+fn qemu(args: &[String]) -> Result<()> {
+    let value_of = |option: &str| {
+        args.iter()
+            .position(|arg| arg == option)
+            .and_then(|i| args.get(i + 1))
+    };
+    let values_of = |option: &'static str| {
+        args.windows(2)
+            .filter(move |pair| pair[0] == option)
+            .map(|pair| pair[1].as_str())
+    };
+
+    let mut files = Vec::new();
+    if let Some(pid_file) = value_of("-pidfile") {
+        std::fs::write(pid_file, std::process::id().to_string())?;
+        files.push(PathBuf::from(pid_file));
+    }
+    let image = values_of("-drive")
+        .filter(|drive| !drive.contains("media=cdrom"))
+        .find_map(|drive| {
+            drive
+                .split(',')
+                .find_map(|option| option.strip_prefix("file="))
+                .map(PathBuf::from)
+        });
+    let memory = qemu_memory_mb(&args.join(" ")).unwrap_or(DEFAULT_MEMORY_MB) as i64 * 1024 * 1024;
+
+    let mut listeners = Vec::new();
+    for monitor in values_of("-qmp") {
+        let Some(path) = monitor.strip_prefix("unix:") else {
+            continue;
+        };
+        let path = PathBuf::from(path.split(',').next().unwrap_or_default());
+        let _ = std::fs::remove_file(&path);
+        listeners.push(UnixListener::bind(&path).context(format!("Failed to bind {path:?}"))?);
+        files.push(path);
+    }
+    if listeners.is_empty() {
+        // Like a guest powering off once installed, nothing could stop it otherwise
+        return Ok(());
+    }
+
+    let vm = Arc::new(Mutex::new(SimulatedVm {
+        image,
+        memory,
+        balloon: memory,
+        jobs: Vec::new(),
+        events: Vec::new(),
+        files,
+    }));
+    for listener in listeners {
+        let vm = vm.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let vm = vm.clone();
+                thread::spawn(move || serve_monitor(stream, &vm));
+            }
+        });
+    }
+
+    loop {
+        thread::sleep(Duration::from_secs(3600));
+    }
+}
+
+/// Stands in for QEMU, qemu-img or FreeRDP when `APPACK_SIMULATE` is set, see
+/// `sandbox::Simulator`. Disk images are text files listing their snapshots, VMs answer
+/// the QMP commands AppPack uses, and FreeRDP windows stay open until they are closed
+/// with a signal.
+pub fn simulate(program: &str, args: &[String]) -> Result<()> {
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    if args.iter().any(|arg| arg == "--version" || arg == "/version") {
+        println!("{name} simulated by AppPack");
+        return Ok(());
+    }
+    if name == "qemu-img" {
+        qemu_img(args)
+    } else if name.starts_with("qemu-system-") {
+        qemu(args)
+    } else if name.contains("freerdp") {
+        loop {
+            thread::sleep(Duration::from_secs(3600));
+        }
+    } else {
+        Err(anyhow!("{program} can't be simulated"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_image() {
+        let image =
+            std::env::temp_dir().join(format!("appack-simulate-{}.qcow2", std::process::id()));
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let path = image.to_str().unwrap();
+
+        qemu_img(&args(&["create", "-f", "qcow2", path, "32G"])).unwrap();
+        qemu_img(&args(&["snapshot", "-c", "appack-init", path])).unwrap();
+        assert_eq!(image_snapshots(&image).unwrap(), ["appack-init"]);
+
+        let mut vm = SimulatedVm {
+            image: Some(image.clone()),
+            memory: 1024,
+            balloon: 1024,
+            jobs: Vec::new(),
+            events: Vec::new(),
+            files: Vec::new(),
+        };
+        let arguments = json!({ "tag": "appack-onclose", "job-id": "job" });
+        vm.execute("snapshot-save", &arguments).unwrap();
+        let jobs = vm.execute("query-jobs", &Value::Null).unwrap();
+        serde_json::from_value::<Vec<qapi::qmp::JobInfo>>(jobs).unwrap();
+        let blocks = vm.execute("query-block", &Value::Null).unwrap();
+        assert_eq!(
+            blocks[0]["inserted"]["image"]["snapshots"][1]["name"],
+            "appack-onclose"
+        );
+        serde_json::from_value::<Vec<qapi::qmp::BlockInfo>>(blocks).unwrap();
+
+        qemu_img(&args(&["snapshot", "-d", "appack-init", path])).unwrap();
+        assert_eq!(image_snapshots(&image).unwrap(), ["appack-onclose"]);
+        std::fs::remove_file(&image).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::config::NotificationLevel;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::notify::notify;
+use crate::utils::output::set_json_result;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const METRICS_FILE: &str = "launches.jsonl";
+
+/// Launches kept per app
+const KEPT_LAUNCHES: usize = 50;
+
+/// Launches needed to tell how long an app usually takes to start
+const BASELINE_LAUNCHES: usize = 3;
+
+/// Guests not answering RDP after this long are not timed
+const RDP_READY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// X.224 connection request of an RDP client, answered by the RDP server of a ready guest
+const RDP_CONNECTION_REQUEST: [u8; 19] = [
+    0x03, 0x00, 0x00, 0x13, 0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x03,
+    0x00, 0x00, 0x00,
+];
+
+/// Durations of a launch of an app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LaunchMetrics {
+    /// Start of the launch, in seconds since the epoch
+    time: u64,
+    version: String,
+    /// Whether the VM loaded a snapshot instead of booting
+    resumed: bool,
+    qmp_ms: u64,
+    rdp_ready_ms: Option<u64>,
+    snapshot_save_ms: Option<u64>,
+}
+
+fn metrics_path(settings: &AppPackLocalSettings, id: &str) -> PathBuf {
+    settings.logs_dir.join(id).join(METRICS_FILE)
+}
+
+fn read_metrics(settings: &AppPackLocalSettings, id: &str) -> Vec<LaunchMetrics> {
+    std::fs::read_to_string(metrics_path(settings, id))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn record(settings: &AppPackLocalSettings, id: &str, metrics: LaunchMetrics) -> Result<()> {
+    let mut launches = read_metrics(settings, id);
+    launches.push(metrics);
+    let kept = &launches[launches.len().saturating_sub(KEPT_LAUNCHES)..];
+
+    let mut content = String::new();
+    for launch in kept {
+        content.push_str(&serde_json::to_string(launch)?);
+        content.push('\n');
+    }
+    let path = metrics_path(settings, id);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, content).context(format!("Failed to write {}", path.display()))
+}
+
+/// Median time the guest usually takes to answer RDP, for launches of the same kind
+fn baseline_ms(launches: &[LaunchMetrics], resumed: bool) -> Option<u64> {
+    let mut durations: Vec<u64> = launches
+        .iter()
+        .filter(|launch| launch.resumed == resumed)
+        .filter_map(|launch| launch.rdp_ready_ms)
+        .collect();
+    if durations.len() < BASELINE_LAUNCHES {
+        return None;
+    }
+
+    durations.sort_unstable();
+    Some(durations[durations.len() / 2])
+}
+
+/// Much slower than usual, and not only by a few seconds
+fn is_slow(duration_ms: u64, baseline_ms: u64) -> bool {
+    duration_ms > baseline_ms * 3 && duration_ms > baseline_ms + 10_000
+}
+
+/// Whether the guest answers an RDP connection request. QEMU accepts connections to the
+/// forwarded port before the guest listens, and closes them right away then.
+fn rdp_answers(rdp_port: u16) -> bool {
+    let Ok(mut stream) = TcpStream::connect(("127.0.0.1", rdp_port)) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let mut reply = [0u8; 4];
+    stream.write_all(&RDP_CONNECTION_REQUEST).is_ok()
+        && stream.read_exact(&mut reply).is_ok()
+        && reply[0] == 0x03
+}
+
+/// Times a launch from the start of QEMU, and records it once the VM stops
+pub(crate) struct LaunchTimer {
+    started: Instant,
+    resumed: bool,
+    qmp_ms: u64,
+    rdp_ready_ms: Arc<AtomicU64>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl LaunchTimer {
+    pub fn start(resumed: bool) -> Self {
+        Self {
+            started: Instant::now(),
+            resumed,
+            qmp_ms: 0,
+            rdp_ready_ms: Arc::new(AtomicU64::new(0)),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Called once QMP answers, waits for the guest to answer RDP in the background and warns
+    /// when it takes much longer than usual
+    pub fn qmp_ready(
+        &mut self,
+        settings: &AppPackLocalSettings,
+        app: &InstalledAppPackEntry,
+        rdp_port: u16,
+    ) {
+        self.qmp_ms = self.started.elapsed().as_millis() as u64;
+        tracing::info!(qmp_ms = self.qmp_ms, "QMP ready");
+
+        let started = self.started;
+        let rdp_ready_ms = self.rdp_ready_ms.clone();
+        let stopped = self.stopped.clone();
+        let baseline = baseline_ms(&read_metrics(settings, &app.id), self.resumed);
+        let config = settings.config.clone();
+        let name = app.name.clone();
+        thread::spawn(move || {
+            while !rdp_answers(rdp_port) {
+                if stopped.load(Ordering::Relaxed) || started.elapsed() > RDP_READY_TIMEOUT {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(500));
+            }
+
+            let duration = started.elapsed().as_millis() as u64;
+            rdp_ready_ms.store(duration, Ordering::Relaxed);
+            tracing::info!(rdp_ready_ms = duration, ?baseline, "RDP ready");
+            if let Some(baseline) = baseline
+                && is_slow(duration, baseline)
+            {
+                notify(
+                    &config,
+                    NotificationLevel::Warning,
+                    &tr!(
+                        "notification-slow-launch",
+                        name = name,
+                        seconds = duration / 1000
+                    ),
+                    &tr!("notification-slow-launch-body", seconds = baseline / 1000),
+                );
+            }
+        });
+    }
+
+    /// Records the launch, with how long the snapshot taken when the VM stopped took
+    pub fn finish(
+        self,
+        settings: &AppPackLocalSettings,
+        app: &InstalledAppPackEntry,
+        snapshot_save: Option<Duration>,
+    ) {
+        self.stopped.store(true, Ordering::Relaxed);
+        let rdp_ready_ms = self.rdp_ready_ms.load(Ordering::Relaxed);
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .saturating_sub(self.started.elapsed());
+        let metrics = LaunchMetrics {
+            time: time.as_secs(),
+            version: app.version.clone(),
+            resumed: self.resumed,
+            qmp_ms: self.qmp_ms,
+            rdp_ready_ms: (rdp_ready_ms > 0).then_some(rdp_ready_ms),
+            snapshot_save_ms: snapshot_save.map(|duration| duration.as_millis() as u64),
+        };
+        if let Err(e) = record(settings, &app.id, metrics) {
+            tracing::warn!("Failed to record the launch: {e:?}");
+        }
+    }
+}
+
+fn average(values: impl Iterator<Item = u64>) -> Option<f64> {
+    let values: Vec<u64> = values.collect();
+    (!values.is_empty()).then(|| values.iter().sum::<u64>() as f64 / values.len() as f64 / 1000.0)
+}
+
+fn seconds(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |seconds| format!("{seconds:.1} s"))
+}
+
+/// Prints how long the installed apps, or one app, take to start and to save their state
+pub fn stats(settings: &AppPackLocalSettings, id: Option<&str>) -> Result<()> {
+    let mut installed = settings.get_installed()?.installed;
+    installed.extend(settings.get_system_installed()?.installed);
+    let mut ids: Vec<String> = installed
+        .into_iter()
+        .map(|app| app.id)
+        .filter(|app_id| id.is_none_or(|id| id == app_id))
+        .collect();
+    ids.sort();
+    ids.dedup();
+    if let Some(id) = id
+        && ids.is_empty()
+    {
+        return Err(ErrorKind::NotInstalled.error(format!("AppPack not installed: {id}")));
+    }
+
+    message!("APP\tLAUNCHES\tTO QMP\tTO RDP (BOOT)\tTO RDP (SNAPSHOT)\tSNAPSHOT SAVE");
+    let mut results = Vec::new();
+    for id in ids {
+        let launches = read_metrics(settings, &id);
+        let rdp_ready = |resumed: bool| {
+            average(
+                launches
+                    .iter()
+                    .filter(|launch| launch.resumed == resumed)
+                    .filter_map(|launch| launch.rdp_ready_ms),
+            )
+        };
+        let qmp = average(launches.iter().map(|launch| launch.qmp_ms));
+        let boot = rdp_ready(false);
+        let resumed = rdp_ready(true);
+        let save = average(launches.iter().filter_map(|launch| launch.snapshot_save_ms));
+
+        message!(
+            "{id}\t{}\t{}\t{}\t{}\t{}",
+            launches.len(),
+            seconds(qmp),
+            seconds(boot),
+            seconds(resumed),
+            seconds(save)
+        );
+        results.push(json!({
+            "id": id,
+            "launches": launches.len(),
+            "qmp_seconds": qmp,
+            "rdp_ready_boot_seconds": boot,
+            "rdp_ready_snapshot_seconds": resumed,
+            "snapshot_save_seconds": save,
+        }));
+    }
+    set_json_result(&results)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_launch() {
+        let launch = |resumed, rdp_ready_ms| LaunchMetrics {
+            time: 0,
+            version: "1.0.0".to_string(),
+            resumed,
+            qmp_ms: 100,
+            rdp_ready_ms: Some(rdp_ready_ms),
+            snapshot_save_ms: None,
+        };
+        let launches = [launch(true, 4000), launch(true, 6000), launch(true, 5000)];
+        assert_eq!(baseline_ms(&launches, true), Some(5000));
+        assert_eq!(baseline_ms(&launches, false), None);
+        assert!(is_slow(60_000, 5000));
+        assert!(!is_slow(12_000, 5000));
+        assert!(!is_slow(2_500, 500));
+    }
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::{
+    QemuProcess, RDP_PORT_VARIABLE, STOP_REQUEST, running_rdp_port, shut_down_vm,
+};
+use crate::internal::orphan::{LaunchState, qemu_pid, remove_stale_files};
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time the app has to take its snapshot and quit once its windows are closed
+const STOP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// FreeRDP processes of this machine connected to `port`. The ones reading their arguments
+/// from stdin are found by their environment.
+fn freerdp_pids(port: u16) -> Vec<i32> {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let port_suffix = format!(":{port}");
+    let variable = format!("{RDP_PORT_VARIABLE}={port}");
+    let has = |file: Vec<u8>, matches: &dyn Fn(&str) -> bool| {
+        file.split(|byte| *byte == 0)
+            .filter_map(|arg| std::str::from_utf8(arg).ok())
+            .any(matches)
+    };
+    processes
+        .flatten()
+        .filter_map(|process| {
+            let pid = process.file_name().to_str()?.parse::<i32>().ok()?;
+            let cmdline = std::fs::read(process.path().join("cmdline")).ok()?;
+            let environ = std::fs::read(process.path().join("environ")).unwrap_or_default();
+            (has(cmdline, &|arg| arg.starts_with("/v:") && arg.ends_with(&port_suffix))
+                || has(environ, &|entry| entry == variable))
+                .then_some(pid)
+        })
+        .collect()
+}
+
+/// Asks the `appack` process supervising the VM to stop it, for VMs no window shows, e.g.
+/// lingering or served to remote clients only
+fn request_stop(app_home: &Path) -> Result<()> {
+    let mut stream = UnixStream::connect(app_home.join("appack.sock"))?;
+    // The RDP port is sent first
+    let mut rdp_port = [0u8; 2];
+    stream.read_exact(&mut rdp_port)?;
+    stream.write_all(&[STOP_REQUEST])?;
+
+    Ok(())
+}
+
+/// Closes the windows of a running app, which then saves its state and stops like when the
+/// user closes them
+pub fn stop(settings: &AppPackLocalSettings, id: &str, version: Option<&str>) -> Result<()> {
+    let app = settings
+        .get_app_installed(id, version)
+        .context("Failed to get installed AppPack")?;
+    let port = running_rdp_port(settings, &app)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("{id} is not running")))?;
+
+    let home = settings.get_app_home_dir(&app);
+    let orphan = LaunchState::orphaned(&home);
+    let pids = freerdp_pids(port);
+    if pids.is_empty() && orphan.is_none() {
+        request_stop(&home).map_err(|_| {
+            ErrorKind::NotFound.error(format!("No window of {id} found on this machine"))
+        })?;
+    }
+    for pid in pids {
+        tracing::debug!(pid, "Closing FreeRDP");
+        // SAFETY: kill only sends a signal
+        unsafe {
+            libc::kill(pid, libc::SIGTERM);
+        }
+    }
+
+    message!("Stopping {}..", app.name);
+    let qmp_socket_path = home.join("qmp-appack.sock");
+    // Nothing else stops a VM whose launcher crashed
+    if let Some(launch_state) = orphan
+        && let Ok(_supervisor) = launch_state.supervise(&home)
+    {
+        tracing::warn!(id, "Stopping a VM left running by a crashed launcher");
+        let _stopping = settings.lock_app(&app.id)?;
+        let mut qemu = QemuProcess::Adopted(qmp_socket_path.clone(), qemu_pid(&home));
+        shut_down_vm(settings, &app, &qmp_socket_path, &launch_state, &mut qemu)?;
+    }
+    let started = Instant::now();
+    while UnixStream::connect(&qmp_socket_path).is_ok() {
+        if started.elapsed() > STOP_TIMEOUT {
+            return Err(ErrorKind::VmRunning.error(format!("{id} is still running")));
+        }
+        thread::sleep(Duration::from_millis(300));
+    }
+    remove_stale_files(&home);
+    tracing::info!(id, "AppPack stopped");
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::helpers::has_snapshot;
+use crate::internal::repo::signature_path;
+use crate::types::AppSnapshotTriggerMode;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::atomic_write::write_atomic;
+use crate::utils::frontend::{frontend, message, warning};
+use crate::utils::sandbox::{self, appack_command};
+use anyhow::{Context, Result, anyhow};
+use std::fs::DirBuilder;
+use std::io::Read;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Stdio;
+
+/// Runs `appack desktop sync` at the login of every user, see `sync_system_entries`
+const LOGIN_SYNC_ENTRY: &str = "appack-desktop-sync.desktop";
+
+/// User and group of `nobody`, the downloads run as when root wasn't reached through sudo
+const NOBODY: u32 = 65534;
+
+/// System-wide installations are shared by every user, only root can change them
+fn check_root() -> Result<()> {
+    // SAFETY: geteuid has no preconditions and can't fail
+    if unsafe { libc::geteuid() } != 0 {
+        return Err(ErrorKind::General
+            .error("Managing system-wide AppPacks requires root, try again with sudo"));
+    }
+
+    Ok(())
+}
+
+/// Settings of the system-wide installation, after checking the user can change it
+pub fn system_settings(settings: &AppPackLocalSettings) -> Result<AppPackLocalSettings> {
+    check_root()?;
+    // What root writes there is read by every user, and written by root only
+    // SAFETY: umask has no preconditions and can't fail
+    unsafe { libc::umask(0o022) };
+
+    let settings = settings.system();
+    std::fs::create_dir_all(&settings.home_dir).context(format!(
+        "Failed to create system directory {}",
+        settings.home_dir.display()
+    ))?;
+    let metadata = std::fs::metadata(&settings.home_dir)?;
+    if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+        return Err(ErrorKind::General.error(format!(
+            "System directory {} must belong to root and only be writable by it, \
+             its images and desktop entries are used by every user",
+            settings.home_dir.display()
+        )));
+    }
+
+    Ok(settings)
+}
+
+/// User the downloads of system-wide installations run as: the one who ran sudo, or nobody
+fn unprivileged_user() -> (u32, u32) {
+    let id = |name| {
+        std::env::var(name)
+            .ok()
+            .and_then(|id| id.parse::<u32>().ok())
+            .filter(|id| *id != 0)
+    };
+    match (id("SUDO_UID"), id("SUDO_GID")) {
+        (Some(uid), Some(gid)) => (uid, gid),
+        _ => (NOBODY, NOBODY),
+    }
+}
+
+/// Downloads a pack for a system-wide installation in a process that dropped the privileges of
+/// root, so that root only opens packs once it checked them. `sources` are pairs of pack and
+/// signature URLs tried in order, the signature is optional unless `signed`.
+pub(crate) fn download_unprivileged(
+    settings: &AppPackLocalSettings,
+    sources: &[(String, String)],
+    sha256: Option<&str>,
+    signed: bool,
+    pack_path: &Path,
+) -> Result<()> {
+    let (uid, gid) = unprivileged_user();
+    let downloads_dir = settings.home_dir.join("downloads");
+    std::fs::create_dir_all(&downloads_dir).context("Failed to create downloads directory")?;
+    let dir = downloads_dir.join(std::process::id().to_string());
+    let _ = std::fs::remove_dir_all(&dir); // Left by a process with the same id
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .context(format!("Failed to create {}", dir.display()))?;
+    std::os::unix::fs::chown(&dir, Some(uid), Some(gid))
+        .context(format!("Failed to give {} to user {uid}", dir.display()))?;
+
+    let result =
+        run_download(settings, &dir, (uid, gid), sources, sha256, signed).and_then(|downloaded| {
+            take_download(&downloaded, pack_path)?;
+            let signature = signature_path(&downloaded);
+            if signature.exists() {
+                take_download(&signature, &signature_path(pack_path))?;
+            }
+            Ok(())
+        });
+    let _ = std::fs::remove_dir_all(&dir); // Ignore error
+
+    result
+}
+
+/// Runs `appack download` as the given user in `dir`, returns the path of the pack it downloaded
+fn run_download(
+    settings: &AppPackLocalSettings,
+    dir: &Path,
+    (uid, gid): (u32, u32),
+    sources: &[(String, String)],
+    sha256: Option<&str>,
+    signed: bool,
+) -> Result<std::path::PathBuf> {
+    let pack = dir.join("pack.zip");
+    let appack = std::env::current_exe().context("Failed to find the AppPack executable")?;
+    let mut command = std::process::Command::new(appack);
+    // The settings of root can't be read, and mustn't be written, by the user
+    for variable in [
+        "HOME",
+        "XDG_DATA_HOME",
+        "SNAP_USER_COMMON",
+        "SNAP_REAL_HOME",
+    ] {
+        command.env(variable, dir);
+    }
+    command
+        .env("APPACK_CONFIG", dir.join("config.yaml"))
+        .env("APPACK_STORAGE_DIR", dir.join("home"))
+        .env("APPACK_CACHE_DIR", dir.join("cache"));
+    let config = &settings.config;
+    let ca_bundle = config.ca_bundle.as_ref().map(|path| path.display().to_string());
+    for (variable, value) in [
+        ("APPACK_PROXY", &config.proxy),
+        ("APPACK_CA_BUNDLE", &ca_bundle),
+        ("APPACK_IPFS_GATEWAY", &config.ipfs_gateway),
+    ] {
+        match value {
+            Some(value) => command.env(variable, value),
+            None => command.env_remove(variable),
+        };
+    }
+    if let Some(rate) = settings.limit_rate {
+        command.arg("--limit-rate").arg(rate.to_string());
+    }
+    command.arg("download").arg("--output").arg(&pack);
+    if let Some(sha256) = sha256 {
+        command.arg("--sha256").arg(sha256);
+    }
+    if signed {
+        command.arg("--signed");
+    }
+    for (url, signature_url) in sources {
+        command
+            .arg("--url")
+            .arg(url)
+            .arg("--signature")
+            .arg(signature_url);
+    }
+
+    tracing::info!(uid, gid, "Downloading as an unprivileged user");
+    // Groups are dropped too, the standard library clears the supplementary ones
+    let mut child = command
+        .uid(uid)
+        .gid(gid)
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run the download as an unprivileged user")?;
+    if let Some(stdout) = child.stdout.take() {
+        forward_output(stdout);
+    }
+    let status = child.wait().context("Failed to wait for the download")?;
+    if !status.success() {
+        return Err(ErrorKind::DownloadFailed.error("Download failed"));
+    }
+
+    Ok(pack)
+}
+
+/// Shows the messages and the progress of the download through the frontend
+fn forward_output(mut stdout: impl Read) {
+    let mut line = Vec::new();
+    let mut buffer = [0; 4096];
+    while let Ok(read) = stdout.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+        for byte in &buffer[..read] {
+            match byte {
+                b'\r' | b'\n' if line.is_empty() => {}
+                b'\r' => frontend().progress(&String::from_utf8_lossy(&std::mem::take(&mut line))),
+                b'\n' => message!("{}", String::from_utf8_lossy(&std::mem::take(&mut line))),
+                byte => line.push(*byte),
+            }
+        }
+    }
+}
+
+/// Moves a file downloaded by the unprivileged user to where root uses it
+fn take_download(downloaded: &Path, path: &Path) -> Result<()> {
+    // The user could have left a link to a file only root can read
+    if !std::fs::symlink_metadata(downloaded)?.file_type().is_file() {
+        return Err(ErrorKind::DownloadFailed
+            .error(format!("Download {} is not a file", downloaded.display())));
+    }
+
+    let _ = std::fs::remove_file(path); // Ignore error
+    if std::fs::rename(downloaded, path).is_err() {
+        // The downloads directory of root is on another filesystem
+        std::fs::copy(downloaded, path)
+            .context(format!("Failed to copy {}", downloaded.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Makes every user get the desktop entries of the apps installed system-wide at login. Those
+/// entries are written by each user in their own menu, as they launch the apps with their own
+/// overlay and settings.
+pub(crate) fn enable_login_sync(settings: &AppPackLocalSettings) -> Result<()> {
+    let entry_path = settings.autostart_dir.join(LOGIN_SYNC_ENTRY);
+    std::fs::create_dir_all(&settings.autostart_dir).context(format!(
+        "Failed to create {}",
+        settings.autostart_dir.display()
+    ))?;
+    let contents = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=AppPack desktop entries\n\
+         Exec={} desktop sync\n\
+         X-GNOME-Autostart-enabled=true\n\
+         NoDisplay=true\n",
+        appack_command()
+    );
+    write_atomic(&entry_path, contents).context(format!("Failed to write {}", entry_path.display()))
+}
+
+/// Stops syncing the desktop entries at login once no app is installed system-wide
+pub(crate) fn disable_login_sync(settings: &AppPackLocalSettings) {
+    let entry_path = settings.autostart_dir.join(LOGIN_SYNC_ENTRY);
+    if let Err(e) = std::fs::remove_file(&entry_path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warning!("Failed to remove {}: {e}", entry_path.display());
+    }
+}
+
+/// Creates the overlay of the current user on top of a system-wide image, if missing.
+/// The system image is never written to, every user keeps its own changes.
+///
+/// Apps always starting from the startup state of their pack get a copy of the image instead:
+/// that state is an internal snapshot, which QEMU can't load through an overlay.
+pub fn ensure_user_overlay(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+) -> Result<()> {
+    let overlay_dir = settings.get_app_home_dir(app);
+    let overlay_path = overlay_dir.join(&app.image);
+    let copy = matches!(app.snapshot_mode, AppSnapshotTriggerMode::Never);
+    if overlay_path.exists() {
+        if !copy || has_snapshot("appack-init", &overlay_path)? {
+            return Ok(());
+        }
+        // An overlay made by an older AppPack, these apps keep no changes in it
+        std::fs::remove_file(&overlay_path)
+            .context(format!("Failed to remove {}", overlay_path.display()))?;
+    }
+
+    let system_image = app
+        .home_dir
+        .as_ref()
+        .map(|dir| dir.join(&app.image))
+        .ok_or_else(|| anyhow!("System-wide AppPack {} has no home directory", app.id))?;
+    if !system_image.exists() {
+        return Err(
+            ErrorKind::NotFound.error(format!("System image {} not found", system_image.display()))
+        );
+    }
+
+    std::fs::create_dir_all(&overlay_dir).context(format!(
+        "Failed to create overlay directory {}",
+        overlay_dir.display()
+    ))?;
+
+    if copy {
+        message!("Copying the image of {} for this user..", app.name);
+        tracing::info!(id = app.id, "Copying system-wide image for the user");
+        // Shares the blocks of the system image on filesystems like Btrfs and XFS
+        let mut partial = overlay_path.as_os_str().to_owned();
+        partial.push(".copying");
+        std::fs::copy(&system_image, &partial)
+            .context(format!("Failed to copy {}", system_image.display()))?;
+        return std::fs::rename(&partial, &overlay_path)
+            .context(format!("Failed to create {}", overlay_path.display()));
+    }
+
+    tracing::info!(id = app.id, "Creating user overlay for system-wide AppPack");
+    sandbox::require("qemu-img")?;
+    let status = sandbox::command("qemu-img")
+        .arg("create")
+        .args(["-f", "qcow2", "-F", "qcow2", "-b"])
+        .arg(&system_image)
+        .arg(&overlay_path)
+        .status()
+        .context("Failed to run qemu-img")?;
+    if !status.success() {
+        return Err(anyhow!("qemu-img failed to create the user overlay"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::output::{emit_event, json_enabled, set_json_result};
+use anyhow::{Context, Result};
+use qapi::{Qmp, qmp};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MB: u64 = 1024 * 1024;
+
+/// Counters of a VM at one point in time
+struct Sample {
+    taken: Instant,
+    /// CPU time of the vCPU threads, in clock ticks
+    cpu_ticks: u64,
+    rss: u64,
+    read_bytes: u64,
+    written_bytes: u64,
+    balloon: Option<u64>,
+}
+
+/// Resource usage of a VM between two samples
+#[derive(Serialize)]
+struct VmUsage {
+    id: String,
+    version: String,
+    /// Of one host CPU, over 100 when the VM uses several
+    cpu_percent: f64,
+    rss: u64,
+    read_per_second: u64,
+    written_per_second: u64,
+    /// Memory the balloon leaves to the guest, when the VM has one
+    balloon: Option<u64>,
+}
+
+/// `utime + stime` of a thread
+fn thread_cpu_ticks(pid: u32, tid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).ok()?;
+    // The command name may contain spaces, the fields are after its closing parenthesis
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+
+    Some(utime + stime)
+}
+
+fn proc_status_field(pid: u32, field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn sample(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Result<Sample> {
+    let qmp_stream = UnixStream::connect(settings.get_app_home_dir(app).join("qmp-appack.sock"))
+        .context("Failed to connect to QMP socket")?;
+    let mut qmp = Qmp::from_stream(&qmp_stream);
+    qmp.handshake().context("Failed to connect to QMP socket")?;
+
+    let cpus = qmp.execute(&qmp::query_cpus_fast {})?;
+    let vcpu_threads: Vec<u32> = cpus
+        .iter()
+        .filter_map(|cpu| serde_json::to_value(cpu).ok()?.get("thread-id")?.as_u64())
+        .map(|tid| tid as u32)
+        .collect();
+    // vCPUs are threads of the QEMU process
+    let pid = vcpu_threads
+        .first()
+        .and_then(|tid| proc_status_field(*tid, "Tgid:"))
+        .context("QEMU process not found")? as u32;
+
+    let (read_bytes, written_bytes) = qmp
+        .execute(&qmp::query_blockstats { query_nodes: None })?
+        .iter()
+        .fold((0, 0), |(read, written), block| {
+            (
+                read + block.stats.rd_bytes.max(0) as u64,
+                written + block.stats.wr_bytes.max(0) as u64,
+            )
+        });
+    let balloon = qmp
+        .execute(&qmp::query_balloon {})
+        .ok()
+        .map(|balloon| balloon.actual.max(0) as u64);
+
+    Ok(Sample {
+        taken: Instant::now(),
+        cpu_ticks: vcpu_threads
+            .iter()
+            .filter_map(|tid| thread_cpu_ticks(pid, *tid))
+            .sum(),
+        rss: proc_status_field(pid, "VmRSS:").unwrap_or(0) * 1024,
+        read_bytes,
+        written_bytes,
+        balloon,
+    })
+}
+
+fn usage(app: &InstalledAppPackEntry, before: &Sample, after: &Sample) -> VmUsage {
+    // SAFETY: sysconf has no side effects
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let seconds = after
+        .taken
+        .duration_since(before.taken)
+        .as_secs_f64()
+        .max(0.001);
+    let per_second =
+        |before: u64, after: u64| (after.saturating_sub(before) as f64 / seconds) as u64;
+
+    VmUsage {
+        id: app.id.clone(),
+        version: app.version.clone(),
+        cpu_percent: after.cpu_ticks.saturating_sub(before.cpu_ticks) as f64
+            / ticks_per_second
+            / seconds
+            * 100.0,
+        rss: after.rss,
+        read_per_second: per_second(before.read_bytes, after.read_bytes),
+        written_per_second: per_second(before.written_bytes, after.written_bytes),
+        balloon: after.balloon,
+    }
+}
+
+fn print_usages(usages: &[VmUsage]) {
+    if std::io::stdout().is_terminal() {
+        // Redraw in place, like top
+        print!("\x1b[2J\x1b[H");
+    }
+    println!("ID\tVERSION\tCPU\tMEMORY\tREAD\tWRITE\tBALLOON");
+    for usage in usages {
+        let balloon = usage
+            .balloon
+            .map_or("-".to_string(), |balloon| format!("{} MB", balloon / MB));
+        println!(
+            "{}\t{}\t{:.1}%\t{} MB\t{} KB/s\t{} KB/s\t{balloon}",
+            usage.id,
+            usage.version,
+            usage.cpu_percent,
+            usage.rss / MB,
+            usage.read_per_second / 1024,
+            usage.written_per_second / 1024,
+        );
+    }
+    if usages.is_empty() {
+        println!("No app is running");
+    }
+}
+
+/// Shows the CPU, memory, disk and balloon usage of the running apps every `interval`,
+/// or only once
+pub fn top(settings: &AppPackLocalSettings, interval: Duration, once: bool) -> Result<()> {
+    let mut samples: HashMap<(String, String), Sample> = HashMap::new();
+
+    loop {
+        let apps: Vec<InstalledAppPackEntry> = settings
+            .get_installed()?
+            .installed
+            .into_iter()
+            .filter(|app| running_rdp_port(settings, app).is_some())
+            .collect();
+        let mut usages = Vec::new();
+        let mut new_samples = HashMap::new();
+        for app in apps {
+            let key = (app.id.clone(), app.version.clone());
+            let after = match sample(settings, &app) {
+                Ok(sample) => sample,
+                Err(e) => {
+                    tracing::debug!(id = app.id, "Failed to query the VM: {e:?}");
+                    continue;
+                }
+            };
+            if let Some(before) = samples.get(&key) {
+                usages.push(usage(&app, before, &after));
+            }
+            new_samples.insert(key, after);
+        }
+
+        // The first round only has counters, the usage is known from the second one
+        let first_round = samples.is_empty() && !new_samples.is_empty();
+        samples = new_samples;
+        if !first_round {
+            if json_enabled() && !once {
+                emit_event("vm_usage", serde_json::json!({ "apps": usages }));
+            } else {
+                print_usages(&usages);
+            }
+            if once {
+                return set_json_result(&usages);
+            }
+        }
+
+        thread::sleep(interval);
+    }
+}
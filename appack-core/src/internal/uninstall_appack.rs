@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::launch::running_rdp_port;
+use crate::internal::pool;
+use crate::internal::system::disable_login_sync;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::icon;
+use crate::utils::prompt::confirm;
+use anyhow::{Result, anyhow};
+use std::fs;
+
+pub fn uninstall_appack(
+    settings: &AppPackLocalSettings,
+    app_id: &str,
+    version: Option<&str>,
+) -> Result<()> {
+    let _app_lock = settings.lock_app(app_id)?;
+    let installed = settings.get_installed()?;
+
+    let app_entries: Vec<_> = if let Some(version) = version {
+        installed
+            .installed
+            .iter()
+            .filter(|e| e.id == app_id && e.version == version)
+            .collect()
+    } else {
+        installed
+            .installed
+            .iter()
+            .filter(|e| e.id == app_id)
+            .collect()
+    };
+
+    if app_entries.is_empty() {
+        if settings.get_system_installed()?.installed.iter().any(|e| e.id == app_id) {
+            return Err(ErrorKind::NotInstalled.error(format!(
+                "{app_id} is installed system-wide, uninstall it with `sudo appack --system uninstall {app_id}`"
+            )));
+        }
+        message!("AppPack not installed: {}", app_id);
+        Err(ErrorKind::NotInstalled.error("AppPack not installed"))?
+    }
+
+    if app_entries.len() > 1 {
+        message!("Multiple versions installed: {}", app_id);
+        Err(anyhow!("Multiple versions installed"))?
+    }
+
+    let app_entry = app_entries[0];
+    if running_rdp_port(settings, app_entry).is_some() {
+        return Err(ErrorKind::VmRunning.error(format!(
+            "{app_id} is running, stop it with `appack stop {app_id}` before uninstalling it"
+        )));
+    }
+
+    // 1. Remove desktop entries
+    if let Some(entries) = &app_entry.desktop_entries {
+        for desktop_entry in entries {
+            let entry_path = settings.get_desktop_entry_path(app_entry, desktop_entry);
+            if !entry_path.exists() {
+                // Apps installed system-wide have their entries in the menu of each user
+                if !settings.is_system() {
+                    message!("Desktop entry not found: {}", entry_path.display());
+                }
+                continue;
+            }
+            fs::remove_file(&entry_path)?;
+        }
+    }
+
+    // 2. Remove icons installed in the icon theme, the others are in the app dir
+    if let Some(entries) = &app_entry.desktop_entries {
+        for desktop_entry in entries {
+            let Some(extension) = icon::extension(&desktop_entry.icon) else {
+                continue;
+            };
+            let icon_file = format!(
+                "{}.{extension}",
+                settings.get_icon_name(app_entry, desktop_entry)
+            );
+            let Ok(size_dirs) = fs::read_dir(&settings.icon_theme_dir) else {
+                continue;
+            };
+            for size_dir in size_dirs.flatten() {
+                let icon_path = size_dir.path().join("apps").join(&icon_file);
+                if icon_path.exists() {
+                    fs::remove_file(&icon_path)?;
+                }
+            }
+        }
+    }
+
+    // 3. Delete AppPack directory
+    {
+        let appack_dir = settings.get_app_home_dir(app_entry);
+        if !appack_dir.exists() {
+            message!("AppPack dir does not exist: {appack_dir:?}");
+            Err(anyhow!("AppPack dir does not exist"))?;
+        }
+
+        fs::remove_dir_all(&appack_dir)?;
+        pool::prune(settings)?;
+    }
+
+    // 4. Stop starting it at login, unless another version is kept
+    let removed_version = app_entry.version.clone();
+    let _lock = settings.lock_installed()?;
+    let mut installed = settings.get_installed()?;
+    installed
+        .installed
+        .retain(|e| e.id != app_id || e.version != removed_version);
+    let autostart_entry = settings.get_autostart_entry_path(app_id);
+    if autostart_entry.exists() && !installed.installed.iter().any(|e| e.id == app_id) {
+        fs::remove_file(&autostart_entry)?;
+    }
+
+    if settings.is_system() && installed.installed.is_empty() {
+        disable_login_sync(settings);
+    }
+
+    settings.save_installed(installed)?;
+    tracing::info!(id = app_id, version = ?version, "AppPack uninstalled");
+
+    Ok(())
+}
+
+pub fn uninstall_all_appacks(settings: &AppPackLocalSettings) -> Result<()> {
+    let installed = settings.get_installed()?;
+    if installed.installed.is_empty() {
+        return Ok(());
+    }
+
+    let question = tr!("prompt-uninstall-all", count = installed.installed.len());
+    if !confirm(&question)? {
+        return Err(ErrorKind::Cancelled.error(tr!("uninstall-cancelled")));
+    }
+
+    for entry in installed.installed {
+        uninstall_appack(settings, &entry.id, Some(&entry.version))?;
+    }
+    Ok(())
+}
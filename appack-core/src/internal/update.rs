@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::internal::install_appack::{fetch_from_repository, install_appack_file};
+use crate::internal::relocate::relocate;
+use crate::internal::repo::{find_latest_in_repositories, sync_repository};
+use crate::internal::uninstall_appack::uninstall_appack;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::config::NotificationLevel;
+use crate::types::error::ErrorKind;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::types::repository::AppPackRepository;
+use crate::utils::frontend::message;
+use crate::utils::i18n::tr;
+use crate::utils::notify::notify;
+use crate::utils::output::set_json_result;
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::Duration;
+
+/// Replaces the given app, or all installed apps, with their newest repository version.
+/// The new pack is downloaded and verified before the installed version is removed.
+pub fn update(settings: &AppPackLocalSettings, id: Option<&str>) -> Result<()> {
+    let installed = settings.get_installed()?.installed;
+    if let Some(id) = id
+        && !installed.iter().any(|app| app.id == id)
+    {
+        return Err(ErrorKind::NotInstalled.error(format!("AppPack '{id}' is not installed")));
+    }
+
+    let mut ids: Vec<&str> = installed
+        .iter()
+        .map(|app| app.id.as_str())
+        .filter(|app_id| id.is_none_or(|id| id == *app_id))
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut updated = Vec::new();
+    for app_id in ids {
+        let Some(current) = installed
+            .iter()
+            .filter(|app| app.id == app_id)
+            .map(|app| app.version.as_str())
+            .max_by(|a, b| AppBuildConfig::compare_versions(a, b))
+        else {
+            continue;
+        };
+
+        let channel = settings.get_app_config(app_id)?.channel().to_string();
+        let Some((repository, entry)) = find_latest_in_repositories(settings, app_id, &channel)?
+        else {
+            message!("{app_id} is not available in any repository on the {channel} channel");
+            continue;
+        };
+
+        if AppBuildConfig::compare_versions(&entry.version, current) != Ordering::Greater {
+            message!("{app_id} {current} is up to date");
+            continue;
+        }
+
+        // Apps moved with `appack relocate` stay where they were moved
+        let location = installed
+            .iter()
+            .find(|app| app.id == app_id && app.version == current)
+            .and_then(|app| app.home_dir.as_deref())
+            .and_then(|dir| dir.parent()?.parent())
+            .map(Path::to_path_buf);
+
+        message!("Updating {app_id} {current} to {}..", entry.version);
+        let pack_path = fetch_from_repository(settings, &repository, &entry)?;
+        let _lock = settings.lock_app(app_id)?;
+        uninstall_appack(settings, app_id, Some(current))?;
+        // The app was already installed on this machine, emulated or not
+        install_appack_file(&pack_path, settings, Some(&entry), true)?;
+        if let Some(location) = location {
+            relocate(settings, &location, Some(app_id), Some(&entry.version))?;
+        }
+        updated.push(serde_json::json!({
+            "id": app_id,
+            "from": current,
+            "to": entry.version,
+        }));
+    }
+
+    message!("{} app(s) updated", updated.len());
+    set_json_result(&updated)?;
+
+    Ok(())
+}
+
+/// Repository indexes older than this are synced again before checking for updates at launch
+const UPDATE_CHECK_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn is_index_stale(settings: &AppPackLocalSettings, repository: &AppPackRepository) -> bool {
+    std::fs::metadata(settings.get_repository_index_path(repository))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|age| age > UPDATE_CHECK_TTL)
+}
+
+/// Shows a desktop notification if a newer version of the app is available.
+/// Meant to run in the background while the app is launching.
+pub fn notify_if_update_available(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+) -> Result<()> {
+    let config = settings.get_app_config(&app.id)?;
+    if !settings.config.notifications() || !config.update_notifications(&settings.config) {
+        return Ok(());
+    }
+
+    for repository in settings.get_repositories()?.repositories.iter() {
+        if is_index_stale(settings, repository) {
+            // An outdated index is still good enough to check for updates
+            let _ = sync_repository(settings, repository);
+        }
+    }
+
+    let Some((_, entry)) = find_latest_in_repositories(settings, &app.id, config.channel())? else {
+        return Ok(());
+    };
+
+    if AppBuildConfig::compare_versions(&entry.version, &app.version) == Ordering::Greater {
+        notify(
+            &settings.config,
+            NotificationLevel::Info,
+            &tr!("notification-update-available", name = app.name),
+            &tr!(
+                "notification-update-available-body",
+                version = entry.version,
+                id = app.id
+            ),
+        );
+    }
+
+    Ok(())
+}
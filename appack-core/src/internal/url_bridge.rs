@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_config::UrlPolicy;
+use crate::types::app_installed::InstalledAppPackEntry;
+use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::warning;
+use crate::utils::i18n::tr;
+use crate::utils::notify;
+use crate::utils::sandbox;
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread;
+
+/// Socket of the `org.appack.urls` virtio-serial port, in the app home. The helper in the
+/// VM writes the links the app opens to the port, one per line.
+pub(crate) const URL_SOCKET: &str = "appack-urls.sock";
+
+/// Longest link forwarded to the browser
+const MAX_URL_LENGTH: usize = 4096;
+
+/// Opens the links sent by the VM in the browser of this machine, until the VM stops.
+/// Packs without the port are ignored.
+pub(crate) fn serve_urls(
+    settings: &AppPackLocalSettings,
+    app: &InstalledAppPackEntry,
+    socket: &Path,
+) {
+    let Ok(stream) = UnixStream::connect(socket) else {
+        return;
+    };
+    tracing::debug!("Forwarding links opened in the VM");
+
+    let settings = settings.clone();
+    let app = app.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(url) = line else {
+                break;
+            };
+            if let Err(e) = open_url(&settings, &app, url.trim()) {
+                warning!("Failed to open a link of the app: {e:#}");
+            }
+        }
+    });
+}
+
+fn open_url(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry, url: &str) -> Result<()> {
+    if !is_web_url(url) {
+        tracing::warn!(
+            url,
+            "Ignoring a link of the app, only web and mail links are opened"
+        );
+        return Ok(());
+    }
+
+    let allowed = match settings.get_app_config(&app.id)?.open_urls() {
+        UrlPolicy::Always => true,
+        UrlPolicy::Never => false,
+        UrlPolicy::Ask => ask(settings, app, url)?,
+    };
+    if !allowed {
+        tracing::info!(url, "Not opening a link of the app");
+        return Ok(());
+    }
+
+    tracing::info!(url, "Opening a link of the app");
+    let mut browser = sandbox::command("xdg-open")
+        .arg(url)
+        .spawn()
+        .context("Failed to run xdg-open")?;
+    browser.wait()?;
+
+    Ok(())
+}
+
+/// Only links a browser or a mail client handles, a file or custom scheme could run programs
+fn is_web_url(url: &str) -> bool {
+    let scheme_allowed = ["https://", "http://", "mailto:"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(scheme))
+    });
+
+    scheme_allowed && url.len() <= MAX_URL_LENGTH && !url.chars().any(char::is_control)
+}
+
+/// Asks with a notification, or in the terminal when none can be shown
+fn ask(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry, url: &str) -> Result<bool> {
+    notify::ask(
+        &settings.config,
+        &tr!("notification-open-url", name = app.name),
+        url,
+        &tr!("notification-open-url-action"),
+    )
+    .context(tr!("hint-open-urls", id = app.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_web_url() {
+        assert!(is_web_url("https://example.com/a?b=c"));
+        assert!(is_web_url("MAILTO:someone@example.com"));
+        assert!(!is_web_url("file:///etc/passwd"));
+        assert!(!is_web_url("javascript:alert(1)"));
+        assert!(!is_web_url("https://example.com/\nrm"));
+    }
+}
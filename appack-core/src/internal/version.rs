@@ -14,10 +14,13 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::types::local_settings::AppPackLocalSettings;
+use crate::utils::frontend::message;
+use crate::utils::output::set_json_result;
 
 pub fn print_version(settings: &AppPackLocalSettings) -> anyhow::Result<()> {
-    println!("AppPack version: {}", env!("CARGO_PKG_VERSION"));
-    println!("Settings: {settings:?}");
+    set_json_result(&serde_json::json!({ "version": env!("CARGO_PKG_VERSION") }))?;
+    message!("AppPack version: {}", env!("CARGO_PKG_VERSION"));
+    message!("Settings: {settings:?}");
 
     Ok(())
 }
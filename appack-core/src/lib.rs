@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The library behind the `appack` command, for frontends that want to manage AppPacks
+//! without running the command line and parsing its output.
+//!
+//! * [`AppPackLocalSettings`] finds the AppPack directories
+//! * [`Registry`] lists, installs, updates, uninstalls and launches apps
+//! * [`spawn_launch`] runs an app, and returns a [`LaunchHandle`] to wait for it or close it
+//! * [`set_frontend`] takes the messages, progress events and questions meant for the user,
+//!   which otherwise go to the terminal
+//! * [`creator`] builds new packs
+//!
+//! Errors carry an [`ErrorKind`], found with [`ErrorKind::of`].
+
+pub mod internal;
+pub mod types;
+pub mod utils;
+
+pub use internal::creator;
+pub use internal::install_appack::install_appack;
+pub use internal::launch::{ClientOptions, LaunchHandle, RemoteOptions, launch, spawn_launch};
+pub use internal::registry::Registry;
+pub use internal::uninstall_appack::uninstall_appack;
+pub use internal::update::update;
+pub use types::error::ErrorKind;
+pub use types::local_settings::AppPackLocalSettings;
+pub use utils::frontend::{Frontend, Terminal, set_frontend};
@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::AppCapability;
+use crate::types::AppDesktopEntry;
+use crate::types::AppImageOptions;
+use crate::types::AppLocale;
+use crate::types::AppSnapshotTriggerMode;
+use crate::types::GuestArch;
+use crate::types::GuestLocale;
+use crate::types::SharedDirsMode;
+use crate::types::app_config::NetworkPolicy;
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use crate::utils::nested_virt;
+use crate::utils::sandbox;
+use crate::utils::xdg_session_type_detector::get_freerdp_executable;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppBuildConfig {
+    pub name: String,
+    pub id: String,
+    pub version: String,
+    pub image: String,
+    pub description: Option<String>,
+    /// Menu categories of the desktop entries, e.g. `Office`
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Search terms of the desktop entries and of `appack search`
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub snapshot: AppSnapshotTriggerMode,
+    pub readme: AppBuildConfigReadmeConfiguration,
+    pub base_command: String,
+    pub install_append: String,
+    pub configure_append: String,
+    pub configure_freerdp: String,
+    pub desktop_entries: Option<Vec<AppDesktopEntry>>,
+    /// Host devices the app can use once the user allows them
+    #[serde(default)]
+    pub capabilities: Vec<AppCapability>,
+    /// How folders shared by the user reach the VM
+    #[serde(default)]
+    pub shared_dirs: SharedDirsMode,
+    /// The app runs VMs itself, e.g. emulators, and needs nested virtualization
+    #[serde(default)]
+    pub requires_nested_virt: bool,
+    /// Architecture of the guest, `x86_64` by default
+    #[serde(default)]
+    pub arch: GuestArch,
+    /// Runs QEMU without its seccomp sandbox and other confinement options at launch, for
+    /// guests needing QEMU to start programs, e.g. `smb=` shares
+    #[serde(default)]
+    pub qemu_unconfined: bool,
+    /// Only destinations the guest can reach, as `host:port`. It reaches them at 10.0.2.100
+    /// and up, in order.
+    #[serde(default)]
+    pub network_allow: Option<Vec<String>>,
+    /// Oldest AppPack able to install the pack, e.g. `>= 1.4`
+    #[serde(default)]
+    pub requires_appack: Option<String>,
+    /// Name, description and readme of the pack in other languages, e.g. `fr` or `pt-BR`
+    #[serde(default)]
+    pub locales: BTreeMap<String, AppLocale>,
+    /// Options of the disk image created for the pack
+    #[serde(default)]
+    pub image_options: AppImageOptions,
+    /// Regional settings of the guest, baked in its `autounattend.xml` by `appack creator new`
+    #[serde(default)]
+    pub guest_locale: Option<GuestLocale>,
+}
+
+impl AppBuildConfig {
+    /// Adds the defaults of the architecture, and lets the VM run VMs itself when the app
+    /// needs it. Snapshots are then taken with the same machine as at launch.
+    fn with_guest_options(&self, full_command: String) -> anyhow::Result<String> {
+        let full_command = self.arch.with_defaults(&full_command);
+        if self.requires_nested_virt {
+            nested_virt::with_nested_virt(&full_command)
+        } else {
+            Ok(full_command)
+        }
+    }
+
+    /// Command of the first boot, `cloud_init_port` being where the host serves the cloud-init
+    /// files for `$CLOUD_INIT_URL`
+    pub fn get_boot_install_command(
+        &self,
+        cloud_init_port: Option<u16>,
+    ) -> anyhow::Result<Command> {
+        let full_command = format!("{} {}", self.base_command, self.install_append);
+        let full_command = full_command.replace("$IMAGE_FILE_PATH", &self.image);
+        let full_command = match cloud_init_port {
+            // The host, as seen from QEMU user networking
+            Some(port) => {
+                full_command.replace("$CLOUD_INIT_URL", &format!("http://10.0.2.2:{port}/"))
+            }
+            None => full_command,
+        };
+        let full_command = self.with_guest_options(full_command)?;
+
+        tracing::info!(command = %full_command, "Full boot install");
+
+        sandbox::require(&self.arch.qemu_binary())?;
+        let full_command_args = full_command.split_whitespace().collect::<Vec<&str>>();
+        let mut command = sandbox::command(&self.arch.qemu_binary());
+        command.args(full_command_args);
+        Ok(command)
+    }
+
+    pub fn get_boot_configure_command(&self, rdp_port: u16) -> anyhow::Result<Command> {
+        let full_command = format!("{} {}", self.base_command, self.configure_append);
+        let full_command = full_command.replace("$IMAGE_FILE_PATH", &self.image);
+        let full_command = full_command.replace("$RDP_PORT", &rdp_port.to_string());
+        let full_command = self.with_guest_options(full_command)?;
+        // Snapshots are taken with the forwarded destinations of the installed app, the
+        // network stays available to set the app up
+        let full_command =
+            NetworkPolicy::of_pack(self.network_allow.as_deref(), true)?.apply(&full_command)?;
+
+        tracing::info!(command = %full_command, "Full boot configure");
+
+        sandbox::require(&self.arch.qemu_binary())?;
+        let full_command_args = full_command.split_whitespace().collect::<Vec<&str>>();
+        let mut command = sandbox::command(&self.arch.qemu_binary());
+        command.args(full_command_args);
+        Ok(command)
+    }
+
+    pub fn get_rdp_configure_command(&self, rdp_port: u16) -> Command {
+        let real_home = sandbox::real_home().unwrap();
+        let full_command = format!("{} /v:localhost:$RDP_PORT", self.configure_freerdp)
+            .replace("$RDP_PORT", &rdp_port.to_string())
+            .replace("$HOME", &real_home.to_string_lossy());
+
+        let full_command_args = full_command.split_whitespace().collect::<Vec<&str>>();
+        let freerdp_exec = get_freerdp_executable();
+        tracing::info!(args = ?full_command_args, "Full {freerdp_exec} args");
+
+        let mut command = sandbox::command(freerdp_exec);
+        command.args(full_command_args);
+        command
+    }
+
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let mut file = std::fs::File::open(path)
+            .context(format!("Unable to open config file '{}'", path.display()))?;
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)
+            .context("Unable to read config file contents")?;
+
+        let cfg: Self = serde_yaml::from_slice(&buffer).context("Invalid YAML format in file")?;
+
+        if !AppBuildConfig::is_valid_version(&cfg.version) {
+            return Err(anyhow!("Invalid character in version: {}", cfg.version));
+        }
+        if let Some(requirement) = &cfg.requires_appack {
+            semver::VersionReq::parse(requirement)
+                .context(format!("Invalid requires_appack: {requirement}"))?;
+        }
+        cfg.image_options.validate()?;
+
+        Ok(cfg)
+    }
+
+    pub fn is_valid_version(version: &str) -> bool {
+        let forbidden_chars = [
+            '/', '\\', ':', '*', '?', '"', '<', '>', '|', ' ', '&', ';', '`', '$',
+        ];
+
+        if version.chars().any(|c| forbidden_chars.contains(&c)) {
+            return false;
+        }
+
+        true
+    }
+
+    /// Compares versions as semantic versions (`1.0.0-rc1` < `1.0.0`). Versions that are not
+    /// are compared part by part, numerically when both parts are numbers (`1.10` > `1.9`).
+    pub fn compare_versions(a: &str, b: &str) -> Ordering {
+        if let (Ok(a), Ok(b)) = (semver::Version::parse(a), semver::Version::parse(b)) {
+            return a.cmp_precedence(&b);
+        }
+
+        let mut a_parts = a.split(['.', '-', '+']);
+        let mut b_parts = b.split(['.', '-', '+']);
+
+        loop {
+            let ordering = match (a_parts.next(), b_parts.next()) {
+                (None, None) => return Ordering::Equal,
+                (Some(_), None) => return Ordering::Greater,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                },
+            };
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppBuildConfigReadmeConfiguration {
+    #[serde(default = "default_readme_folder")]
+    pub folder: String,
+    #[serde(default = "default_readme_index")]
+    pub index: String,
+}
+
+fn default_readme_folder() -> String {
+    "readme".to_string()
+}
+
+fn default_readme_index() -> String {
+    "README.md".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(AppBuildConfig::compare_versions("1.10.0", "1.9.3"), Ordering::Greater);
+        assert_eq!(AppBuildConfig::compare_versions("1.3.0-rc1", "1.3.0"), Ordering::Less);
+        assert_eq!(AppBuildConfig::compare_versions("1.3.0+b2", "1.3.0+b1"), Ordering::Equal);
+        assert_eq!(AppBuildConfig::compare_versions("2024.10", "2024.9"), Ordering::Greater);
+    }
+}
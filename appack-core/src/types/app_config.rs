@@ -0,0 +1,734 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::AppCapability;
+use crate::types::config::AppPackConfig;
+use serde::{Deserialize, Serialize};
+use anyhow::{Context, anyhow};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+/// User preferences of installed apps, kept across updates, stored in `app_config.yaml`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AppPackAppConfigs {
+    #[serde(default)]
+    pub apps: BTreeMap<String, AppPackAppConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AppPackAppConfig {
+    /// Release channel followed by `appack update`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Show a desktop notification at launch when a newer version is available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_notifications: Option<bool>,
+    /// How much the image may grow past its installed size, e.g. 10G
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_image_growth: Option<String>,
+    /// Monitors the app is displayed on, `all` or a list of monitor numbers like `0,1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitors: Option<String>,
+    /// Plays the sound of the app, `true` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<bool>,
+    /// Lets the app record from the microphone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub microphone: Option<bool>,
+    /// Lets the app print on the printers of this machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub printer: Option<bool>,
+    /// Lets the app use the smartcards and security keys of this machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smartcard: Option<bool>,
+    /// USB devices given to the app while it runs, as `vendor:product` ids like `1050:0407`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usb_devices: Option<String>,
+    /// Folders shared with the app, as `<host-dir>[:<name>]` separated by commas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shares: Option<String>,
+    /// Codecs and compression of the session, for the network between the VM and the window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub performance: Option<PerformanceProfile>,
+    /// What to do with links the app opens, see `UrlPolicy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_urls: Option<UrlPolicy>,
+    /// Whether the clock of the VM keeps local time or UTC, the choice of the pack when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtc: Option<RtcBase>,
+    /// Sets the clock of the guest to the time of this machine when the app resumes from a
+    /// snapshot, `true` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_sync: Option<bool>,
+    /// Gives the guest the timezone and the regional format of this machine, `false` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_locale: Option<bool>,
+    /// Starts the session at the resolution of the primary display of this machine instead
+    /// of the one of the snapshot, `true` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_resolution: Option<bool>,
+    /// Host CPUs the virtual CPUs run on, e.g. `2-5,8`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_pinning: Option<String>,
+    /// Cores and threads per core of the VM, e.g. `4x2`, the choice of the pack when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_topology: Option<String>,
+    /// Backs the memory of the VM with huge pages, which must be reserved on the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hugepages: Option<bool>,
+    /// Destinations the guest can reach, see `NetworkPolicy`. The choice of the pack when not
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_allow: Option<String>,
+    /// Days the logs of the app are kept, the global `log_retention_days` when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_retention_days: Option<u32>,
+    /// States of the app kept when it closes, the global `keep_snapshots` when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_snapshots: Option<u32>,
+}
+
+impl AppPackAppConfig {
+    pub fn channel(&self) -> &str {
+        self.channel.as_deref().unwrap_or(DEFAULT_CHANNEL)
+    }
+
+    /// Falls back to the global setting
+    pub fn update_notifications(&self, config: &AppPackConfig) -> bool {
+        self.update_notifications
+            .or(config.update_notifications)
+            .unwrap_or(true)
+    }
+
+    pub fn audio(&self) -> bool {
+        self.audio.unwrap_or(true)
+    }
+
+    /// Whether the user allowed the app to use a host device
+    pub fn allows(&self, capability: AppCapability) -> bool {
+        match capability {
+            AppCapability::Microphone => self.microphone.unwrap_or(false),
+            AppCapability::Printer => self.printer.unwrap_or(false),
+            AppCapability::Smartcard => self.smartcard.unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    pub fn usb_devices(&self) -> anyhow::Result<Vec<UsbDevice>> {
+        self.usb_devices
+            .as_deref()
+            .map(UsbDevice::parse_list)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn shares(&self) -> anyhow::Result<Vec<SharedFolder>> {
+        self.shares
+            .as_deref()
+            .map(SharedFolder::parse_list)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn performance(&self) -> PerformanceProfile {
+        self.performance.unwrap_or_default()
+    }
+
+    pub fn open_urls(&self) -> UrlPolicy {
+        self.open_urls.unwrap_or_default()
+    }
+
+    pub fn time_sync(&self) -> bool {
+        self.time_sync.unwrap_or(true)
+    }
+
+    pub fn host_locale(&self) -> bool {
+        self.host_locale.unwrap_or(false)
+    }
+
+    pub fn host_resolution(&self) -> bool {
+        self.host_resolution.unwrap_or(true)
+    }
+
+    pub fn cpu_pinning(&self) -> anyhow::Result<Option<CpuPinning>> {
+        self.cpu_pinning
+            .as_deref()
+            .map(CpuPinning::parse)
+            .transpose()
+    }
+
+    pub fn cpu_topology(&self) -> anyhow::Result<Option<CpuTopology>> {
+        self.cpu_topology
+            .as_deref()
+            .map(CpuTopology::parse)
+            .transpose()
+    }
+
+    pub fn hugepages(&self) -> bool {
+        self.hugepages.unwrap_or(false)
+    }
+
+    /// Falls back to the policy of the pack
+    pub fn network_policy(
+        &self,
+        network_allow: Option<&[String]>,
+        network: bool,
+    ) -> anyhow::Result<NetworkPolicy> {
+        match &self.network_allow {
+            Some(value) => NetworkPolicy::parse(value),
+            None => NetworkPolicy::of_pack(network_allow, network),
+        }
+    }
+
+    pub fn monitors(&self) -> anyhow::Result<Option<MonitorSelection>> {
+        self.monitors
+            .as_deref()
+            .map(MonitorSelection::parse)
+            .transpose()
+    }
+}
+
+/// Codec and compression presets, so packs don't need to embed them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerformanceProfile {
+    /// FreeRDP measures the network, unless the pack picks its own settings
+    #[default]
+    Auto,
+    /// Best quality, the VM and the window are on the same machine or network
+    Lan,
+    /// H.264 and compression, for remote clients on slow networks
+    Wan,
+}
+
+impl PerformanceProfile {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "lan" => Ok(Self::Lan),
+            "wan" => Ok(Self::Wan),
+            _ => Err(anyhow!(
+                "Invalid performance profile '{value}', expected auto, lan or wan"
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Lan => "lan",
+            Self::Wan => "wan",
+        }
+    }
+
+    /// Options overriding the ones of the pack, none for `auto`
+    pub fn freerdp_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::Auto => &[],
+            Self::Lan => &["/network:lan", "/gfx:RFX", "-compression"],
+            Self::Wan => &["/network:broadband-low", "/gfx:AVC420", "/compression-level:2"],
+        }
+    }
+}
+
+/// Whether links opened in the VM open in the browser of this machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlPolicy {
+    /// Asks with a desktop notification
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+impl UrlPolicy {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "ask" => Ok(Self::Ask),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(anyhow!("Invalid value '{value}', expected ask, always or never")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// Time kept by the clock of the VM. Windows expects local time, Linux UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RtcBase {
+    Localtime,
+    Utc,
+}
+
+impl RtcBase {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "localtime" => Ok(Self::Localtime),
+            "utc" => Ok(Self::Utc),
+            _ => Err(anyhow!("Invalid value '{value}', expected localtime or utc")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Localtime => "localtime",
+            Self::Utc => "utc",
+        }
+    }
+}
+
+/// Monitors FreeRDP displays an app on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MonitorSelection {
+    /// Span all monitors, or all the selected ones
+    pub multimon: bool,
+    /// Monitor numbers as listed by `xfreerdp3 /monitor-list`
+    pub monitors: Vec<u32>,
+}
+
+impl MonitorSelection {
+    /// Parses `all` or a comma separated list of monitor numbers
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        if value == "all" {
+            return Ok(Self {
+                multimon: true,
+                monitors: Vec::new(),
+            });
+        }
+
+        let monitors = value
+            .split(',')
+            .map(|m| m.trim().parse())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| {
+                anyhow!(
+                    "Invalid monitors '{value}', expected all or monitor numbers like 0,1"
+                )
+            })?;
+
+        Ok(Self {
+            multimon: false,
+            monitors,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.multimon && self.monitors.is_empty()
+    }
+
+    pub fn freerdp_args(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // A single monitor is only selected in fullscreen
+        if let [monitor] = self.monitors[..]
+            && !self.multimon
+        {
+            return Some(format!("/f /monitors:{monitor}"));
+        }
+
+        let mut args = "/multimon".to_string();
+        if !self.monitors.is_empty() {
+            let monitors: Vec<_> = self.monitors.iter().map(u32::to_string).collect();
+            args.push_str(&format!(" /monitors:{}", monitors.join(",")));
+        }
+
+        Some(args)
+    }
+}
+
+/// USB device of the host, passed through to the VM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsbDevice {
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+impl UsbDevice {
+    /// Parses a comma separated list of `vendor:product` ids in hexadecimal, as shown by `lsusb`
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        value
+            .split(',')
+            .map(|device| {
+                let (vendor, product) = device.trim().split_once(':').unwrap_or_default();
+                Ok(Self {
+                    vendor_id: u16::from_str_radix(vendor, 16)?,
+                    product_id: u16::from_str_radix(product, 16)?,
+                })
+            })
+            .collect::<Result<_, std::num::ParseIntError>>()
+            .map_err(|_| {
+                anyhow!(
+                    "Invalid USB devices '{value}', expected vendor:product ids like 1050:0407"
+                )
+            })
+    }
+}
+
+/// Host CPUs the virtual CPUs of the VM are pinned to
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuPinning {
+    pub host_cpus: Vec<u32>,
+}
+
+impl CpuPinning {
+    /// Parses a list of CPU numbers and ranges, like `taskset`, e.g. `2-5,8`
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow!("Invalid CPU list '{value}', expected CPU numbers like 2-5,8");
+        let mut host_cpus = Vec::new();
+        for part in value.split(',').map(str::trim) {
+            let (first, last) = part.split_once('-').unwrap_or((part, part));
+            let first: u32 = first.parse().map_err(|_| invalid())?;
+            let last: u32 = last.parse().map_err(|_| invalid())?;
+            if first > last {
+                return Err(invalid());
+            }
+            host_cpus.extend(first..=last);
+        }
+
+        Ok(Self { host_cpus })
+    }
+
+    /// Host CPU of a virtual CPU, virtual CPUs share them round robin when there are fewer
+    pub fn host_cpu(&self, vcpu: usize) -> u32 {
+        self.host_cpus[vcpu % self.host_cpus.len()]
+    }
+}
+
+/// CPU topology of the VM, some apps license or schedule per core
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuTopology {
+    pub cores: u32,
+    pub threads: u32,
+}
+
+impl CpuTopology {
+    /// Parses `<cores>x<threads>`, or a number of cores of one thread
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let (cores, threads) = value.split_once('x').unwrap_or((value, "1"));
+        match (cores.trim().parse(), threads.trim().parse()) {
+            (Ok(cores), Ok(threads)) if cores > 0 && threads > 0 => Ok(Self { cores, threads }),
+            _ => Err(anyhow!(
+                "Invalid CPU topology '{value}', expected cores x threads like 4x2"
+            )),
+        }
+    }
+
+    /// Value of the `-smp` option of QEMU
+    pub fn qemu_smp(&self) -> String {
+        format!(
+            "{},sockets=1,cores={},threads={}",
+            self.cores * self.threads,
+            self.cores,
+            self.threads
+        )
+    }
+}
+
+/// Folder of the host shared with the app, available as `\\tsclient\<name>` in the guest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedFolder {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+impl SharedFolder {
+    /// Parses `<host-dir>[:<name>]`, the name defaults to the name of the folder
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        let is_name = |name: &str| !name.is_empty() && name.chars().all(is_name_char);
+
+        let (path, name) = match value.rsplit_once(':') {
+            Some((path, name)) if !path.is_empty() && is_name(name) => (path, Some(name)),
+            _ => (value, None),
+        };
+        let path = match path.strip_prefix("~/") {
+            Some(relative) => crate::utils::sandbox::real_home()?.join(relative),
+            None => PathBuf::from(path),
+        };
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => path
+                .file_name()
+                .map(|n| n.to_string_lossy().replace(|c| !is_name_char(c), "_"))
+                .filter(|n| is_name(n))
+                .ok_or_else(|| anyhow!("Name the shared folder with {value}:<name>"))?,
+        };
+
+        Ok(Self { path, name })
+    }
+
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        value.split(',').map(|v| Self::parse(v.trim())).collect()
+    }
+
+    /// The `/drive:` option of FreeRDP, with whitespaces escaped
+    pub fn freerdp_arg(&self) -> anyhow::Result<String> {
+        let path = std::fs::canonicalize(&self.path)
+            .context(format!("Shared folder {} not found", self.path.display()))?;
+
+        Ok(format!("/drive:{},{}", self.name, path.display()).replace(' ', "$WHITESPACE"))
+    }
+}
+
+/// First address of the guest network the destinations allowed to the guest are reached at
+const FORWARDED_ADDRESSES_START: u8 = 100;
+
+/// Destination the guest can reach when its network is restricted
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkDestination {
+    pub host: String,
+    pub port: u16,
+}
+
+/// Destinations the guest can reach on the network
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkPolicy {
+    /// Anything the host can reach
+    All,
+    /// Only these destinations, none when empty
+    Only(Vec<NetworkDestination>),
+}
+
+impl NetworkPolicy {
+    /// Parses `all`, `none`, or `host:port` destinations separated by commas
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.trim() {
+            "all" => Ok(Self::All),
+            "none" => Ok(Self::Only(Vec::new())),
+            value => Self::only(value.split(',').map(str::trim)),
+        }
+    }
+
+    /// Policy of a pack listing the destinations of its guest in `network_allow`. Packs
+    /// without the `network` capability reach nothing else.
+    pub fn of_pack(network_allow: Option<&[String]>, network: bool) -> anyhow::Result<Self> {
+        match network_allow {
+            Some(destinations) => Self::only(destinations.iter().map(|d| d.trim())),
+            None if network => Ok(Self::All),
+            None => Ok(Self::Only(Vec::new())),
+        }
+    }
+
+    fn only<'a>(destinations: impl Iterator<Item = &'a str>) -> anyhow::Result<Self> {
+        let destinations = destinations
+            .map(|destination| {
+                let (host, port) = destination.rsplit_once(':').unwrap_or((destination, ""));
+                let valid_host = !host.is_empty()
+                    && host
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+                match port.parse() {
+                    Ok(port) if valid_host => Ok(NetworkDestination {
+                        host: host.to_string(),
+                        port,
+                    }),
+                    _ => Err(anyhow!(
+                        "Invalid destination '{destination}', expected host:port like \
+                         license.example.com:443"
+                    )),
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if destinations.len() > (u8::MAX - FORWARDED_ADDRESSES_START) as usize {
+            return Err(anyhow!("Too many destinations allowed to the guest"));
+        }
+
+        Ok(Self::Only(destinations))
+    }
+
+    /// Restricts the networks of a QEMU command to user mode networks reaching the allowed
+    /// destinations only, at 10.0.2.100, 10.0.2.101 and so on, in order. Each destination is a
+    /// single connection QEMU opens when it starts. Fails on networks that can't be restricted.
+    pub fn apply(&self, qemu_command: &str) -> anyhow::Result<String> {
+        let Self::Only(destinations) = self else {
+            return Ok(qemu_command.to_string());
+        };
+
+        let mut args: Vec<String> = qemu_command.split_whitespace().map(str::to_string).collect();
+        let is_network_option = |arg: &String| matches!(arg.as_str(), "-netdev" | "-nic" | "-net");
+        if !args.iter().any(is_network_option) && !args.iter().any(|arg| arg == "-nodefaults") {
+            // QEMU gives a user mode network to machines without any
+            args.extend(["-nic".to_string(), "user".to_string()]);
+        }
+
+        let mut chardevs = Vec::new();
+        let mut networks = 0;
+        for i in 1..args.len() {
+            if !is_network_option(&args[i - 1]) {
+                continue;
+            }
+            let backend = args[i].split(',').next().unwrap_or_default();
+            match (args[i - 1].as_str(), backend.trim_start_matches("type=")) {
+                (_, "none") | ("-net", "nic") => continue,
+                (_, "user") => {}
+                (option, _) => {
+                    return Err(anyhow!(
+                        "The network of the guest can't be restricted with `{option} {}`, \
+                         only user mode networks can",
+                        args[i]
+                    ));
+                }
+            }
+
+            args[i].push_str(",restrict=on");
+            for (destination, address) in destinations.iter().zip(FORWARDED_ADDRESSES_START..) {
+                let id = format!("appack-fwd{networks}-{address}");
+                args[i].push_str(&format!(
+                    ",guestfwd=tcp:10.0.2.{address}:{}-chardev:{id}",
+                    destination.port
+                ));
+                chardevs.push(format!(
+                    "-chardev socket,id={id},host={},port={}",
+                    destination.host, destination.port
+                ));
+            }
+            networks += 1;
+        }
+
+        Ok(args.into_iter().chain(chardevs).collect::<Vec<_>>().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_folder() {
+        let share = SharedFolder::parse("/srv/data:team-data").unwrap();
+        assert_eq!(share.path, PathBuf::from("/srv/data"));
+        assert_eq!(share.name, "team-data");
+
+        let share = SharedFolder::parse("/mnt/My Scans").unwrap();
+        assert_eq!(share.name, "My_Scans");
+
+        assert!(SharedFolder::parse("/").is_err());
+    }
+
+    #[test]
+    fn test_usb_devices() {
+        assert_eq!(
+            UsbDevice::parse_list("1050:0407, 04e6:5116").unwrap(),
+            vec![
+                UsbDevice {
+                    vendor_id: 0x1050,
+                    product_id: 0x0407
+                },
+                UsbDevice {
+                    vendor_id: 0x04e6,
+                    product_id: 0x5116
+                }
+            ]
+        );
+        assert!(UsbDevice::parse_list("yubikey").is_err());
+    }
+
+    #[test]
+    fn test_monitor_args() {
+        let all = MonitorSelection::parse("all").unwrap();
+        assert_eq!(all.freerdp_args().as_deref(), Some("/multimon"));
+
+        let one = MonitorSelection::parse("1").unwrap();
+        assert_eq!(one.freerdp_args().as_deref(), Some("/f /monitors:1"));
+
+        let two = MonitorSelection::parse("0, 2").unwrap();
+        assert_eq!(
+            two.freerdp_args().as_deref(),
+            Some("/multimon /monitors:0,2")
+        );
+
+        assert_eq!(MonitorSelection::default().freerdp_args(), None);
+        assert!(MonitorSelection::parse("left").is_err());
+    }
+
+    #[test]
+    fn test_cpu_tuning() {
+        let pinning = CpuPinning::parse("2-4, 8").unwrap();
+        assert_eq!(pinning.host_cpus, vec![2, 3, 4, 8]);
+        assert_eq!(pinning.host_cpu(5), 3);
+        assert!(CpuPinning::parse("4-2").is_err());
+
+        let topology = CpuTopology::parse("4x2").unwrap();
+        assert_eq!(topology.qemu_smp(), "8,sockets=1,cores=4,threads=2");
+        assert_eq!(CpuTopology::parse("6").unwrap().threads, 1);
+        assert!(CpuTopology::parse("0x2").is_err());
+    }
+
+    #[test]
+    fn test_network_policy() {
+        let command = "-m 4G -netdev user,id=n0,hostfwd=tcp::3389-:3389";
+        assert_eq!(NetworkPolicy::parse("all").unwrap().apply(command).unwrap(), command);
+        let none = NetworkPolicy::parse("none").unwrap();
+        assert_eq!(
+            none.apply(command).unwrap(),
+            "-m 4G -netdev user,id=n0,hostfwd=tcp::3389-:3389,restrict=on"
+        );
+        assert_eq!(
+            NetworkPolicy::parse("lic.example.com:443").unwrap().apply(command).unwrap(),
+            "-m 4G -netdev user,id=n0,hostfwd=tcp::3389-:3389,restrict=on,\
+             guestfwd=tcp:10.0.2.100:443-chardev:appack-fwd0-100 \
+             -chardev socket,id=appack-fwd0-100,host=lic.example.com,port=443"
+        );
+        assert!(NetworkPolicy::parse("lic.example.com").is_err());
+
+        // Every network is restricted, including the one QEMU adds by default
+        assert_eq!(none.apply("-m 4G").unwrap(), "-m 4G -nic user,restrict=on");
+        assert_eq!(none.apply("-m 4G -nodefaults").unwrap(), "-m 4G -nodefaults");
+        assert_eq!(
+            none.apply("-net nic -net user,id=n0 -nic none").unwrap(),
+            "-net nic -net user,id=n0,restrict=on -nic none"
+        );
+        for command in ["-netdev tap,id=n0", "-nic bridge,br=br0", "-net socket,listen=:1234"] {
+            assert!(none.apply(command).is_err(), "{command}");
+        }
+    }
+
+    #[test]
+    fn test_network_forwards() {
+        // Each guestfwd needs its own chardev, declared in the command
+        let policy = NetworkPolicy::parse("a.example.com:443, b.example.com:1947").unwrap();
+        let command = policy
+            .apply("-netdev user,id=n0 -netdev user,id=n1")
+            .unwrap();
+        let args: Vec<&str> = command.split_whitespace().collect();
+        let forwards: Vec<&str> = args
+            .iter()
+            .flat_map(|arg| arg.split(','))
+            .filter_map(|option| option.split_once("-chardev:").map(|(_, id)| id))
+            .collect();
+        assert_eq!(forwards.len(), 4);
+        for id in &forwards {
+            let declared = args
+                .windows(2)
+                .filter(|pair| pair[0] == "-chardev" && pair[1].contains(&format!("id={id},")))
+                .count();
+            assert_eq!(declared, 1, "{id}");
+        }
+        assert!(command.contains("host=b.example.com,port=1947"));
+    }
+}
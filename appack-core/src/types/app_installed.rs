@@ -0,0 +1,475 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::error::ErrorKind;
+use crate::types::{
+    AppCapability, AppDesktopEntry, AppLocale, AppReadme, AppSnapshotTriggerMode, GuestArch,
+    GuestLocale, SharedDirsMode,
+};
+use crate::utils::i18n::{localized, tr};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InstalledAppPackEntry {
+    pub id: String,
+    pub version: String,
+    pub name: String,
+    pub image: String,
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    pub desktop_entries: Option<Vec<AppDesktopEntry>>,
+    pub snapshot_mode: AppSnapshotTriggerMode,
+    pub qemu_command: String,
+    pub freerdp_command: String,
+    /// Directory of the app when it was moved out of the AppPack home with `appack relocate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home_dir: Option<PathBuf>,
+    /// Size of the image when the app was installed, to measure how much it grew since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_size: Option<u64>,
+    /// SHA-256 of the image as packed, installs of the same image share it until it changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_sha256: Option<String>,
+    /// Installed system-wide with `appack --system install`, each user runs it from an overlay
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub system: bool,
+    /// Host devices the app can use once the user allows them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<AppCapability>,
+    /// How folders shared by the user reach the VM
+    #[serde(default, skip_serializing_if = "is_rdp")]
+    pub shared_dirs: SharedDirsMode,
+    /// The app runs VMs itself and needs nested virtualization
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_nested_virt: bool,
+    /// Architecture of the guest
+    #[serde(default, skip_serializing_if = "is_x86_64")]
+    pub arch: GuestArch,
+    /// QEMU runs without its sandbox
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub qemu_unconfined: bool,
+    /// Only destinations the guest can reach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_allow: Option<Vec<String>>,
+    /// Versions of AppPack able to install the pack
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_appack: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme: Option<AppReadme>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub locales: BTreeMap<String, AppLocale>,
+    /// Regional settings the guest was installed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guest_locale: Option<GuestLocale>,
+}
+
+impl InstalledAppPackEntry {
+    /// Description in the language of the user, English otherwise
+    pub fn localized_description(&self) -> Option<&str> {
+        localized(&self.locales)
+            .and_then(|locale| locale.description.as_deref())
+            .or(self.description.as_deref())
+    }
+
+    /// Index of the readme in the language of the user, English otherwise
+    pub fn localized_readme(&self) -> Option<&str> {
+        let readme = self.readme.as_ref()?;
+        let index = localized(&self.locales)
+            .and_then(|locale| locale.readme.as_deref())
+            .unwrap_or(&readme.index);
+
+        Some(index)
+    }
+
+    /// Reads the `AppPack.yaml` of a pack, once checked this version of AppPack supports it
+    pub fn from_pack_yaml(content: &[u8]) -> anyhow::Result<Self> {
+        let value: Value = serde_yaml::from_slice(content).context("Invalid YAML file")?;
+        if let Some(requirement) = value.get("requires_appack").and_then(Value::as_str) {
+            check_appack_version(requirement)?;
+        }
+
+        let app: Self = serde_yaml::from_value(value).context("Invalid YAML file")?;
+        app.check_desktop_values()?;
+        Ok(app)
+    }
+
+    /// Values copied into desktop entries can't span several lines, a line break would add keys
+    /// or groups to the entry
+    fn check_desktop_values(&self) -> anyhow::Result<()> {
+        let values = self.categories.iter().chain(&self.keywords);
+        if let Some(value) = values
+            .into_iter()
+            .find(|value| value.contains(char::is_control))
+        {
+            return Err(ErrorKind::ArchiveInvalid.error(format!(
+                "Invalid category or keyword {value:?}, it contains control characters"
+            )));
+        }
+
+        // Translations become `Name[<language>]=` and `Comment[<language>]=` lines
+        for (language, locale) in &self.locales {
+            let values = [
+                Some(language),
+                locale.name.as_ref(),
+                locale.description.as_ref(),
+            ];
+            if let Some(value) = values
+                .into_iter()
+                .flatten()
+                .find(|value| value.contains(char::is_control))
+            {
+                return Err(ErrorKind::ArchiveInvalid.error(format!(
+                    "Invalid translation {value:?}, it contains control characters"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn declares(&self, capability: AppCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// FreeRDP arguments of the pack, without the ones using capabilities it didn't declare
+    pub fn declared_freerdp_args(&self, args: &str) -> String {
+        declared_freerdp_args(&self.capabilities, args)
+    }
+
+    /// FreeRDP command of the pack, only using the capabilities it declared
+    pub fn granted_freerdp_command(&self) -> String {
+        granted_freerdp_command(&self.capabilities, &self.freerdp_command)
+    }
+}
+
+/// FreeRDP arguments without the ones using capabilities not in `capabilities`
+pub fn declared_freerdp_args(capabilities: &[AppCapability], args: &str) -> String {
+    args.split_whitespace()
+        .filter(|arg| {
+            let undeclared = AppCapability::ALL.into_iter().find(|capability| {
+                !capability.is_device()
+                    && !capabilities.contains(capability)
+                    && capability.is_redirected_by(arg)
+            });
+            if let Some(capability) = undeclared {
+                tracing::warn!(
+                    arg,
+                    capability = capability.config_key(),
+                    "Ignoring an option of the pack using a capability it didn't declare"
+                );
+            }
+            undeclared.is_none()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// FreeRDP command only using the given capabilities
+pub fn granted_freerdp_command(capabilities: &[AppCapability], freerdp_command: &str) -> String {
+    let mut command = declared_freerdp_args(capabilities, freerdp_command);
+    // FreeRDP shares the clipboard and plays the sound by default
+    for (capability, disabled) in [
+        (AppCapability::Clipboard, "-clipboard"),
+        (AppCapability::Audio, "/audio-mode:2"),
+    ] {
+        if !capabilities.contains(&capability)
+            && !command.split_whitespace().any(|arg| arg == disabled)
+        {
+            command.push(' ');
+            command.push_str(disabled);
+        }
+    }
+
+    command
+}
+
+/// Fails when this version of AppPack doesn't meet the requirement of a pack
+pub fn check_appack_version(requirement: &str) -> anyhow::Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let matches = semver::VersionReq::parse(requirement)
+        .ok()
+        .zip(semver::Version::parse(current).ok())
+        .is_some_and(|(requirement, current)| requirement.matches(&current));
+    if !matches {
+        return Err(ErrorKind::EnvironmentMissing.error(tr!(
+            "error-requires-appack",
+            requirement = requirement,
+            version = current
+        )));
+    }
+
+    Ok(())
+}
+
+fn is_rdp(mode: &SharedDirsMode) -> bool {
+    *mode == SharedDirsMode::Rdp
+}
+
+fn is_x86_64(arch: &GuestArch) -> bool {
+    *arch == GuestArch::X86_64
+}
+
+/// Migrations of `installed.yaml`, the first one upgrades version 1 to version 2.
+/// Files written before versioning have no `schema_version` and are version 1.
+const MIGRATIONS: [fn(&mut Value, &Path) -> anyhow::Result<()>; 2] =
+    [migrate_v1_to_v2, migrate_v2_to_v3];
+
+pub const INSTALLED_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InstalledAppPacks {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub installed: Vec<InstalledAppPackEntry>,
+}
+
+impl Default for InstalledAppPacks {
+    fn default() -> Self {
+        Self {
+            schema_version: INSTALLED_SCHEMA_VERSION,
+            installed: Vec::new(),
+        }
+    }
+}
+
+impl InstalledAppPacks {
+    /// Parses `installed.yaml`, migrating it to the current schema version.
+    /// Also returns whether a migration happened, in which case the file should be saved again.
+    pub fn from_yaml(content: &str, home_dir: &Path) -> anyhow::Result<(Self, bool)> {
+        let mut value: Value = serde_yaml::from_str(content)?;
+        if value.is_null() {
+            return Ok((Self::default(), false));
+        }
+
+        let version = match value.get("schema_version") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| {
+                    let message = format!("Invalid schema_version: {version:?}");
+                    ErrorKind::SchemaUnsupported.error(message)
+                })?,
+        };
+
+        if version > INSTALLED_SCHEMA_VERSION {
+            return Err(ErrorKind::SchemaUnsupported.error(format!(
+                "Schema version {version} is not supported, please update AppPack"
+            )));
+        }
+
+        for migration in MIGRATIONS.iter().skip(version as usize - 1) {
+            migration(&mut value, home_dir)?;
+        }
+        value["schema_version"] = Value::from(INSTALLED_SCHEMA_VERSION);
+
+        Ok((
+            serde_yaml::from_value(value)?,
+            version != INSTALLED_SCHEMA_VERSION,
+        ))
+    }
+}
+
+/// Version 2 records the image size of apps, needed by the `max_image_growth` quota.
+/// For apps installed before, the size at migration time is the best baseline available.
+fn migrate_v1_to_v2(installed: &mut Value, home_dir: &Path) -> anyhow::Result<()> {
+    let Some(apps) = installed
+        .get_mut("installed")
+        .and_then(Value::as_sequence_mut)
+    else {
+        return Ok(());
+    };
+
+    for app in apps.iter_mut() {
+        let (Some(id), Some(version), Some(image)) = (
+            app.get("id").and_then(Value::as_str),
+            app.get("version").and_then(Value::as_str),
+            app.get("image").and_then(Value::as_str),
+        ) else {
+            return Err(anyhow!("Invalid installed app: {app:?}"));
+        };
+
+        let app_dir = match app.get("home_dir").and_then(Value::as_str) {
+            Some(dir) => PathBuf::from(dir),
+            None => home_dir.join(id).join(version),
+        };
+        if let Ok(metadata) = std::fs::metadata(app_dir.join(image)) {
+            app["image_size"] = Value::from(metadata.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// Version 3 only gives apps the capabilities their pack declares. Apps installed before keep
+/// what they could use.
+fn migrate_v2_to_v3(installed: &mut Value, _home_dir: &Path) -> anyhow::Result<()> {
+    let Some(apps) = installed
+        .get_mut("installed")
+        .and_then(Value::as_sequence_mut)
+    else {
+        return Ok(());
+    };
+
+    for app in apps.iter_mut() {
+        let mut capabilities = app
+            .get("capabilities")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        for capability in AppCapability::LEGACY {
+            let capability = Value::from(capability.config_key());
+            if !capabilities.contains(&capability) {
+                capabilities.push(capability);
+            }
+        }
+        app["capabilities"] = Value::Sequence(capabilities);
+    }
+
+    Ok(())
+}
+
+impl From<AppBuildConfig> for InstalledAppPackEntry {
+    fn from(value: AppBuildConfig) -> Self {
+        Self {
+            id: value.id,
+            version: value.version,
+            image: value.image,
+            name: value.name,
+            description: value.description,
+            desktop_entries: None,
+            qemu_command: format!("{} {}", value.base_command, value.configure_append),
+            freerdp_command: value.configure_freerdp,
+            snapshot_mode: value.snapshot,
+            home_dir: None,
+            image_size: None,
+            image_sha256: None,
+            system: false,
+            capabilities: value.capabilities,
+            shared_dirs: value.shared_dirs,
+            requires_nested_virt: value.requires_nested_virt,
+            arch: value.arch,
+            qemu_unconfined: value.qemu_unconfined,
+            network_allow: value.network_allow,
+            requires_appack: value.requires_appack,
+            categories: value.categories,
+            keywords: value.keywords,
+            readme: Path::new(&value.readme.folder)
+                .file_name()
+                .map(|folder| AppReadme {
+                    folder: folder.to_string_lossy().to_string(),
+                    index: value.readme.index,
+                }),
+            locales: value.locales,
+            guest_locale: value.guest_locale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_from_v1() {
+        let content = "installed:\n- id: demo\n  version: '1.0'\n  name: Demo\n  image: image.qcow2\n  snapshot_mode: NeverLoad\n  qemu_command: ''\n  freerdp_command: ''\n";
+        let (installed, migrated) =
+            InstalledAppPacks::from_yaml(content, Path::new("/nonexistent")).unwrap();
+
+        assert!(migrated);
+        assert_eq!(installed.schema_version, INSTALLED_SCHEMA_VERSION);
+        assert_eq!(installed.installed[0].id, "demo");
+        assert_eq!(installed.installed[0].image_size, None);
+        assert!(installed.installed[0].declares(AppCapability::Network));
+    }
+
+    #[test]
+    fn test_granted_freerdp_command() {
+        let app: InstalledAppPackEntry = serde_yaml::from_str(
+            "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '', capabilities: [clipboard], \
+             freerdp_command: '/u:x +clipboard /drive:home,$HOME /sound'}",
+        )
+        .unwrap();
+        assert_eq!(app.granted_freerdp_command(), "/u:x +clipboard /audio-mode:2");
+    }
+
+    #[test]
+    fn test_requires_appack() {
+        let pack = "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+                    snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: ''";
+        let pack_requiring = |requirement| format!("{pack}, requires_appack: '{requirement}'}}");
+        let app = InstalledAppPackEntry::from_pack_yaml(pack_requiring(">= 0.1").as_bytes());
+        assert!(app.is_ok());
+        // A newer pack may not even deserialize, its requirement is checked first
+        let newer = format!("{pack}, requires_appack: '>= 999', image_size: big}}");
+        let e = InstalledAppPackEntry::from_pack_yaml(newer.as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("999"), "{e}");
+    }
+
+    #[test]
+    fn test_control_characters_are_rejected() {
+        let pack = "{id: demo, version: 1.0.0, name: Demo, image: image.qcow2, \
+                    snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: ''";
+        let app =
+            InstalledAppPackEntry::from_pack_yaml(format!("{pack}, keywords: [a]}}").as_bytes());
+        assert!(app.is_ok());
+        for field in ["categories", "keywords"] {
+            let injected = format!("{pack}, {field}: [\"x\\nActions=a;\\n[Desktop Action a]\"]}}");
+            let e = InstalledAppPackEntry::from_pack_yaml(injected.as_bytes()).unwrap_err();
+            assert_eq!(ErrorKind::of(&e), ErrorKind::ArchiveInvalid, "{field}");
+        }
+        for locales in [
+            "{fr: {name: \"x\\nExec=sh\"}}",
+            "{fr: {description: \"x\\n[Desktop Action a]\"}}",
+            "{\"fr]=x\\nExec=sh\\n#\": {name: x}}",
+        ] {
+            let injected = format!("{pack}, locales: {locales}}}");
+            let e = InstalledAppPackEntry::from_pack_yaml(injected.as_bytes()).unwrap_err();
+            assert_eq!(ErrorKind::of(&e), ErrorKind::ArchiveInvalid, "{locales}");
+        }
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let content = format!(
+            "schema_version: {}\ninstalled: []\n",
+            INSTALLED_SCHEMA_VERSION + 1
+        );
+        let e = InstalledAppPacks::from_yaml(&content, Path::new("/nonexistent")).unwrap_err();
+        assert_eq!(ErrorKind::of(&e), ErrorKind::SchemaUnsupported);
+
+        let (_, migrated) = InstalledAppPacks::from_yaml("", Path::new("/nonexistent")).unwrap();
+        assert!(!migrated);
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        for version in ["0", "-1", "4294967297", "'2'"] {
+            let content = format!("schema_version: {version}\ninstalled: []\n");
+            let e = InstalledAppPacks::from_yaml(&content, Path::new("/nonexistent")).unwrap_err();
+            assert_eq!(ErrorKind::of(&e), ErrorKind::SchemaUnsupported, "{version}");
+        }
+    }
+}
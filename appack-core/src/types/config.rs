@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::repository::AppPackRepository;
+use crate::utils::atomic_write::write_atomic;
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Keys of `config.yaml` that can be changed with `appack config set` or an `APPACK_<KEY>` environment variable
+pub const CONFIG_KEYS: [&str; 27] = [
+    "storage_dir",
+    "cache_dir",
+    "rdp_client",
+    "rdp_args",
+    "notifications",
+    "notification_level",
+    "update_notifications",
+    "limit_rate",
+    "proxy",
+    "ca_bundle",
+    "ipfs_gateway",
+    "max_memory",
+    "max_cpus",
+    "max_storage",
+    "enforce_quotas",
+    "max_log_size",
+    "log_retention_days",
+    "keep_snapshots",
+    "keep_versions",
+    "snapshot_timeout",
+    "quit_timeout",
+    "linger",
+    "idle_memory",
+    "control_token",
+    "gateway",
+    "gateway_user",
+    "gateway_domain",
+];
+
+/// Severity of a desktop notification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+/// The global configuration file, `~/.config/appack/config.yaml`.
+/// Every setting is optional, AppPack falls back to its defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppPackConfig {
+    /// Where apps and AppPack data are stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// FreeRDP executable used to display apps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdp_client: Option<String>,
+    /// FreeRDP arguments added to every launch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdp_args: Option<String>,
+    /// Enables desktop notifications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<bool>,
+    /// Least severe notifications shown, the others are only logged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_level: Option<NotificationLevel>,
+    /// Default for apps that don't set `update_notifications` themselves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_notifications: Option<bool>,
+    /// Maximum transfer rate of downloads and uploads, e.g. 2M
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_rate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file of the root certificates to trust
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipfs_gateway: Option<String>,
+    /// Maximum memory given to an app VM, e.g. 4G
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<String>,
+    /// Maximum number of CPUs given to an app VM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpus: Option<u32>,
+    /// Maximum disk space used by apps and the cache, e.g. 100G
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_storage: Option<String>,
+    /// Refuse to launch apps when a quota is exceeded, instead of warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforce_quotas: Option<bool>,
+    /// Size from which app logs are rotated, e.g. 10M
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_log_size: Option<String>,
+    /// Number of days logs are kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_retention_days: Option<u32>,
+    /// States of an app kept when it closes, the newest one being loaded at launch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_snapshots: Option<u32>,
+    /// Versions of an app kept by `appack prune`, the newest ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<u32>,
+    /// Seconds an app has to save its state when it closes, before it is powered off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_timeout: Option<u64>,
+    /// Seconds an app has to stop once asked to, before it is powered off then killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quit_timeout: Option<u64>,
+    /// Seconds an app keeps running after its last window closed, to reopen right away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linger: Option<u64>,
+    /// Memory left to an app VM while no window shows it, e.g. 1G. Needs a balloon device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_memory: Option<String>,
+    /// Secret shared by `appack launch --listen` and the remote clients connecting to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_token: Option<String>,
+    /// RD Gateway remote launches go through, `host[:port]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    /// Gateway user, the password is read from the keyring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway_user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway_domain: Option<String>,
+    /// Repositories in addition to the ones added with `appack repo add`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<AppPackRepository>,
+    /// Short names of installed apps, `id` or `id@version`, managed with `appack alias`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl AppPackConfig {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read config file {}", path.display()))?;
+        serde_yaml::from_str(&content)
+            .context(format!("Failed to parse config file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory {}", parent.display()))?;
+        }
+
+        let mut content = serde_yaml::to_string(self).context("Failed to serialize config")?;
+        if content.trim() == "{}" {
+            // Keep the file easy to edit by hand
+            content.clear();
+        }
+        write_atomic(path, content)
+            .context(format!("Failed to write config file {}", path.display()))
+    }
+
+    /// Values from `APPACK_<KEY>` environment variables take precedence over the file
+    pub fn with_env_overrides(mut self) -> anyhow::Result<Self> {
+        for key in CONFIG_KEYS {
+            let variable = format!("APPACK_{}", key.to_uppercase());
+            if let Ok(value) = std::env::var(&variable) {
+                self.set(key, &value)
+                    .context(format!("Invalid {variable}"))?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn notifications(&self) -> bool {
+        self.notifications.unwrap_or(true)
+    }
+
+    pub fn notification_level(&self) -> NotificationLevel {
+        self.notification_level.unwrap_or_default()
+    }
+
+    pub fn snapshot_timeout(&self) -> Duration {
+        Duration::from_secs(self.snapshot_timeout.unwrap_or(600))
+    }
+
+    pub fn quit_timeout(&self) -> Duration {
+        Duration::from_secs(self.quit_timeout.unwrap_or(60))
+    }
+
+    pub fn linger(&self) -> Duration {
+        Duration::from_secs(self.linger.unwrap_or(0))
+    }
+
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        check_config_key(key)?;
+
+        let value = serde_yaml::to_value(self)?;
+        Ok(match value.get(key) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(value) => Some(serde_yaml::to_string(value)?.trim_end().to_string()),
+        })
+    }
+
+    pub fn set(&mut self, key: &str, raw_value: &str) -> anyhow::Result<()> {
+        check_config_key(key)?;
+
+        let mut value = serde_yaml::to_value(&*self)?;
+        // Numbers and booleans are parsed, but a string setting may look like a number too
+        value[key] = serde_yaml::from_str(raw_value).unwrap_or(Value::Null);
+        let config = serde_yaml::from_value(value.clone()).or_else(|_| {
+            value[key] = Value::String(raw_value.to_string());
+            serde_yaml::from_value(value)
+        });
+
+        *self = config.context(format!("Invalid value for {key}: {raw_value}"))?;
+
+        Ok(())
+    }
+
+    pub fn unset(&mut self, key: &str) -> anyhow::Result<()> {
+        check_config_key(key)?;
+
+        let mut value = serde_yaml::to_value(&*self)?;
+        value[key] = Value::Null;
+        *self = serde_yaml::from_value(value)?;
+
+        Ok(())
+    }
+}
+
+pub fn check_config_key(key: &str) -> anyhow::Result<()> {
+    if !CONFIG_KEYS.contains(&key) {
+        return Err(anyhow!(
+            "Unknown setting '{key}', expected one of: {}",
+            CONFIG_KEYS.join(", ")
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+/// Categories of errors, each exiting with its own code so scripts can tell failures apart.
+/// Codes are stable, new kinds only get new codes. Code 2 is used by invalid command lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Any error without a more specific kind
+    General,
+    /// The app (or version) is not installed
+    NotInstalled,
+    /// The app is already installed
+    AlreadyInstalled,
+    /// The pack was not found in the repositories
+    NotFound,
+    /// The app VM is running, and the operation needs it closed
+    VmRunning,
+    /// A directory, plug or program AppPack needs is missing
+    EnvironmentMissing,
+    /// The pack file is not a valid AppPack archive
+    ArchiveInvalid,
+    /// A download failed
+    DownloadFailed,
+    /// A signature is invalid, or made by an untrusted key
+    SignatureInvalid,
+    /// The user refused a confirmation
+    Cancelled,
+    /// A confirmation was required but no terminal was available, see `--yes`
+    ConfirmationRequired,
+    /// `installed.yaml` has a schema version this AppPack cannot read
+    SchemaUnsupported,
+    /// The name given for an app matches several installed apps
+    Ambiguous,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::NotInstalled => 3,
+            ErrorKind::AlreadyInstalled => 4,
+            ErrorKind::NotFound => 5,
+            ErrorKind::VmRunning => 6,
+            ErrorKind::EnvironmentMissing => 7,
+            ErrorKind::ArchiveInvalid => 8,
+            ErrorKind::DownloadFailed => 9,
+            ErrorKind::SignatureInvalid => 10,
+            ErrorKind::Cancelled => 11,
+            ErrorKind::ConfirmationRequired => 12,
+            ErrorKind::SchemaUnsupported => 13,
+            ErrorKind::Ambiguous => 14,
+        }
+    }
+
+    /// Name used in the `--json` error output
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::General => "general",
+            ErrorKind::NotInstalled => "not_installed",
+            ErrorKind::AlreadyInstalled => "already_installed",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::VmRunning => "vm_running",
+            ErrorKind::EnvironmentMissing => "environment_missing",
+            ErrorKind::ArchiveInvalid => "archive_invalid",
+            ErrorKind::DownloadFailed => "download_failed",
+            ErrorKind::SignatureInvalid => "signature_invalid",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::ConfirmationRequired => "confirmation_required",
+            ErrorKind::SchemaUnsupported => "schema_unsupported",
+            ErrorKind::Ambiguous => "ambiguous",
+        }
+    }
+
+    /// Creates an error of this kind, which can be given more context like any other error
+    pub fn error(self, message: impl Into<String>) -> anyhow::Error {
+        anyhow::Error::new(AppPackError {
+            kind: self,
+            message: message.into(),
+        })
+    }
+
+    /// Finds the kind of an error from its causes
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<AppPackError>() {
+                return error.kind;
+            }
+            if cause.is::<ureq::Error>() {
+                return ErrorKind::DownloadFailed;
+            }
+            if cause.is::<zip::result::ZipError>() {
+                return ErrorKind::ArchiveInvalid;
+            }
+        }
+
+        ErrorKind::General
+    }
+}
+
+#[derive(Debug)]
+pub struct AppPackError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl fmt::Display for AppPackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppPackError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn kind_is_found_through_context() {
+        let result: anyhow::Result<()> = Err(ErrorKind::NotInstalled.error("not installed"));
+        let error = result.context("Failed to get installed AppPack").unwrap_err();
+
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotInstalled);
+        assert_eq!(error.root_cause().to_string(), "not installed");
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("other")), ErrorKind::General);
+    }
+}
@@ -0,0 +1,620 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::AppDesktopEntry;
+use crate::types::app_build_config::AppBuildConfig;
+use crate::types::app_config::{AppPackAppConfig, AppPackAppConfigs};
+use crate::types::app_installed::{InstalledAppPackEntry, InstalledAppPacks};
+use crate::types::config::AppPackConfig;
+use crate::types::error::ErrorKind;
+use crate::types::repository::{AppPackRepositories, AppPackRepository, RepositoryIndex};
+use crate::types::signing::{AppPackPublicKey, AppPackSignature};
+use crate::utils::atomic_write::write_atomic;
+use crate::utils::checksum::sha256_file;
+use crate::utils::i18n::tr;
+use crate::utils::lock::FileLock;
+use crate::utils::rate_limit::parse_rate;
+use anyhow::{Context, anyhow};
+use std::path::{Path, PathBuf};
+
+/// Directories of the AppPack home that are not app directories
+pub const RESERVED_HOME_DIR_NAMES: [&str; 8] = [
+    "repositories",
+    "trusted_keys",
+    "downloads",
+    "cache",
+    "logs",
+    "system",
+    "pool",
+    "locks",
+];
+
+/// Where system-wide installations used to put their desktop entries, they are now written by
+/// each user and only removed from there
+const SYSTEM_DESKTOP_ENTRIES_DIR: &str = "/usr/local/share/applications/appack";
+
+/// Start of the names of the desktop entries and icons a user writes for system-wide apps
+pub(crate) const SYSTEM_FILE_PREFIX: &str = "system_";
+
+/// Where system-wide installations used to put the icons of their desktop entries
+const SYSTEM_ICON_THEME_DIR: &str = "/usr/local/share/icons/hicolor";
+
+/// Where system-wide installations put the entries of apps started at login
+const SYSTEM_AUTOSTART_DIR: &str = "/etc/xdg/autostart";
+
+#[derive(Debug, Clone)]
+pub struct AppPackLocalSettings {
+    pub installed_file: PathBuf,
+    pub home_dir: PathBuf,
+    pub desktop_entries_dir: PathBuf,
+    /// hicolor icon theme the icons of desktop entries are installed to
+    pub icon_theme_dir: PathBuf,
+    /// XDG autostart entries of the apps started at login
+    pub autostart_dir: PathBuf,
+    pub config_file: PathBuf,
+    pub repositories_file: PathBuf,
+    pub app_config_file: PathBuf,
+    pub repositories_dir: PathBuf,
+    pub trusted_keys_dir: PathBuf,
+    pub downloads_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub logs_dir: PathBuf,
+    /// Home of the apps installed system-wide
+    pub system_dir: PathBuf,
+    /// Maximum transfer rate of pack downloads and uploads, in bytes per second
+    pub limit_rate: Option<u64>,
+    /// Contents of `config.yaml`, with the environment overrides applied
+    pub config: AppPackConfig,
+}
+
+/// Home of the apps installed system-wide: `APPACK_SYSTEM_DIR`, the common directory of the snap,
+/// or `/var/lib/appack`
+fn system_dir() -> PathBuf {
+    std::env::var_os("APPACK_SYSTEM_DIR")
+        .or_else(|| std::env::var_os("SNAP_COMMON"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/var/lib/appack"))
+}
+
+/// Keeps the desktop entries and icons of system-wide apps apart from the ones of the apps of
+/// the user, "system" being a reserved id
+fn system_prefix(app: &InstalledAppPackEntry) -> &'static str {
+    if app.system { SYSTEM_FILE_PREFIX } else { "" }
+}
+
+/// Id of the app of `installed` a user typed, see `AppPackLocalSettings::resolve_app_id`
+fn match_app_id(installed: &[InstalledAppPackEntry], query: &str) -> anyhow::Result<String> {
+    if installed.iter().any(|app| app.id == query) {
+        return Ok(query.to_string());
+    }
+
+    let query = query.to_lowercase();
+    let mut matches: Vec<&InstalledAppPackEntry> = installed
+        .iter()
+        .filter(|app| {
+            let id = app.id.to_lowercase();
+            id.ends_with(&format!(".{query}")) || app.name.to_lowercase().contains(&query)
+        })
+        .collect();
+    matches.sort_by(|a, b| a.id.cmp(&b.id));
+    matches.dedup_by(|a, b| a.id == b.id);
+
+    match matches.as_slice() {
+        [] => Err(ErrorKind::NotInstalled.error(format!("No installed app matches '{query}'"))),
+        [app] => Ok(app.id.clone()),
+        _ => Err(ErrorKind::Ambiguous.error(format!(
+            "'{query}' matches several apps — please use one of their ids:\n{}",
+            matches
+                .iter()
+                .map(|app| format!("  {}\t{}", app.id, app.name))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))),
+    }
+}
+
+impl AppPackLocalSettings {
+    #[cfg(not(debug_assertions))]
+    fn default_dirs() -> (PathBuf, PathBuf) {
+        use crate::utils::sandbox::{Sandbox, get_sandbox};
+
+        if get_sandbox() == Sandbox::Flatpak {
+            // ~/.var/app/<id>/data, the per-app data directory of Flatpak
+            let data_home = PathBuf::from(std::env::var("XDG_DATA_HOME").unwrap());
+            let user_real_home = PathBuf::from(std::env::var("HOME").unwrap());
+            return (data_home, user_real_home);
+        }
+
+        let snap_home = std::env::var("SNAP_USER_COMMON").unwrap();
+        let snap_home = PathBuf::from(snap_home);
+        let user_real_home = std::env::var("SNAP_REAL_HOME").unwrap();
+        let user_real_home = PathBuf::from(user_real_home);
+        (snap_home, user_real_home)
+    }
+
+    #[cfg(debug_assertions)]
+    fn default_dirs() -> (PathBuf, PathBuf) {
+        let home_str = std::env::var("HOME").unwrap();
+        let snap_home = PathBuf::from(&home_str)
+            .join("snap")
+            .join("appack")
+            .join("common");
+        let user_real_home = PathBuf::from(home_str);
+        (snap_home, user_real_home)
+    }
+
+    /// Loads the settings from `config.yaml` (or the file set by `APPACK_CONFIG`) and the environment
+    pub fn load() -> anyhow::Result<Self> {
+        let (snap_home, user_real_home) = Self::default_dirs();
+        let config_file = std::env::var_os("APPACK_CONFIG")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                user_real_home
+                    .join(".config")
+                    .join("appack")
+                    .join("config.yaml")
+            });
+        let config = AppPackConfig::new(&config_file)?.with_env_overrides()?;
+
+        let limit_rate = config
+            .limit_rate
+            .as_deref()
+            .map(parse_rate)
+            .transpose()
+            .context("Invalid limit_rate setting")?;
+        let home = config.storage_dir.clone().unwrap_or(snap_home);
+
+        Ok(Self {
+            installed_file: home.join("installed.yaml"),
+            repositories_file: home.join("repositories.yaml"),
+            app_config_file: home.join("app_config.yaml"),
+            repositories_dir: home.join("repositories"),
+            trusted_keys_dir: home.join("trusted_keys"),
+            downloads_dir: home.join("downloads"),
+            cache_dir: config
+                .cache_dir
+                .clone()
+                .unwrap_or_else(|| home.join("cache")),
+            logs_dir: home.join("logs"),
+            system_dir: system_dir(),
+            limit_rate,
+            desktop_entries_dir: user_real_home
+                .join(".local")
+                .join("share")
+                .join("applications")
+                .join("appack"),
+            icon_theme_dir: user_real_home
+                .join(".local")
+                .join("share")
+                .join("icons")
+                .join("hicolor"),
+            autostart_dir: user_real_home.join(".config").join("autostart"),
+            home_dir: home,
+            config_file,
+            config,
+        })
+    }
+
+    /// Settings of the system-wide installation, used by `appack --system` as root.
+    /// Repositories, keys and downloads stay the ones of the current user.
+    pub fn system(&self) -> Self {
+        let home = self.system_dir.clone();
+        Self {
+            installed_file: home.join("installed.yaml"),
+            app_config_file: home.join("app_config.yaml"),
+            logs_dir: home.join("logs"),
+            desktop_entries_dir: PathBuf::from(SYSTEM_DESKTOP_ENTRIES_DIR),
+            icon_theme_dir: PathBuf::from(SYSTEM_ICON_THEME_DIR),
+            autostart_dir: PathBuf::from(SYSTEM_AUTOSTART_DIR),
+            home_dir: home,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.home_dir == self.system_dir
+    }
+
+    pub fn check_ok(&self) -> anyhow::Result<()> {
+        if !self.home_dir.exists() {
+            return Err(ErrorKind::EnvironmentMissing.error(format!(
+                "Home directory does not exist: {}",
+                self.home_dir.display()
+            )));
+        }
+
+        if !self.desktop_entries_dir.exists() {
+            match std::fs::create_dir_all(&self.desktop_entries_dir) {
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(
+                        ErrorKind::EnvironmentMissing.error(format!("Desktop entries directory does not exist and could not be created: {} ({e})",self.desktop_entries_dir.display()
+                    )).context(tr!("hint-check-plugs")));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Locks `installed.yaml` against other AppPack processes until the returned guard is dropped.
+    /// Hold it across a `get_installed`/`save_installed` cycle so no change is lost, but not
+    /// during long operations: every launch and listing waits for it.
+    pub fn lock_installed(&self) -> anyhow::Result<Option<FileLock>> {
+        if !self.home_dir.exists() {
+            // Nothing installed yet, and nowhere to put the lock file
+            return Ok(None);
+        }
+
+        FileLock::acquire(&self.home_dir.join("installed.yaml.lock")).map(Some)
+    }
+
+    /// Locks an app against other AppPack processes installing, removing, resetting or stopping
+    /// it until the returned guard is dropped. Take it before `lock_installed` when both are
+    /// needed, and never the other way around.
+    pub fn lock_app(&self, id: &str) -> anyhow::Result<Option<FileLock>> {
+        if !self.home_dir.exists() {
+            return Ok(None);
+        }
+
+        let dir = self.home_dir.join("locks");
+        std::fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+        FileLock::acquire(&dir.join(format!("{id}.lock"))).map(Some)
+    }
+
+    /// Copy of `installed.yaml` before its last change
+    pub fn installed_backup_file(&self) -> PathBuf {
+        self.installed_file.with_extension("yaml.bak")
+    }
+
+    /// Reads a list of installed apps, migrated to the current schema version
+    pub fn read_installed_file(&self, path: &Path) -> anyhow::Result<(InstalledAppPacks, bool)> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read installed file {}", path.display()))?;
+        InstalledAppPacks::from_yaml(&content, &self.home_dir)
+            .context(format!("Failed to parse installed file {}", path.display()))
+    }
+
+    pub fn get_installed(&self) -> anyhow::Result<InstalledAppPacks> {
+        let _lock = self.lock_installed()?;
+        if !self.installed_file.exists() {
+            return Ok(InstalledAppPacks::default());
+        }
+
+        let (installed_app_packs, migrated) =
+            self.read_installed_file(&self.installed_file)
+                .context(tr!("hint-recover"))?;
+        if migrated {
+            self.save_installed_file(&installed_app_packs)?;
+        }
+
+        Ok(installed_app_packs)
+    }
+
+    pub fn save_installed(&self, installed_app_packs: InstalledAppPacks) -> anyhow::Result<()> {
+        self.save_installed_file(&installed_app_packs)
+    }
+
+    fn save_installed_file(&self, installed_app_packs: &InstalledAppPacks) -> anyhow::Result<()> {
+        let _lock = self.lock_installed()?;
+        let installed_filepath = self.installed_file.clone();
+        let content = serde_yaml::to_string(installed_app_packs)
+            .context("Failed to serialize installed app packs")?;
+
+        // Only a readable file is worth keeping, a corrupted one must not replace a good backup
+        let current = std::fs::read_to_string(&installed_filepath).unwrap_or_default();
+        if InstalledAppPacks::from_yaml(&current, &self.home_dir).is_ok() {
+            write_atomic(&self.installed_backup_file(), current)
+                .context("Failed to back up installed file")?;
+        }
+
+        write_atomic(&installed_filepath, content).context(format!(
+            "Failed to write installed file {}",
+            installed_filepath.display()
+        ))?;
+
+        Ok(())
+    }
+
+    /// Readme extracted at install, in the system installation for system-wide apps
+    pub fn get_app_readme_dir(&self, app: &InstalledAppPackEntry) -> PathBuf {
+        self.get_app_files_dir(app).join("readme")
+    }
+
+    /// Directory the files of the pack were extracted to, in the system installation for
+    /// system-wide apps
+    pub fn get_app_files_dir(&self, app: &InstalledAppPackEntry) -> PathBuf {
+        match &app.home_dir {
+            Some(dir) if app.system => dir.clone(),
+            _ => self.get_app_home_dir(app),
+        }
+    }
+
+    pub fn get_app_home_dir(&self, app: &InstalledAppPackEntry) -> PathBuf {
+        if app.system {
+            // Only the overlay of the user, the system image is read-only
+            return self.home_dir.join("system").join(&app.id).join(&app.version);
+        }
+
+        match &app.home_dir {
+            Some(dir) => dir.clone(),
+            None => self.default_app_home_dir(app),
+        }
+    }
+
+    /// Directory of an app in the AppPack home, where it is installed
+    pub fn default_app_home_dir(&self, app: &InstalledAppPackEntry) -> PathBuf {
+        self.home_dir.join(app.id.clone()).join(app.version.clone())
+    }
+
+    /// Apps installed system-wide, read without locking since users can't write there
+    pub fn get_system_installed(&self) -> anyhow::Result<InstalledAppPacks> {
+        let installed_file = self.system_dir.join("installed.yaml");
+        if self.is_system() || !installed_file.exists() {
+            return Ok(InstalledAppPacks::default());
+        }
+
+        let content = std::fs::read_to_string(&installed_file).context(format!(
+            "Failed to read installed file {}",
+            installed_file.display()
+        ))?;
+        let (mut installed, _) = InstalledAppPacks::from_yaml(&content, &self.system_dir)?;
+        for app in installed.installed.iter_mut() {
+            app.home_dir = Some(
+                app.home_dir
+                    .take()
+                    .unwrap_or_else(|| self.system_dir.join(&app.id).join(&app.version)),
+            );
+            app.system = true;
+        }
+
+        Ok(installed)
+    }
+
+    pub fn get_app_installed(
+        &self,
+        id: &str,
+        version: Option<&str>,
+    ) -> anyhow::Result<InstalledAppPackEntry> {
+        let all_installed = self
+            .get_installed()
+            .context("Failed to get installed app packs")?;
+
+        // Apps of the user hide the system-wide ones with the same id
+        let mut all_installed = all_installed.installed;
+        if !all_installed.iter().any(|i| i.id == id) {
+            all_installed = self.get_system_installed()?.installed;
+        }
+
+        let mut matches = all_installed.iter().filter(|i| i.id == id);
+
+        // Without a version, the newest one installed
+        let found = match version {
+            Some(v) => matches.find(|i| i.version == v),
+            None => {
+                matches.max_by(|a, b| AppBuildConfig::compare_versions(&a.version, &b.version))
+            }
+        };
+
+        found
+            .cloned()
+            .ok_or_else(|| ErrorKind::NotInstalled.error("AppPack (or version) is not installed"))
+    }
+
+    /// Id of the installed app a user typed, e.g. `photoshop` for `com.adobe.photoshop`: an
+    /// exact id, the last parts of an id, or a part of a name
+    pub fn resolve_app_id(&self, query: &str) -> anyhow::Result<String> {
+        let mut installed = self.get_installed()?.installed;
+        installed.extend(self.get_system_installed()?.installed);
+
+        match_app_id(&installed, query)
+    }
+
+    pub fn get_desktop_entry_path(
+        &self,
+        app_entry: &InstalledAppPackEntry,
+        desktop_entry: &AppDesktopEntry,
+    ) -> PathBuf {
+        self.desktop_entries_dir.join(format!(
+            "{}{}_{}_{}",
+            system_prefix(app_entry),
+            app_entry.id,
+            app_entry.version,
+            desktop_entry.entry
+        ))
+    }
+
+    /// Name of the icon of a desktop entry in the icon theme, unique per installed version
+    pub fn get_icon_name(
+        &self,
+        app_entry: &InstalledAppPackEntry,
+        desktop_entry: &AppDesktopEntry,
+    ) -> String {
+        let stem = Path::new(&desktop_entry.icon)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!(
+            "appack_{}{}_{}_{stem}",
+            system_prefix(app_entry),
+            app_entry.id,
+            app_entry.version
+        )
+    }
+
+    pub fn get_autostart_entry_path(&self, id: &str) -> PathBuf {
+        self.autostart_dir.join(format!("appack-{id}.desktop"))
+    }
+
+    /// Repositories added with `appack repo add`, followed by the ones from `config.yaml`
+    pub fn get_repositories(&self) -> anyhow::Result<AppPackRepositories> {
+        let mut repositories = self.get_user_repositories()?;
+        for repository in self.config.repositories.iter() {
+            if !repositories
+                .repositories
+                .iter()
+                .any(|r| r.name == repository.name)
+            {
+                repositories.repositories.push(repository.clone());
+            }
+        }
+
+        Ok(repositories)
+    }
+
+    /// Repositories added with `appack repo add`
+    pub fn get_user_repositories(&self) -> anyhow::Result<AppPackRepositories> {
+        if !self.repositories_file.exists() {
+            return Ok(AppPackRepositories::default());
+        }
+
+        let content = std::fs::read_to_string(&self.repositories_file).context(format!(
+            "Failed to read repositories file {}",
+            self.repositories_file.display()
+        ))?;
+        serde_yaml::from_str(&content).context(format!(
+            "Failed to parse repositories file {}",
+            self.repositories_file.display()
+        ))
+    }
+
+    pub fn save_repositories(&self, repositories: AppPackRepositories) -> anyhow::Result<()> {
+        let content =
+            serde_yaml::to_string(&repositories).context("Failed to serialize repositories")?;
+        write_atomic(&self.repositories_file, content).context(format!(
+            "Failed to write repositories file {}",
+            self.repositories_file.display()
+        ))?;
+
+        Ok(())
+    }
+
+    pub fn get_app_configs(&self) -> anyhow::Result<AppPackAppConfigs> {
+        if !self.app_config_file.exists() {
+            return Ok(AppPackAppConfigs::default());
+        }
+
+        let content = std::fs::read_to_string(&self.app_config_file).context(format!(
+            "Failed to read app config file {}",
+            self.app_config_file.display()
+        ))?;
+        serde_yaml::from_str(&content).context(format!(
+            "Failed to parse app config file {}",
+            self.app_config_file.display()
+        ))
+    }
+
+    pub fn save_app_configs(&self, configs: AppPackAppConfigs) -> anyhow::Result<()> {
+        let content = serde_yaml::to_string(&configs).context("Failed to serialize app config")?;
+        write_atomic(&self.app_config_file, content).context(format!(
+            "Failed to write app config file {}",
+            self.app_config_file.display()
+        ))?;
+
+        Ok(())
+    }
+
+    pub fn get_app_config(&self, id: &str) -> anyhow::Result<AppPackAppConfig> {
+        Ok(self.get_app_configs()?.apps.remove(id).unwrap_or_default())
+    }
+
+    pub fn get_trusted_keys(&self) -> anyhow::Result<Vec<AppPackPublicKey>> {
+        let mut keys = Vec::new();
+        if !self.trusted_keys_dir.exists() {
+            return Ok(keys);
+        }
+
+        for entry in std::fs::read_dir(&self.trusted_keys_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "pub") {
+                keys.push(AppPackPublicKey::new(&path)?);
+            }
+        }
+
+        Ok(keys)
+    }
+
+    pub fn get_repository_index_path(&self, repository: &AppPackRepository) -> PathBuf {
+        self.repositories_dir
+            .join(&repository.name)
+            .join("index.yaml")
+    }
+
+    /// Reads the index saved by the last `appack repo sync`, checking its signature again
+    /// in case a key was untrusted since.
+    pub fn get_repository_index(
+        &self,
+        repository: &AppPackRepository,
+    ) -> anyhow::Result<RepositoryIndex> {
+        let index_path = self.get_repository_index_path(repository);
+        if !index_path.exists() {
+            return Err(anyhow!("Repository '{}' was never synced", repository.name)
+                .context("Run `appack repo sync` first"));
+        }
+
+        let signature = AppPackSignature::new(&index_path.with_extension("yaml.sig"))?;
+        signature
+            .verify(&sha256_file(&index_path)?, &self.get_trusted_keys()?)
+            .context(format!(
+                "Index of repository '{}' is not trusted",
+                repository.name
+            ))?;
+
+        let content = std::fs::read_to_string(&index_path)
+            .context(format!("Failed to read index {}", index_path.display()))?;
+        serde_yaml::from_str(&content)
+            .context(format!("Failed to parse index {}", index_path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(id: &str, name: &str) -> InstalledAppPackEntry {
+        serde_yaml::from_str(&format!(
+            "{{id: {id}, version: 1.0.0, name: {name}, image: image.qcow2, \
+             snapshot_mode: NeverLoad, qemu_command: '', freerdp_command: ''}}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_match_app_id() {
+        let installed = [
+            app("com.adobe.photoshop", "Photoshop"),
+            app("com.adobe.photoshop", "Photoshop"), // Another version
+            app("com.adobe.illustrator", "Illustrator"),
+            app("org.notepad-plus-plus", "Notepad++"),
+            app("com.microsoft.notepad", "Notepad"),
+        ];
+
+        // Unique matches: the exact id, its last part, a part of the name
+        assert_eq!(
+            match_app_id(&installed, "com.adobe.illustrator").unwrap(),
+            "com.adobe.illustrator"
+        );
+        assert_eq!(match_app_id(&installed, "photoshop").unwrap(), "com.adobe.photoshop");
+        assert_eq!(match_app_id(&installed, "Illust").unwrap(), "com.adobe.illustrator");
+
+        let error = match_app_id(&installed, "notepad").unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Ambiguous);
+        assert!(error.to_string().contains("com.microsoft.notepad"));
+        assert!(error.to_string().contains("org.notepad-plus-plus"));
+
+        let error = match_app_id(&installed, "gimp").unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotInstalled);
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Desired state of the installed apps, converged by `appack apply`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppPackManifest {
+    #[serde(default)]
+    pub apps: Vec<AppPackManifestEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppPackManifestEntry {
+    pub id: String,
+    /// Exact version to install, the newest one of the channel when not set
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Release channel followed by the app, also used by `appack update`
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Path or URL of the pack, instead of the synced repositories
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Repository to install a pinned version from
+    #[serde(default)]
+    pub repo: Option<String>,
+}
+
+impl AppPackManifest {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read manifest {}", path.display()))?;
+        let manifest: Self = serde_yaml::from_str(&content)
+            .context(format!("Invalid manifest {}", path.display()))?;
+        manifest.validate()?;
+
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for (i, app) in self.apps.iter().enumerate() {
+            if self.apps[..i].iter().any(|other| other.id == app.id) {
+                return Err(anyhow!(
+                    "{} is listed more than once in the manifest",
+                    app.id
+                ));
+            }
+            if app.version.is_some() && app.channel.is_some() {
+                return Err(anyhow!(
+                    "{}: pin a version or follow a channel, not both",
+                    app.id
+                ));
+            }
+            if app.repo.is_some() && app.version.is_none() {
+                return Err(anyhow!(
+                    "{}: a repository can only be set with a version",
+                    app.id
+                ));
+            }
+            if app.source.is_some() && (app.channel.is_some() || app.repo.is_some()) {
+                return Err(anyhow!(
+                    "{}: an app installed from a source has no channel or repository",
+                    app.id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let manifest: AppPackManifest = serde_yaml::from_str(
+            "apps:\n- id: demo\n  channel: beta\n- id: other\n  version: '1.0'\n  repo: main\n",
+        )
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+
+        let manifest: AppPackManifest =
+            serde_yaml::from_str("apps:\n- id: demo\n- id: demo\n  version: '1.0'\n").unwrap();
+        assert!(manifest.validate().is_err());
+
+        let manifest: AppPackManifest =
+            serde_yaml::from_str("apps:\n- id: demo\n  version: '1.0'\n  channel: beta\n").unwrap();
+        assert!(manifest.validate().is_err());
+    }
+}
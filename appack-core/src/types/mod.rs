@@ -0,0 +1,456 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::rate_limit::parse_size;
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+pub mod app_build_config;
+pub mod app_config;
+pub mod app_installed;
+pub mod config;
+pub mod error;
+pub mod local_settings;
+pub mod manifest;
+pub mod repository;
+pub mod signing;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDesktopEntry {
+    pub entry: String,
+    pub icon: String,
+    #[serde(default)]
+    pub rdp_args: String,
+    /// Shows only this program as a native window instead of the whole desktop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_app: Option<AppRemoteApp>,
+    /// Guest user the entry logs in as, instead of the one of `freerdp_command`. Its password
+    /// is read from the keyring at launch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl AppDesktopEntry {
+    /// FreeRDP arguments of the entry, including the guest user and the RemoteApp ones
+    pub fn freerdp_args(&self) -> String {
+        let args = match &self.remote_app {
+            Some(remote_app) if self.rdp_args.is_empty() => remote_app.freerdp_arg(),
+            Some(remote_app) => format!("{} {}", self.rdp_args, remote_app.freerdp_arg()),
+            None => self.rdp_args.clone(),
+        };
+        match &self.user {
+            Some(user) => format!("/u:{} {args}", user.replace(' ', "$WHITESPACE"))
+                .trim_end()
+                .to_string(),
+            None => args,
+        }
+    }
+}
+
+/// RemoteApp (RAIL) program started by a desktop entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppRemoteApp {
+    /// Path of the program in the guest, or `||alias` of a published RemoteApp
+    pub program: String,
+    /// Title of the window, the program name when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Command line arguments of the program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<String>,
+}
+
+impl AppRemoteApp {
+    /// The `/app:` option of FreeRDP, with whitespaces escaped for the launch command
+    pub fn freerdp_arg(&self) -> String {
+        let mut arg = format!("/app:program:{}", self.program);
+        if let Some(name) = &self.name {
+            arg.push_str(&format!(",name:{name}"));
+        }
+        if let Some(cmd) = &self.cmd {
+            arg.push_str(&format!(",cmd:{cmd}"));
+        }
+
+        arg.replace(' ', "$WHITESPACE")
+    }
+}
+
+/// What of the host a pack can ask access to. Packs only get what they declare, the user grants
+/// it when installing the app, and has to allow devices on top of that.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppCapability {
+    Microphone,
+    /// Printers of the host, through CUPS
+    Printer,
+    /// Smartcards and security keys of the host, through PC/SC
+    Smartcard,
+    /// Webcams of the host, only allowed for one launch at a time
+    Camera,
+    /// Folders of the host shared by the pack itself, like `/drive:home,$HOME`
+    HomeShare,
+    /// Copy and paste between the app and the host
+    Clipboard,
+    /// Sound of the app played on the host
+    Audio,
+    /// USB devices the user gives to the app
+    Usb,
+    /// Network access of the guest, beyond its `network_allow` destinations
+    Network,
+}
+
+impl AppCapability {
+    pub const ALL: [AppCapability; 9] = [
+        AppCapability::Microphone,
+        AppCapability::Printer,
+        AppCapability::Smartcard,
+        AppCapability::Camera,
+        AppCapability::HomeShare,
+        AppCapability::Clipboard,
+        AppCapability::Audio,
+        AppCapability::Usb,
+        AppCapability::Network,
+    ];
+
+    pub const DEVICES: [AppCapability; 4] = [
+        AppCapability::Microphone,
+        AppCapability::Printer,
+        AppCapability::Smartcard,
+        AppCapability::Camera,
+    ];
+
+    /// What apps installed before packs declared their capabilities could use
+    pub const LEGACY: [AppCapability; 5] = [
+        AppCapability::HomeShare,
+        AppCapability::Clipboard,
+        AppCapability::Audio,
+        AppCapability::Usb,
+        AppCapability::Network,
+    ];
+
+    /// Devices have to be allowed by the user at launch or in the settings of the app
+    pub fn is_device(&self) -> bool {
+        Self::DEVICES.contains(self)
+    }
+
+    /// Name of the device, also the setting of the app allowing it
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            Self::Microphone => "microphone",
+            Self::Printer => "printer",
+            Self::Smartcard => "smartcard",
+            Self::Camera => "camera",
+            Self::HomeShare => "home_share",
+            Self::Clipboard => "clipboard",
+            Self::Audio => "audio",
+            Self::Usb => "usb",
+            Self::Network => "network",
+        }
+    }
+
+    /// Whether the user can allow the device for good, instead of at each launch
+    pub fn can_be_saved(&self) -> bool {
+        !matches!(self, Self::Camera)
+    }
+
+    /// FreeRDP option redirecting the device
+    pub fn freerdp_arg(&self) -> Option<&'static str> {
+        match self {
+            Self::Microphone => Some("/microphone"),
+            Self::Printer => Some("/printer"),
+            Self::Smartcard => Some("/smartcard"),
+            Self::Camera => Some("/vc:rdpecam,device:*"),
+            _ => None,
+        }
+    }
+
+    /// Whether a FreeRDP option redirects the device, including its aliases
+    pub fn is_redirected_by(&self, arg: &str) -> bool {
+        match self {
+            Self::Microphone => arg.starts_with("/mic"),
+            Self::Printer => arg.starts_with("/printer"),
+            Self::Smartcard => arg.starts_with("/smartcard"),
+            Self::Camera => arg.starts_with("/vc:rdpecam") || arg.starts_with("/camera"),
+            Self::HomeShare => {
+                arg.starts_with("/drive")
+                    || matches!(arg, "+drives" | "/drives" | "+home-drive" | "/home-drive")
+            }
+            Self::Clipboard => arg.starts_with("/clipboard") || arg == "+clipboard",
+            // `/audio-mode:2` mutes the app
+            Self::Audio => {
+                arg.starts_with("/sound")
+                    || (arg.starts_with("/audio-mode") && arg != "/audio-mode:2")
+            }
+            Self::Usb => arg.starts_with("/usb"),
+            Self::Network => false,
+        }
+    }
+}
+
+/// Translations of a pack for one language
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppLocale {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Index of the readme in this language, in the readme folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme: Option<String>,
+}
+
+/// Regional settings the Windows of a pack was installed with, by its `autounattend.xml`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestLocale {
+    /// Language tag of the regional format, e.g. `fr-FR`
+    pub locale: String,
+    /// XKB layout like `fr`, or Windows layout id like `0x40C`
+    pub keyboard: String,
+    /// IANA timezone like `Europe/Paris`, or Windows timezone
+    pub timezone: String,
+}
+
+/// Readme of a pack, as stored in the pack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppReadme {
+    /// Folder of the readme in the pack
+    pub folder: String,
+    pub index: String,
+}
+
+/// How the folders the user shares reach the VM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum SharedDirsMode {
+    /// RDP drive redirection, shown as `\\tsclient\<name>`, works with any guest
+    #[default]
+    Rdp,
+    /// virtiofs exports mounted by the guest, faster and available without a session.
+    /// The guest needs the virtio-win virtiofs driver.
+    Virtiofs,
+}
+
+/// Files scaffolded by `appack creator new`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CreatorTemplate {
+    /// A Windows VM installed from an ISO, with RemoteApp
+    #[default]
+    Windows,
+    /// An Ubuntu cloud image set up by cloud-init, serving the app with xrdp
+    LinuxXrdp,
+}
+
+/// How much of a disk image is allocated when it is created
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImagePreallocation {
+    Off,
+    /// Allocates the qcow2 metadata, faster writes for a small image
+    Metadata,
+    /// Reserves the whole image on the host disk, fastest writes
+    Falloc,
+}
+
+/// Compression of the clusters of a disk image compressed with `qemu-img convert -c`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageCompression {
+    Zlib,
+    /// Smaller and faster to decompress, needs QEMU 5.1
+    Zstd,
+}
+
+/// Options of the disk image created by `appack creator new` or `appack creator image`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppImageOptions {
+    /// Image the disk starts from, e.g. a cloud image, as a path or a URL. The disk is empty
+    /// otherwise.
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Size of the disk of the guest, e.g. `64G`
+    #[serde(default = "default_image_size")]
+    pub size: String,
+    #[serde(default)]
+    pub preallocation: Option<ImagePreallocation>,
+    /// Size of the qcow2 clusters, from 512 to 2M, e.g. `64K`
+    #[serde(default)]
+    pub cluster_size: Option<String>,
+    #[serde(default)]
+    pub compression_type: Option<ImageCompression>,
+}
+
+fn default_image_size() -> String {
+    "32G".to_string()
+}
+
+impl Default for AppImageOptions {
+    fn default() -> Self {
+        Self {
+            base: None,
+            size: default_image_size(),
+            preallocation: None,
+            cluster_size: None,
+            compression_type: None,
+        }
+    }
+}
+
+impl AppImageOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        parse_size(&self.size).context("Invalid image size")?;
+        if let Some(cluster_size) = &self.cluster_size {
+            let bytes = parse_size(cluster_size).context("Invalid cluster_size")?;
+            if !bytes.is_power_of_two() || !(512..=2 * 1024 * 1024).contains(&bytes) {
+                return Err(anyhow!(
+                    "Invalid cluster_size '{cluster_size}', expected a power of two from 512 to 2M"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Value of the `-o` option of `qemu-img create`, `None` without options
+    pub fn qemu_img_options(&self) -> Option<String> {
+        let mut options = Vec::new();
+        if let Some(preallocation) = self.preallocation {
+            let mode = match preallocation {
+                ImagePreallocation::Off => "off",
+                ImagePreallocation::Metadata => "metadata",
+                ImagePreallocation::Falloc => "falloc",
+            };
+            options.push(format!("preallocation={mode}"));
+        }
+        if let Some(cluster_size) = &self.cluster_size {
+            options.push(format!("cluster_size={}", cluster_size.trim()));
+        }
+        if let Some(compression) = self.compression_type {
+            let compression = match compression {
+                ImageCompression::Zlib => "zlib",
+                ImageCompression::Zstd => "zstd",
+            };
+            options.push(format!("compression_type={compression}"));
+        }
+
+        (!options.is_empty()).then(|| options.join(","))
+    }
+}
+
+/// Architecture of the guest, which picks the QEMU emulator running it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuestArch {
+    #[default]
+    #[serde(rename = "x86_64")]
+    X86_64,
+    Aarch64,
+}
+
+/// UEFI firmware of aarch64 guests, as packaged by the distributions
+const AARCH64_FIRMWARES: [&str; 3] = [
+    "/usr/share/AAVMF/AAVMF_CODE.fd",
+    "/usr/share/edk2/aarch64/QEMU_EFI.fd",
+    "/usr/share/qemu-efi-aarch64/QEMU_EFI.fd",
+];
+
+impl GuestArch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64",
+            Self::Aarch64 => "aarch64",
+        }
+    }
+
+    pub fn qemu_binary(&self) -> String {
+        format!("qemu-system-{}", self.as_str())
+    }
+
+    /// Adds the machine, CPU and firmware the architecture needs, unless the Qemu command
+    /// sets them
+    pub fn with_defaults(&self, qemu_command: &str) -> String {
+        let args: Vec<&str> = qemu_command.split_whitespace().collect();
+        let has = |options: &[&str]| args.iter().any(|arg| options.contains(arg));
+        let mut defaults = Vec::new();
+        match self {
+            // QEMU picks working defaults for PCs
+            Self::X86_64 => {}
+            Self::Aarch64 => {
+                if !has(&["-machine", "-M"]) {
+                    defaults.push("-machine virt".to_string());
+                }
+                if !has(&["-cpu"]) {
+                    // The CPU of the host is only available to guests of the same architecture
+                    let cpu = if std::env::consts::ARCH == "aarch64" { "host" } else { "max" };
+                    defaults.push(format!("-cpu {cpu}"));
+                }
+                if !has(&["-bios"]) && !qemu_command.contains("if=pflash") {
+                    // QEMU also looks for its own firmware in its data directory
+                    let firmware = AARCH64_FIRMWARES
+                        .into_iter()
+                        .find(|path| std::path::Path::new(path).exists())
+                        .unwrap_or("edk2-aarch64-code.fd");
+                    defaults.push(format!("-bios {firmware}"));
+                }
+            }
+        }
+
+        std::iter::once(qemu_command.to_string())
+            .chain(defaults)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AppSnapshotTriggerMode {
+    OnClose,
+    Never,
+    NeverLoad,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guest_arch_defaults() {
+        assert_eq!(GuestArch::X86_64.with_defaults("-m 4G"), "-m 4G");
+        assert_eq!(GuestArch::Aarch64.qemu_binary(), "qemu-system-aarch64");
+
+        let command = "-M virt,gic-version=3 -cpu cortex-a72 -bios QEMU_EFI.fd -m 4G";
+        assert_eq!(GuestArch::Aarch64.with_defaults(command), command);
+        let command = GuestArch::Aarch64.with_defaults("-m 4G -cpu max");
+        assert!(command.starts_with("-m 4G -cpu max -machine virt -bios "));
+    }
+
+    #[test]
+    fn test_image_options() {
+        let options: AppImageOptions =
+            serde_yaml::from_str("preallocation: metadata\ncluster_size: 64K").unwrap();
+        assert_eq!(options.size, "32G");
+        assert!(options.validate().is_ok());
+        assert_eq!(
+            options.qemu_img_options().as_deref(),
+            Some("preallocation=metadata,cluster_size=64K")
+        );
+        assert_eq!(AppImageOptions::default().qemu_img_options(), None);
+
+        let options: AppImageOptions = serde_yaml::from_str("cluster_size: 96K").unwrap();
+        assert!(options.validate().is_err());
+    }
+}
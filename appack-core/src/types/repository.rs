@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_config::DEFAULT_CHANNEL;
+use serde::{Deserialize, Serialize};
+
+/// A repository as configured by the user in `repositories.yaml`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppPackRepository {
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish: Option<RepositoryPublishConfig>,
+}
+
+/// How `appack publish` uploads files to a repository.
+/// Credentials are never stored here, they are read from the environment.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RepositoryPublishConfig {
+    pub backend: RepositoryPublishBackend,
+    /// Upload endpoint, defaults to the repository URL
+    pub url: Option<String>,
+    /// Region used to sign S3 requests
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RepositoryPublishBackend {
+    HttpPut,
+    WebDav,
+    S3,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AppPackRepositories {
+    #[serde(default)]
+    pub repositories: Vec<AppPackRepository>,
+}
+
+/// The `index.yaml` file served at the root of a repository.
+/// It must be accompanied by an `index.yaml.sig` signature made by a trusted key.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RepositoryIndex {
+    #[serde(default)]
+    pub packs: Vec<RepositoryIndexEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RepositoryIndexEntry {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Path of the pack relative to the repository URL, or an absolute URL
+    pub file: String,
+    pub sha256: String,
+    pub size: Option<u64>,
+    /// Alternative locations of the pack, tried in order when the main one fails.
+    /// Each mirror must also serve the `.sig` file next to the pack.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// IPFS CID of the pack, preferred over HTTP when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipfs: Option<String>,
+    /// BitTorrent magnet link of the pack, preferred over HTTP when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnet: Option<String>,
+    /// Chunk index of the pack, relative to the repository URL, used for differential downloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<String>,
+    /// Release channel, `stable` when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+/// Content-defined chunks of a pack, published next to it as `<pack>.chunks`.
+/// A client holding a previous version of the pack only downloads the chunks it doesn't have.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ChunkIndex {
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Chunk {
+    pub offset: u64,
+    pub length: u64,
+    pub sha256: String,
+}
+
+impl RepositoryIndexEntry {
+    pub fn channel(&self) -> &str {
+        self.channel.as_deref().unwrap_or(DEFAULT_CHANNEL)
+    }
+
+    /// Whether all words of a query are in the id, name, description, categories or keywords
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = [&self.id, &self.name]
+            .into_iter()
+            .chain(&self.description)
+            .chain(&self.categories)
+            .chain(&self.keywords)
+            .map(|text| text.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
+impl AppPackRepository {
+    pub fn index_url(&self) -> String {
+        self.file_url("index.yaml")
+    }
+
+    pub fn file_url(&self, file: &str) -> String {
+        if file.contains("://") {
+            return file.to_string();
+        }
+
+        format!("{}/{}", self.url.trim_end_matches('/'), file)
+    }
+}
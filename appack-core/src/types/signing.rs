@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::utils::i18n::tr;
+use anyhow::{Context, anyhow};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Public half of a signing key, as distributed by repository maintainers
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppPackPublicKey {
+    pub public_key: String,
+    pub comment: Option<String>,
+}
+
+/// Secret half of a signing key. Never share this file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppPackSecretKey {
+    pub secret_key: String,
+}
+
+/// Detached signature of a file, stored next to it with a `.sig` extension
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppPackSignature {
+    pub key_id: String,
+    pub sha256: String,
+    pub signature: String,
+}
+
+impl AppPackPublicKey {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Unable to read key file '{}'", path.display()))?;
+        let key: Self = serde_yaml::from_str(&content).context("Invalid public key file")?;
+        key.verifying_key()?;
+
+        Ok(key)
+    }
+
+    pub fn verifying_key(&self) -> anyhow::Result<VerifyingKey> {
+        let bytes: [u8; 32] = hex::decode(&self.public_key)
+            .context("Public key is not valid hex")?
+            .try_into()
+            .map_err(|_| anyhow!("Public key must be 32 bytes long"))?;
+
+        VerifyingKey::from_bytes(&bytes).context("Invalid public key")
+    }
+
+    /// Short identifier of the key, used to name trusted keys and reference them in signatures
+    pub fn key_id(&self) -> anyhow::Result<String> {
+        let key = self.verifying_key()?;
+        let digest = Sha256::digest(key.as_bytes());
+        Ok(hex::encode(&digest[..8]))
+    }
+}
+
+impl AppPackSecretKey {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Unable to read key file '{}'", path.display()))?;
+        let key: Self = serde_yaml::from_str(&content).context("Invalid secret key file")?;
+        key.signing_key()?;
+
+        Ok(key)
+    }
+
+    pub fn generate() -> anyhow::Result<Self> {
+        let mut bytes = [0u8; 32];
+        File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(&mut bytes))
+            .context("Failed to gather randomness for key generation")?;
+
+        Ok(Self {
+            secret_key: hex::encode(bytes),
+        })
+    }
+
+    pub fn signing_key(&self) -> anyhow::Result<SigningKey> {
+        let bytes: [u8; 32] = hex::decode(&self.secret_key)
+            .context("Secret key is not valid hex")?
+            .try_into()
+            .map_err(|_| anyhow!("Secret key must be 32 bytes long"))?;
+
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+
+    pub fn public_key(&self, comment: Option<String>) -> anyhow::Result<AppPackPublicKey> {
+        let key = self.signing_key()?;
+        Ok(AppPackPublicKey {
+            public_key: hex::encode(key.verifying_key().as_bytes()),
+            comment,
+        })
+    }
+
+    pub fn sign_digest(&self, sha256: &str) -> anyhow::Result<AppPackSignature> {
+        let key = self.signing_key()?;
+        let digest = hex::decode(sha256).context("Digest is not valid hex")?;
+
+        Ok(AppPackSignature {
+            key_id: self.public_key(None)?.key_id()?,
+            sha256: sha256.to_string(),
+            signature: hex::encode(key.sign(&digest).to_bytes()),
+        })
+    }
+}
+
+impl AppPackSignature {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Unable to read signature file '{}'", path.display()))?;
+        serde_yaml::from_str(&content).context("Invalid signature file")
+    }
+
+    /// Checks that the signature was made by one of the given keys for the given digest
+    pub fn verify(&self, sha256: &str, trusted_keys: &[AppPackPublicKey]) -> anyhow::Result<()> {
+        if !self.sha256.eq_ignore_ascii_case(sha256) {
+            return Err(ErrorKind::SignatureInvalid.error(format!(
+                "Signature mismatch: the file checksum is {sha256} but the signature was made for {}",
+                self.sha256
+            ))
+            .context("The file was modified after being signed"));
+        }
+
+        let mut signing_key = None;
+        for key in trusted_keys {
+            if key.key_id()? == self.key_id {
+                signing_key = Some(key);
+                break;
+            }
+        }
+
+        let signing_key = signing_key.ok_or_else(|| {
+            ErrorKind::SignatureInvalid
+                .error(format!("Signed by untrusted key '{}'", self.key_id))
+                .context(tr!("hint-trust-key"))
+        })?;
+
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .context("Signature is not valid hex")?
+            .try_into()
+            .map_err(|_| anyhow!("Signature must be 64 bytes long"))?;
+        let digest = hex::decode(sha256).context("Digest is not valid hex")?;
+
+        signing_key
+            .verifying_key()?
+            .verify(&digest, &Signature::from_bytes(&signature_bytes))
+            .map_err(|_| {
+                ErrorKind::SignatureInvalid.error(format!("Signature mismatch for key '{}'", self.key_id))
+                    .context("The signature is invalid, the file may have been tampered with")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::checksum::sha256_bytes;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = AppPackSecretKey::generate().unwrap();
+        let public = secret.public_key(None).unwrap();
+        let digest = sha256_bytes(b"some pack");
+
+        let signature = secret.sign_digest(&digest).unwrap();
+        assert!(signature.verify(&digest, &[public]).is_ok());
+    }
+
+    #[test]
+    fn test_verify_untrusted_key() {
+        let secret = AppPackSecretKey::generate().unwrap();
+        let other = AppPackSecretKey::generate().unwrap();
+        let digest = sha256_bytes(b"some pack");
+
+        let signature = secret.sign_digest(&digest).unwrap();
+        let trusted = [other.public_key(None).unwrap()];
+        assert!(signature.verify(&digest, &trusted).is_err());
+    }
+
+    #[test]
+    fn test_verify_tampered_signature() {
+        let secret = AppPackSecretKey::generate().unwrap();
+        let public = secret.public_key(None).unwrap();
+        let digest = sha256_bytes(b"some pack");
+        let other_digest = sha256_bytes(b"another pack");
+
+        let mut signature = secret.sign_digest(&digest).unwrap();
+        signature.sha256 = other_digest.clone();
+        assert!(signature.verify(&other_digest, &[public]).is_err());
+    }
+}
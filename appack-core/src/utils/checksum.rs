@@ -13,22 +13,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use serde::{Deserialize, Serialize};
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
 
-pub mod app_build_config;
-pub mod app_installed;
-pub mod local_settings;
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path).context(format!("Unable to open file {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .context(format!("Unable to compute checksum of {}", path.display()))?;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppDesktopEntry {
-    pub entry: String,
-    pub icon: String,
-    pub rdp_args: String,
+    Ok(hex::encode(hasher.finalize()))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AppSnapshotTriggerMode {
-    OnClose,
-    Never,
-    NeverLoad,
+pub fn sha256_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
 }
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::repository::{Chunk, ChunkIndex};
+use crate::utils::download::http_agent;
+use crate::utils::frontend::message;
+use crate::utils::rate_limit::RateLimitedReader;
+use anyhow::{Context, anyhow};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const MIN_CHUNK_SIZE: u64 = 256 * 1024;
+const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+// Cut points are found on average every 1 MiB
+const CHUNK_MASK: u64 = (1 << 20) - 1;
+
+// Random values for the gear rolling hash. They must never change, or chunk indexes
+// of already published packs won't match the ones computed locally anymore.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6170_7061_636b_2121;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Splits a file in content-defined chunks, so that a change in the file only changes
+/// the chunks around it instead of shifting all the following ones.
+pub fn chunk_file(path: &Path) -> anyhow::Result<ChunkIndex> {
+    let mut file = File::open(path).context(format!("Unable to open {}", path.display()))?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut chunks = Vec::new();
+
+    let mut hasher = Sha256::new();
+    let mut hash: u64 = 0;
+    let mut offset: u64 = 0;
+    let mut length: u64 = 0;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        let mut start = 0;
+        for (i, byte) in buffer[..read].iter().enumerate() {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            length += 1;
+
+            if (length >= MIN_CHUNK_SIZE && hash & CHUNK_MASK == 0) || length >= MAX_CHUNK_SIZE {
+                hasher.update(&buffer[start..=i]);
+                chunks.push(Chunk {
+                    offset,
+                    length,
+                    sha256: hex::encode(hasher.finalize_reset()),
+                });
+                offset += length;
+                length = 0;
+                hash = 0;
+                start = i + 1;
+            }
+        }
+        hasher.update(&buffer[start..read]);
+    }
+
+    if length > 0 {
+        chunks.push(Chunk {
+            offset,
+            length,
+            sha256: hex::encode(hasher.finalize()),
+        });
+    }
+
+    Ok(ChunkIndex { chunks })
+}
+
+/// Rebuilds the file described by `index` at `destination`, copying the chunks found in `base`
+/// and downloading the others from `url` with HTTP range requests.
+pub fn download_chunked(
+    url: &str,
+    index: &ChunkIndex,
+    base: &Path,
+    destination: &Path,
+    limit_rate: Option<u64>,
+) -> anyhow::Result<()> {
+    let base_chunks: HashMap<String, Chunk> = chunk_file(base)?
+        .chunks
+        .into_iter()
+        .map(|chunk| (chunk.sha256.clone(), chunk))
+        .collect();
+
+    let mut base_file = File::open(base).context(format!("Unable to open {}", base.display()))?;
+    let mut outfile = File::create(destination)
+        .context(format!("Unable to create file {}", destination.display()))?;
+    let agent = http_agent()?;
+    let mut reused = 0;
+    let mut downloaded = 0;
+
+    let mut i = 0;
+    while i < index.chunks.len() {
+        let chunk = &index.chunks[i];
+        if let Some(local) = base_chunks.get(&chunk.sha256) {
+            base_file.seek(SeekFrom::Start(local.offset))?;
+            io::copy(&mut (&mut base_file).take(local.length), &mut outfile)?;
+            reused += local.length;
+            i += 1;
+            continue;
+        }
+
+        // Download consecutive missing chunks in a single request
+        let start = chunk.offset;
+        let mut end = chunk.offset + chunk.length;
+        i += 1;
+        while i < index.chunks.len() && !base_chunks.contains_key(&index.chunks[i].sha256) {
+            end = index.chunks[i].offset + index.chunks[i].length;
+            i += 1;
+        }
+
+        let mut response = agent
+            .get(url)
+            .header("Range", format!("bytes={start}-{}", end - 1))
+            .call()
+            .context(format!("Failed to download {url}"))?;
+        if response.status().as_u16() != 206 {
+            return Err(anyhow!("Server does not support range requests"));
+        }
+
+        let reader = response.body_mut().as_reader().take(end - start);
+        let copied = io::copy(
+            &mut RateLimitedReader::new(reader, limit_rate),
+            &mut outfile,
+        )
+        .context(format!("Failed to download {url}"))?;
+        if copied != end - start {
+            return Err(anyhow!("Incomplete range received from {url}"));
+        }
+        downloaded += copied;
+    }
+
+    message!(
+        "Reused {} MB from the previous version, downloaded {} MB",
+        reused / 1024 / 1024,
+        downloaded / 1024 / 1024
+    );
+
+    Ok(())
+}
@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Flags the options of pack commands that reach the host beyond the directory of the app,
+//! so users approve them before installing
+
+/// Characters a shell would interpret. Pack commands are not run through a shell, a pack
+/// using them is likely trying something.
+const SHELL_METACHARACTERS: [char; 6] = [';', '|', '&', '`', '<', '>'];
+
+/// FreeRDP options only changing the display, the login and the performance of the session, or
+/// redirecting the devices users allow with capabilities. Other options are reviewed.
+const FREERDP_SAFE_OPTIONS: &[&str] = &[
+    "admin",
+    "aero",
+    "app",
+    "async-channels",
+    "async-update",
+    "audio-mode",
+    "auto-reconnect",
+    "auto-reconnect-max-retries",
+    "bitmap-cache",
+    "bpp",
+    "camera",
+    "cert",
+    "cert-ignore",
+    "cert-tofu",
+    "clipboard",
+    "compression",
+    "compression-level",
+    "d",
+    "decorations",
+    "disp",
+    "dynamic-resolution",
+    "f",
+    "floatbar",
+    "fonts",
+    "gdi",
+    "gestures",
+    "gfx",
+    "glyph-cache",
+    "grab-keyboard",
+    "grab-mouse",
+    "h",
+    "jpeg",
+    "kbd",
+    "log-level",
+    "menu-anims",
+    "mic",
+    "microphone",
+    "monitors",
+    "mouse-motion",
+    "mouse-relative",
+    "multimon",
+    "multitouch",
+    "network",
+    "nsc",
+    "offscreen-cache",
+    "p",
+    "printer",
+    "relax-order-checks",
+    "rfx",
+    "rfx-mode",
+    "scale",
+    "scale-desktop",
+    "scale-device",
+    "sec",
+    "shell",
+    "shell-dir",
+    "size",
+    "smart-sizing",
+    "smartcard",
+    "sound",
+    "span",
+    "t",
+    "themes",
+    "timeout",
+    "title",
+    "toggle-fullscreen",
+    "u",
+    "w",
+    "wallpaper",
+    "window-drag",
+    "window-position",
+    "wm-class",
+    "workarea",
+];
+
+/// Options of the QEMU command of a pack that reach the host beyond the app directory
+pub fn qemu_risks(qemu_command: &str) -> Vec<String> {
+    let args: Vec<&str> = qemu_command.split_whitespace().collect();
+    let mut risks = shell_risks(&args);
+
+    for (i, option) in args.iter().enumerate() {
+        let value = args.get(i + 1).copied().unwrap_or_default();
+        let risk = match *option {
+            "-netdev" | "-nic" | "-net" => netdev_risk(value),
+            "-chardev" => chardev_risk(value),
+            "-monitor" | "-mon" if value != "none" => Some("controls the whole VM and QEMU"),
+            "-serial" | "-parallel" | "-qmp" | "-gdb" => char_device_risk(value),
+            "-incoming" => incoming_risk(value),
+            "-virtfs" | "-fsdev" => Some("shares a host folder with the VM"),
+            "-plugin" | "-add-fd" | "-readconfig" | "-run-with" | "-runas" | "-chroot"
+            | "-sandbox" => Some("changes how QEMU runs on the host"),
+            "-device" if is_host_device(value) => Some("gives the VM a device of the host"),
+            "-cdrom" | "-hda" | "-hdb" | "-hdc" | "-hdd" | "-fda" | "-fdb" | "-kernel"
+            | "-initrd" | "-bios"
+                if is_host_path(value) =>
+            {
+                Some("uses a file of the host")
+            }
+            _ if option.starts_with('-') && value_paths(value).any(is_host_path) => {
+                Some("uses a file of the host")
+            }
+            _ => None,
+        };
+        if let Some(risk) = risk {
+            risks.push(format!("`{option} {value}` {risk}"));
+        }
+    }
+
+    risks
+}
+
+/// Arguments of a FreeRDP command of a pack that reach the host or another machine, or that
+/// are not known to be safe
+pub fn freerdp_risks(freerdp_args: &str) -> Vec<String> {
+    let args: Vec<&str> = freerdp_args.split_whitespace().collect();
+    let mut risks = shell_risks(&args);
+
+    for arg in args {
+        let (name, value) = arg.split_once(':').unwrap_or((arg, ""));
+        let risk = match name {
+            "/drive" | "/drives" | "+drives" | "/home-drive" | "+home-drive" => {
+                "shares a host folder with the VM"
+            }
+            "/a" | "/addin" if value.starts_with("drive,") => "shares a host folder with the VM",
+            "/usb" | "/serial" | "/parallel" => "gives the VM a device of the host",
+            "/v" | "/gateway" | "/g" | "/proxy" => "connects to another machine than the VM",
+            "/rdp2tcp" => "runs a program of the host",
+            "/tls" if value.contains("secrets-file") => "writes the TLS secrets to a host file",
+            "/tls" => continue,
+            // The camera capability
+            "/vc" if value.starts_with("rdpecam") => continue,
+            "/a" | "/addin" | "/vc" | "/dvc" => "loads a FreeRDP plugin of the host",
+            _ if has_shell_characters(arg) => continue,
+            _ if FREERDP_SAFE_OPTIONS.contains(&name.trim_start_matches(['/', '+', '-'])) => {
+                continue;
+            }
+            _ => "is not an option AppPack knows to be safe",
+        };
+        risks.push(format!("`{arg}` {risk}"));
+    }
+
+    risks
+}
+
+fn has_shell_characters(arg: &str) -> bool {
+    arg.contains(SHELL_METACHARACTERS) || arg.contains("$(")
+}
+
+fn shell_risks(args: &[&str]) -> Vec<String> {
+    args.iter()
+        .filter(|arg| has_shell_characters(arg))
+        .map(|arg| format!("`{arg}` contains shell characters"))
+        .collect()
+}
+
+/// Whether a path is outside of the app directory. The firmwares of the distributions are
+/// shared with every VM.
+fn is_host_path(path: &str) -> bool {
+    let path = path.trim_matches('\'');
+    (path.starts_with('/') && !path.starts_with("/usr/share/"))
+        || path.starts_with('~')
+        || path.split('/').any(|part| part == "..")
+}
+
+/// Values of the options of `-drive file=...,if=virtio` style arguments naming files
+fn value_paths(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').filter_map(|option| {
+        let (key, value) = option.split_once('=')?;
+        matches!(
+            key,
+            "file" | "path" | "filename" | "mem-path" | "script" | "downscript"
+        )
+        .then_some(value)
+    })
+}
+
+fn netdev_risk(value: &str) -> Option<&'static str> {
+    let backend = value.split(',').next().unwrap_or_default();
+    match backend {
+        "user" | "nic" | "none" => {
+            let options: Vec<&str> = value.split(',').collect();
+            if options.iter().any(|option| option.starts_with("guestfwd=")) {
+                Some("forwards guest connections to the host")
+            } else if options.iter().any(|option| option.starts_with("smb=")) {
+                Some("shares a host folder with the VM")
+            } else {
+                None
+            }
+        }
+        _ => Some("connects the VM to a network of the host"),
+    }
+}
+
+fn chardev_risk(value: &str) -> Option<&'static str> {
+    let backend = value.split(',').next().unwrap_or_default();
+    match backend {
+        "socket" if value.split(',').any(|option| option.starts_with("host=")) => {
+            Some("opens a network socket on the host")
+        }
+        "null" | "socket" | "pty" | "vc" | "ringbuf" | "memory" | "msmouse" | "spicevmc"
+        | "spiceport" | "stdio" | "qemu-vdagent" => None,
+        _ => Some("connects the VM to a file or device of the host"),
+    }
+}
+
+/// `-incoming` loads the state of the VM, `exec:` from the output of a host command
+fn incoming_risk(value: &str) -> Option<&'static str> {
+    let backend = value.split(':').next().unwrap_or_default();
+    match backend {
+        "defer" => None,
+        "exec" => Some("runs a program of the host"),
+        _ => Some("loads the VM from a file or socket of the host"),
+    }
+}
+
+/// `-serial tcp:...` style devices, which are chardevs in short
+fn char_device_risk(value: &str) -> Option<&'static str> {
+    let (backend, target) = value.split_once(':').unwrap_or((value, ""));
+    match backend {
+        "none" | "null" | "stdio" | "pty" | "vc" | "mon" | "chardev" => None,
+        "unix" if !is_host_path(target.split(',').next().unwrap_or_default()) => None,
+        "tcp" | "udp" | "telnet" | "websocket" => Some("opens a network socket on the host"),
+        _ => Some("connects the VM to a file or device of the host"),
+    }
+}
+
+fn is_host_device(device: &str) -> bool {
+    let driver = device.split(',').next().unwrap_or_default();
+    matches!(
+        driver,
+        "vfio-pci" | "usb-host" | "vfio-ap" | "vfio-ccw" | "vfio-platform"
+    ) || driver.starts_with("vhost-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_has_no_risks() {
+        let qemu_command = "-enable-kvm -m 4G -drive file=$IMAGE_FILE_PATH,if=virtio,format=qcow2 \
+            -chardev socket,id=appack-qga,path=qga.sock,server=on,wait=off \
+            -netdev user,id=hostnet0,hostfwd=tcp::$RDP_PORT-:3389 \
+            -qmp unix:qmp-appack.sock,server=on,wait=off";
+        assert!(qemu_risks(qemu_command).is_empty());
+        assert!(freerdp_risks("/u:VmUser /p:VmPassword /cert:ignore +clipboard").is_empty());
+    }
+
+    #[test]
+    fn test_risks() {
+        let qemu_command = "-netdev tap,id=n0 -drive file=/etc/shadow,format=raw \
+            -chardev socket,id=c,host=0.0.0.0,port=4444 -serial file:/tmp/x \
+            -netdev user,id=n1,guestfwd=tcp:10.0.2.100:80-cmd:sh \
+            -sandbox off -monitor vc -monitor none";
+        assert_eq!(qemu_risks(qemu_command).len(), 7);
+        let risks = freerdp_risks("/drive:root,/ /v:example.com $(id)");
+        assert_eq!(risks.len(), 3);
+    }
+
+    #[test]
+    fn test_freerdp_allowlist() {
+        let template = "/u:VmUser /p:VmPassword /cert:ignore +clipboard +auto-reconnect \
+            +menu-anims +window-drag /wm-class:Demo /t:Demo /app:program:cmd.exe \
+            /tls:seclevel:0 /vc:rdpecam,device:* /audio-mode:2 -clipboard";
+        assert!(freerdp_risks(template).is_empty());
+
+        for (arg, risk) in [
+            ("/a:drive,name,/", "shares a host folder"),
+            ("/rdp2tcp:/usr/bin/nc", "runs a program"),
+            ("/tls:secrets-file:/tmp/keys", "TLS secrets"),
+            ("/vc:echo", "plugin"),
+            ("/dvc:urbdrc,dev:1", "plugin"),
+            ("/args-from:/tmp/args", "not an option"),
+        ] {
+            let risks = freerdp_risks(arg);
+            assert_eq!(risks.len(), 1, "{arg}");
+            assert!(risks[0].contains(risk), "{}", risks[0]);
+        }
+    }
+
+    #[test]
+    fn test_incoming_risk() {
+        assert!(qemu_risks("-incoming defer").is_empty());
+        let risks = qemu_risks("-incoming exec:cat\x20state");
+        assert!(risks[0].contains("runs a program"), "{risks:?}");
+        assert_eq!(qemu_risks("-incoming file:/tmp/state").len(), 1);
+    }
+}
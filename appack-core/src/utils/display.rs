@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::sandbox;
+use std::env;
+
+/// Size of a `WxH+X+Y` geometry or a `WxH` mode
+fn parse_size(geometry: &str) -> Option<(u32, u32)> {
+    let size = geometry.split('+').next()?;
+    let (width, height) = size.split_once('x')?;
+    let height = height.trim_end_matches(|c: char| !c.is_ascii_digit());
+
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Primary output of `xrandr --current`, or the first one connected when none is primary
+fn parse_xrandr(output: &str) -> Option<(u32, u32)> {
+    let connected: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|words| words.get(1) == Some(&"connected"))
+        .collect();
+    let geometry = |words: &Vec<&str>| words.iter().skip(2).find_map(|word| parse_size(word));
+
+    connected
+        .iter()
+        .filter(|words| words.get(2) == Some(&"primary"))
+        .chain(&connected)
+        .find_map(geometry)
+}
+
+fn xrandr_size() -> Option<(u32, u32)> {
+    env::var_os("DISPLAY")?;
+    let output = sandbox::command("xrandr")
+        .arg("--current")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    parse_xrandr(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Preferred mode of the first connected screen, when no X server answers
+fn drm_size() -> Option<(u32, u32)> {
+    let mut connectors: Vec<_> = std::fs::read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            std::fs::read_to_string(path.join("status")).is_ok_and(|s| s.trim() == "connected")
+        })
+        .collect();
+    connectors.sort();
+
+    connectors.iter().find_map(|connector| {
+        let modes = std::fs::read_to_string(connector.join("modes")).ok()?;
+        parse_size(modes.lines().next()?)
+    })
+}
+
+/// Size in pixels of the primary display of this machine
+pub fn primary_display_size() -> Option<(u32, u32)> {
+    xrandr_size().or_else(drm_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xrandr() {
+        let output = "\
+Screen 0: minimum 320 x 200, current 4480 x 1440, maximum 16384 x 16384
+eDP-1 connected 1920x1080+2560+0 (normal left inverted right x axis y axis) 309mm x 174mm
+   1920x1080     60.00*+
+HDMI-1 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+DP-1 disconnected (normal left inverted right x axis y axis)
+";
+        assert_eq!(parse_xrandr(output), Some((2560, 1440)));
+        let output = output.replace(" primary", "");
+        assert_eq!(parse_xrandr(&output), Some((1920, 1080)));
+        assert_eq!(parse_xrandr("DP-1 connected (normal left)"), None);
+        assert_eq!(parse_size("1920x1080i"), Some((1920, 1080)));
+    }
+}
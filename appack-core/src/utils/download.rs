@@ -0,0 +1,240 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::frontend::message;
+use crate::utils::p2p::{download_magnet, ipfs_gateway_url, is_magnet_link};
+use crate::utils::rate_limit::RateLimitedReader;
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use ureq::Proxy;
+use ureq::tls::{PemItem, RootCerts, TlsConfig, parse_pem};
+
+/// Network settings from the configuration, set once at startup
+#[derive(Debug, Default)]
+pub struct NetworkConfig {
+    pub proxy: Option<String>,
+    pub ca_bundle: Option<PathBuf>,
+    pub ipfs_gateway: Option<String>,
+}
+
+static NETWORK_CONFIG: OnceLock<NetworkConfig> = OnceLock::new();
+
+pub fn configure_network(config: NetworkConfig) {
+    let _ = NETWORK_CONFIG.set(config); // Ignore error, only the first configuration is used
+}
+
+pub fn network_config() -> &'static NetworkConfig {
+    NETWORK_CONFIG.get_or_init(NetworkConfig::default)
+}
+
+/// Builds the HTTP agent used for all repository traffic.
+/// Without a configured proxy, the usual `https_proxy`/`http_proxy`/`all_proxy` variables are used
+/// (honoring `no_proxy`). Without a configured CA bundle, `SSL_CERT_FILE` is used if set.
+pub fn http_agent() -> anyhow::Result<ureq::Agent> {
+    let network = network_config();
+    let proxy = match network.proxy.as_deref() {
+        Some(proxy) if !proxy.is_empty() => {
+            Some(Proxy::new(proxy).context(format!("Invalid proxy {proxy}"))?)
+        }
+        _ => Proxy::try_from_env(),
+    };
+
+    let mut tls_config = TlsConfig::builder();
+    let ca_bundle = network
+        .ca_bundle
+        .clone()
+        .or_else(|| std::env::var_os("SSL_CERT_FILE").map(PathBuf::from));
+    if let Some(ca_bundle) = ca_bundle {
+        let pem = std::fs::read(&ca_bundle)
+            .context(format!("Failed to read CA bundle {}", ca_bundle.display()))?;
+        let certs = parse_pem(&pem)
+            .filter_map(|item| match item {
+                Ok(PemItem::Certificate(cert)) => Some(Ok(cert)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid CA bundle")?;
+        if certs.is_empty() {
+            return Err(anyhow!("CA bundle contains no certificate"));
+        }
+        tls_config = tls_config.root_certs(RootCerts::new_with_certs(&certs));
+    }
+
+    let config = ureq::Agent::config_builder()
+        .proxy(proxy)
+        .tls_config(tls_config.build())
+        .build();
+
+    Ok(config.new_agent())
+}
+
+// file:// URLs are supported so that a repository can live on a local or network mounted folder
+fn local_path(url: &str) -> Option<&Path> {
+    url.strip_prefix("file://").map(Path::new)
+}
+
+pub fn fetch_bytes(url: &str) -> anyhow::Result<Vec<u8>> {
+    if let Some(path) = local_path(url) {
+        return std::fs::read(path).context(format!("Failed to read {}", path.display()));
+    }
+
+    let mut response = http_agent()?
+        .get(url)
+        .call()
+        .context(format!("Failed to fetch {url}"))?;
+
+    response
+        .body_mut()
+        .with_config()
+        .limit(64 * 1024 * 1024)
+        .read_to_vec()
+        .context(format!("Failed to read response from {url}"))
+}
+
+pub fn download_file(url: &str, destination: &Path) -> anyhow::Result<()> {
+    if let Some(path) = local_path(url) {
+        std::fs::copy(path, destination).context(format!(
+            "Failed to copy {} to {}",
+            path.display(),
+            destination.display()
+        ))?;
+        return Ok(());
+    }
+
+    let mut response = http_agent()?
+        .get(url)
+        .call()
+        .context(format!("Failed to download {url}"))?;
+
+    let mut outfile = File::create(destination)
+        .context(format!("Unable to create file {}", destination.display()))?;
+    io::copy(&mut response.body_mut().as_reader(), &mut outfile)
+        .context(format!("Failed to download {url}"))?;
+
+    Ok(())
+}
+
+/// Saved next to a partial download so it can be resumed by a later invocation
+#[derive(Debug, Deserialize, Serialize)]
+struct PartialDownloadState {
+    url: String,
+    sha256: Option<String>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn resumable_offset(
+    part_path: &Path,
+    state_path: &Path,
+    url: &str,
+    expected_sha256: Option<&str>,
+) -> u64 {
+    let Ok(content) = std::fs::read_to_string(state_path) else {
+        return 0;
+    };
+    let Ok(state) = serde_yaml::from_str::<PartialDownloadState>(&content) else {
+        return 0;
+    };
+
+    // With a known checksum the same content can be resumed from any mirror
+    let same_content = match (expected_sha256, &state.sha256) {
+        (Some(expected), Some(previous)) => expected.eq_ignore_ascii_case(previous),
+        _ => state.url == url,
+    };
+
+    if !same_content {
+        return 0;
+    }
+
+    std::fs::metadata(part_path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Downloads a (large) file using HTTP range requests to resume a previous interrupted
+/// download of the same content. The partial file is kept as `<destination>.part` until complete.
+/// `ipfs://` URLs go through the IPFS gateway and `magnet:` links are handed to a BitTorrent client.
+pub fn download_file_resumable(
+    url: &str,
+    destination: &Path,
+    expected_sha256: Option<&str>,
+    limit_rate: Option<u64>,
+) -> anyhow::Result<()> {
+    if is_magnet_link(url) {
+        return download_magnet(url, destination, limit_rate);
+    }
+    if let Some(gateway_url) = ipfs_gateway_url(url) {
+        return download_file_resumable(&gateway_url, destination, expected_sha256, limit_rate);
+    }
+    if local_path(url).is_some() {
+        return download_file(url, destination);
+    }
+
+    let part_path = with_suffix(destination, ".part");
+    let state_path = with_suffix(destination, ".part.yaml");
+    let offset = resumable_offset(&part_path, &state_path, url, expected_sha256);
+
+    let state = PartialDownloadState {
+        url: url.to_string(),
+        sha256: expected_sha256.map(str::to_string),
+    };
+    std::fs::write(&state_path, serde_yaml::to_string(&state)?)
+        .context("Failed to save download state")?;
+
+    let mut request = http_agent()?.get(url);
+    if offset > 0 {
+        message!("Resuming previous download ({offset} bytes already downloaded)");
+        request = request.header("Range", format!("bytes={offset}-"));
+    }
+
+    let mut response = match request.call() {
+        Ok(response) => response,
+        // The previous download was complete, but not moved in place
+        Err(ureq::Error::StatusCode(416)) if offset > 0 => {
+            std::fs::rename(&part_path, destination)?;
+            let _ = std::fs::remove_file(&state_path); // Ignore error
+            return Ok(());
+        }
+        Err(e) => return Err(anyhow!(e).context(format!("Failed to download {url}"))),
+    };
+
+    let mut outfile = if response.status().as_u16() == 206 {
+        OpenOptions::new().append(true).open(&part_path)
+    } else {
+        if offset > 0 {
+            message!("Server does not support resuming downloads, starting over");
+        }
+        File::create(&part_path)
+    }
+    .context(format!("Unable to open file {}", part_path.display()))?;
+
+    let mut reader = RateLimitedReader::new(response.body_mut().as_reader(), limit_rate);
+    io::copy(&mut reader, &mut outfile).context(format!("Failed to download {url}"))?;
+
+    std::fs::rename(&part_path, destination).context(format!(
+        "Failed to move download to {}",
+        destination.display()
+    ))?;
+    let _ = std::fs::remove_file(&state_path); // Ignore error
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Copy loop for the image of a pack. It decompresses on the calling thread and writes on
+//! another one, in large block-aligned chunks that don't fill the page cache.
+
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::mpsc;
+use std::thread;
+
+/// Size of the chunks written, a multiple of the block size of any disk
+const BUFFER_SIZE: usize = 8 * 1024 * 1024;
+/// Chunks decompressed ahead of the disk, bounding the memory used to about 50 MB
+const QUEUE_DEPTH: usize = 4;
+/// Written data is flushed and dropped from the page cache by windows of this size
+const WRITEBACK_WINDOW: u64 = 64 * 1024 * 1024;
+
+/// Advises the kernel on how a file will be used. Only a hint, errors are ignored.
+pub fn advise(file: &File, advice: libc::c_int) {
+    advise_range(file.as_raw_fd(), 0, 0, advice);
+}
+
+/// Advises the kernel on how a range of a file will be used, a length of 0 meaning up to its end
+fn advise_range(fd: RawFd, offset: u64, len: u64, advice: libc::c_int) {
+    // SAFETY: posix_fadvise only reads its integer arguments, an invalid fd is reported as EBADF
+    let error =
+        unsafe { libc::posix_fadvise(fd, offset as libc::off_t, len as libc::off_t, advice) };
+    if error != 0 {
+        tracing::debug!(
+            "posix_fadvise({advice}) failed: {}",
+            io::Error::from_raw_os_error(error)
+        );
+    }
+}
+
+/// Runs sync_file_range on a range of a file. Errors of the writeback itself are returned, other
+/// ones only mean the range stays in the page cache.
+fn sync_range(fd: RawFd, offset: u64, len: u64, flags: libc::c_uint) -> io::Result<()> {
+    // SAFETY: sync_file_range only reads its integer arguments, an invalid fd is reported as EBADF
+    let result =
+        unsafe { libc::sync_file_range(fd, offset as libc::off64_t, len as libc::off64_t, flags) };
+    if result == 0 {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::EIO | libc::ENOSPC) => Err(error),
+        _ => {
+            tracing::debug!("sync_file_range failed: {error}");
+            Ok(())
+        }
+    }
+}
+
+/// Starts writing a range of a file to disk, without waiting for it
+fn start_writeback(fd: RawFd, offset: u64, len: u64) -> io::Result<()> {
+    sync_range(fd, offset, len, libc::SYNC_FILE_RANGE_WRITE)
+}
+
+/// Waits for a range of a file to be on disk, and drops it from the page cache
+fn drop_written(fd: RawFd, offset: u64, len: u64) -> io::Result<()> {
+    sync_range(
+        fd,
+        offset,
+        len,
+        libc::SYNC_FILE_RANGE_WAIT_BEFORE
+            | libc::SYNC_FILE_RANGE_WRITE
+            | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+    )?;
+    advise_range(fd, offset, len, libc::POSIX_FADV_DONTNEED);
+    Ok(())
+}
+
+/// Reads until the buffer is full or the reader is exhausted
+fn fill(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Copies `reader` to the start of `file`, which is expected to be about `size` bytes.
+/// `inspect` sees every chunk on the writing thread, e.g. to hash it. Returns the bytes copied.
+pub fn copy_to_file(
+    reader: &mut impl Read,
+    file: &File,
+    size: u64,
+    mut inspect: impl FnMut(&[u8]) + Send,
+) -> io::Result<u64> {
+    let fd = file.as_raw_fd();
+    // Reserves the space upfront, on filesystems supporting it, for a less fragmented image
+    // SAFETY: fallocate only reads its integer arguments, an invalid fd is reported as EBADF
+    let reserved =
+        unsafe { libc::fallocate(fd, libc::FALLOC_FL_KEEP_SIZE, 0, size as libc::off_t) };
+    if reserved != 0 {
+        tracing::debug!("fallocate failed: {}", io::Error::last_os_error());
+    }
+    advise(file, libc::POSIX_FADV_SEQUENTIAL);
+
+    let (full_sender, full_receiver) = mpsc::sync_channel::<Vec<u8>>(QUEUE_DEPTH);
+    let (empty_sender, empty_receiver) = mpsc::channel::<Vec<u8>>();
+
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || -> io::Result<u64> {
+            let mut output = file;
+            let mut written = 0;
+            let mut window_start = 0;
+            let mut previous_window = None;
+            for buffer in full_receiver {
+                output.write_all(&buffer)?;
+                inspect(&buffer);
+                written += buffer.len() as u64;
+                // Buffers are reused for the next chunks
+                let _ = empty_sender.send(buffer);
+
+                if written - window_start >= WRITEBACK_WINDOW {
+                    start_writeback(fd, window_start, written - window_start)?;
+                    if let Some((offset, len)) = previous_window {
+                        drop_written(fd, offset, len)?;
+                    }
+                    previous_window = Some((window_start, written - window_start));
+                    window_start = written;
+                }
+            }
+
+            Ok(written)
+        });
+
+        let read = (|| -> io::Result<()> {
+            loop {
+                let mut buffer = empty_receiver
+                    .try_recv()
+                    .unwrap_or_else(|_| vec![0; BUFFER_SIZE]);
+                buffer.resize(BUFFER_SIZE, 0);
+                let filled = fill(reader, &mut buffer)?;
+                if filled == 0 {
+                    return Ok(());
+                }
+                buffer.truncate(filled);
+                // The writer stopped on an error, returned below
+                if full_sender.send(buffer).is_err() || filled < BUFFER_SIZE {
+                    return Ok(());
+                }
+            }
+        })();
+        drop(full_sender);
+
+        let written = writer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+        read?;
+
+        Ok(written)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_to_file() {
+        let path = std::env::temp_dir().join(format!("appack-extract-test-{}", std::process::id()));
+        // Several chunks and a partial one
+        let data: Vec<u8> = (0..BUFFER_SIZE * 2 + 12345).map(|i| i as u8).collect();
+        let file = File::create(&path).unwrap();
+
+        let mut inspected = 0;
+        let copied = copy_to_file(&mut data.as_slice(), &file, data.len() as u64, |chunk| {
+            inspected += chunk.len()
+        })
+        .unwrap();
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(inspected, data.len());
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
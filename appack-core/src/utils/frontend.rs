@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Where the messages and questions for the user go: the terminal for the command line, or
+//! the interface of a frontend embedding the library
+
+use crate::types::error::ErrorKind;
+use crate::utils::i18n::tr;
+use anyhow::Context;
+use serde_json::Value;
+use std::io::{IsTerminal, Write};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Interface of the user. Questions may be asked from any thread, e.g. the one of `spawn_launch`.
+pub trait Frontend: Send + Sync {
+    /// Information, e.g. "Installation complete"
+    fn message(&self, text: &str);
+
+    /// Something went wrong but the operation goes on, or it failed and more details follow
+    fn warning(&self, text: &str);
+
+    /// Progress of a long operation, replacing the previous one until the next message
+    fn progress(&self, text: &str);
+
+    /// Progress events of long operations, e.g. `vm_started`, see the JSON output in the docs
+    fn event(&self, _event: &str, _fields: &Value) {}
+
+    /// Asks to confirm an action. Fails with `ErrorKind::ConfirmationRequired` when nobody can
+    /// answer, `--yes` being the way around it on the command line.
+    fn confirm(&self, question: &str) -> anyhow::Result<bool>;
+
+    /// Asks to type `word` to confirm a dangerous action, only `override_flag` skips it
+    fn confirm_typed(
+        &self,
+        question: &str,
+        word: &str,
+        override_flag: &str,
+    ) -> anyhow::Result<bool>;
+
+    /// Asks to pick one of `options`, which can be given as `argument` instead
+    fn pick(&self, question: &str, options: &[String], argument: &str) -> anyhow::Result<usize>;
+}
+
+static FRONTEND: OnceLock<Box<dyn Frontend>> = OnceLock::new();
+
+/// Sends the messages and questions for the user to `frontend` instead of the terminal.
+/// Set it once, before calling the library.
+pub fn set_frontend(frontend: impl Frontend + 'static) {
+    let _ = FRONTEND.set(Box::new(frontend));
+}
+
+pub(crate) fn frontend() -> &'static dyn Frontend {
+    FRONTEND
+        .get_or_init(|| Box::new(Terminal::default()))
+        .as_ref()
+}
+
+/// Shows a message through the frontend, e.g. `message!("Removed {id}")`
+macro_rules! message {
+    () => {
+        $crate::utils::frontend::frontend().message("")
+    };
+    ($($arg:tt)*) => {
+        $crate::utils::frontend::frontend().message(&format!($($arg)*))
+    };
+}
+pub(crate) use message;
+
+/// Shows a warning through the frontend, e.g. `warning!("Failed to remove {id}: {e}")`
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::utils::frontend::frontend().warning(&format!($($arg)*))
+    };
+}
+pub(crate) use warning;
+
+/// The command line: messages on stdout, warnings on stderr and questions read from stdin
+#[derive(Default)]
+pub struct Terminal {
+    /// Whether the last line written is a progress line, ended by the next message
+    in_progress: AtomicBool,
+}
+
+impl Terminal {
+    fn end_progress(&self) {
+        if self.in_progress.swap(false, Ordering::Relaxed) {
+            println!();
+        }
+    }
+
+    fn read_answer(&self, prompt: &str) -> anyhow::Result<String> {
+        self.end_progress();
+        print!("{prompt} ");
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        std::io::stdin()
+            .read_line(&mut answer)
+            .context("Failed to read the answer")?;
+
+        Ok(answer)
+    }
+}
+
+impl Frontend for Terminal {
+    fn message(&self, text: &str) {
+        self.end_progress();
+        println!("{text}");
+    }
+
+    fn warning(&self, text: &str) {
+        self.end_progress();
+        eprintln!("{text}");
+    }
+
+    fn progress(&self, text: &str) {
+        self.in_progress.store(true, Ordering::Relaxed);
+        print!("\r{text}");
+        let _ = std::io::stdout().flush();
+    }
+
+    fn confirm(&self, question: &str) -> anyhow::Result<bool> {
+        if !std::io::stdin().is_terminal() {
+            return Err(ErrorKind::ConfirmationRequired.error(tr!(
+                "prompt-confirmation-required",
+                question = question,
+                flag = "--yes"
+            )));
+        }
+
+        let answer = self.read_answer(&tr!("prompt-yes-no", question = question))?;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+
+    fn confirm_typed(
+        &self,
+        question: &str,
+        word: &str,
+        override_flag: &str,
+    ) -> anyhow::Result<bool> {
+        if !std::io::stdin().is_terminal() {
+            return Err(ErrorKind::ConfirmationRequired.error(tr!(
+                "prompt-confirmation-required",
+                question = question,
+                flag = override_flag
+            )));
+        }
+
+        let answer =
+            self.read_answer(&tr!("prompt-type-word", question = question, word = word))?;
+        Ok(answer.trim() == word)
+    }
+
+    fn pick(&self, question: &str, options: &[String], argument: &str) -> anyhow::Result<usize> {
+        if !std::io::stdin().is_terminal() {
+            return Err(ErrorKind::ConfirmationRequired.error(tr!(
+                "prompt-choice-required",
+                question = question,
+                argument = argument
+            )));
+        }
+
+        self.message(question);
+        for (i, option) in options.iter().enumerate() {
+            self.message(&format!("  {}) {option}", i + 1));
+        }
+        loop {
+            let answer = self.read_answer(&tr!("prompt-pick", count = options.len()))?;
+            let answer = answer.trim();
+            // Also the end of stdin
+            if answer.is_empty() {
+                return Err(ErrorKind::Cancelled.error(tr!("prompt-no-choice")));
+            }
+            if let Ok(choice) = answer.parse::<usize>()
+                && (1..=options.len()).contains(&choice)
+            {
+                return Ok(choice - 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Socket of the `org.qemu.guest_agent.0` port, in the app home
+pub const GUEST_AGENT_SOCKET: &str = "qga.sock";
+
+/// Time the agent has to answer, it doesn't if it isn't installed in the VM
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a command of the QEMU guest agent of the VM, and returns its result
+pub fn execute(app_home: &Path, command: &str, arguments: Value) -> Result<Value> {
+    let mut stream = UnixStream::connect(app_home.join(GUEST_AGENT_SOCKET))
+        .context("The pack has no guest agent port")?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    // Replies to an earlier, timed out, client may still be queued: sync on a fresh id first
+    let sync_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or_default();
+    writeln!(
+        stream,
+        "{}",
+        json!({ "execute": "guest-sync", "arguments": { "id": sync_id } })
+    )?;
+    loop {
+        let reply = read_reply(&mut reader)?;
+        if reply["return"] == sync_id {
+            break;
+        }
+    }
+
+    let mut request = json!({ "execute": command });
+    if !arguments.is_null() {
+        request["arguments"] = arguments;
+    }
+    writeln!(stream, "{request}")?;
+    let reply = read_reply(&mut reader)?;
+    match reply.get("error") {
+        Some(error) => Err(anyhow!("{command} failed in the VM: {}", error["desc"])),
+        None => Ok(reply["return"].clone()),
+    }
+}
+
+fn read_reply(reader: &mut impl BufRead) -> Result<Value> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .context("The guest agent doesn't answer, is it running in the VM?")?;
+    if line.is_empty() {
+        return Err(anyhow!("The guest agent closed the connection"));
+    }
+
+    serde_json::from_str(&line).context("Invalid reply of the guest agent")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn test_execute_skips_stale_replies() {
+        let home = std::env::temp_dir().join(format!("appack-qga-test-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        let listener = UnixListener::bind(home.join(GUEST_AGENT_SOCKET)).unwrap();
+        let agent = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let sync: Value = serde_json::from_str(&line).unwrap();
+            // Reply to a client that gave up
+            writeln!(&stream, r#"{{"return": {{"stale": true}}}}"#).unwrap();
+            writeln!(&stream, "{}", json!({ "return": sync["arguments"]["id"] })).unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            writeln!(&stream, r#"{{"return": {{"command": {}}}}}"#, line.trim()).unwrap();
+        });
+
+        let reply = execute(&home, "guest-set-time", json!({ "time": 1 })).unwrap();
+        assert_eq!(reply["command"]["execute"], "guest-set-time");
+        assert_eq!(reply["command"]["arguments"]["time"], 1);
+
+        agent.join().unwrap();
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::env;
+use std::fs;
+
+/// IANA timezones and the Windows timezone covering them, from the CLDR mapping
+const WINDOWS_TIMEZONES: &[(&str, &str)] = &[
+    ("UTC", "UTC"),
+    ("Etc/UTC", "UTC"),
+    ("Europe/London", "GMT Standard Time"),
+    ("Europe/Dublin", "GMT Standard Time"),
+    ("Europe/Lisbon", "GMT Standard Time"),
+    ("Europe/Paris", "Romance Standard Time"),
+    ("Europe/Brussels", "Romance Standard Time"),
+    ("Europe/Madrid", "Romance Standard Time"),
+    ("Europe/Copenhagen", "Romance Standard Time"),
+    ("Europe/Berlin", "W. Europe Standard Time"),
+    ("Europe/Amsterdam", "W. Europe Standard Time"),
+    ("Europe/Rome", "W. Europe Standard Time"),
+    ("Europe/Vienna", "W. Europe Standard Time"),
+    ("Europe/Zurich", "W. Europe Standard Time"),
+    ("Europe/Stockholm", "W. Europe Standard Time"),
+    ("Europe/Oslo", "W. Europe Standard Time"),
+    ("Europe/Luxembourg", "W. Europe Standard Time"),
+    ("Europe/Warsaw", "Central European Standard Time"),
+    ("Europe/Zagreb", "Central European Standard Time"),
+    ("Europe/Prague", "Central Europe Standard Time"),
+    ("Europe/Budapest", "Central Europe Standard Time"),
+    ("Europe/Athens", "GTB Standard Time"),
+    ("Europe/Bucharest", "GTB Standard Time"),
+    ("Europe/Helsinki", "FLE Standard Time"),
+    ("Europe/Kiev", "FLE Standard Time"),
+    ("Europe/Kyiv", "FLE Standard Time"),
+    ("Europe/Istanbul", "Turkey Standard Time"),
+    ("Europe/Moscow", "Russian Standard Time"),
+    ("America/New_York", "Eastern Standard Time"),
+    ("America/Toronto", "Eastern Standard Time"),
+    ("America/Chicago", "Central Standard Time"),
+    ("America/Denver", "Mountain Standard Time"),
+    ("America/Phoenix", "US Mountain Standard Time"),
+    ("America/Los_Angeles", "Pacific Standard Time"),
+    ("America/Vancouver", "Pacific Standard Time"),
+    ("America/Anchorage", "Alaskan Standard Time"),
+    ("Pacific/Honolulu", "Hawaiian Standard Time"),
+    ("America/Mexico_City", "Central Standard Time (Mexico)"),
+    ("America/Sao_Paulo", "E. South America Standard Time"),
+    ("America/Argentina/Buenos_Aires", "Argentina Standard Time"),
+    ("America/Bogota", "SA Pacific Standard Time"),
+    ("America/Santiago", "Pacific SA Standard Time"),
+    ("Africa/Cairo", "Egypt Standard Time"),
+    ("Africa/Johannesburg", "South Africa Standard Time"),
+    ("Africa/Lagos", "W. Central Africa Standard Time"),
+    ("Africa/Casablanca", "Morocco Standard Time"),
+    ("Asia/Dubai", "Arabian Standard Time"),
+    ("Asia/Jerusalem", "Israel Standard Time"),
+    ("Asia/Kolkata", "India Standard Time"),
+    ("Asia/Calcutta", "India Standard Time"),
+    ("Asia/Bangkok", "SE Asia Standard Time"),
+    ("Asia/Jakarta", "SE Asia Standard Time"),
+    ("Asia/Singapore", "Singapore Standard Time"),
+    ("Asia/Shanghai", "China Standard Time"),
+    ("Asia/Hong_Kong", "China Standard Time"),
+    ("Asia/Taipei", "Taipei Standard Time"),
+    ("Asia/Seoul", "Korea Standard Time"),
+    ("Asia/Tokyo", "Tokyo Standard Time"),
+    ("Australia/Perth", "W. Australia Standard Time"),
+    ("Australia/Brisbane", "E. Australia Standard Time"),
+    ("Australia/Sydney", "AUS Eastern Standard Time"),
+    ("Australia/Melbourne", "AUS Eastern Standard Time"),
+    ("Pacific/Auckland", "New Zealand Standard Time"),
+];
+
+/// IANA timezone of this machine, e.g. `Europe/Paris`
+pub fn host_timezone() -> Option<String> {
+    let from_env = env::var("TZ")
+        .ok()
+        .map(|tz| tz.trim_start_matches(':').to_string());
+    // Debian based distributions
+    let from_file = || {
+        fs::read_to_string("/etc/timezone")
+            .ok()
+            .map(|tz| tz.trim().to_string())
+    };
+    // systemd links /etc/localtime to the zone in /usr/share/zoneinfo
+    let from_link = || {
+        let target = fs::read_link("/etc/localtime").ok()?;
+        let target = target.to_string_lossy();
+        let (_, zone) = target.split_once("zoneinfo/")?;
+        Some(zone.to_string())
+    };
+
+    from_env
+        .filter(|tz| !tz.is_empty())
+        .or_else(|| from_file().filter(|tz| !tz.is_empty()))
+        .or_else(from_link)
+}
+
+/// Windows name of an IANA timezone, for `tzutil`
+pub fn windows_timezone(timezone: &str) -> Option<&'static str> {
+    WINDOWS_TIMEZONES
+        .iter()
+        .find(|(iana, _)| *iana == timezone)
+        .map(|(_, windows)| *windows)
+}
+
+/// Regional format of this machine as a language tag, e.g. `fr-FR`
+pub fn host_locale() -> Option<String> {
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    language_tag(&locale)
+}
+
+/// `fr_FR.UTF-8@euro` gives `fr-FR`, `C` and `POSIX` give nothing
+fn language_tag(locale: &str) -> Option<String> {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, country) = name.split_once('_')?;
+    let valid =
+        |part: &str, len| part.len() == len && part.chars().all(|c| c.is_ascii_alphabetic());
+    if !valid(language, 2) && !valid(language, 3) || !valid(country, 2) {
+        return None;
+    }
+
+    Some(format!(
+        "{}-{}",
+        language.to_lowercase(),
+        country.to_uppercase()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_tags_and_timezones() {
+        assert_eq!(language_tag("fr_FR.UTF-8@euro").as_deref(), Some("fr-FR"));
+        assert_eq!(language_tag("de_CH").as_deref(), Some("de-CH"));
+        assert_eq!(language_tag("C.UTF-8"), None);
+        assert_eq!(language_tag("POSIX"), None);
+
+        assert_eq!(
+            windows_timezone("Europe/Paris"),
+            Some("Romance Standard Time")
+        );
+        assert_eq!(windows_timezone("Mars/Olympus_Mons"), None);
+    }
+}
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Message catalogs embedded in the binary, by language
+const CATALOGS: &[(&str, &str)] = &[("en", include_str!("../../locales/en/appack.ftl"))];
+
+/// Translates a message of the catalogs, e.g. `tr!("prompt-reset", id = app.id, version = app.version)`
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::utils::i18n::translate($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value.to_string());)+
+        $crate::utils::i18n::translate($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+/// Language of the user from `LC_ALL`, `LC_MESSAGES` or `LANG`, e.g. `fr-FR` for `fr_FR.UTF-8`
+fn user_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| locale_to_language(&value))
+}
+
+fn locale_to_language(locale: &str) -> Option<String> {
+    let language = locale.split(['.', '@']).next()?.replace('_', "-");
+    match language.as_str() {
+        "" | "C" | "POSIX" => None,
+        _ => Some(language),
+    }
+}
+
+/// Finds the catalog of a language, falling back from `pt-BR` to `pt`
+fn find_catalog(language: &str) -> Option<(&'static str, &'static str)> {
+    let primary = language.split('-').next().unwrap_or(language);
+    CATALOGS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(language))
+        .or_else(|| CATALOGS.iter().find(|(name, _)| *name == primary))
+        .copied()
+}
+
+fn load_bundle(language: &str, catalog: &str) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = language.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Unicode isolation marks show up as garbage in terminals
+    bundle.set_use_isolating(false);
+
+    let resource =
+        FluentResource::try_new(catalog.to_string()).unwrap_or_else(|(resource, errors)| {
+            tracing::warn!(?errors, "Invalid messages in catalog");
+            resource
+        });
+    let _ = bundle.add_resource(resource);
+
+    bundle
+}
+
+/// Bundles to look messages up in: the language of the user, then English
+fn bundles() -> &'static [FluentBundle<FluentResource>] {
+    static BUNDLES: OnceLock<Vec<FluentBundle<FluentResource>>> = OnceLock::new();
+
+    BUNDLES.get_or_init(|| {
+        let mut languages = Vec::new();
+        if let Some(catalog) = user_language().and_then(|language| find_catalog(&language)) {
+            languages.push(catalog);
+        }
+        if languages
+            .first()
+            .is_none_or(|(name, _)| *name != DEFAULT_LANGUAGE)
+        {
+            languages.extend(find_catalog(DEFAULT_LANGUAGE));
+        }
+
+        languages
+            .into_iter()
+            .map(|(language, catalog)| load_bundle(language, catalog))
+            .collect()
+    })
+}
+
+/// Value for the language of the user in a map by language, e.g. `fr` or `pt-BR`
+pub fn localized<T>(values: &BTreeMap<String, T>) -> Option<&T> {
+    localized_for(values, &user_language()?)
+}
+
+/// Finds the value of a language, falling back from `pt-BR` to `pt`
+fn localized_for<'a, T>(values: &'a BTreeMap<String, T>, language: &str) -> Option<&'a T> {
+    let primary = language.split('-').next().unwrap_or(language);
+    let find = |language: &str| {
+        values
+            .iter()
+            .find(|(name, _)| name.replace('_', "-").eq_ignore_ascii_case(language))
+            .map(|(_, value)| value)
+    };
+
+    find(language).or_else(|| find(primary))
+}
+
+/// Formats a message in the language of the user, use the `tr!` macro instead
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    for bundle in bundles() {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = Vec::new();
+            return bundle
+                .format_pattern(pattern, args, &mut errors)
+                .into_owned();
+        }
+    }
+
+    tracing::warn!(id, "Missing translation");
+    id.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_are_valid() {
+        for (language, catalog) in CATALOGS {
+            assert!(
+                FluentResource::try_new(catalog.to_string()).is_ok(),
+                "invalid catalog {language}"
+            );
+        }
+    }
+
+    #[test]
+    fn locale_and_arguments() {
+        assert_eq!(locale_to_language("pt_BR.UTF-8").as_deref(), Some("pt-BR"));
+        assert_eq!(locale_to_language("C.UTF-8"), None);
+        assert_eq!(find_catalog("en-GB").map(|(name, _)| name), Some("en"));
+        let values = BTreeMap::from([("fr".to_string(), 1), ("pt_BR".to_string(), 2)]);
+        assert_eq!(localized_for(&values, "fr-CA"), Some(&1));
+        assert_eq!(localized_for(&values, "pt-BR"), Some(&2));
+        assert_eq!(localized_for(&values, "de"), None);
+
+        let bundle = load_bundle("en", CATALOGS[0].1);
+        let mut args = FluentArgs::new();
+        args.set("question", "Continue?");
+        let message = bundle.get_message("prompt-yes-no").unwrap();
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(message.value().unwrap(), Some(&args), &mut errors);
+        assert_eq!(text, "Continue? [y/N]");
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+/// Sizes of the hicolor theme, icons of other sizes are not looked up
+const HICOLOR_SIZES: [u32; 14] = [16, 22, 24, 32, 36, 48, 64, 72, 96, 128, 192, 256, 384, 512];
+
+/// Folder of the hicolor theme an icon goes in, like `scalable/apps` or `48x48/apps`.
+/// `None` when the theme can't hold it, the icon is then referenced by its path.
+pub fn theme_subdir(file_name: &str, data: &[u8]) -> Option<String> {
+    match extension(file_name)?.as_str() {
+        "svg" => Some("scalable/apps".to_string()),
+        "png" => {
+            let (width, height) = png_size(data)?;
+            (width == height && HICOLOR_SIZES.contains(&width))
+                .then(|| format!("{width}x{width}/apps"))
+        }
+        _ => None,
+    }
+}
+
+/// Lowercase extension of an icon, which the theme needs in the file name
+pub fn extension(file_name: &str) -> Option<String> {
+    Path::new(file_name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Width and height from the IHDR chunk, always the first of a PNG
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.get(..8)? != b"\x89PNG\r\n\x1a\n" || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_subdir() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(48u32.to_be_bytes());
+        png.extend(48u32.to_be_bytes());
+
+        assert_eq!(theme_subdir("app.PNG", &png).unwrap(), "48x48/apps");
+        assert_eq!(theme_subdir("app.svg", b"<svg/>").unwrap(), "scalable/apps");
+        assert_eq!(theme_subdir("app.png", b"<svg/>"), None);
+        assert_eq!(theme_subdir("app.ico", &png), None);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::utils::sandbox;
+use anyhow::{Result, anyhow};
+use std::env;
+use std::fs;
+
+/// XKB layouts and their Windows keyboard layout ids
+const LAYOUTS: [(&str, u32); 37] = [
+    ("us", 0x0000_0409),
+    ("us(intl)", 0x0002_0409),
+    ("us(dvorak)", 0x0001_0409),
+    ("gb", 0x0000_0809),
+    ("ie", 0x0000_1809),
+    ("fr", 0x0000_040C),
+    ("be", 0x0000_080C),
+    ("ca", 0x0000_1009),
+    ("ch", 0x0000_0807),
+    ("ch(fr)", 0x0000_100C),
+    ("de", 0x0000_0407),
+    ("at", 0x0000_0407),
+    ("es", 0x0000_040A),
+    ("latam", 0x0000_080A),
+    ("it", 0x0000_0410),
+    ("pt", 0x0000_0816),
+    ("br", 0x0000_0416),
+    ("nl", 0x0000_0413),
+    ("se", 0x0000_041D),
+    ("no", 0x0000_0414),
+    ("dk", 0x0000_0406),
+    ("fi", 0x0000_040B),
+    ("is", 0x0000_040F),
+    ("pl", 0x0000_0415),
+    ("cz", 0x0000_0405),
+    ("sk", 0x0000_041B),
+    ("hu", 0x0000_040E),
+    ("si", 0x0000_0424),
+    ("hr", 0x0000_041A),
+    ("ro", 0x0001_0418),
+    ("ru", 0x0000_0419),
+    ("ua", 0x0000_0422),
+    ("tr", 0x0000_041F),
+    ("gr", 0x0000_0408),
+    ("il", 0x0000_040D),
+    ("jp", 0x0000_0411),
+    ("kr", 0x0000_0412),
+];
+
+/// Windows keyboard layout id of an XKB layout like `fr` or `de(nodeadkeys)`, or of a
+/// hexadecimal id like `0x40C`. Only the first of several layouts is used.
+pub fn layout_id(layout: &str) -> Result<u32> {
+    let layout = layout.split(',').next().unwrap_or_default().trim();
+    if let Some(hex) = layout
+        .strip_prefix("0x")
+        .or_else(|| layout.strip_prefix("0X"))
+    {
+        return u32::from_str_radix(hex, 16)
+            .map_err(|_| anyhow!("Invalid keyboard layout id '{layout}'"));
+    }
+
+    // The variant when it has its own Windows layout, the layout otherwise
+    let name = layout.to_lowercase();
+    let base = name.split('(').next().unwrap_or_default();
+    LAYOUTS
+        .iter()
+        .find(|(xkb, _)| *xkb == name)
+        .or_else(|| LAYOUTS.iter().find(|(xkb, _)| *xkb == base))
+        .map(|(_, id)| *id)
+        .ok_or_else(|| {
+            anyhow!("Unknown keyboard layout '{layout}', use its Windows id instead, like 0x40C")
+        })
+}
+
+/// Checks a keyboard layout for the command line
+pub fn parse_layout(layout: &str) -> Result<String> {
+    layout_id(layout)?;
+    Ok(layout.to_string())
+}
+
+/// FreeRDP option selecting a keyboard layout in the session
+pub fn freerdp_arg(layout: &str) -> Result<String> {
+    Ok(format!("/kbd:layout:0x{:08X}", layout_id(layout)?))
+}
+
+/// Keyboard layout of this machine, as an XKB layout
+pub fn host_layout() -> Option<String> {
+    env::var("XKB_DEFAULT_LAYOUT")
+        .ok()
+        .filter(|layout| !layout.is_empty())
+        .or_else(setxkbmap_layout)
+        .or_else(system_layout)
+        .or_else(locale_layout)
+}
+
+fn setxkbmap_layout() -> Option<String> {
+    env::var_os("DISPLAY")?;
+    let output = sandbox::command("setxkbmap")
+        .arg("-query")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("layout:"))
+        .map(|layout| layout.trim().to_string())
+}
+
+/// Layout configured for the whole system, by Debian based or systemd based distributions
+fn system_layout() -> Option<String> {
+    let debian = fs::read_to_string("/etc/default/keyboard")
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("XKBLAYOUT=")?;
+                Some(value.trim_matches('"').to_string())
+            })
+        });
+    let systemd = || {
+        let content = fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf").ok()?;
+        content.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("Option")?.trim();
+            let value = value.strip_prefix("\"XkbLayout\"")?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+    };
+
+    debian.or_else(systemd).filter(|layout| !layout.is_empty())
+}
+
+/// Guesses the layout from the country of the locale, e.g. `fr_BE.UTF-8` gives `be`
+fn locale_layout() -> Option<String> {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    layout_from_locale(&locale)
+}
+
+fn layout_from_locale(locale: &str) -> Option<String> {
+    let (language, rest) = locale.split_once('_')?;
+    let country = rest
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    // Most countries share their code with their XKB layout
+    let layout = match (language, country.as_str()) {
+        ("en", "au" | "nz" | "ca") => "us",
+        ("fr", "ch") => "ch(fr)",
+        ("fr", "ca") => "ca",
+        ("es", country) if country != "es" => "latam",
+        (_, country) => country,
+    };
+
+    layout_id(layout).ok().map(|_| layout.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_layouts_to_windows_ids() {
+        assert_eq!(freerdp_arg("fr").unwrap(), "/kbd:layout:0x0000040C");
+        assert_eq!(
+            freerdp_arg("de(nodeadkeys),us").unwrap(),
+            "/kbd:layout:0x00000407"
+        );
+        assert_eq!(freerdp_arg("0x809").unwrap(), "/kbd:layout:0x00000809");
+        assert_eq!(freerdp_arg("ch(fr)").unwrap(), "/kbd:layout:0x0000100C");
+        assert!(freerdp_arg("xx").is_err());
+
+        assert_eq!(layout_from_locale("fr_BE.UTF-8").as_deref(), Some("be"));
+        assert_eq!(layout_from_locale("en_US.UTF-8").as_deref(), Some("us"));
+        assert_eq!(layout_from_locale("es_MX.UTF-8").as_deref(), Some("latam"));
+        assert_eq!(layout_from_locale("C.UTF-8"), None);
+    }
+}
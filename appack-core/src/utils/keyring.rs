@@ -0,0 +1,37 @@
+use crate::utils::sandbox;
+use anyhow::{Context, Result};
+
+/// Attribute shared by every secret AppPack stores in the keyring
+const SERVICE: (&str, &str) = ("service", "appack");
+
+/// Reads a secret from the desktop keyring through `secret-tool`, `None` when it is not stored
+pub fn lookup(attributes: &[(&str, &str)]) -> Result<Option<String>> {
+    let mut command = sandbox::command("secret-tool");
+    command.arg("lookup");
+    for (key, value) in std::iter::once(&SERVICE).chain(attributes) {
+        command.arg(key).arg(value);
+    }
+
+    let output = command
+        .output()
+        .context("Failed to run secret-tool, is libsecret-tools installed?")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let secret = String::from_utf8(output.stdout).context("Invalid secret in the keyring")?;
+    Ok(Some(secret.trim_end_matches('\n').to_string()))
+}
+
+/// Command storing a secret for `lookup`, for the user to run
+pub fn store_command(label: &str, attributes: &[(&str, &str)]) -> String {
+    let attributes: Vec<String> = std::iter::once(&SERVICE)
+        .chain(attributes)
+        .map(|(key, value)| format!("{key} {value}"))
+        .collect();
+
+    format!(
+        "secret-tool store --label='{label}' {}",
+        attributes.join(" ")
+    )
+}
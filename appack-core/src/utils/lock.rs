@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::frontend::message;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::thread::{self, ThreadId};
+
+/// Lock file and the thread holding it
+type LockKey = (PathBuf, ThreadId);
+
+/// Lock files held by the threads of this process, with how many guards currently use them.
+/// Each thread opens its own file, so flock also keeps the threads of this process apart.
+static HELD_LOCKS: LazyLock<Mutex<HashMap<LockKey, (File, usize)>>> = LazyLock::new(Mutex::default);
+
+/// Exclusive advisory lock (flock) shared with other AppPack processes, released when dropped
+pub struct FileLock {
+    key: LockKey,
+}
+
+impl FileLock {
+    /// Waits until no other process or thread holds the lock.
+    /// The lock is reentrant: a thread already holding it gets it immediately.
+    pub fn acquire(path: &Path) -> anyhow::Result<Self> {
+        let key = (path.to_path_buf(), thread::current().id());
+        if let Some((_, count)) = HELD_LOCKS.lock().unwrap().get_mut(&key) {
+            *count += 1;
+            return Ok(Self { key });
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .context(format!("Failed to open lock file {}", path.display()))?;
+
+        // Waiting must not hold HELD_LOCKS, other threads may use or release their own locks
+        if file.try_lock().is_err() {
+            message!("Waiting for another AppPack operation to finish..");
+            file.lock()
+                .context(format!("Failed to lock {}", path.display()))?;
+        }
+
+        HELD_LOCKS.lock().unwrap().insert(key.clone(), (file, 1));
+
+        Ok(Self { key })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let mut held = HELD_LOCKS.lock().unwrap();
+        if let Some((_, count)) = held.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                // Closing the file releases the lock
+                held.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_reentrant() {
+        let path = std::env::temp_dir().join(format!("appack-lock-test-{}", std::process::id()));
+
+        let outer = FileLock::acquire(&path).unwrap();
+        let inner = FileLock::acquire(&path).unwrap();
+        let key = (path.clone(), thread::current().id());
+        drop(inner);
+        assert!(HELD_LOCKS.lock().unwrap().contains_key(&key));
+        drop(outer);
+        assert!(!HELD_LOCKS.lock().unwrap().contains_key(&key));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lock_excludes_other_threads() {
+        let path = std::env::temp_dir().join(format!("appack-lock-thread-{}", std::process::id()));
+
+        let held = FileLock::acquire(&path).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let waiter = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let _lock = FileLock::acquire(&path).unwrap();
+                sender.send(()).unwrap();
+            })
+        };
+        let timeout = std::time::Duration::from_millis(200);
+        assert!(receiver.recv_timeout(timeout).is_err());
+        drop(held);
+        receiver.recv_timeout(timeout * 25).unwrap();
+        waiter.join().unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
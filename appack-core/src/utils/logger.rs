@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Target of events written to the log file only, for messages already shown to the user
+pub const FILE_ONLY_TARGET: &str = "appack::file_only";
+
+/// Log level of the console, from `--log-level` or the number of `-v` flags
+pub fn console_log_level(log_level: Option<LevelFilter>, verbose: u8) -> LevelFilter {
+    log_level.unwrap_or(match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    })
+}
+
+/// Logs to stderr at the given level, and to a daily log file in `log_dir` at least at debug level,
+/// keeping `retention_days` files.
+/// The returned guard flushes the log file when dropped, keep it until the program exits.
+pub fn init_logging(
+    log_dir: &Path,
+    level: LevelFilter,
+    retention_days: u32,
+) -> Option<WorkerGuard> {
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .with_filter(filter_fn(move |metadata| {
+            metadata.target() != FILE_ONLY_TARGET && level >= *metadata.level()
+        }));
+
+    let appender = std::fs::create_dir_all(log_dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("appack")
+                .filename_suffix("log")
+                .max_log_files(retention_days.max(1) as usize)
+                .build(log_dir)
+                .map_err(|e| e.to_string())
+        });
+
+    match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let file_layer = tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(level.max(LevelFilter::DEBUG));
+
+            tracing_subscriber::registry()
+                .with(console_layer)
+                .with(file_layer)
+                .init();
+
+            Some(guard)
+        }
+        Err(e) => {
+            tracing_subscriber::registry().with(console_layer).init();
+            tracing::warn!("Logging to the console only, unable to open the log file: {e}");
+
+            None
+        }
+    }
+}
@@ -13,7 +13,34 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod atomic_write;
+pub mod checksum;
+pub mod display;
+pub mod chunks;
+pub mod command_review;
+pub mod download;
+pub mod extract;
+pub mod frontend;
+pub mod guest_agent;
+pub mod host_locale;
+pub mod i18n;
+pub mod icon;
+pub mod keyboard;
+pub mod keyring;
+pub mod lock;
 pub mod logger;
+pub mod nested_virt;
+pub mod notify;
+pub mod output;
+pub mod p2p;
+pub mod prompt;
+pub mod qemu_sandbox;
 pub mod qmp;
+pub mod rate_limit;
+pub mod sandbox;
+pub mod scale;
+pub mod snap;
+pub mod upload;
+pub mod virtiofs;
 pub mod zip_dir;
 pub mod xdg_session_type_detector;
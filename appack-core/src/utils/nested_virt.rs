@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use anyhow::Result;
+
+/// KVM modules of Intel and AMD CPUs, and the CPU flag they expose to the guest
+const KVM_MODULES: [(&str, &str); 2] = [("kvm_intel", "vmx"), ("kvm_amd", "svm")];
+
+/// CPU flag letting the guest run VMs itself, if KVM of this machine allows it
+fn host_nested_flag() -> Result<&'static str> {
+    for (module, flag) in KVM_MODULES {
+        let Ok(nested) = std::fs::read_to_string(format!("/sys/module/{module}/parameters/nested"))
+        else {
+            continue;
+        };
+        if matches!(nested.trim(), "Y" | "1") {
+            return Ok(flag);
+        }
+
+        return Err(ErrorKind::EnvironmentMissing.error(format!(
+            "This app needs nested virtualization, which is disabled. Enable it with \
+             `echo 'options {module} nested=1' | sudo tee /etc/modprobe.d/kvm-nested.conf`, \
+             then restart your machine"
+        )));
+    }
+
+    Err(ErrorKind::EnvironmentMissing.error(
+        "This app needs nested virtualization, only available with KVM on Intel and AMD CPUs",
+    ))
+}
+
+/// Adds a CPU flag to the `-cpu` option of a Qemu command, `-cpu host` when it has none
+fn with_cpu_flag(qemu_command: &str, flag: &str) -> String {
+    let mut args: Vec<String> = qemu_command
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    match args.iter().position(|arg| arg == "-cpu") {
+        Some(i) if i + 1 < args.len() => {
+            let has_flag = args[i + 1]
+                .split(',')
+                .any(|option| option.trim_start_matches('+') == flag);
+            if !has_flag {
+                args[i + 1] = format!("{},+{flag}", args[i + 1]);
+            }
+        }
+        _ => args.extend(["-cpu".to_string(), format!("host,+{flag}")]),
+    }
+
+    args.join(" ")
+}
+
+/// Lets the VM of a pack needing nested virtualization run VMs, or explains how to enable it
+pub fn with_nested_virt(qemu_command: &str) -> Result<String> {
+    let flag = host_nested_flag()?;
+
+    Ok(with_cpu_flag(qemu_command, flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_flag() {
+        assert_eq!(
+            with_cpu_flag("-m 4G -cpu host,migratable=on -smp 2", "vmx"),
+            "-m 4G -cpu host,migratable=on,+vmx -smp 2"
+        );
+        assert_eq!(with_cpu_flag("-cpu host,+svm", "svm"), "-cpu host,+svm");
+        assert_eq!(with_cpu_flag("-m 4G", "vmx"), "-m 4G -cpu host,+vmx");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::types::config::{AppPackConfig, NotificationLevel};
+use crate::utils::frontend::{frontend, message};
+use crate::utils::sandbox;
+
+/// Shows a desktop notification, unless notifications are disabled or below
+/// `notification_level`. Without a notification daemon, `notify-send` is tried, then the
+/// message is printed. Failing to notify never fails the caller.
+pub fn notify(config: &AppPackConfig, level: NotificationLevel, summary: &str, body: &str) {
+    tracing::info!(?level, summary, body, "Notification");
+    if !config.notifications() || level < config.notification_level() {
+        return;
+    }
+
+    let urgency = match level {
+        NotificationLevel::Info => notify_rust::Urgency::Low,
+        NotificationLevel::Warning => notify_rust::Urgency::Normal,
+        NotificationLevel::Error => notify_rust::Urgency::Critical,
+    };
+    let shown = notify_rust::Notification::new()
+        .appname("AppPack")
+        .summary(summary)
+        .body(body)
+        .urgency(urgency)
+        .show();
+    let Err(e) = shown else {
+        return;
+    };
+    tracing::debug!("Failed to show desktop notification: {e}");
+
+    let urgency = match level {
+        NotificationLevel::Info => "low",
+        NotificationLevel::Warning => "normal",
+        NotificationLevel::Error => "critical",
+    };
+    let sent = sandbox::command("notify-send")
+        .args([
+            "--app-name=AppPack",
+            &format!("--urgency={urgency}"),
+            summary,
+            body,
+        ])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !sent {
+        message!("{summary}: {body}");
+    }
+}
+
+/// Asks a question with a desktop notification having an `action` button, true when it is
+/// clicked. With notifications disabled or no notification daemon, the frontend asks it
+/// instead, which fails when nobody can answer.
+pub fn ask(
+    config: &AppPackConfig,
+    summary: &str,
+    body: &str,
+    action: &str,
+) -> anyhow::Result<bool> {
+    tracing::info!(summary, body, "Question");
+    if config.notifications() {
+        let shown = notify_rust::Notification::new()
+            .appname("AppPack")
+            .summary(summary)
+            .body(body)
+            .action("yes", action)
+            .show();
+        match shown {
+            Ok(handle) => {
+                let mut answer = false;
+                handle.wait_for_action(|clicked| answer = clicked == "yes");
+                return Ok(answer);
+            }
+            Err(e) => tracing::debug!("Failed to show desktop notification: {e}"),
+        }
+    }
+
+    frontend().confirm(&format!("{summary}: {body}"))
+}
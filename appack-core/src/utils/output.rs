@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::utils::frontend::frontend;
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::sync::{Mutex, OnceLock};
+
+/// Original stdout when `--json` is set, where only JSON lines are written
+static JSON_OUTPUT: OnceLock<Mutex<File>> = OnceLock::new();
+static JSON_RESULT: Mutex<Option<Value>> = Mutex::new(None);
+
+/// Switches to JSON output: stdout only receives JSON lines, and the usual human readable
+/// messages are sent to stderr instead.
+pub fn enable_json_output() -> anyhow::Result<()> {
+    let stdout = std::io::stdout().as_raw_fd();
+    let stderr = std::io::stderr().as_raw_fd();
+
+    // SAFETY: only standard file descriptors are duplicated, before any output is written
+    let json_output = unsafe {
+        let json_fd = libc::dup(stdout);
+        if json_fd < 0 || libc::dup2(stderr, stdout) < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to redirect stdout");
+        }
+        File::from_raw_fd(json_fd)
+    };
+
+    let _ = JSON_OUTPUT.set(Mutex::new(json_output));
+
+    Ok(())
+}
+
+pub fn json_enabled() -> bool {
+    JSON_OUTPUT.get().is_some()
+}
+
+fn write_json_line(value: &Value) {
+    if let Some(output) = JSON_OUTPUT.get() {
+        let mut output = output.lock().unwrap();
+        let _ = writeln!(output, "{value}");
+        let _ = output.flush();
+    }
+}
+
+/// Sets the data of the final JSON result of the command
+pub fn set_json_result<T: Serialize>(result: &T) -> anyhow::Result<()> {
+    if json_enabled() {
+        *JSON_RESULT.lock().unwrap() = Some(serde_json::to_value(result)?);
+    }
+
+    Ok(())
+}
+
+/// Reports the progress of a long command to the frontend, and as a JSON line
+/// e.g. `{"event": "vm_started"}`
+pub fn emit_event(event: &str, fields: Value) {
+    frontend().event(event, &fields);
+
+    if !json_enabled() {
+        return;
+    }
+
+    let mut line = json!({ "event": event });
+    if let (Some(line), Value::Object(fields)) = (line.as_object_mut(), fields) {
+        line.extend(fields);
+    }
+    write_json_line(&line);
+}
+
+/// Emits the last JSON line of a command, with its result or its error
+pub fn emit_json_outcome(outcome: &anyhow::Result<()>) {
+    let line = match outcome {
+        Ok(()) => json!({
+            "status": "ok",
+            "result": JSON_RESULT.lock().unwrap().take(),
+        }),
+        Err(e) => json!({
+            "status": "error",
+            "error": {
+                "kind": ErrorKind::of(e).name(),
+                "code": ErrorKind::of(e).exit_code(),
+                "message": e.to_string(),
+                "causes": e.chain().skip(1).map(|cause| cause.to_string()).collect::<Vec<_>>(),
+            },
+        }),
+    };
+    write_json_line(&line);
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::download::network_config;
+use crate::utils::sandbox;
+use anyhow::{Context, anyhow};
+use std::path::Path;
+
+/// Gateway used to fetch `ipfs://` URLs, a local IPFS node by default
+fn ipfs_gateway() -> String {
+    network_config()
+        .ipfs_gateway
+        .as_deref()
+        .unwrap_or("http://127.0.0.1:8080")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Maps `ipfs://<cid>` to the matching HTTP gateway URL
+pub fn ipfs_gateway_url(url: &str) -> Option<String> {
+    let cid = url.strip_prefix("ipfs://")?;
+    Some(format!("{}/ipfs/{cid}", ipfs_gateway()))
+}
+
+pub fn is_magnet_link(url: &str) -> bool {
+    url.starts_with("magnet:")
+}
+
+/// Downloads a single-file torrent with aria2c, which must be installed on the host
+pub fn download_magnet(
+    magnet: &str,
+    destination: &Path,
+    limit_rate: Option<u64>,
+) -> anyhow::Result<()> {
+    let directory = destination
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid destination {}", destination.display()))?;
+    let file_name = destination
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid destination {}", destination.display()))?;
+
+    sandbox::require("aria2c")?;
+    let mut command = sandbox::command("aria2c");
+    if let Some(limit_rate) = limit_rate {
+        command.arg(format!("--max-overall-download-limit={limit_rate}"));
+    }
+
+    let status = command
+        .arg("--seed-time=0")
+        .arg("--follow-torrent=mem")
+        .arg("--allow-overwrite=true")
+        .arg(format!("--dir={}", directory.display()))
+        .arg(format!("--index-out=1={}", file_name.to_string_lossy()))
+        .arg(magnet)
+        .status()
+        .context("Failed to run aria2c, install it to download packs over BitTorrent")?;
+
+    if !status.success() {
+        return Err(anyhow!("aria2c failed to download the torrent ({status})"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::frontend::{frontend, message};
+use crate::utils::i18n::tr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Answers yes to every confirmation, set by `--yes` or the `APPACK_NONINTERACTIVE` variable
+pub fn set_assume_yes() {
+    ASSUME_YES.store(true, Ordering::Relaxed);
+}
+
+fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+        || std::env::var("APPACK_NONINTERACTIVE")
+            .is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false" | "no"))
+}
+
+/// Asks the user to confirm an action, through the frontend.
+/// Fails instead of waiting for an answer when stdin is not a terminal, unless `--yes` is set.
+pub fn confirm(question: &str) -> anyhow::Result<bool> {
+    if assume_yes() {
+        message!("{}", tr!("prompt-assumed-yes", question = question));
+        return Ok(true);
+    }
+
+    frontend().confirm(question)
+}
+
+/// Asks the user to type `word` to confirm a dangerous action. `--yes` doesn't answer it,
+/// only `override_flag` does, so it fails when stdin is not a terminal.
+pub fn confirm_typed(question: &str, word: &str, override_flag: &str) -> anyhow::Result<bool> {
+    frontend().confirm_typed(question, word, override_flag)
+}
+
+/// Asks the user to pick one of `options`, by number. Fails when stdin is not a terminal, the
+/// choice has to be given as `argument` then.
+pub fn pick(question: &str, options: &[String], argument: &str) -> anyhow::Result<usize> {
+    frontend().pick(question, options, argument)
+}
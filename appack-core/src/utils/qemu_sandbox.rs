@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::utils::sandbox;
+
+/// Forbids the QEMU process system calls it doesn't need to run a guest, so a guest escaping
+/// into QEMU can't start programs or gain privileges
+const SECCOMP_OPTIONS: &str = "-sandbox on,obsolete=deny,elevateprivileges=deny,spawn=deny";
+
+/// Whether QEMU was built with seccomp, `-sandbox` is only listed then
+fn supports_seccomp(qemu_binary: &str) -> bool {
+    // The simulator takes any option
+    if sandbox::simulating() {
+        return true;
+    }
+
+    sandbox::command(qemu_binary)
+        .arg("-help")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("-sandbox"))
+}
+
+/// Options are added even if the pack sets them: QEMU applies every `-sandbox` given, so a
+/// `-sandbox off` of the pack can't turn the sandbox off. Monitors and `-sandbox` set by the
+/// pack are approved by the user at install, see `command_review`.
+fn confined(qemu_command: &str, seccomp: bool) -> String {
+    // Settings of the machine come from the pack only, not from /etc/qemu.
+    // The default monitor controls the whole VM from its display.
+    let mut confined = vec![qemu_command, "-no-user-config", "-monitor none"];
+    if seccomp {
+        confined.push(SECCOMP_OPTIONS);
+    }
+
+    confined.join(" ")
+}
+
+/// Adds the confinement options QEMU supports to the command of an app
+pub fn with_confinement(qemu_command: &str, qemu_binary: &str) -> String {
+    let seccomp = supports_seccomp(qemu_binary);
+    if !seccomp {
+        tracing::warn!("{qemu_binary} was built without seccomp, running it without -sandbox");
+    }
+
+    confined(qemu_command, seccomp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confined() {
+        assert_eq!(
+            confined("-m 4G", true),
+            format!("-m 4G -no-user-config -monitor none {SECCOMP_OPTIONS}")
+        );
+        assert_eq!(
+            confined("-m 4G -sandbox off", true),
+            format!("-m 4G -sandbox off -no-user-config -monitor none {SECCOMP_OPTIONS}")
+        );
+        assert_eq!(
+            confined("-m 4G", false),
+            "-m 4G -no-user-config -monitor none"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::app_config::{CpuPinning, UsbDevice};
+use crate::utils::sandbox;
+use anyhow::{Context, anyhow};
+use qapi::{Qmp, Stream, qmp};
+use std::io::{self, BufReader};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// How long a job runs between two reports of its progress
+const JOB_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress of a QMP job, in arbitrary units of the job
+#[derive(Debug, Clone, Copy)]
+pub struct JobProgress {
+    pub current: i64,
+    pub total: i64,
+}
+
+/// Whether the QMP socket has something to read within `timeout`, including what was already
+/// buffered
+fn wait_readable(
+    qmp: &Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    timeout: Duration,
+) -> io::Result<bool> {
+    if !qmp.inner().get_ref_read().buffer().is_empty() {
+        return Ok(true);
+    }
+
+    let mut poll_fd = libc::pollfd {
+        fd: qmp.inner().get_ref_write().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: poll only reads and writes the given pollfd
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) };
+    if ready < 0 {
+        let error = io::Error::last_os_error();
+        return match error.kind() {
+            io::ErrorKind::Interrupted => Ok(false),
+            _ => Err(error),
+        };
+    }
+
+    Ok(ready > 0)
+}
+
+/// Waits for a job to conclude, woken up by its `JOB_STATUS_CHANGE` events, then dismisses it.
+/// `on_progress` is called with its progress while it runs.
+fn wait_for_job(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    job_id: &str,
+    on_progress: &mut dyn FnMut(JobProgress),
+) -> anyhow::Result<()> {
+    loop {
+        let mut concluded = false;
+        for event in qmp.events() {
+            if let qmp::Event::JOB_STATUS_CHANGE { data, .. } = event
+                && data.id == job_id
+            {
+                tracing::debug!(job_id, status = ?data.status, "Job status changed");
+                concluded |= data.status == qmp::JobStatus::concluded;
+            }
+        }
+        if !concluded && wait_readable(qmp, JOB_PROGRESS_INTERVAL)? {
+            // Reads the events received meanwhile
+            qmp.nop().context("Failed to read QMP events")?;
+            continue;
+        }
+
+        // The job concluded, or its progress is due
+        let jobs = qmp
+            .execute(&qmp::query_jobs {})
+            .context("Failed to get jobs")?;
+        let job = jobs
+            .into_iter()
+            .find(|j| j.id == job_id)
+            .ok_or_else(|| anyhow!("Failed to find job with id '{job_id}'"))?;
+        tracing::trace!(?job, "Job status");
+        on_progress(JobProgress {
+            current: job.current_progress,
+            total: job.total_progress,
+        });
+
+        match job.status {
+            qmp::JobStatus::concluded => {
+                if let Err(e) = qmp.execute(&qmp::job_dismiss {
+                    id: job_id.to_string(),
+                }) {
+                    tracing::debug!("Failed to dismiss job '{job_id}': {e}");
+                }
+                return match job.error {
+                    Some(err) => Err(anyhow!(err)),
+                    None => Ok(()),
+                };
+            }
+            qmp::JobStatus::created
+            | qmp::JobStatus::running
+            | qmp::JobStatus::paused
+            | qmp::JobStatus::ready
+            | qmp::JobStatus::standby
+            | qmp::JobStatus::waiting
+            | qmp::JobStatus::pending => {}
+            _ => return Err(anyhow!("Job in unexpected state: {job:?}")),
+        }
+    }
+}
+
+pub fn take_snapshot_blocking(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    snapshot_name: &str,
+) -> anyhow::Result<()> {
+    take_snapshot_with_progress(qmp, snapshot_name, &mut |_| {})
+}
+
+/// Takes a snapshot of the running VM, reporting the progress of the job to `on_progress`
+pub fn take_snapshot_with_progress(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    snapshot_name: &str,
+    on_progress: &mut dyn FnMut(JobProgress),
+) -> anyhow::Result<()> {
+    let blocks = qmp
+        .execute(&qmp::query_block {})
+        .context("Failed to get block info")?;
+    let blocks = blocks
+        .iter()
+        .filter(|b| b.inserted.is_some())
+        .collect::<Vec<_>>();
+
+    if blocks.len() != 1 {
+        return Err(anyhow!(
+            "Expected 1 block device, got {} ({blocks:?})",
+            blocks.len()
+        ));
+    }
+
+    let block = &blocks[0];
+    let block_inserted = block
+        .inserted
+        .clone()
+        .context("BlockInfo does not contain 'inserted' data.")?;
+
+    let block_node_name = block_inserted
+        .node_name
+        .context("BlockDeviceInfo does not contain 'node_name'.")?;
+
+    let job_name = format!("{snapshot_name}-snapshot");
+
+    qmp.execute(&qmp::snapshot_save {
+        tag: snapshot_name.to_string(),
+        vmstate: block_node_name.clone(),
+        devices: [block_node_name.clone()].to_vec(),
+        job_id: job_name.clone(),
+    })
+    .context("Failed to make snapshot")?;
+
+    wait_for_job(qmp, &job_name, on_progress).context("Failed to take snapshot")?;
+    tracing::info!("Snapshot complete");
+
+    Ok(())
+}
+
+pub fn delete_snapshot_blocking(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    snapshot_name: &str,
+) -> anyhow::Result<()> {
+    delete_snapshot_with_progress(qmp, snapshot_name, &mut |_| {})
+}
+
+/// Deletes a snapshot of the running VM, reporting the progress of the job to `on_progress`
+pub fn delete_snapshot_with_progress(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    snapshot_name: &str,
+    on_progress: &mut dyn FnMut(JobProgress),
+) -> anyhow::Result<()> {
+    let blocks = qmp
+        .execute(&qmp::query_block {})
+        .context("Failed to get block info")?;
+    let blocks = blocks
+        .iter()
+        .filter(|b| b.inserted.is_some())
+        .collect::<Vec<_>>();
+
+    if blocks.len() != 1 {
+        return Err(anyhow!(
+            "Expected 1 block device, got {} ({blocks:?})",
+            blocks.len()
+        ));
+    }
+
+    let block = &blocks[0];
+    let block_inserted = block
+        .inserted
+        .clone()
+        .context("BlockInfo does not contain 'inserted' data.")?;
+
+    if let Some(snapshots) = block_inserted.image.base.snapshots {
+        let is_snapshot_present = snapshots
+            .iter()
+            .any(|snapshot| snapshot.name == snapshot_name);
+
+        if !is_snapshot_present {
+            return Err(anyhow!("Cannot delete snapshot {snapshot_name}")
+                .context("Failed to delete snapshot, it is not found."));
+        }
+    }
+
+    let block_node_name = block_inserted
+        .node_name
+        .context("BlockDeviceInfo does not contain 'node_name'.")?;
+
+    let job_name = format!("{snapshot_name}-del-snapshot");
+
+    qmp.execute(&qmp::snapshot_delete {
+        tag: snapshot_name.to_string(),
+        devices: [block_node_name.clone()].to_vec(),
+        job_id: job_name.clone(),
+    })
+    .context("Failed to make snapshot")?;
+
+    wait_for_job(qmp, &job_name, on_progress).context("Failed to delete snapshot")?;
+    tracing::info!("Snapshot '{snapshot_name}' deleted");
+
+    Ok(())
+}
+
+pub fn has_snapshot_qmp(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    snapshot_name: &str,
+) -> anyhow::Result<bool> {
+    let blocks = qmp
+        .execute(&qmp::query_block {})
+        .context("Failed to get block info")?;
+    let blocks = blocks
+        .iter()
+        .filter(|b| b.inserted.is_some())
+        .collect::<Vec<_>>();
+
+    if blocks.len() != 1 {
+        return Err(anyhow!(
+            "Expected 1 block device, got {} ({blocks:?})",
+            blocks.len()
+        ));
+    }
+
+    let block = &blocks[0];
+    let block_inserted = block
+        .inserted
+        .clone()
+        .context("BlockInfo does not contain 'inserted' data.")?;
+
+    if let Some(snapshots) = block_inserted.image.base.snapshots {
+        let is_snapshot_present = snapshots
+            .iter()
+            .any(|snapshot| snapshot.name == snapshot_name);
+
+        if is_snapshot_present {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Id of the passed through USB device, to remove it later
+fn usb_device_id(index: usize) -> String {
+    format!("appack-usb-{index}")
+}
+
+/// Passes USB devices of the host through to the running VM
+pub fn attach_usb_devices(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    devices: &[UsbDevice],
+) -> anyhow::Result<()> {
+    for (index, device) in devices.iter().enumerate() {
+        let mut arguments = serde_json::Map::new();
+        arguments.insert(
+            "vendorid".to_string(),
+            format!("0x{:04x}", device.vendor_id).into(),
+        );
+        arguments.insert(
+            "productid".to_string(),
+            format!("0x{:04x}", device.product_id).into(),
+        );
+
+        qmp.execute(&qmp::device_add {
+            driver: "usb-host".to_string(),
+            id: Some(usb_device_id(index)),
+            bus: None,
+            arguments,
+        })
+        .context(format!(
+            "Failed to pass USB device {:04x}:{:04x} through",
+            device.vendor_id, device.product_id
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Gives the USB devices back to the host, they can't be part of a snapshot
+pub fn detach_usb_devices(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    count: usize,
+) {
+    for index in 0..count {
+        // Devices that failed to attach can't be removed either
+        if let Err(e) = qmp.execute(&qmp::device_del {
+            id: usb_device_id(index),
+        }) {
+            tracing::debug!("Failed to remove USB device {}: {e}", usb_device_id(index));
+        }
+    }
+}
+
+/// Pins the threads of the virtual CPUs to host CPUs, with `taskset` as QEMU may run outside
+/// of the sandbox
+pub fn pin_vcpus(
+    qmp: &mut Qmp<Stream<BufReader<&UnixStream>, &UnixStream>>,
+    pinning: &CpuPinning,
+) -> anyhow::Result<()> {
+    let cpus = qmp
+        .execute(&qmp::query_cpus_fast {})
+        .context("Failed to list the virtual CPUs")?;
+    for cpu in cpus {
+        let cpu = serde_json::to_value(cpu)?;
+        let (Some(index), Some(thread_id)) = (cpu["cpu-index"].as_u64(), cpu["thread-id"].as_i64())
+        else {
+            continue;
+        };
+        let host_cpu = pinning.host_cpu(index as usize);
+        let output = sandbox::command("taskset")
+            .args(["-p", "-c", &host_cpu.to_string(), &thread_id.to_string()])
+            .output()
+            .context("Failed to run taskset, is util-linux installed?")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to pin virtual CPU {index} to CPU {host_cpu}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        tracing::debug!(index, host_cpu, "Pinned virtual CPU");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::anyhow;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// Parses a size in bytes, with an optional K, M, G or T suffix (e.g. `500K`, `20G`)
+pub fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let size = size.trim();
+    let (number, multiplier) = match size.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&size[..size.len() - 1], 1024),
+        Some('M') => (&size[..size.len() - 1], 1024 * 1024),
+        Some('G') => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        Some('T') => (&size[..size.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+
+    let number: u64 = number.parse().map_err(|_| {
+        anyhow!("Invalid size '{size}', expected a number of bytes like 500K or 20G")
+    })?;
+
+    Ok(number * multiplier)
+}
+
+/// Parses a transfer rate in bytes per second, with an optional K, M or G suffix (e.g. `500K`, `2M`)
+pub fn parse_rate(rate: &str) -> anyhow::Result<u64> {
+    let rate = parse_size(rate).map_err(|_| {
+        anyhow!("Invalid rate '{rate}', expected a number of bytes per second like 500K or 2M")
+    })?;
+    if rate == 0 {
+        return Err(anyhow!("Rate must be greater than zero"));
+    }
+
+    Ok(rate)
+}
+
+/// Wraps a reader so that it is not read faster than `bytes_per_second`
+pub struct RateLimitedReader<R> {
+    inner: R,
+    bytes_per_second: Option<u64>,
+    started: Instant,
+    transferred: u64,
+}
+
+impl<R: Read> RateLimitedReader<R> {
+    pub fn new(inner: R, bytes_per_second: Option<u64>) -> Self {
+        Self {
+            inner,
+            bytes_per_second,
+            started: Instant::now(),
+            transferred: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for RateLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(bytes_per_second) = self.bytes_per_second else {
+            return self.inner.read(buf);
+        };
+
+        // Small reads keep the rate smooth even for low limits
+        let max_len = buf.len().min((bytes_per_second as usize / 10).max(1));
+        let read = self.inner.read(&mut buf[..max_len])?;
+        self.transferred += read as u64;
+
+        let expected = Duration::from_secs_f64(self.transferred as f64 / bytes_per_second as f64);
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+
+        Ok(read)
+    }
+}
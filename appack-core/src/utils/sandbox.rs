@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::utils::i18n::tr;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Packaging sandboxes AppPack can run in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sandbox {
+    Snap,
+    Flatpak,
+    None,
+}
+
+pub fn get_sandbox() -> Sandbox {
+    static SANDBOX_CACHE: OnceLock<Sandbox> = OnceLock::new();
+
+    *SANDBOX_CACHE.get_or_init(|| {
+        if std::env::var_os("FLATPAK_ID").is_some() {
+            Sandbox::Flatpak
+        } else if std::env::var_os("SNAP").is_some() {
+            Sandbox::Snap
+        } else {
+            Sandbox::None
+        }
+    })
+}
+
+/// Home directory of the user, outside of the sandbox
+pub fn real_home() -> anyhow::Result<PathBuf> {
+    let variable = match get_sandbox() {
+        Sandbox::Snap => "SNAP_REAL_HOME",
+        Sandbox::Flatpak | Sandbox::None => "HOME",
+    };
+
+    std::env::var_os(variable)
+        .map(PathBuf::from)
+        .context(format!("{variable} is not set"))
+}
+
+/// Directory of the files shipped with AppPack, like the creator templates
+pub fn assets_dir() -> anyhow::Result<PathBuf> {
+    match get_sandbox() {
+        Sandbox::Flatpak => Ok(PathBuf::from("/app/share/appack/assets")),
+        Sandbox::Snap | Sandbox::None => {
+            let snap = std::env::var("SNAP").context("Failed to get assets path")?;
+            Ok(Path::new(&snap).join("assets"))
+        }
+    }
+}
+
+/// Command line running `appack` from outside the sandbox, used in desktop entries
+pub fn appack_command() -> String {
+    match (get_sandbox(), std::env::var("FLATPAK_ID")) {
+        (Sandbox::Flatpak, Ok(id)) => format!("flatpak run --command=appack {id}"),
+        _ => "appack".to_string(),
+    }
+}
+
+/// Runs the programs AppPack relies on, like QEMU, qemu-img and FreeRDP. Replaced with
+/// `set_executor`, e.g. by the `Simulator` in tests.
+pub trait Executor: Send + Sync {
+    /// Creates a command running `program`, with `env` added to its environment
+    fn command(&self, program: &str, env: &[(&str, &str)]) -> Command;
+
+    /// Whether `program` can be run without being installed
+    fn provides(&self, _program: &str) -> bool {
+        false
+    }
+
+    /// Whether the VMs are stand-ins, which run on any machine and take any QEMU option
+    fn simulates_vms(&self) -> bool {
+        false
+    }
+}
+
+/// Runs the programs of the machine. In Flatpak, programs that are not shipped in the
+/// sandbox, like QEMU or FreeRDP, are run on the host with `flatpak-spawn`.
+pub struct HostExecutor;
+
+impl Executor for HostExecutor {
+    fn command(&self, program: &str, env: &[(&str, &str)]) -> Command {
+        if get_sandbox() == Sandbox::Flatpak && !in_path(program) {
+            let mut command = Command::new("flatpak-spawn");
+            command
+                .arg("--host")
+                .args(env.iter().map(|(key, value)| format!("--env={key}={value}")))
+                .arg(program);
+            return command;
+        }
+
+        let mut command = Command::new(program);
+        command.envs(env.iter().copied());
+        command
+    }
+}
+
+/// Replaces QEMU, qemu-img and FreeRDP with `appack simulate`, so AppPack runs as usual
+/// without a hypervisor or a display
+pub struct Simulator {
+    /// The `appack` binary
+    pub appack: PathBuf,
+}
+
+impl Simulator {
+    fn is_simulated(program: &str) -> bool {
+        let name = Path::new(program)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(program);
+        name.starts_with("qemu-") || name.contains("freerdp")
+    }
+}
+
+impl Executor for Simulator {
+    fn command(&self, program: &str, env: &[(&str, &str)]) -> Command {
+        if !Self::is_simulated(program) {
+            return HostExecutor.command(program, env);
+        }
+
+        let mut command = Command::new(&self.appack);
+        command.arg("simulate").arg(program).envs(env.iter().copied());
+        command
+    }
+
+    fn provides(&self, program: &str) -> bool {
+        Self::is_simulated(program)
+    }
+
+    fn simulates_vms(&self) -> bool {
+        true
+    }
+}
+
+static EXECUTOR: OnceLock<Box<dyn Executor>> = OnceLock::new();
+
+/// Runs programs with `executor` instead of the `HostExecutor`. Set it once, before calling
+/// the library.
+pub fn set_executor(executor: impl Executor + 'static) {
+    let _ = EXECUTOR.set(Box::new(executor));
+}
+
+fn executor() -> &'static dyn Executor {
+    EXECUTOR.get_or_init(|| Box::new(HostExecutor)).as_ref()
+}
+
+/// Whether QEMU and FreeRDP are replaced by stand-ins, see `Simulator`
+pub fn simulating() -> bool {
+    executor().simulates_vms()
+}
+
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// FreeRDP clients, any FreeRDP 3 one can be used with the `rdp_client` setting
+const FREERDP_CLIENTS: [&str; 5] = [
+    "xfreerdp3",
+    "sdl-freerdp3",
+    "wlfreerdp3",
+    "xfreerdp",
+    "wlfreerdp",
+];
+
+fn is_installed(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    in_path(program)
+}
+
+/// `3.5.1` in `This is FreeRDP version 3.5.1 (n/a)`
+fn freerdp_version(version_output: &str) -> Option<&str> {
+    version_output
+        .split("version ")
+        .nth(1)?
+        .split_whitespace()
+        .next()
+}
+
+/// Why a FreeRDP client is missing, and which installed one can replace it
+fn freerdp_hint(program: &str) -> String {
+    for client in FREERDP_CLIENTS {
+        if client == program || !in_path(client) {
+            continue;
+        }
+
+        let output = Command::new(client).arg("--version").output();
+        let output = output
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            .unwrap_or_default();
+        let version = freerdp_version(&output).unwrap_or("?");
+        if version.starts_with('3') {
+            return tr!("hint-freerdp-alternative", program = program, alternative = client);
+        }
+        return tr!(
+            "hint-freerdp-unsupported",
+            program = program,
+            alternative = client,
+            version = version
+        );
+    }
+
+    tr!("hint-freerdp-not-found", program = program)
+}
+
+/// Checks `program` is installed before running it, and explains what to install otherwise
+pub fn require(program: &str) -> anyhow::Result<()> {
+    // Programs missing from the Flatpak sandbox run on the host, out of sight
+    if executor().provides(program)
+        || get_sandbox() == Sandbox::Flatpak
+        || is_installed(program)
+    {
+        return Ok(());
+    }
+
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    let hint = if name.contains("freerdp") {
+        freerdp_hint(program)
+    } else if name.starts_with("qemu-") {
+        tr!("hint-qemu-not-found", binary = program)
+    } else {
+        tr!("hint-program-not-found", program = program)
+    };
+
+    Err(ErrorKind::EnvironmentMissing.error(hint))
+}
+
+/// Creates a command running `program` with the executor, see `HostExecutor`
+pub fn command(program: &str) -> Command {
+    command_with_env(program, &[])
+}
+
+/// Like `command`, with environment variables `flatpak-spawn` passes on to the host
+pub fn command_with_env(program: &str, env: &[(&str, &str)]) -> Command {
+    executor().command(program, env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freerdp_version() {
+        let output = "This is FreeRDP version 2.11.5 (2.11.5)\nBuild configuration: ...";
+        assert_eq!(freerdp_version(output), Some("2.11.5"));
+        assert_eq!(freerdp_version("xfreerdp: unknown option"), None);
+    }
+
+    #[test]
+    fn test_simulator() {
+        let simulator = Simulator {
+            appack: PathBuf::from("/usr/bin/appack"),
+        };
+        let qemu = simulator.command("qemu-system-x86_64", &[("APPACK_RDP_PORT", "3389")]);
+        assert_eq!(qemu.get_program(), "/usr/bin/appack");
+        assert_eq!(qemu.get_args().collect::<Vec<_>>(), ["simulate", "qemu-system-x86_64"]);
+        assert!(simulator.provides("/usr/bin/xfreerdp3"));
+        assert!(!simulator.provides("ssh"));
+    }
+}
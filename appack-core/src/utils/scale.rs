@@ -0,0 +1,75 @@
+use crate::utils::sandbox;
+use anyhow::{Result, anyhow};
+use std::env;
+
+/// Scale factors the session may use, in percent
+const SCALE_RANGE: std::ops::RangeInclusive<u32> = 100..=500;
+
+/// Checks a scale factor for the command line, in percent like `150` or `150%`
+pub fn parse_scale(scale: &str) -> Result<u32> {
+    scale
+        .trim_end_matches('%')
+        .parse::<u32>()
+        .ok()
+        .filter(|percent| SCALE_RANGE.contains(percent))
+        .ok_or_else(|| anyhow!("Invalid scale '{scale}', expected a percentage from 100 to 500"))
+}
+
+/// FreeRDP options scaling the session. Windows only scales devices like the mouse
+/// pointer by 100, 140 or 180%, the closest one is used.
+pub fn freerdp_args(percent: u32) -> Vec<String> {
+    let device = [100u32, 140, 180]
+        .into_iter()
+        .min_by_key(|device| device.abs_diff(percent))
+        .unwrap_or(100);
+
+    vec![
+        format!("/scale:{device}"),
+        format!("/scale-desktop:{percent}"),
+    ]
+}
+
+/// Scale factor of this machine in percent, from the toolkit settings or the X resources
+pub fn host_scale() -> Option<u32> {
+    let env_scale = |var: &str| env::var(var).ok()?.trim().parse::<f64>().ok();
+    let toolkit = match (env_scale("GDK_SCALE"), env_scale("GDK_DPI_SCALE")) {
+        (None, None) => env_scale("QT_SCALE_FACTOR"),
+        (scale, dpi_scale) => Some(scale.unwrap_or(1.0) * dpi_scale.unwrap_or(1.0)),
+    };
+    let factor = toolkit.or_else(xft_scale)?;
+
+    let percent = (factor * 100.0).round() as u32;
+    Some(percent.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end()))
+}
+
+/// `Xft.dpi` against the 96 DPI of an unscaled screen
+fn xft_scale() -> Option<f64> {
+    env::var_os("DISPLAY")?;
+    let output = sandbox::command("xrdb")
+        .arg("-query")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let dpi = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Xft.dpi:"))?
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+
+    Some(dpi / 96.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_closest_device_scale() {
+        assert_eq!(freerdp_args(200), ["/scale:180", "/scale-desktop:200"]);
+        assert_eq!(freerdp_args(125), ["/scale:140", "/scale-desktop:125"]);
+        assert_eq!(parse_scale("150%").unwrap(), 150);
+        assert!(parse_scale("50").is_err());
+    }
+}
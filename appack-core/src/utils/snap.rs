@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::error::ErrorKind;
+use crate::utils::i18n::tr;
+use crate::utils::sandbox::{Sandbox, get_sandbox};
+use anyhow::Result;
+use std::process::{Command, Stdio};
+
+/// Plugs of the snap that apps can't run without
+const REQUIRED_PLUGS: [&str; 5] = ["kvm", "desktop", "home", "network", "audio-playback"];
+
+/// Whether a plug of the snap is connected, `None` when `snapctl` can't tell
+fn is_connected(plug: &str) -> Option<bool> {
+    let status = Command::new("snapctl")
+        .args(["is-connected", plug])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    match status.code() {
+        Some(0) => Some(true),
+        Some(1) => Some(false),
+        _ => None,
+    }
+}
+
+/// Required plugs that are not connected, none outside of the snap
+pub fn missing_plugs() -> Vec<&'static str> {
+    if get_sandbox() != Sandbox::Snap {
+        return Vec::new();
+    }
+
+    REQUIRED_PLUGS
+        .into_iter()
+        .filter(|plug| is_connected(plug) == Some(false))
+        .collect()
+}
+
+/// `snap connect` command line connecting a plug
+pub fn connect_command(plug: &str) -> String {
+    // Parallel installs of the snap are named like `appack_beta`
+    let snap = std::env::var("SNAP_INSTANCE_NAME").unwrap_or_else(|_| "appack".to_string());
+    format!("sudo snap connect {snap}:{plug}")
+}
+
+/// Checks the plugs apps need are connected, and tells how to connect them otherwise
+pub fn require_plugs() -> Result<()> {
+    let missing = missing_plugs();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let commands: Vec<String> = missing.iter().map(|plug| connect_command(plug)).collect();
+    Err(ErrorKind::EnvironmentMissing.error(tr!(
+        "hint-snap-plugs",
+        plugs = missing.join(", "),
+        commands = commands.join("\n")
+    )))
+}
@@ -0,0 +1,98 @@
+use crate::types::app_config::SharedFolder;
+use crate::utils::sandbox;
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time virtiofsd has to create its socket
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// virtiofsd daemons exporting folders to a VM, stopped when dropped
+pub struct VirtiofsExports {
+    daemons: Vec<Child>,
+    sockets: Vec<PathBuf>,
+}
+
+impl Drop for VirtiofsExports {
+    fn drop(&mut self) {
+        for daemon in &mut self.daemons {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+        }
+        for socket in &self.sockets {
+            let _ = std::fs::remove_file(socket);
+        }
+    }
+}
+
+fn socket_name(share: &SharedFolder) -> String {
+    format!("virtiofs-{}.sock", share.name)
+}
+
+/// Qemu options adding the exports of `start_exports` to the VM. The folders are tagged
+/// with their name, which the guest mounts.
+/// virtiofs needs the memory of the VM to be shared with virtiofsd, hence `memory_mb`.
+pub fn qemu_args(shares: &[SharedFolder], memory_mb: u64) -> String {
+    let mut args = format!(
+        "-object memory-backend-memfd,id=appack-mem,size={memory_mb}M,share=on \
+         -machine memory-backend=appack-mem"
+    );
+    for share in shares {
+        let id = format!("appack-fs-{}", share.name);
+        args.push_str(&format!(
+            " -chardev socket,id={id},path={} -device vhost-user-fs-pci,chardev={id},tag={}",
+            socket_name(share),
+            share.name
+        ));
+    }
+
+    args
+}
+
+/// Starts a virtiofsd per folder, with its socket in the app home
+pub fn start_exports(
+    app_home: &Path,
+    shares: &[SharedFolder],
+    log: &File,
+) -> Result<VirtiofsExports> {
+    let mut exports = VirtiofsExports {
+        daemons: Vec::new(),
+        sockets: Vec::new(),
+    };
+
+    if !shares.is_empty() {
+        sandbox::require("virtiofsd")?;
+    }
+    for share in shares {
+        let path = std::fs::canonicalize(&share.path)
+            .context(format!("Shared folder {} not found", share.path.display()))?;
+        let socket = socket_name(share);
+        let _ = std::fs::remove_file(app_home.join(&socket));
+
+        let daemon = sandbox::command("virtiofsd")
+            .current_dir(app_home)
+            .arg(format!("--socket-path={socket}"))
+            .arg("--shared-dir")
+            .arg(&path)
+            .args(["--sandbox=none", "--cache=auto"])
+            .stdout(log.try_clone()?)
+            .stderr(log.try_clone()?)
+            .spawn()
+            .context("Failed to start virtiofsd")?;
+        exports.daemons.push(daemon);
+        exports.sockets.push(app_home.join(&socket));
+
+        let started = Instant::now();
+        while !app_home.join(&socket).exists() {
+            if started.elapsed() > SOCKET_TIMEOUT {
+                return Err(anyhow!("virtiofsd didn't start for {}", path.display()));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    Ok(exports)
+}
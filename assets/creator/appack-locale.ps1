@@ -0,0 +1,11 @@
+# Applies the regional format of the AppPack user, e.g. dates and currencies, to the session.
+# AppPack writes it in host-locale.txt and runs the "AppPack locale" task, see README.md.
+$file = 'C:\ProgramData\AppPack\host-locale.txt'
+if (-not (Test-Path $file)) {
+    exit
+}
+
+$locale = (Get-Content $file -TotalCount 1).Trim()
+if ($locale -and (Get-Culture).Name -ne $locale) {
+    Set-Culture $locale
+}
@@ -0,0 +1,11 @@
+# Opens a link in the browser of the AppPack user instead of a browser of the VM.
+# Register it as the handler of http, https and mailto links, see README.md.
+param([Parameter(Mandatory = $true)][string]$Url)
+
+$port = [System.IO.File]::OpenWrite('\\.\Global\org.appack.urls')
+try {
+    $line = [System.Text.Encoding]::UTF8.GetBytes("$Url`n")
+    $port.Write($line, 0, $line.Length)
+} finally {
+    $port.Close()
+}
@@ -1,25 +0,0 @@
-// SPDX-License-Identifier: GPL-3.0-only
-// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
-//
-// This program is free software: you can redistribute it and/or modify
-// it under the terms of the GNU General Public License as published by
-// the Free Software Foundation, version 3.
-//
-// This program is distributed in the hope that it will be useful,
-// but WITHOUT ANY WARRANTY; without even the implied warranty of
-// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
-// GNU General Public License for more details.
-//
-// You should have received a copy of the GNU General Public License
-// along with this program. If not, see <https://www.gnu.org/licenses/>.
-
-use crate::types::local_settings::AppPackLocalSettings;
-use anyhow::Result;
-
-pub fn list_installed(settings: AppPackLocalSettings) -> Result<()> {
-    let installed_apps = settings.get_installed()?;
-    println!("Installed app packs:");
-    println!("{:#?}", installed_apps); // Todo impl display or something
-
-    Ok(())
-}
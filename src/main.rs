@@ -13,43 +13,135 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-mod internal;
-mod types;
-mod utils;
+mod ui;
 
-use crate::internal::creator::{
-    creator_boot, creator_boot_install, creator_new, creator_pack, creator_snapshot,
-};
-use crate::internal::info::print_info;
-use crate::internal::install_appack::install_appack;
-use crate::internal::launch::launch;
-use crate::internal::list_installed::list_installed;
-use crate::internal::reset::reset;
-use crate::internal::uninstall_appack::{uninstall_all_appacks, uninstall_appack};
-use crate::internal::version::print_version;
-use crate::types::local_settings::AppPackLocalSettings;
-use crate::utils::logger::log_debug;
 use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use appack_core::internal::alias::{alias_add, alias_list, alias_remove, resolve_alias};
+use appack_core::internal::apply::apply;
+use appack_core::internal::autostart::{AutostartOptions, autostart_disable, autostart_enable};
+use appack_core::internal::cache::{cache_clean, cache_list, cache_path};
+use appack_core::internal::config::{
+    app_config_get, app_config_set, app_config_unset, config_get, config_set, config_unset,
+};
+use appack_core::internal::creator::{
+    UnattendOptions, creator_boot, creator_boot_install, creator_image, creator_new, creator_pack,
+    creator_snapshot,
+};
+use appack_core::internal::desktop::{
+    entry_rdp_args, list_entries, regenerate_desktop_entries, sync_system_entries,
+};
+use appack_core::internal::doctor::doctor;
+use appack_core::internal::console::console;
+use appack_core::internal::backup::{backup, restore};
+use appack_core::internal::balloon::balloon;
+use appack_core::internal::simulate::simulate;
+use appack_core::internal::events::events;
+use appack_core::internal::info::print_info;
+use appack_core::internal::install_appack::{
+    download_from_sources, install_appack, set_trust_desktop_entries,
+};
+use appack_core::internal::launch::{
+    ClientOptions, RemoteOptions, launch, parse_window_size, print_launch_command,
+};
+use appack_core::internal::list_installed::{list_installed, pick_installed_app};
+use appack_core::internal::logs::{DEFAULT_LOG_RETENTION_DAYS, logs};
+use appack_core::internal::repo::{
+    repo_add, repo_keygen, repo_keys, repo_list, repo_remove, repo_sign, repo_sync, repo_trust,
+    repo_untrust,
+};
+use appack_core::internal::publish::{PublishOptions, publish};
+use appack_core::internal::pool::verify;
+use appack_core::internal::prune::prune;
+use appack_core::internal::quota::du;
+use appack_core::internal::rdp_file::export_rdp_file;
+use appack_core::internal::recover::recover;
+use appack_core::internal::relocate::relocate;
+use appack_core::internal::remote::launch_remote;
+use appack_core::internal::reset::reset;
+use appack_core::internal::retention::gc;
+use appack_core::internal::readme::readme;
+use appack_core::internal::screenshot::screenshot;
+use appack_core::internal::search::search;
+use appack_core::internal::report::report;
+use appack_core::internal::self_update::self_update;
+use appack_core::internal::stats::stats;
+use appack_core::internal::stop::stop;
+use appack_core::internal::system::system_settings;
+use appack_core::internal::top::top;
+use appack_core::internal::uninstall_appack::{uninstall_all_appacks, uninstall_appack};
+use appack_core::internal::update::update;
+use appack_core::internal::version::print_version;
+use appack_core::types::{CreatorTemplate, SharedDirsMode};
+use appack_core::types::app_config::{MonitorSelection, SharedFolder};
+use appack_core::types::error::ErrorKind;
+use appack_core::types::local_settings::AppPackLocalSettings;
+use appack_core::types::repository::{RepositoryPublishBackend, RepositoryPublishConfig};
+use appack_core::utils::download::{NetworkConfig, configure_network};
+use appack_core::utils::keyboard;
+use appack_core::utils::scale;
+use appack_core::utils::logger::{FILE_ONLY_TARGET, console_log_level, init_logging};
+use appack_core::utils::output::{emit_json_outcome, enable_json_output};
+use appack_core::utils::prompt::set_assume_yes;
+use appack_core::utils::rate_limit::parse_rate;
+use appack_core::utils::sandbox::{Simulator, set_executor};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
     #[clap(subcommand)]
     action: CliAction,
+    /// Maximum transfer rate of pack downloads and uploads in bytes per second, e.g. 500K or 2M
+    #[clap(long, global = true, value_parser = parse_rate)]
+    limit_rate: Option<u64>,
+    /// Log level of the console: error, warn, info, debug or trace. All logs from debug level are
+    /// also written to the logs directory
+    #[clap(long, global = true)]
+    log_level: Option<LevelFilter>,
+    /// Show more logs, can be repeated
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Print results, progress events and errors as JSON lines on stdout.
+    /// Other messages are printed on stderr.
+    #[clap(long, global = true)]
+    json: bool,
+    /// Answer yes to confirmations instead of asking, for scripts.
+    /// Also enabled by the APPACK_NONINTERACTIVE environment variable.
+    #[clap(short, long, visible_alias = "non-interactive", global = true)]
+    yes: bool,
+    /// Install desktop entries running another command than `appack launch` without asking.
+    /// `--yes` doesn't, as they may run anything.
+    #[clap(long, global = true)]
+    trust_desktop_entries: bool,
+    /// Manage the AppPacks installed for every user of the machine instead of your own ones.
+    /// Requires root.
+    #[clap(long, global = true)]
+    system: bool,
 }
 
 #[derive(Debug, Subcommand)]
 enum CliAction {
     #[clap(alias = "i")]
     Install {
-        file: PathBuf,
+        /// Path or URL of an AppPack file, or id of an AppPack from a synced repository
+        pack: String,
+        #[clap(long)]
+        version: Option<String>,
+        #[clap(long)]
+        repo: Option<String>,
+        /// Install the app even if this machine can only emulate it, which is very slow
+        #[clap(long)]
+        force: bool,
     },
 
     #[clap(alias = "u")]
     Uninstall {
-        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        /// Id of the app, picked among the installed ones when not given
+        #[arg(conflicts_with = "all")]
         id: Option<String>,
         #[arg(requires = "id")]
         version: Option<String>,
@@ -58,71 +150,639 @@ enum CliAction {
         all: bool,
     },
 
+    /// Update an app, or all installed apps, to the newest repository version
+    Update {
+        id: Option<String>,
+    },
+
+    /// Save the installed apps, their data and your settings to a tar file, e.g. to move them
+    /// to another machine
+    Backup {
+        #[clap(short, long)]
+        output: PathBuf,
+        /// Leave the disk images out, the apps are installed again from the repositories at
+        /// restore, without their data
+        #[clap(long)]
+        without_images: bool,
+    },
+
+    /// Reinstate the apps and settings of a backup made by `appack backup`
+    Restore {
+        file: PathBuf,
+    },
+
+    /// Install, update and optionally uninstall apps to match a manifest file
+    Apply {
+        manifest: PathBuf,
+        /// Also uninstall the apps not listed in the manifest
+        #[clap(long)]
+        prune: bool,
+    },
+
     Creator {
+        #[clap(subcommand)]
         action: CliCreatorAction,
     },
 
+    Repo {
+        #[clap(subcommand)]
+        action: CliRepoAction,
+    },
+
+    /// Give installed apps short names, e.g. `appack launch ps`
+    Alias {
+        #[clap(subcommand)]
+        action: CliAliasAction,
+    },
+
+    /// Find AppPacks in the synced repositories by name, description, category or keyword
+    Search {
+        #[clap(default_value = "")]
+        query: String,
+        /// Only list the AppPacks of this menu category, e.g. Office
+        #[clap(long)]
+        category: Option<String>,
+    },
+
+    /// Start an app when you log in
+    Autostart {
+        #[clap(subcommand)]
+        action: CliAutostartAction,
+    },
+
+    /// Manage the desktop entries of installed apps
+    Desktop {
+        #[clap(subcommand)]
+        action: CliDesktopAction,
+    },
+
+    /// List the desktop entries of an app with the command each one runs, and flag the ones
+    /// changed after installation
+    Entries {
+        /// Id of the app, picked among the installed ones when not given
+        id: Option<String>,
+        #[clap(long, requires = "id")]
+        version: Option<String>,
+    },
+
+    /// Sign and upload a pack to a repository, updating its index
+    Publish {
+        file: PathBuf,
+        #[clap(long)]
+        repo: String,
+        /// Secret key used to sign the pack and the index
+        #[clap(long)]
+        key: PathBuf,
+        /// Alternative URL of the pack, can be repeated
+        #[clap(long = "mirror")]
+        mirrors: Vec<String>,
+        /// IPFS CID of the pack, if it is also shared over IPFS
+        #[clap(long)]
+        ipfs: Option<String>,
+        /// Magnet link of the pack, if it is also shared over BitTorrent
+        #[clap(long)]
+        magnet: Option<String>,
+        /// Release channel of the pack, stable by default
+        #[clap(long)]
+        channel: Option<String>,
+    },
+
+    /// Get or change global settings, or settings of an app
+    Config {
+        #[clap(subcommand)]
+        action: CliConfigAction,
+    },
+
+    /// Manage downloaded packs kept for later installs
+    Cache {
+        #[clap(subcommand)]
+        action: CliCacheAction,
+    },
+
     #[clap(alias = "li")]
     ListInstalled,
 
-    Launch {
+    /// Browse, launch and manage the installed apps in a terminal dashboard
+    Ui,
+
+    /// Print the events of a running app, like shutdowns, resets or disk errors
+    Events {
+        id: String,
+        #[clap(long)]
+        version: Option<String>,
+    },
+
+    /// Attach the terminal to the serial console of a running app, Ctrl+] detaches
+    Console {
         id: String,
+        #[clap(long)]
+        version: Option<String>,
+    },
+
+    /// Show the CPU, memory, disk and balloon usage of the running apps
+    Top {
+        /// Seconds between updates
+        #[clap(long, default_value_t = 2)]
+        interval: u64,
+        /// Print the usage once instead of updating it
+        #[clap(long)]
+        once: bool,
+    },
+
+    /// Show how long apps take to start and to save their state
+    Stats { id: Option<String> },
+
+    /// Show the disk space used by apps and the cache, against the quotas
+    Du,
+
+    /// Delete the saved states and logs of apps beyond keep_snapshots and log_retention_days
+    Gc { id: Option<String> },
+
+    /// Check that the images shared between installs are intact
+    Verify,
+
+    /// Uninstall the versions of apps older than the newest ones, keep_versions (1) by default
+    Prune {
+        id: Option<String>,
+
+        /// Versions of each app to keep
+        #[arg(long)]
+        keep: Option<u32>,
+
+        /// Move the saved state of the last removed version to the newest one, when both have
+        /// the same image
+        #[arg(long)]
+        migrate_state: bool,
+    },
+
+    /// Check that apps can run on this machine, and tell how to fix what is missing
+    Doctor,
+
+    /// Check for a new release of AppPack, and install it with --apply. Snap and Flatpak
+    /// installs are updated by their store.
+    SelfUpdate {
+        /// Only tell whether a new release is available, the default
+        #[clap(long, conflicts_with = "apply")]
+        check: bool,
+        /// Download the new release, check its signature and replace this binary with it
+        #[clap(long)]
+        apply: bool,
+    },
+
+    /// Gather the versions, doctor checks and recent logs of an app into a tar file to attach
+    /// to a bug report
+    Report {
+        id: Option<String>,
+        #[clap(short, long, default_value = "appack-report.tar")]
+        output: PathBuf,
+    },
+
+    /// List the log files of an app, or all log files
+    Logs {
+        id: Option<String>,
+        /// Remove the log files instead
+        #[clap(long)]
+        clean: bool,
+    },
+
+    Launch {
+        /// Id of the app, the end of its id, e.g. `photoshop` for `com.adobe.photoshop`, or
+        /// a part of its name. Picked among the installed apps when not given.
+        id: Option<String>,
         rdp_args: Option<String>,
+        #[clap(long, conflicts_with = "remote")]
+        version: Option<String>,
+        /// Launch the app like this desktop entry does, given by its file name or the name
+        /// shown in the menu
+        #[clap(long, conflicts_with_all = ["rdp_args", "remote"])]
+        entry: Option<String>,
+        /// Also serve the app to remote clients on this address, e.g. 0.0.0.0:7000
+        #[clap(long, conflicts_with = "remote")]
+        listen: Option<String>,
+        /// Only serve remote clients, without opening the app on this machine
+        #[clap(long, requires = "listen")]
+        headless: bool,
+        /// Open the app served by `appack launch --listen` on another machine, e.g.
+        /// ssh://user@host/7000 through SSH or host:7000 on a trusted network
+        #[clap(long, requires = "id")]
+        remote: Option<String>,
+        /// Display the app on all monitors, or on all the ones given with --monitor
+        #[clap(long)]
+        multimon: bool,
+        /// Display the app on this monitor, can be repeated. Monitors are listed by
+        /// `xfreerdp3 /monitor-list`
+        #[clap(long = "monitor")]
+        monitors: Vec<u32>,
+        /// Open the app in fullscreen
+        #[clap(long, conflicts_with = "size")]
+        fullscreen: bool,
+        /// Initial window size, WxH like 1920x1080 or a percentage of the screen like 80%
+        #[clap(long, value_parser = parse_window_size)]
+        size: Option<String>,
+        /// Don't play the sound of the app
+        #[clap(long)]
+        mute: bool,
+        /// Let the app record from the microphone
+        #[clap(long)]
+        mic: bool,
+        /// Let the app print on the printers of this machine
+        #[clap(long)]
+        printer: bool,
+        /// Let the app use the smartcards and security keys of this machine
+        #[clap(long)]
+        smartcard: bool,
+        /// Let the app use the webcams of this machine
+        #[clap(long)]
+        camera: bool,
+        /// Share a folder with the app as \\tsclient\<name>, the name of the folder by default.
+        /// Can be repeated
+        #[clap(long = "share", value_name = "DIR[:NAME]", value_parser = SharedFolder::parse)]
+        shares: Vec<SharedFolder>,
+        /// How shared folders reach the app, virtiofs needs a driver in the VM. The choice of
+        /// the pack by default
+        #[clap(long, value_enum)]
+        shared_dirs: Option<SharedDirsMode>,
+        /// Open this document with the app, for desktop entries with a RemoteApp
+        #[clap(long, value_name = "FILE")]
+        open: Option<PathBuf>,
+        /// Keyboard layout of the app, like fr or de(nodeadkeys), or a Windows layout id
+        /// like 0x40C. The layout of this machine by default
+        #[clap(long, value_parser = keyboard::parse_layout)]
+        keyboard: Option<String>,
+        /// Scale the app by this percentage, from 100 to 500. The scale of this machine by
+        /// default
+        #[clap(long, value_parser = scale::parse_scale)]
+        scale: Option<u32>,
+        /// Print the QEMU and FreeRDP command lines and the snapshot the launch would use,
+        /// without starting anything
+        #[clap(long, conflicts_with_all = ["remote", "listen"])]
+        print_command: bool,
+    },
+
+    /// Close the windows of a running app, which then saves its state and stops
+    Stop {
+        id: String,
         #[clap(long)]
         version: Option<String>,
     },
 
+    /// Move installed apps to another directory, e.g. an external drive
+    Relocate {
+        destination: PathBuf,
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<String>,
+        #[clap(long, requires = "id")]
+        version: Option<String>,
+
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Restore the list of installed apps if it is corrupted
+    Recover {
+        /// Rebuild the list from the app directories instead of restoring its backup
+        #[clap(long)]
+        rebuild: bool,
+    },
+
+    /// Write a .rdp file to connect to a running app with another RDP client
+    RdpFile {
+        id: String,
+        #[clap(long)]
+        version: Option<String>,
+        /// Host name or address the file connects to, the name of this machine by default
+        #[clap(long)]
+        host: Option<String>,
+        /// Where to write the file, <id>.rdp by default
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
     Reset {
+        /// Id of the app, picked among the installed ones when not given
+        id: Option<String>,
+        #[clap(long)]
+        version: Option<String>,
+    },
+
+    /// Save the screen of a running app as a PNG, even when its window shows nothing
+    Screenshot {
         id: String,
         #[clap(long)]
         version: Option<String>,
+        /// Where to write the image, <id>.png by default
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Give the memory of a running app back to this machine, down to the given size
+    Balloon {
+        id: String,
+        /// Memory left to the app, in megabytes
+        memory_mb: u64,
+        #[clap(long)]
+        version: Option<String>,
+    },
+
+    /// Run a simulated QEMU, qemu-img or FreeRDP, used instead of them when APPACK_SIMULATE
+    /// is set
+    #[clap(hide = true)]
+    Simulate {
+        program: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Download a pack for `appack --system`, run by it as an unprivileged user
+    #[clap(hide = true)]
+    Download {
+        #[clap(long)]
+        output: PathBuf,
+        #[clap(long)]
+        sha256: Option<String>,
+        /// Fail when the signature of the pack can't be downloaded
+        #[clap(long)]
+        signed: bool,
+        /// URLs of the pack, tried in order
+        #[clap(long, required = true)]
+        url: Vec<String>,
+        /// URL of the signature of each URL of the pack
+        #[clap(long)]
+        signature: Vec<String>,
     },
 
     Version,
     Info {
         file: PathBuf,
     },
+    /// Print the readme of an installed app, in your language when the pack has it
+    Readme {
+        id: String,
+        #[clap(long)]
+        version: Option<String>,
+    },
 }
 
-#[derive(Debug, Subcommand, ValueEnum, Clone)]
+#[derive(Debug, Subcommand, Clone)]
 enum CliCreatorAction {
-    New,
+    New {
+        /// Files to start from
+        #[clap(long, value_enum, default_value = "windows")]
+        template: CreatorTemplate,
+        /// Regional format of Windows, e.g. fr-FR. The one of this machine by default.
+        #[clap(long)]
+        locale: Option<String>,
+        /// Keyboard layout of Windows, e.g. fr or 0x40C
+        #[clap(long)]
+        keyboard: Option<String>,
+        /// Timezone of Windows, e.g. Europe/Paris
+        #[clap(long)]
+        timezone: Option<String>,
+    },
+    /// Create the disk image of the pack from its manifest, once the one of `new` is removed
+    Image,
     Boot,
     BootInstall,
     Snapshot,
     Pack,
 }
 
-fn main() -> Result<()> {
-    log_debug("AppPack starting");
+#[derive(Debug, Subcommand)]
+enum CliConfigAction {
+    /// Print all global settings, or all settings of an app
+    List { id: Option<String> },
+    /// `appack config get <key>`, or `appack config get <id> <key>` for an app
+    Get {
+        #[clap(num_args = 1..=2, value_names = ["ID", "KEY"])]
+        args: Vec<String>,
+    },
+    /// `appack config set <key> <value>`, or `appack config set <id> <key> <value>` for an app
+    Set {
+        #[clap(num_args = 2..=3, value_names = ["ID", "KEY", "VALUE"])]
+        args: Vec<String>,
+    },
+    /// `appack config unset <key>`, or `appack config unset <id> <key>` for an app
+    Unset {
+        #[clap(num_args = 1..=2, value_names = ["ID", "KEY"])]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCacheAction {
+    List,
+    /// Remove all cached packs
+    Clean,
+    /// Print the cache directory
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliAutostartAction {
+    Enable {
+        id: String,
+        /// Open the app in fullscreen
+        #[clap(long)]
+        fullscreen: bool,
+        /// Only serve remote clients on this address, without opening the app
+        #[clap(long)]
+        listen: Option<String>,
+    },
+    Disable {
+        id: String,
+    },
+}
 
-    let args = match Cli::try_parse() {
-        Ok(args) => args,
+#[derive(Debug, Subcommand)]
+enum CliDesktopAction {
+    /// Write the desktop entries of an app, or of all apps, again from their pack
+    Regenerate { id: Option<String> },
+    /// Write the desktop entries of the apps installed system-wide in your menu, done at login
+    Sync,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliAliasAction {
+    /// Make an alias stand for an installed app, `<id>` or `<id>@<version>`
+    Add { alias: String, target: String },
+    Remove { alias: String },
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliRepoAction {
+    Add {
+        name: String,
+        url: String,
+        /// How `appack publish` uploads to this repository
+        #[clap(long)]
+        publish_backend: Option<RepositoryPublishBackend>,
+        /// Upload endpoint, if different from the repository URL
+        #[clap(long, requires = "publish_backend")]
+        publish_url: Option<String>,
+        #[clap(long, requires = "publish_backend")]
+        s3_region: Option<String>,
+    },
+    Remove {
+        name: String,
+    },
+    List,
+    /// Fetch and verify repository indexes
+    Sync {
+        name: Option<String>,
+    },
+    /// Trust a repository public key
+    Trust {
+        keyfile: PathBuf,
+    },
+    Untrust {
+        key_id: String,
+    },
+    /// List trusted keys
+    Keys,
+    /// Generate a signing key pair (<output>.key and <output>.pub)
+    Keygen {
+        output: PathBuf,
+        #[clap(long)]
+        comment: Option<String>,
+    },
+    /// Sign a pack or repository index with a secret key
+    Sign {
+        file: PathBuf,
+        #[clap(long)]
+        key: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Cli::parse();
+    if args.json
+        && let Err(e) = enable_json_output()
+    {
+        eprintln!("Error: {e:?}");
+        return ExitCode::FAILURE;
+    }
+    if args.yes {
+        set_assume_yes();
+    }
+    if args.trust_desktop_entries {
+        set_trust_desktop_entries();
+    }
+    // Runs without a hypervisor or a display, e.g. in tests
+    if std::env::var("APPACK_SIMULATE")
+        .is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false" | "no"))
+    {
+        let appack = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("appack"));
+        set_executor(Simulator { appack });
+    }
+
+    let result = AppPackLocalSettings::load().and_then(|settings| start(args, settings));
+    emit_json_outcome(&result);
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            log_debug("Error parsing arguments:");
-            log_debug(&e);
+            eprintln!("Error: {e:?}");
+            ExitCode::from(ErrorKind::of(&e).exit_code())
+        }
+    }
+}
 
-            // Keep the clap error message formatting
-            Cli::parse();
-            return Err(anyhow::anyhow!(e));
+/// Commands running apps in the session of a user, root doesn't run them for everyone
+fn runs_apps(action: &CliAction) -> bool {
+    matches!(
+        action,
+        CliAction::Launch { .. }
+            | CliAction::Stop { .. }
+            | CliAction::Ui
+            | CliAction::Console { .. }
+            | CliAction::Top { .. }
+            | CliAction::Events { .. }
+            | CliAction::Screenshot { .. }
+            | CliAction::Balloon { .. }
+            | CliAction::RdpFile { .. }
+    )
+}
+
+fn start(args: Cli, mut settings: AppPackLocalSettings) -> Result<()> {
+    if args.system {
+        if runs_apps(&args.action) {
+            return Err(ErrorKind::General.error(
+                "Apps installed system-wide are run by each user, run it without --system",
+            ));
         }
+        settings = system_settings(&settings)?;
+    }
+    // The dashboard draws on the whole terminal, its logs only go to the log file
+    let console_level = match args.action {
+        CliAction::Ui => LevelFilter::OFF,
+        _ => console_log_level(args.log_level, args.verbose),
     };
+    let _log_guard = init_logging(
+        &settings.logs_dir,
+        console_level,
+        settings
+            .config
+            .log_retention_days
+            .unwrap_or(DEFAULT_LOG_RETENTION_DAYS),
+    );
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "AppPack starting");
+
+    configure_network(NetworkConfig {
+        proxy: settings.config.proxy.clone(),
+        ca_bundle: settings.config.ca_bundle.clone(),
+        ipfs_gateway: settings.config.ipfs_gateway.clone(),
+    });
+    if args.limit_rate.is_some() {
+        settings.limit_rate = args.limit_rate;
+    }
+
+    let result = run(args.action, &settings);
+    if let Err(e) = &result {
+        tracing::error!(target: FILE_ONLY_TARGET, "{e:?}");
+    }
 
-    let settings = AppPackLocalSettings::default();
+    result
+}
 
-    match args.action {
-        CliAction::Install { file } => install_appack(file, settings)?,
+fn run(action: CliAction, settings: &AppPackLocalSettings) -> Result<()> {
+    match action {
+        CliAction::Install {
+            pack,
+            version,
+            repo,
+            force,
+        } => install_appack(settings, &pack, version.as_deref(), repo.as_deref(), force)?,
         CliAction::Uninstall { id, version, all } => {
             if all {
-                uninstall_all_appacks(&settings)?
+                uninstall_all_appacks(settings)?
             } else {
-                uninstall_appack(&settings, &id.unwrap(), version.as_deref())?
+                let (id, version) = app_or_pick(settings, id, version)?;
+                uninstall_appack(settings, &id, version.as_deref())?
             }
         }
+        CliAction::Update { id } => update(settings, id.as_deref())?,
+        CliAction::Apply { manifest, prune } => apply(settings, &manifest, prune)?,
+        CliAction::Backup {
+            output,
+            without_images,
+        } => backup(settings, &output, !without_images)?,
+        CliAction::Restore { file } => restore(settings, &file)?,
         CliAction::Creator { action } => match action {
-            CliCreatorAction::New => {
-                creator_new()?;
+            CliCreatorAction::New {
+                template,
+                locale,
+                keyboard,
+                timezone,
+            } => {
+                let unattend = UnattendOptions {
+                    locale,
+                    keyboard,
+                    timezone,
+                };
+                creator_new(template, unattend)?;
             }
+            CliCreatorAction::Image => creator_image()?,
             CliCreatorAction::BootInstall => {
                 creator_boot_install()?;
             }
@@ -136,26 +796,275 @@ fn main() -> Result<()> {
                 creator_pack()?;
             }
         },
+        CliAction::Search { query, category } => search(settings, &query, category.as_deref())?,
+        CliAction::Alias { action } => match action {
+            CliAliasAction::Add { alias, target } => alias_add(settings, &alias, &target)?,
+            CliAliasAction::Remove { alias } => alias_remove(settings, &alias)?,
+            CliAliasAction::List => alias_list(settings)?,
+        },
+        CliAction::Repo { action } => match action {
+            CliRepoAction::Add {
+                name,
+                url,
+                publish_backend,
+                publish_url,
+                s3_region,
+            } => {
+                let publish = publish_backend.map(|backend| RepositoryPublishConfig {
+                    backend,
+                    url: publish_url,
+                    region: s3_region,
+                });
+                repo_add(settings, &name, &url, publish)?
+            }
+            CliRepoAction::Remove { name } => repo_remove(settings, &name)?,
+            CliRepoAction::List => repo_list(settings)?,
+            CliRepoAction::Sync { name } => repo_sync(settings, name.as_deref())?,
+            CliRepoAction::Trust { keyfile } => repo_trust(settings, &keyfile)?,
+            CliRepoAction::Untrust { key_id } => repo_untrust(settings, &key_id)?,
+            CliRepoAction::Keys => repo_keys(settings)?,
+            CliRepoAction::Keygen { output, comment } => repo_keygen(&output, comment)?,
+            CliRepoAction::Sign { file, key } => repo_sign(&file, &key)?,
+        },
+        CliAction::Autostart { action } => match action {
+            CliAutostartAction::Enable {
+                id,
+                fullscreen,
+                listen,
+            } => autostart_enable(settings, &id, &AutostartOptions { fullscreen, listen })?,
+            CliAutostartAction::Disable { id } => autostart_disable(settings, &id)?,
+        },
+        CliAction::Entries { id, version } => {
+            let (id, version) = app_or_pick(settings, id, version)?;
+            list_entries(settings, &id, version.as_deref())?
+        }
+        CliAction::Desktop { action } => match action {
+            CliDesktopAction::Regenerate { id } => {
+                regenerate_desktop_entries(settings, id.as_deref())?
+            }
+            CliDesktopAction::Sync => sync_system_entries(settings)?,
+        },
+        CliAction::Publish {
+            file,
+            repo,
+            key,
+            mirrors,
+            ipfs,
+            magnet,
+            channel,
+        } => {
+            let options = PublishOptions {
+                mirrors,
+                ipfs,
+                magnet,
+                channel,
+            };
+            publish(settings, &file, &repo, &key, options)?
+        }
+        CliAction::Config { action } => match action {
+            CliConfigAction::List { id: None } => config_get(settings, None)?,
+            CliConfigAction::List { id: Some(id) } => app_config_get(settings, &id, None)?,
+            CliConfigAction::Get { args } => match args.as_slice() {
+                [key] => config_get(settings, Some(key))?,
+                [id, key] => app_config_get(settings, id, Some(key))?,
+                _ => unreachable!(),
+            },
+            CliConfigAction::Set { args } => match args.as_slice() {
+                [key, value] => config_set(settings, key, value)?,
+                [id, key, value] => app_config_set(settings, id, key, value)?,
+                _ => unreachable!(),
+            },
+            CliConfigAction::Unset { args } => match args.as_slice() {
+                [key] => config_unset(settings, key)?,
+                [id, key] => app_config_unset(settings, id, key)?,
+                _ => unreachable!(),
+            },
+        },
+        CliAction::Cache { action } => match action {
+            CliCacheAction::List => cache_list(settings)?,
+            CliCacheAction::Clean => cache_clean(settings)?,
+            CliCacheAction::Path => cache_path(settings)?,
+        },
         CliAction::ListInstalled => {
-            list_installed(settings)?;
+            list_installed(settings.clone())?;
+        }
+        CliAction::Ui => ui::ui(settings)?,
+        CliAction::Events { id, version } => events(settings, &id, version.as_deref())?,
+        CliAction::Console { id, version } => console(settings, &id, version.as_deref())?,
+        CliAction::Top { interval, once } => {
+            top(settings, Duration::from_secs(interval.max(1)), once)?
         }
+        CliAction::Stats { id } => stats(settings, id.as_deref())?,
+        CliAction::Du => du(settings)?,
+        CliAction::Gc { id } => gc(settings, id.as_deref())?,
+        CliAction::Verify => verify(settings)?,
+        CliAction::Prune {
+            id,
+            keep,
+            migrate_state,
+        } => prune(settings, id.as_deref(), keep, migrate_state)?,
+        CliAction::Logs { id, clean } => logs(settings, id.as_deref(), clean)?,
         CliAction::Version => {
-            print_version(&settings)?;
+            print_version(settings)?;
         }
         CliAction::Info { file } => {
             print_info(&file)?;
         }
+        CliAction::Readme { id, version } => readme(settings, &id, version.as_deref())?,
+        CliAction::Doctor => {
+            doctor(settings)?;
+        }
+        CliAction::SelfUpdate { check: _, apply } => self_update(apply)?,
+        CliAction::Report { id, output } => report(settings, id.as_deref(), &output)?,
         CliAction::Launch {
             id,
             version,
             rdp_args,
+            entry,
+            listen,
+            headless,
+            remote,
+            multimon,
+            monitors,
+            fullscreen,
+            size,
+            mute,
+            mic,
+            printer,
+            smartcard,
+            camera,
+            shares,
+            shared_dirs,
+            open,
+            keyboard,
+            scale,
+            print_command,
+        } => {
+            let monitors = MonitorSelection { multimon, monitors };
+            let client = ClientOptions {
+                monitors: (!monitors.is_empty()).then_some(monitors),
+                fullscreen,
+                size,
+                mute,
+                microphone: mic,
+                printer,
+                smartcard,
+                camera,
+                shares,
+                shared_dirs,
+                open,
+                keyboard,
+                scale,
+            };
+            // Apps of a remote machine are only known to it
+            let (id, version) = match (remote.is_some(), id) {
+                (true, Some(id)) => (id, version),
+                (_, id) => {
+                    let (id, version) = app_or_pick(settings, id, version)?;
+                    (settings.resolve_app_id(&id)?, version)
+                }
+            };
+            let rdp_args = match entry {
+                Some(entry) => {
+                    let app = settings.get_app_installed(&id, version.as_deref())?;
+                    let rdp_args = entry_rdp_args(settings, &app, &entry)?;
+                    (!rdp_args.is_empty()).then_some(rdp_args)
+                }
+                None => rdp_args,
+            };
+            if print_command {
+                let rdp_args = rdp_args.as_deref();
+                print_launch_command(settings, &id, version.as_deref(), rdp_args, &client)?;
+                return Ok(());
+            }
+            match remote {
+                Some(address) => {
+                    launch_remote(settings, &address, &id, rdp_args.as_deref(), &client)?
+                }
+                None => launch(
+                    settings,
+                    id,
+                    version.as_deref(),
+                    rdp_args.as_deref(),
+                    &client,
+                    &RemoteOptions { listen, headless },
+                )?,
+            }
+        }
+        CliAction::Stop { id, version } => {
+            let (id, version) = resolve_alias(settings, id, version);
+            stop(settings, &id, version.as_deref())?
+        }
+        CliAction::Relocate {
+            destination,
+            id,
+            version,
+            all: _,
+        } => relocate(settings, &destination, id.as_deref(), version.as_deref())?,
+        CliAction::Recover { rebuild } => recover(settings, rebuild)?,
+        CliAction::RdpFile {
+            id,
+            version,
+            host,
+            output,
         } => {
-            launch(&settings, id, version.as_deref(), rdp_args.as_deref())?;
+            export_rdp_file(
+                settings,
+                &id,
+                version.as_deref(),
+                host.as_deref(),
+                output.as_deref(),
+            )?;
         }
         CliAction::Reset { id, version } => {
-            reset(&settings, id, version.as_deref())?;
+            let (id, version) = app_or_pick(settings, id, version)?;
+            reset(settings, id, version.as_deref())?;
+        }
+        CliAction::Screenshot {
+            id,
+            version,
+            output,
+        } => screenshot(settings, &id, version.as_deref(), output.as_deref())?,
+        CliAction::Balloon {
+            id,
+            memory_mb,
+            version,
+        } => balloon(settings, &id, version.as_deref(), memory_mb)?,
+        CliAction::Simulate { program, args } => simulate(&program, &args)?,
+        CliAction::Download {
+            output,
+            sha256,
+            signed,
+            url,
+            signature,
+        } => {
+            let sources: Vec<_> = url.into_iter().zip(signature).collect();
+            download_from_sources(
+                &sources,
+                sha256.as_deref(),
+                signed,
+                &output,
+                settings.limit_rate,
+                |_| Ok(()),
+            )?
         }
     }
 
     Ok(())
 }
+
+/// The app given on the command line or its alias, or the app and version the user picks when
+/// none is
+fn app_or_pick(
+    settings: &AppPackLocalSettings,
+    id: Option<String>,
+    version: Option<String>,
+) -> Result<(String, Option<String>)> {
+    match id {
+        Some(id) => Ok(resolve_alias(settings, id, version)),
+        None => {
+            let app = pick_installed_app(settings)?;
+            Ok((app.id, Some(app.version)))
+        }
+    }
+}
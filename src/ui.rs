@@ -0,0 +1,527 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `appack ui`, a terminal dashboard of the installed apps
+
+use anyhow::{Context, Result};
+use appack_core::internal::launch::{
+    ClientOptions, LaunchHandle, RemoteOptions, running_rdp_port, spawn_launch,
+};
+use appack_core::internal::logs::latest_app_log;
+use appack_core::internal::quota::app_disk_usage;
+use appack_core::internal::reset::reset;
+use appack_core::internal::stop::stop;
+use appack_core::internal::uninstall_appack::uninstall_appack;
+use appack_core::types::app_installed::InstalledAppPackEntry;
+use appack_core::types::error::ErrorKind;
+use appack_core::types::local_settings::AppPackLocalSettings;
+use appack_core::{Frontend, Terminal, set_frontend};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState};
+use std::collections::VecDeque;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the status of the apps is refreshed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const HELP: &str = "↑↓ select  Enter launch  s stop  r reset  u uninstall  l logs  q quit";
+const PROMPT_HELP: &str = "Type the answer  Enter answer  Esc cancel";
+
+/// What the launches and stops running in the background tell the dashboard
+enum Update {
+    Message(String),
+    /// A question, answered with the text the user typed, `None` when they cancel it
+    Question {
+        text: String,
+        answer: Sender<Option<String>>,
+    },
+}
+
+/// Frontend of the dashboard: messages go to its message bar and questions are answered in it.
+/// The terminal gets them while the dashboard is left for an action.
+struct DashboardFrontend {
+    updates: Sender<Update>,
+    suspended: Arc<AtomicBool>,
+    terminal: Terminal,
+}
+
+impl DashboardFrontend {
+    fn suspended(&self) -> bool {
+        self.suspended.load(Ordering::Relaxed)
+    }
+
+    fn show(&self, text: &str) {
+        let _ = self.updates.send(Update::Message(text.to_string()));
+    }
+
+    /// Asks a question in the dashboard and waits for the answer
+    fn ask(&self, text: String) -> Result<Option<String>> {
+        let (answer, answered) = mpsc::channel();
+        self.updates
+            .send(Update::Question { text, answer })
+            .context("The dashboard is closed")?;
+        answered.recv().context("The dashboard is closed")
+    }
+}
+
+impl Frontend for DashboardFrontend {
+    fn message(&self, text: &str) {
+        match self.suspended() {
+            true => self.terminal.message(text),
+            false => self.show(text),
+        }
+    }
+
+    fn warning(&self, text: &str) {
+        match self.suspended() {
+            true => self.terminal.warning(text),
+            false => self.show(text),
+        }
+    }
+
+    fn progress(&self, text: &str) {
+        match self.suspended() {
+            true => self.terminal.progress(text),
+            false => self.show(text),
+        }
+    }
+
+    fn confirm(&self, question: &str) -> Result<bool> {
+        if self.suspended() {
+            return self.terminal.confirm(question);
+        }
+
+        let answer = self.ask(format!("{question} [y/N]"))?.unwrap_or_default();
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+
+    fn confirm_typed(&self, question: &str, word: &str, override_flag: &str) -> Result<bool> {
+        if self.suspended() {
+            return self.terminal.confirm_typed(question, word, override_flag);
+        }
+
+        let answer = self.ask(format!("{question} Type {word} to continue:"))?;
+        Ok(answer.is_some_and(|answer| answer.trim() == word))
+    }
+
+    fn pick(&self, question: &str, options: &[String], argument: &str) -> Result<usize> {
+        if self.suspended() {
+            return self.terminal.pick(question, options, argument);
+        }
+
+        let mut text = question.to_string();
+        for (i, option) in options.iter().enumerate() {
+            text.push_str(&format!("\n  {}) {option}", i + 1));
+        }
+        text.push_str(&format!("\nChoice [1-{}]:", options.len()));
+        loop {
+            let answer = self.ask(text.clone())?.unwrap_or_default();
+            let answer = answer.trim();
+            if answer.is_empty() {
+                return Err(ErrorKind::Cancelled.error("Nothing chosen"));
+            }
+            if let Ok(choice) = answer.parse::<usize>()
+                && (1..=options.len()).contains(&choice)
+            {
+                return Ok(choice - 1);
+            }
+        }
+    }
+}
+
+/// A question of a launch, shown instead of the message bar until it is answered
+struct Prompt {
+    text: String,
+    input: String,
+    answer: Sender<Option<String>>,
+}
+
+/// An app launched from the dashboard, running on a thread of the dashboard
+struct Launch {
+    app: InstalledAppPackEntry,
+    handle: LaunchHandle,
+}
+
+struct AppRow {
+    app: InstalledAppPackEntry,
+    running: bool,
+    size: u64,
+}
+
+struct Dashboard<'a> {
+    settings: &'a AppPackLocalSettings,
+    rows: Vec<AppRow>,
+    table: TableState,
+    message: String,
+    updates: (Sender<Update>, Receiver<Update>),
+    prompts: VecDeque<Prompt>,
+    launches: Vec<Launch>,
+    /// Whether the dashboard was left for an action, see `DashboardFrontend`
+    suspended: Arc<AtomicBool>,
+    /// Whether quitting was asked while apps launched from the dashboard are open
+    quitting: bool,
+}
+
+impl<'a> Dashboard<'a> {
+    fn new(settings: &'a AppPackLocalSettings) -> Self {
+        Self {
+            settings,
+            rows: Vec::new(),
+            table: TableState::default().with_selected(0),
+            message: String::new(),
+            updates: mpsc::channel(),
+            prompts: VecDeque::new(),
+            launches: Vec::new(),
+            suspended: Arc::new(AtomicBool::new(false)),
+            quitting: false,
+        }
+    }
+
+    fn frontend(&self) -> DashboardFrontend {
+        DashboardFrontend {
+            updates: self.updates.0.clone(),
+            suspended: self.suspended.clone(),
+            terminal: Terminal::default(),
+        }
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let mut apps = self.settings.get_installed()?.installed;
+        // Apps of the user hide the system-wide ones with the same id, as for `appack launch`
+        let system_apps: Vec<_> = self
+            .settings
+            .get_system_installed()?
+            .installed
+            .into_iter()
+            .filter(|system| !apps.iter().any(|app| app.id == system.id))
+            .collect();
+        apps.extend(system_apps);
+
+        self.rows = apps
+            .into_iter()
+            .map(|app| AppRow {
+                running: running_rdp_port(self.settings, &app).is_some(),
+                size: app_disk_usage(self.settings, &app),
+                app,
+            })
+            .collect();
+        if self
+            .table
+            .selected()
+            .is_none_or(|index| index >= self.rows.len())
+        {
+            self.table.select((!self.rows.is_empty()).then_some(0));
+        }
+
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&InstalledAppPackEntry> {
+        self.rows.get(self.table.selected()?).map(|row| &row.app)
+    }
+
+    /// Launches the selected app on a thread, the dashboard stays usable and answers the
+    /// questions of the launch while the app runs
+    fn launch(&mut self) {
+        let Some(app) = self.selected().cloned() else {
+            return;
+        };
+
+        let handle = spawn_launch(
+            self.settings.clone(),
+            app.id.clone(),
+            Some(app.version.clone()),
+            None,
+            ClientOptions::default(),
+            RemoteOptions::default(),
+        );
+        self.message = format!("Launching {}..", app.name);
+        self.launches.push(Launch { app, handle });
+    }
+
+    /// Closes the selected app in the background, saving its state like `appack stop`
+    fn stop(&mut self) {
+        let Some(app) = self.selected().cloned() else {
+            return;
+        };
+
+        let launch = self
+            .launches
+            .iter()
+            .position(|launch| launch.app.id == app.id && launch.app.version == app.version)
+            .map(|index| self.launches.remove(index));
+        let settings = self.settings.clone();
+        let updates = self.updates.0.clone();
+        self.message = format!("Stopping {}..", app.name);
+        thread::spawn(move || {
+            let result = match launch {
+                Some(launch) => launch.handle.stop(),
+                None => stop(&settings, &app.id, Some(&app.version)),
+            };
+            if let Err(e) = result {
+                let _ = updates.send(Update::Message(format!(
+                    "Failed to stop {}: {e}, press l for its logs",
+                    app.id
+                )));
+            }
+        });
+    }
+
+    /// Reports the launches that ended since the last call
+    fn reap_launches(&mut self) {
+        let (finished, running) = std::mem::take(&mut self.launches)
+            .into_iter()
+            .partition::<Vec<_>, _>(|launch| launch.handle.is_finished());
+        self.launches = running;
+        for launch in finished {
+            if let Err(e) = launch.handle.wait() {
+                self.message = match ErrorKind::of(&e) {
+                    ErrorKind::Cancelled => format!("{}: {e}", launch.app.name),
+                    _ => format!("{} failed: {e}, press l for its logs", launch.app.name),
+                };
+            }
+        }
+    }
+
+    /// Closes the apps launched from the dashboard before quitting, as they run in it
+    fn close_launches(&mut self) {
+        self.suspended.store(true, Ordering::Relaxed);
+        ratatui::restore();
+        for launch in std::mem::take(&mut self.launches) {
+            println!("Closing {}..", launch.app.name);
+            if let Err(e) = launch.handle.stop() {
+                println!("Error: {e:?}");
+            }
+        }
+    }
+
+    /// Leaves the dashboard to run an action printing to the terminal or asking questions
+    fn suspended(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        action: impl FnOnce(&AppPackLocalSettings, &InstalledAppPackEntry) -> Result<()>,
+    ) -> Result<()> {
+        let Some(app) = self.selected().cloned() else {
+            return Ok(());
+        };
+
+        self.suspended.store(true, Ordering::Relaxed);
+        ratatui::restore();
+        if let Err(e) = action(self.settings, &app) {
+            println!("Error: {e:?}");
+        }
+        println!("\nPress Enter to go back to the dashboard");
+        let _ = std::io::stdin().read_line(&mut String::new());
+        *terminal = ratatui::init();
+        self.suspended.store(false, Ordering::Relaxed);
+
+        self.message.clear();
+        self.refresh()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let message_height = self
+            .prompts
+            .front()
+            .map_or(1, |prompt| prompt.text.lines().count() as u16);
+        let [table_area, message_area, help_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(message_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new(["Name", "Id", "Version", "Status", "Size"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.rows.iter().map(|row| {
+            let status = if row.running {
+                Cell::from("running").green()
+            } else {
+                Cell::from("stopped")
+            };
+            let name = match row.app.system {
+                true => format!("{} (system)", row.app.name),
+                false => row.app.name.clone(),
+            };
+            Row::new([
+                Cell::from(name),
+                Cell::from(row.app.id.as_str()),
+                Cell::from(row.app.version.as_str()),
+                status,
+                Cell::from(format!("{} MB", row.size / 1024 / 1024)),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(Block::bordered().title(" AppPack "))
+        .row_highlight_style(Style::new().reversed());
+
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+        if self.rows.is_empty() {
+            self.message = "No apps installed, install one with `appack install`".to_string();
+        }
+        match self.prompts.front() {
+            Some(prompt) => {
+                let text = format!("{} {}", prompt.text, prompt.input);
+                frame.render_widget(Paragraph::new(text).bold(), message_area);
+                frame.render_widget(Line::from(PROMPT_HELP).dim(), help_area);
+            }
+            None => {
+                frame.render_widget(Paragraph::new(self.message.as_str()), message_area);
+                frame.render_widget(Line::from(HELP).dim(), help_area);
+            }
+        }
+    }
+
+    /// Takes the messages and questions of the launches and stops running in the background
+    fn receive_updates(&mut self) {
+        while let Ok(update) = self.updates.1.try_recv() {
+            match update {
+                Update::Message(message) => self.message = message,
+                Update::Question { text, answer } => self.prompts.push_back(Prompt {
+                    text,
+                    input: String::new(),
+                    answer,
+                }),
+            }
+        }
+    }
+
+    /// Types the answer of the question shown
+    fn answer(&mut self, key: KeyCode) {
+        let Some(prompt) = self.prompts.front_mut() else {
+            return;
+        };
+        let answer = match key {
+            KeyCode::Char(c) => {
+                prompt.input.push(c);
+                return;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+                return;
+            }
+            KeyCode::Enter => Some(std::mem::take(&mut prompt.input)),
+            KeyCode::Esc => None,
+            _ => return,
+        };
+        if let Some(prompt) = self.prompts.pop_front() {
+            let _ = prompt.answer.send(answer);
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.refresh()?;
+        let mut refreshed = Instant::now();
+
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            self.receive_updates();
+            self.reap_launches();
+            if refreshed.elapsed() > REFRESH_INTERVAL {
+                self.refresh()?;
+                refreshed = Instant::now();
+            }
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if !self.prompts.is_empty() {
+                self.answer(key.code);
+                continue;
+            }
+
+            let quitting = std::mem::take(&mut self.quitting);
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    if self.launches.is_empty() || quitting {
+                        self.close_launches();
+                        return Ok(());
+                    }
+                    self.quitting = true;
+                    self.message = format!(
+                        "{} app(s) launched from the dashboard are open, press q again to close \
+                         them and quit",
+                        self.launches.len()
+                    );
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::Enter => self.launch(),
+                KeyCode::Char('s') => self.stop(),
+                KeyCode::Char('r') => self.suspended(terminal, |settings, app| {
+                    reset(settings, app.id.clone(), Some(&app.version))
+                })?,
+                KeyCode::Char('u') => self.suspended(terminal, |settings, app| {
+                    uninstall_appack(settings, &app.id, Some(&app.version))
+                })?,
+                KeyCode::Char('l') => self.suspended(terminal, view_logs)?,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Opens the last log of an app in the pager
+fn view_logs(settings: &AppPackLocalSettings, app: &InstalledAppPackEntry) -> Result<()> {
+    let Some(log) = latest_app_log(settings, &app.id)? else {
+        println!("No logs for {}", app.id);
+        return Ok(());
+    };
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    Command::new(&pager)
+        .arg(&log)
+        .status()
+        .context(format!("Failed to run {pager}"))?;
+
+    Ok(())
+}
+
+pub fn ui(settings: &AppPackLocalSettings) -> Result<()> {
+    let mut dashboard = Dashboard::new(settings);
+    set_frontend(dashboard.frontend());
+
+    let mut terminal = ratatui::init();
+    let result = dashboard.run(&mut terminal);
+    ratatui::restore();
+
+    result
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: GPL-3.0-only
+// Copyright (C) 2025 Paul <abonnementspaul (at) gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Packs, installs, launches and stops an app with the simulated QEMU and FreeRDP of
+//! `APPACK_SIMULATE`
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const BUILD_CONFIG: &str = "\
+name: Simulated
+id: simulated
+version: 1.0.0
+image: image.qcow2
+snapshot: OnClose
+readme:
+  folder: readme
+base_command: -enable-kvm -m 2G -drive file=$IMAGE_FILE_PATH,if=virtio,format=qcow2
+install_append: -display sdl
+configure_append: -qmp unix:qmp-appack.sock,server=on,wait=off
+configure_freerdp: /u:user /cert:ignore
+";
+
+fn appack(home: &Path, dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_appack"));
+    command
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("APPACK_SIMULATE", "1")
+        .env("APPACK_NOTIFICATIONS", "false")
+        .env_remove("SNAP")
+        .env_remove("FLATPAK_ID");
+    command
+}
+
+fn run(command: &mut Command) -> String {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = command.output().unwrap();
+    let stdout = String::from_utf8_lossy(&stdout).to_string();
+    assert!(
+        status.success(),
+        "{command:?} failed: {stdout}{}",
+        String::from_utf8_lossy(&stderr)
+    );
+    stdout
+}
+
+/// Whether a FreeRDP window of this window class is open
+fn window_open(wm_class: &str) -> bool {
+    let arg = format!("/wm-class:{wm_class}");
+    std::fs::read_dir("/proc")
+        .unwrap()
+        .flatten()
+        .any(|process| {
+            std::fs::read(process.path().join("cmdline")).is_ok_and(|cmdline| {
+                cmdline
+                    .split(|byte| *byte == 0)
+                    .any(|a| a == arg.as_bytes())
+            })
+        })
+}
+
+#[test]
+fn test_simulated_app_lifecycle() {
+    let root: PathBuf =
+        std::env::temp_dir().join(format!("appack-simulate-{}", std::process::id()));
+    let home = root.join("home");
+    let pack = root.join("pack");
+    std::fs::create_dir_all(home.join("snap/appack/common")).unwrap();
+    std::fs::create_dir_all(pack.join("readme")).unwrap();
+    std::fs::write(pack.join("readme/README.md"), "# Simulated").unwrap();
+    std::fs::write(pack.join("AppPackBuildConfig.yaml"), BUILD_CONFIG).unwrap();
+
+    let qemu_img = ["simulate", "qemu-img"];
+    run(appack(&home, &pack)
+        .args(qemu_img)
+        .args(["create", "-f", "qcow2", "image.qcow2"]));
+    run(appack(&home, &pack)
+        .args(qemu_img)
+        .args(["snapshot", "-c", "appack-init", "image.qcow2"]));
+    run(appack(&home, &pack).args(["creator", "pack"]));
+    run(appack(&home, &pack).args(["--yes", "install", "simulated_1.0.0.zip"]));
+
+    let print_command = ["launch", "simulated", "--print-command"];
+    let dry_run = run(appack(&home, &pack).args(print_command));
+    assert!(dry_run.contains("Snapshot: appack-init"), "{dry_run}");
+
+    let mut launch = appack(&home, &pack)
+        .args(["launch", "simulated"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let started = Instant::now();
+    while !window_open("appack-simulated") {
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "The app didn't start"
+        );
+        thread::sleep(Duration::from_millis(200));
+    }
+    let dry_run = run(appack(&home, &pack).args(print_command));
+    assert!(dry_run.contains("is running"), "{dry_run}");
+    run(appack(&home, &pack).args(["stop", "simulated"]));
+    assert!(launch.wait().unwrap().success());
+
+    // The state of the app was saved when its window closed
+    let dry_run = run(appack(&home, &pack).args(print_command));
+    assert!(dry_run.contains("Snapshot: appack-onclose"), "{dry_run}");
+
+    run(appack(&home, &pack).args(["--yes", "uninstall", "simulated"]));
+    std::fs::remove_dir_all(&root).unwrap();
+}